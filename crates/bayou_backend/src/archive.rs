@@ -0,0 +1,112 @@
+//! Writing static libraries, which are `ar` archives of object files.
+//!
+//! Archives use the GNU format, with a symbol index so that linkers
+//! can find definitions without an extra `ranlib` step.
+
+use cranelift_object::object::read::{File, Object as _, ObjectSymbol as _};
+
+use crate::{BackendError, BackendResult};
+
+const MAGIC: &[u8] = b"!<arch>\n";
+const HEADER_SIZE: usize = 60;
+
+/// The longest member name that fits in a header, including the terminating `/`.
+const MAX_SHORT_NAME: usize = 16;
+
+/// Write an archive of object files, given as member names and object data.
+pub fn write_archive(members: &[(String, Vec<u8>)]) -> BackendResult<Vec<u8>> {
+    let mut symbol_names = Vec::new();
+    // the index of the member defining each symbol
+    let mut symbol_members = Vec::new();
+
+    for (index, (_, object_data)) in members.iter().enumerate() {
+        let file = File::parse(&**object_data).map_err(BackendError::ReadObject)?;
+
+        for symbol in file.symbols() {
+            if symbol.is_global() && symbol.is_definition() {
+                symbol_names.extend(symbol.name_bytes().map_err(BackendError::ReadObject)?);
+                symbol_names.push(0);
+                symbol_members.push(index);
+            }
+        }
+    }
+
+    // members with long names refer to their offset in the long names member
+    let mut long_names = String::new();
+    let header_names: Vec<_> = members
+        .iter()
+        .map(|(name, _)| {
+            let name = format!("{name}/");
+            if name.len() <= MAX_SHORT_NAME {
+                return name;
+            }
+
+            let offset = long_names.len();
+            long_names.push_str(&name);
+            long_names.push('\n');
+            format!("/{offset}")
+        })
+        .collect();
+
+    let symbol_table_size = 4 + 4 * symbol_members.len() + symbol_names.len();
+
+    let mut member_offset = MAGIC.len() + HEADER_SIZE + padded(symbol_table_size);
+    if !long_names.is_empty() {
+        member_offset += HEADER_SIZE + padded(long_names.len());
+    }
+
+    let member_offsets: Vec<_> = members
+        .iter()
+        .map(|(_, object_data)| {
+            let offset = member_offset;
+            member_offset += HEADER_SIZE + padded(object_data.len());
+            offset
+        })
+        .collect();
+
+    let mut symbol_table = Vec::with_capacity(symbol_table_size);
+    symbol_table.extend((symbol_members.len() as u32).to_be_bytes());
+    for &member in &symbol_members {
+        symbol_table.extend((member_offsets[member] as u32).to_be_bytes());
+    }
+    symbol_table.extend(symbol_names);
+
+    let mut archive = MAGIC.to_vec();
+    write_member(&mut archive, "/", &symbol_table);
+
+    if !long_names.is_empty() {
+        write_member(&mut archive, "//", long_names.as_bytes());
+    }
+
+    for (header_name, (_, object_data)) in header_names.iter().zip(members) {
+        write_member(&mut archive, header_name, object_data);
+    }
+
+    Ok(archive)
+}
+
+/// Write a member, with a header that leaves out timestamps and owners
+/// so that the archive is reproducible.
+fn write_member(archive: &mut Vec<u8>, name: &str, data: &[u8]) {
+    let header = format!(
+        "{name:<16}{date:<12}{uid:<6}{gid:<6}{mode:<8}{size:<10}`\n",
+        date = 0,
+        uid = 0,
+        gid = 0,
+        mode = 644,
+        size = data.len(),
+    );
+    debug_assert_eq!(header.len(), HEADER_SIZE);
+
+    archive.extend(header.as_bytes());
+    archive.extend(data);
+
+    // members are aligned to two bytes
+    if data.len() % 2 == 1 {
+        archive.push(b'\n');
+    }
+}
+
+fn padded(size: usize) -> usize {
+    size + size % 2
+}
@@ -1,139 +1,674 @@
+use std::collections::HashMap;
 use std::ops::ControlFlow::{self, Break, Continue};
 
 use bayou_interner::Interner;
 use bayou_ir::ir::{Block as IrBlock, *};
-use bayou_ir::symbols::{LocalId, Symbols};
+use bayou_ir::symbols::{FuncId, FunctionSymbol, LocalId, StaticId, Symbols};
 use bayou_ir::{BinOp, Type as IrType, UnOp};
-use cranelift::codegen::verify_function;
+use bayou_session::diagnostics::sources::{Source as _, SourceMap as _};
+use bayou_session::sourcemap::{SourceMap, SourceSpan};
+use bayou_session::{CodegenOptions, CrateType, OptLevel};
+use bayou_utils::keyvec::KeyVec;
+use bayou_utils::parallel;
+use cranelift::codegen::control::ControlPlane;
+use cranelift::codegen::ir::{Endianness, Function, SourceLoc, StackSlot};
+use cranelift::codegen::isa::OwnedTargetIsa;
+use cranelift::codegen::CompiledCode;
 use cranelift::prelude::*;
-use cranelift_module::{Linkage, Module as _};
+use cranelift_jit::{JITBuilder, JITModule};
+use cranelift_module::{DataDescription, DataId, FuncId as ClifFuncId, Linkage, Module};
 use cranelift_object::{ObjectBuilder, ObjectModule, ObjectProduct};
 use target_lexicon::Triple;
 
-use crate::layout::{ConstantAsImm, TypeExt, TypeLayout};
-use crate::{BackendError, BackendResult};
+use crate::debuginfo::DebugContext;
+use crate::func_cache::{CodegenStats, FunctionCache};
+use crate::layout::{slice_len_offset, ConstantAsImm, TypeExt, TypeLayout};
+use crate::{BackendError, BackendResult, JitProgram};
 
 struct UnreachableCode;
 
-pub struct Codegen {
-    ctx: codegen::Context,
+/// Generates code for a package into any kind of Cranelift module,
+/// such as an object file or executable memory.
+pub struct Codegen<M: Module> {
     builder_ctx: FunctionBuilderContext,
-    module: ObjectModule,
+    module: M,
+
+    ptr_ty: Type,
+    bounds_checks: bool,
+
+    rodata: Rodata,
+
+    main_func: Option<ClifFuncId>,
+    static_ids: KeyVec<StaticId, DataId>,
+
+    debug: Option<DebugContext>,
+
+    func_cache: Option<FunctionCache>,
+    stats: CodegenStats,
 }
 
-impl Codegen {
-    pub fn new(target: Triple, package_name: &str) -> BackendResult<Self> {
-        let mut flag_builder = settings::builder();
-        flag_builder.set("is_pic", "true").unwrap();
-        flag_builder.set("opt_level", "speed").unwrap();
+fn build_isa(
+    target: Triple,
+    options: &CodegenOptions,
+    default_pic: bool,
+) -> BackendResult<OwnedTargetIsa> {
+    let opt_level = match options.opt_level {
+        // Cranelift doesn't distinguish between more levels of optimization
+        OptLevel::O0 => "none",
+        OptLevel::O1 | OptLevel::O2 => "speed",
+        OptLevel::Os => "speed_and_size",
+    };
 
-        let flags = settings::Flags::new(flag_builder);
+    let is_pic = options.pic.unwrap_or(default_pic);
 
-        let arch = target.architecture;
-        let isa = match isa::lookup(target) {
-            Ok(isa_builder) => isa_builder.finish(flags)?,
-            Err(_) => {
-                return Err(BackendError::UnsupportedArch(arch));
-            }
+    let flag = |enabled: bool| if enabled { "true" } else { "false" };
+
+    let settings = [
+        ("opt_level", opt_level),
+        ("is_pic", flag(is_pic)),
+        // the verifier catches bugs in codegen, so it runs in every build,
+        // unless it's turned off with `-C cranelift.enable_verifier=false`
+        ("enable_verifier", "true"),
+        // debuggers find locals relative to the frame pointer
+        ("preserve_frame_pointers", flag(options.debug_info)),
+    ];
+
+    let mut flag_builder = settings::builder();
+    let user_settings = options
+        .cranelift_settings
+        .iter()
+        .map(|(name, value)| (name.as_str(), value.as_str()));
+
+    for (name, value) in settings.into_iter().chain(user_settings) {
+        flag_builder
+            .set(name, value)
+            .map_err(|error| BackendError::InvalidSetting {
+                name: name.to_owned(),
+                error,
+            })?;
+    }
+
+    let flags = settings::Flags::new(flag_builder);
+
+    let arch = target.architecture;
+    match isa::lookup(target) {
+        Ok(isa_builder) => Ok(isa_builder.finish(flags)?),
+        Err(_) => Err(BackendError::UnsupportedArch(arch)),
+    }
+}
+
+impl Codegen<ObjectModule> {
+    pub fn new(
+        target: Triple,
+        package_name: &str,
+        options: &CodegenOptions,
+    ) -> BackendResult<Self> {
+        let isa = build_isa(target, options, true)?;
+
+        let debug = match options.debug_info {
+            true => Some(DebugContext::new(&*isa, package_name)?),
+            false => None,
         };
 
         let module_builder =
             ObjectBuilder::new(isa, package_name, cranelift_module::default_libcall_names())?;
 
-        let module = ObjectModule::new(module_builder);
+        let mut codegen = Self::with_module(ObjectModule::new(module_builder), options);
+        codegen.debug = debug;
+
+        Ok(codegen)
+    }
+
+    pub fn finish(self) -> BackendResult<ObjectProduct> {
+        let mut product = self.module.finish();
+
+        if let Some(debug) = self.debug {
+            debug.emit(&mut product)?;
+        }
+
+        Ok(product)
+    }
+}
+
+impl Codegen<JITModule> {
+    /// Generate code into memory so that it can be run in this process.
+    pub fn new_jit(options: &CodegenOptions) -> BackendResult<Self> {
+        let isa = build_isa(Triple::host(), options, false)?;
 
-        Ok(Self {
-            ctx: module.make_context(),
+        // extern functions are looked up in the symbols of this process
+        let module_builder = JITBuilder::with_isa(isa, cranelift_module::default_libcall_names());
+
+        Ok(Self::with_module(JITModule::new(module_builder), options))
+    }
+
+    /// Finalize all functions, making them ready to be called.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the package has no `main` function.
+    pub fn finish_jit(mut self) -> BackendResult<JitProgram> {
+        self.module.finalize_definitions()?;
+
+        let main = self.main_func.expect("no `main` function");
+        let main = self.module.get_finalized_function(main);
+
+        let mut statics = KeyVec::new();
+        for &id in &self.static_ids {
+            let (data, _) = self.module.get_finalized_data(id);
+
+            // keys are allocated in the same order as the symbols
+            let _: StaticId = statics.insert(data.cast_mut());
+        }
+
+        Ok(JitProgram {
+            module: Some(self.module),
+            main,
+            statics,
+        })
+    }
+}
+
+impl<M: Module> Codegen<M> {
+    fn with_module(module: M, options: &CodegenOptions) -> Self {
+        Self {
             builder_ctx: FunctionBuilderContext::new(),
+            ptr_ty: module.target_config().pointer_type(),
             module,
-        })
+
+            bounds_checks: !options.unchecked_indexing,
+
+            rodata: Rodata::default(),
+
+            main_func: None,
+            static_ids: KeyVec::new(),
+
+            debug: None,
+
+            func_cache: None,
+            stats: CodegenStats::default(),
+        }
+    }
+
+    /// Reuse functions compiled in earlier builds, and cache the functions compiled now.
+    #[must_use]
+    pub fn with_function_cache(mut self, func_cache: FunctionCache) -> Self {
+        self.func_cache = Some(func_cache);
+        self
     }
 
-    pub fn compile_package(&mut self, package: &Package) -> BackendResult<()> {
+    pub fn stats(&self) -> CodegenStats {
+        self.stats
+    }
+
+    pub fn compile_package(
+        &mut self,
+        package: &Package,
+        source_map: &SourceMap,
+    ) -> BackendResult<()> {
+        let func_ids = self.declare_funcs(package)?;
+        let static_ids = self.declare_statics(package)?;
+
+        self.main_func = package.ir.main_func.map(|id| func_ids[id]);
+
+        // Cranelift IR is generated on this thread, because it declares
+        // functions and data in the module, but compiling it to machine code
+        // is most of the work, and happens in parallel
+        let mut funcs = vec![];
+
         for item in &package.ir.items {
             match item {
                 Item::FuncDecl(func_decl) => {
-                    self.gen_func_decl(func_decl, &package.symbols, &package.interner)?;
+                    funcs.push(self.gen_func_decl(
+                        func_decl,
+                        package,
+                        &func_ids,
+                        &static_ids,
+                        source_map,
+                    ));
+                }
+
+                // constants are inlined wherever they are used
+                Item::Const(_) => {}
+
+                Item::Static(decl) => {
+                    self.define_static(static_ids[decl.id], decl.id, package)?;
                 }
             }
         }
 
+        let compiled = self.compile_funcs(&funcs)?;
+
+        for (func, compiled) in funcs.iter().zip(&compiled) {
+            self.define_func(func, compiled, package, source_map)?;
+        }
+
+        self.define_rodata()?;
+        self.static_ids = static_ids;
+
         Ok(())
     }
 
-    pub fn finish(self) -> ObjectProduct {
-        self.module.finish()
+    /// Compile functions to machine code on multiple threads, each with its own context.
+    fn compile_funcs(&mut self, funcs: &[GeneratedFunc]) -> BackendResult<Vec<CompiledCode>> {
+        let isa = self.module.isa();
+        let func_cache = self.func_cache.as_ref();
+
+        let results = parallel::map_with(
+            funcs,
+            || (codegen::Context::new(), func_cache.cloned()),
+            |(ctx, func_cache), func| {
+                ctx.clear();
+                ctx.func = func.func.clone();
+
+                // the verifier runs as part of compiling if it is enabled
+                // (any error from it is a compiler bug)
+                let mut ctrl_plane = ControlPlane::default();
+                let cached = match func_cache {
+                    Some(func_cache) => {
+                        ctx.compile_with_cache(isa, func_cache, &mut ctrl_plane)
+                            .map_err(|err| err.inner)?
+                            .1
+                    }
+                    None => {
+                        ctx.compile(isa, &mut ctrl_plane).map_err(|err| err.inner)?;
+                        false
+                    }
+                };
+
+                Ok((ctx.compiled_code().unwrap().clone(), cached))
+            },
+        );
+
+        results
+            .into_iter()
+            .map(|result: BackendResult<_>| {
+                let (compiled, cached) = result?;
+
+                if cached {
+                    self.stats.funcs_cached += 1;
+                } else {
+                    self.stats.funcs_compiled += 1;
+                }
+
+                Ok(compiled)
+            })
+            .collect()
     }
 
-    fn gen_func_decl(
+    /// Define a compiled function in the module, along with its debug information.
+    fn define_func(
         &mut self,
-        func_decl: &FuncDecl,
-        symbols: &Symbols,
-        interner: &Interner,
+        func: &GeneratedFunc,
+        compiled: &CompiledCode,
+        package: &Package,
+        source_map: &SourceMap,
     ) -> BackendResult<()> {
-        self.module.clear_context(&mut self.ctx);
+        self.module.define_function_bytes(
+            func.clif_id,
+            &func.func,
+            u64::from(compiled.buffer.alignment),
+            compiled.code_buffer(),
+            compiled.buffer.relocs(),
+        )?;
 
-        let func_symbol = &symbols.funcs[func_decl.id];
+        if let Some(debug) = &mut self.debug {
+            debug.define_function(
+                func.clif_id,
+                &package.symbols.funcs[func.id],
+                &func.stack_slots,
+                compiled,
+                &package.symbols,
+                &package.interner,
+                source_map,
+            );
+        }
+
+        Ok(())
+    }
+
+    /// Declare all functions up front so that they can be called before they are defined.
+    ///
+    /// Executables export every function, but libraries only export functions declared with `export`.
+    fn declare_funcs(&mut self, package: &Package) -> BackendResult<KeyVec<FuncId, ClifFuncId>> {
+        let mut func_ids = KeyVec::new();
+
+        for (id, func_symbol) in package.symbols.funcs.iter().enumerate() {
+            let signature = self.func_signature(func_symbol, &package.symbols);
+
+            let linkage = if func_symbol.is_extern {
+                Linkage::Import
+            } else if func_symbol.is_exported || package.crate_type == CrateType::Bin {
+                Linkage::Export
+            } else {
+                Linkage::Local
+            };
+
+            let name = package.func_symbol(FuncId(id));
+            let id = self.module.declare_function(&name, linkage, &signature)?;
+
+            // keys are allocated in the same order as the symbols
+            let _ = func_ids.insert(id);
+        }
+
+        Ok(func_ids)
+    }
+
+    fn declare_statics(&mut self, package: &Package) -> BackendResult<KeyVec<StaticId, DataId>> {
+        let mut static_ids = KeyVec::new();
+
+        // statics can't be exported from libraries
+        let linkage = match package.crate_type {
+            CrateType::Bin => Linkage::Export,
+            CrateType::Obj | CrateType::StaticLib | CrateType::Cdylib | CrateType::Lib => {
+                Linkage::Local
+            }
+        };
+
+        for id in 0..package.symbols.statics.len() {
+            let name = package.static_symbol(StaticId(id));
+            let id = self.module.declare_data(&name, linkage, true, false)?;
+
+            // keys are allocated in the same order as the symbols
+            let _ = static_ids.insert(id);
+        }
+
+        Ok(static_ids)
+    }
+
+    fn define_static(
+        &mut self,
+        data_id: DataId,
+        static_id: StaticId,
+        package: &Package,
+    ) -> BackendResult<()> {
+        let static_symbol = &package.symbols.statics[static_id];
+
+        // evaluated before codegen
+        let value = static_symbol.value.unwrap();
+
+        let layout = static_symbol.ty.layout(self.ptr_ty);
+        let endianness = self.module.isa().endianness();
+
+        let mut description = DataDescription::new();
+        description.set_align(layout.align() as u64);
+
+        let mut bytes = vec![0; layout.size()];
 
-        match func_symbol.ret_ty.layout() {
-            TypeLayout::Integer(ty) => {
-                self.ctx.func.signature.returns.push(AbiParam::new(ty));
+        match value {
+            Constant::Str(istr) => {
+                let s = &package.interner[istr];
+
+                let rodata_id = self.rodata.data_id(&mut self.module, s.as_bytes());
+                let rodata = self
+                    .module
+                    .declare_data_in_data(rodata_id, &mut description);
+                description.write_data_addr(0, rodata, 0);
+
+                let len_offset = slice_len_offset(self.ptr_ty);
+                write_int(&mut bytes[len_offset..], s.len() as u64, endianness);
+            }
+
+            Constant::F32(f) => write_int(&mut bytes, f.get().to_bits() as u64, endianness),
+            Constant::F64(f) => write_int(&mut bytes, f.get().to_bits(), endianness),
+
+            Constant::Void => {}
+
+            _ => write_int(&mut bytes, value.as_imm().unwrap() as u64, endianness),
+        }
+
+        description.define(bytes.into_boxed_slice());
+        self.module.define_data(data_id, &description)?;
+
+        Ok(())
+    }
+
+    /// Aggregates are passed by address, and returned through a pointer
+    /// passed as a hidden first parameter.
+    fn func_signature(&self, func_symbol: &FunctionSymbol, symbols: &Symbols) -> Signature {
+        let mut signature = self.module.make_signature();
+
+        match func_symbol.ret_ty.layout(self.ptr_ty) {
+            TypeLayout::Scalar(ty) => {
+                signature.returns.push(AbiParam::new(ty));
+            }
+            TypeLayout::Aggregate { .. } => {
+                signature.params.push(AbiParam::new(self.ptr_ty));
             }
             TypeLayout::Void | TypeLayout::Never => {}
         }
 
-        let mut builder = FunctionBuilder::new(&mut self.ctx.func, &mut self.builder_ctx);
+        for &param in &func_symbol.params {
+            match symbols.locals[param].ty.layout(self.ptr_ty) {
+                TypeLayout::Scalar(ty) => {
+                    signature.params.push(AbiParam::new(ty));
+                }
+                TypeLayout::Aggregate { .. } => {
+                    signature.params.push(AbiParam::new(self.ptr_ty));
+                }
+                TypeLayout::Void | TypeLayout::Never => {}
+            }
+        }
+
+        signature
+    }
+
+    fn gen_func_decl(
+        &mut self,
+        func_decl: &FuncDecl,
+        package: &Package,
+        func_ids: &KeyVec<FuncId, ClifFuncId>,
+        static_ids: &KeyVec<StaticId, DataId>,
+        source_map: &SourceMap,
+    ) -> GeneratedFunc {
+        let symbols = &package.symbols;
+        let func_symbol = &symbols.funcs[func_decl.id];
+
+        let mut func = Function::new();
+        func.signature = self.func_signature(func_symbol, symbols);
+
+        let mut builder = FunctionBuilder::new(&mut func, &mut self.builder_ctx);
 
         let entry_block = builder.create_block();
         builder.append_block_params_for_function_params(entry_block);
         builder.switch_to_block(entry_block);
         builder.seal_block(entry_block); // no predecessors
 
+        let mut param_values = builder.block_params(entry_block).to_vec().into_iter();
+
+        let ret_addr = match func_symbol.ret_ty.layout(self.ptr_ty) {
+            TypeLayout::Aggregate { .. } => param_values.next(),
+            _ => None,
+        };
+
         // function codegen
-        let mut func_codegen = FuncCodegen { builder, symbols };
+        let mut func_codegen = FuncCodegen {
+            builder,
+            symbols,
+            interner: &package.interner,
+
+            module: &mut self.module,
+            func_ids,
+            static_ids,
+            rodata: &mut self.rodata,
+            source_map,
+
+            ptr_ty: self.ptr_ty,
+            bounds_checks: self.bounds_checks,
+
+            ret_addr,
+            stack_slots: HashMap::new(),
+
+            debug: self.debug.as_mut(),
+            srcloc: SourceLoc::default(),
+        };
+
+        for &param in &func_symbol.params {
+            let value = match symbols.locals[param].ty.layout(self.ptr_ty) {
+                // there is a block parameter for every integer or aggregate parameter
+                TypeLayout::Scalar(ty) => RValue::Value(param_values.next().unwrap(), ty),
+                TypeLayout::Aggregate { .. } => RValue::Memory(param_values.next().unwrap()),
+                TypeLayout::Void | TypeLayout::Never => RValue::Void,
+            };
+
+            func_codegen.declare_local(param, value);
+        }
 
         if let ControlFlow::Continue(val) = func_codegen.gen_block_expr(&func_decl.block) {
-            match val {
-                RValue::Value(val, _) => {
-                    func_codegen.builder.ins().return_(&[val]);
-                }
-                RValue::Void => {
-                    func_codegen.builder.ins().return_(&[]);
-                }
-            }
+            func_codegen.gen_return(val, &func_symbol.ret_ty);
         }
 
+        let stack_slots = std::mem::take(&mut func_codegen.stack_slots);
         func_codegen.builder.finalize();
 
-        // Any error returned here is a compiler bug.
-        // TODO: should there be a feature flag for stuff like this?
-        verify_function(&self.ctx.func, self.module.isa()).expect("function verification failed");
+        GeneratedFunc {
+            id: func_decl.id,
+            clif_id: func_ids[func_decl.id],
+            func,
+            stack_slots,
+        }
+    }
+
+    fn define_rodata(&mut self) -> BackendResult<()> {
+        for (id, bytes) in self.rodata.undefined.drain(..) {
+            let mut description = DataDescription::new();
+            description.define(bytes);
 
-        // declare and define in module (not final)
-        let name = &interner[func_symbol.ident.istr];
-        let id = self
-            .module
-            .declare_function(name, Linkage::Export, &self.ctx.func.signature)?;
-        self.module.define_function(id, &mut self.ctx)?;
+            self.module.define_data(id, &description)?;
+        }
 
         Ok(())
     }
 }
 
+/// The Cranelift IR of a function, before it is compiled.
+struct GeneratedFunc {
+    id: FuncId,
+    clif_id: ClifFuncId,
+    func: Function,
+
+    /// The stack slots of locals, for debug information.
+    stack_slots: HashMap<LocalId, StackSlot>,
+}
+
+/// Read-only data objects, deduplicated by their contents.
+#[derive(Default)]
+struct Rodata {
+    ids: HashMap<Box<[u8]>, DataId>,
+
+    /// Objects that have been declared but not yet defined.
+    undefined: Vec<(DataId, Box<[u8]>)>,
+}
+
+impl Rodata {
+    /// Get the ID of a read-only data object with the given contents.
+    fn data_id(&mut self, module: &mut impl Module, bytes: &[u8]) -> DataId {
+        if let Some(&id) = self.ids.get(bytes) {
+            return id;
+        }
+
+        let id = module
+            .declare_anonymous_data(false, false)
+            .expect("declaring anonymous data can't fail");
+
+        self.ids.insert(bytes.into(), id);
+        self.undefined.push((id, bytes.into()));
+
+        id
+    }
+}
+
+/// Write the low bytes of an integer into a buffer of the same size.
+fn write_int(buf: &mut [u8], value: u64, endianness: Endianness) {
+    let len = buf.len();
+    match endianness {
+        Endianness::Little => buf.copy_from_slice(&value.to_le_bytes()[..len]),
+        Endianness::Big => buf.copy_from_slice(&value.to_be_bytes()[8 - len..]),
+    }
+}
+
 enum RValue {
     Value(Value, Type),
+
+    /// An aggregate stored in memory at the given address.
+    Memory(Value),
+
     Void,
 }
 
-struct FuncCodegen<'a> {
+/// A memory location that can be assigned to.
+enum Place {
+    /// A local that lives in a variable.
+    Local(LocalId),
+
+    /// A location in memory.
+    Addr(Value),
+}
+
+struct FuncCodegen<'a, M: Module> {
     builder: FunctionBuilder<'a>,
     symbols: &'a Symbols,
+    interner: &'a Interner,
+
+    module: &'a mut M,
+    func_ids: &'a KeyVec<FuncId, ClifFuncId>,
+    static_ids: &'a KeyVec<StaticId, DataId>,
+    rodata: &'a mut Rodata,
+    source_map: &'a SourceMap,
+
+    ptr_ty: Type,
+    bounds_checks: bool,
+
+    /// Where to write the return value, if it is an aggregate.
+    ret_addr: Option<Value>,
+
+    /// Stack slots for locals that live in memory.
+    stack_slots: HashMap<LocalId, StackSlot>,
+
+    debug: Option<&'a mut DebugContext>,
+    /// The source location of the expression being generated, if there is debug info.
+    srcloc: SourceLoc,
 }
 
-impl FuncCodegen<'_> {
+impl<M: Module> FuncCodegen<'_, M> {
+    fn declare_local(&mut self, local: LocalId, value: RValue) {
+        let local_symbol = &self.symbols.locals[local];
+        let layout = local_symbol.ty.layout(self.ptr_ty);
+
+        // with debug info, locals are kept in memory so that debuggers can find them
+        let in_memory = match layout {
+            TypeLayout::Aggregate { .. } => true,
+            TypeLayout::Scalar(_) => local_symbol.address_taken || self.debug.is_some(),
+            TypeLayout::Void | TypeLayout::Never => false,
+        };
+
+        if in_memory {
+            let slot = self.create_stack_slot(&layout);
+            self.stack_slots.insert(local, slot);
+
+            let addr = self.builder.ins().stack_addr(self.ptr_ty, slot, 0);
+            self.store(value, addr, &layout);
+        } else if let RValue::Value(value, ty) = value {
+            let var = Variable::new(local.0);
+
+            self.builder.declare_var(var, ty);
+            self.builder.def_var(var, value);
+        }
+    }
+
+    fn create_stack_slot(&mut self, layout: &TypeLayout) -> StackSlot {
+        // Cranelift aligns stack slots based on their size, so make sure
+        // the size is a multiple of the alignment.
+        let size =
+            u32::try_from(layout.stride()).expect("type sizes should be checked before codegen");
+
+        self.builder
+            .create_sized_stack_slot(StackSlotData::new(StackSlotKind::ExplicitSlot, size))
+    }
+
     fn gen_stmt(&mut self, stmt: &Stmt) -> ControlFlow<UnreachableCode> {
         match stmt {
             Stmt::Assign { local, expr } => self.gen_assignment_stmt(*local, expr),
+            Stmt::Store { place, expr } => self.gen_store_stmt(place, expr),
             Stmt::Drop(expr) => {
                 // just ignore the generated value
                 let _ = self.gen_expr(expr)?;
@@ -144,26 +679,32 @@ impl FuncCodegen<'_> {
     }
 
     fn gen_assignment_stmt(&mut self, local: LocalId, expr: &Expr) -> ControlFlow<UnreachableCode> {
-        let var = Variable::new(local.0);
+        let value = self.gen_expr(expr)?;
+        self.declare_local(local, value);
+
+        Continue(())
+    }
+
+    fn gen_store_stmt(&mut self, place: &Expr, expr: &Expr) -> ControlFlow<UnreachableCode> {
+        // like Rust, evaluate the value before the place
+        let value = self.gen_expr(expr)?;
+        let layout = place.ty.as_ref().unwrap().layout(self.ptr_ty);
 
-        match self.gen_expr(expr)? {
-            RValue::Value(value, ty) => {
-                self.builder.declare_var(var, ty);
-                self.builder.def_var(var, value);
+        match self.gen_place(place)? {
+            Place::Local(local) => {
+                if let RValue::Value(value, _) = value {
+                    self.builder.def_var(Variable::new(local.0), value);
+                }
             }
-            RValue::Void => {}
+            Place::Addr(addr) => self.store(value, addr, &layout),
         }
 
         Continue(())
     }
 
     fn gen_return_stmt(&mut self, expr: &Expr) -> ControlFlow<UnreachableCode> {
-        match self.gen_expr(expr)? {
-            RValue::Value(val, _ty) => {
-                self.builder.ins().return_(&[val]);
-            }
-            RValue::Void => {}
-        }
+        let value = self.gen_expr(expr)?;
+        self.gen_return(value, expr.ty.as_ref().unwrap());
 
         let after_return = self.builder.create_block();
         self.builder.switch_to_block(after_return);
@@ -173,26 +714,169 @@ impl FuncCodegen<'_> {
         Break(UnreachableCode)
     }
 
+    fn gen_return(&mut self, value: RValue, ty: &IrType) {
+        match value {
+            RValue::Value(val, _) => {
+                self.builder.ins().return_(&[val]);
+            }
+            RValue::Memory(addr) => {
+                // aggregates are returned through a pointer given by the caller
+                let ret_addr = self.ret_addr.unwrap();
+                self.copy(ret_addr, addr, &ty.layout(self.ptr_ty));
+
+                self.builder.ins().return_(&[]);
+            }
+            RValue::Void => {
+                self.builder.ins().return_(&[]);
+            }
+        }
+    }
+
+    fn gen_unreachable(&mut self) -> UnreachableCode {
+        self.builder.ins().trap(TrapCode::UnreachableCodeReached);
+
+        let after_trap = self.builder.create_block();
+        self.builder.switch_to_block(after_trap);
+        self.builder.seal_block(after_trap); // nothing jumps here, dead code
+
+        UnreachableCode
+    }
+
     fn gen_expr(&mut self, expr: &Expr) -> ControlFlow<UnreachableCode, RValue> {
+        let Some(debug) = &mut self.debug else {
+            return self.gen_expr_kind(expr);
+        };
+
+        // attribute instructions to the innermost expression that they belong to
+        let outer_loc = self.srcloc;
+        self.srcloc = debug.source_loc(expr.span);
+        self.builder.set_srcloc(self.srcloc);
+
+        let value = self.gen_expr_kind(expr);
+
+        self.srcloc = outer_loc;
+        self.builder.set_srcloc(outer_loc);
+        value
+    }
+
+    fn gen_expr_kind(&mut self, expr: &Expr) -> ControlFlow<UnreachableCode, RValue> {
         match &expr.kind {
             ExprKind::Constant(constant) => Continue(self.gen_constant_expr(constant)),
             ExprKind::Var(local) => Continue(self.gen_var_expr(*local)),
+            ExprKind::Const(id) => {
+                // evaluated before codegen
+                let value = self.symbols.consts[*id].value.unwrap();
+                Continue(self.gen_constant_expr(&value))
+            }
+            ExprKind::Static(_) => match self.gen_place(expr)? {
+                Place::Addr(addr) => self.load(addr, expr.ty.as_ref().unwrap()),
+                Place::Local(_) => unreachable!("statics are always in memory"),
+            },
             ExprKind::UnOp { op, expr } => self.gen_unop_expr(*op, expr),
             ExprKind::BinOp { op, lhs, rhs } => self.gen_binop_expr(*op, lhs, rhs),
+            ExprKind::AddressOf(place) => self.gen_address_of_expr(place),
+            ExprKind::Deref(ptr) => self.gen_deref_expr(ptr, expr.ty.as_ref().unwrap()),
+            ExprKind::Cast { expr, ty } => self.gen_cast_expr(expr, ty),
+            ExprKind::Call { func, args } => self.gen_call_expr(*func, args),
+            ExprKind::Array(elements) => self.gen_array_expr(elements, expr.ty.as_ref().unwrap()),
+            ExprKind::Index { .. } => match self.gen_place(expr)? {
+                Place::Addr(addr) => self.load(addr, expr.ty.as_ref().unwrap()),
+                Place::Local(_) => unreachable!("array elements are always in memory"),
+            },
+            ExprKind::Len(array) => self.gen_len_expr(array),
             ExprKind::Block(block) => self.gen_block_expr(block),
             ExprKind::If { cond, then, else_ } => {
-                self.gen_if_expr(cond, then, else_.as_deref(), expr.ty.unwrap())
+                self.gen_if_expr(cond, then, else_.as_deref(), expr.ty.as_ref().unwrap())
             }
         }
     }
 
+    fn gen_place(&mut self, expr: &Expr) -> ControlFlow<UnreachableCode, Place> {
+        match &expr.kind {
+            ExprKind::Var(local) => match self.stack_slots.get(local) {
+                Some(&slot) => {
+                    let addr = self.builder.ins().stack_addr(self.ptr_ty, slot, 0);
+                    Continue(Place::Addr(addr))
+                }
+                None => Continue(Place::Local(*local)),
+            },
+
+            ExprKind::Static(id) => {
+                let global = self
+                    .module
+                    .declare_data_in_func(self.static_ids[*id], self.builder.func);
+                let addr = self.builder.ins().global_value(self.ptr_ty, global);
+
+                Continue(Place::Addr(addr))
+            }
+
+            ExprKind::Deref(ptr) => match self.gen_expr(ptr)? {
+                RValue::Value(addr, _) => Continue(Place::Addr(addr)),
+                RValue::Memory(_) | RValue::Void => unreachable!(),
+            },
+
+            ExprKind::Index { base, index } => {
+                let base_ty = base.ty.as_ref().unwrap();
+                let element_layout = base_ty.element().unwrap().layout(self.ptr_ty);
+
+                // arrays and slices are aggregates, so they are always in memory
+                let RValue::Memory(base_addr) = self.gen_expr(base)? else {
+                    unreachable!();
+                };
+
+                let index_value = match self.gen_expr(index)? {
+                    RValue::Value(value, _) => value,
+                    RValue::Memory(_) | RValue::Void => unreachable!(),
+                };
+
+                let (data, len) = match base_ty {
+                    IrType::Array(_, len) => {
+                        let len = self.builder.ins().iconst(types::I64, *len as i64);
+                        (base_addr, len)
+                    }
+                    IrType::Slice(_) => self.load_slice(base_addr),
+                    _ => unreachable!(),
+                };
+
+                self.gen_bounds_check(index_value, len, expr.span);
+
+                let offset = self
+                    .builder
+                    .ins()
+                    .imul_imm(index_value, element_layout.stride() as i64);
+                let offset = self.int_cast(offset, types::I64, self.ptr_ty);
+
+                Continue(Place::Addr(self.builder.ins().iadd(data, offset)))
+            }
+
+            // checked by the type checker
+            _ => unreachable!("not a place expression"),
+        }
+    }
+
     fn gen_constant_expr(&mut self, constant: &Constant) -> RValue {
-        match constant.ty().layout() {
-            TypeLayout::Integer(ty) => {
-                // constant must have an immediate because it is an integer
-                let val = self.builder.ins().iconst(ty, constant.as_imm().unwrap());
+        match constant.ty().layout(self.ptr_ty) {
+            TypeLayout::Scalar(ty) => {
+                let val = match constant {
+                    Constant::F32(f) => self.builder.ins().f32const(f.get()),
+                    Constant::F64(f) => self.builder.ins().f64const(f.get()),
+
+                    // constant must have an immediate because it is an integer
+                    _ => self.builder.ins().iconst(ty, constant.as_imm().unwrap()),
+                };
                 RValue::Value(val, ty)
             }
+            TypeLayout::Aggregate { .. } => match constant {
+                Constant::Str(istr) => {
+                    let s = &self.interner[*istr];
+
+                    let ptr = self.gen_rodata_addr(s.as_bytes());
+                    let len = self.builder.ins().iconst(types::I64, s.len() as i64);
+
+                    self.make_slice(ptr, len, &IrType::Str)
+                }
+                _ => unreachable!(),
+            },
             TypeLayout::Void => RValue::Void,
             TypeLayout::Never => unreachable!(),
         }
@@ -202,13 +886,19 @@ impl FuncCodegen<'_> {
         // variables of type never and variables with expressions containing unreachable code will have stopped the codegen by now, so
         // we don't need to worry about them
 
-        let local_ty = self.symbols.locals[local].ty;
-        let layout = local_ty.layout();
+        let layout = self.symbols.locals[local].ty.layout(self.ptr_ty);
 
         match layout {
-            TypeLayout::Integer(ty) => {
-                let var = Variable::new(local.0);
-                RValue::Value(self.builder.use_var(var), ty)
+            TypeLayout::Scalar(ty) => match self.stack_slots.get(&local) {
+                Some(&slot) => RValue::Value(self.builder.ins().stack_load(ty, slot, 0), ty),
+                None => {
+                    let var = Variable::new(local.0);
+                    RValue::Value(self.builder.use_var(var), ty)
+                }
+            },
+            TypeLayout::Aggregate { .. } => {
+                let slot = self.stack_slots[&local];
+                RValue::Memory(self.builder.ins().stack_addr(self.ptr_ty, slot, 0))
             }
             TypeLayout::Void => RValue::Void,
             TypeLayout::Never => unreachable!(),
@@ -216,17 +906,18 @@ impl FuncCodegen<'_> {
     }
 
     fn gen_unop_expr(&mut self, op: UnOp, expr: &Expr) -> ControlFlow<UnreachableCode, RValue> {
-        let expr = match self.gen_expr(expr)? {
-            RValue::Value(value, _) => value,
-            RValue::Void => unreachable!(),
+        let (expr, ty) = match self.gen_expr(expr)? {
+            RValue::Value(value, ty) => (value, ty),
+            RValue::Memory(_) | RValue::Void => unreachable!(),
         };
 
         let val = match op {
+            UnOp::Negate if ty.is_float() => self.builder.ins().fneg(expr),
             UnOp::Negate => self.builder.ins().ineg(expr),
             UnOp::BitwiseInvert => self.builder.ins().bnot(expr),
         };
 
-        Continue(RValue::Value(val, types::I64))
+        Continue(RValue::Value(val, ty))
     }
 
     fn gen_binop_expr(
@@ -235,29 +926,368 @@ impl FuncCodegen<'_> {
         lhs: &Expr,
         rhs: &Expr,
     ) -> ControlFlow<UnreachableCode, RValue> {
-        let lhs = match self.gen_expr(lhs)? {
-            RValue::Value(value, _) => value,
-            RValue::Void => unreachable!(),
+        let lhs_ty = lhs.ty.clone().unwrap();
+        let rhs_is_ptr = rhs.ty.as_ref().and_then(IrType::pointee).is_some();
+
+        let (lhs, ty) = match self.gen_expr(lhs)? {
+            RValue::Value(value, ty) => (value, ty),
+            RValue::Memory(_) | RValue::Void => unreachable!(),
         };
 
         let rhs = match self.gen_expr(rhs)? {
             RValue::Value(value, _) => value,
-            RValue::Void => unreachable!(),
+            RValue::Memory(_) | RValue::Void => unreachable!(),
         };
 
+        if op.is_comparison() {
+            return Continue(self.gen_comparison(op, &lhs_ty, lhs, rhs));
+        }
+
+        if let Some(pointee) = lhs_ty.pointee() {
+            return Continue(self.gen_ptr_arithmetic(op, pointee, lhs, rhs, rhs_is_ptr));
+        }
+
         let ins = self.builder.ins();
+        let val = if ty.is_float() {
+            match op {
+                BinOp::Add => ins.fadd(lhs, rhs),
+                BinOp::Sub => ins.fsub(lhs, rhs),
+                BinOp::Mul => ins.fmul(lhs, rhs),
+                BinOp::Div => ins.fdiv(lhs, rhs),
+                _ => unreachable!("invalid float operation"),
+            }
+        } else {
+            match op {
+                BinOp::Add => ins.iadd(lhs, rhs),
+                BinOp::Sub => ins.isub(lhs, rhs),
+                BinOp::Mul => ins.imul(lhs, rhs),
+                BinOp::Div => ins.sdiv(lhs, rhs),
+                BinOp::Mod => ins.srem(lhs, rhs),
+                BinOp::BitwiseAnd => ins.band(lhs, rhs),
+                BinOp::BitwiseOr => ins.bor(lhs, rhs),
+                BinOp::BitwiseXor => ins.bxor(lhs, rhs),
+                _ => unreachable!(),
+            }
+        };
+
+        Continue(RValue::Value(val, ty))
+    }
+
+    fn gen_comparison(&mut self, op: BinOp, ty: &IrType, lhs: Value, rhs: Value) -> RValue {
+        let val = if ty.is_float() {
+            let cc = match op {
+                BinOp::Eq => FloatCC::Equal,
+                BinOp::NotEq => FloatCC::NotEqual,
+                BinOp::Gt => FloatCC::GreaterThan,
+                BinOp::Lt => FloatCC::LessThan,
+                BinOp::GtEq => FloatCC::GreaterThanOrEqual,
+                BinOp::LtEq => FloatCC::LessThanOrEqual,
+                _ => unreachable!("not a comparison"),
+            };
+
+            self.builder.ins().fcmp(cc, lhs, rhs)
+        } else {
+            // only `i64` is signed
+            let signed = *ty == IrType::I64;
+
+            let cc = match (op, signed) {
+                (BinOp::Eq, _) => IntCC::Equal,
+                (BinOp::NotEq, _) => IntCC::NotEqual,
+                (BinOp::Gt, true) => IntCC::SignedGreaterThan,
+                (BinOp::Lt, true) => IntCC::SignedLessThan,
+                (BinOp::GtEq, true) => IntCC::SignedGreaterThanOrEqual,
+                (BinOp::LtEq, true) => IntCC::SignedLessThanOrEqual,
+                (BinOp::Gt, false) => IntCC::UnsignedGreaterThan,
+                (BinOp::Lt, false) => IntCC::UnsignedLessThan,
+                (BinOp::GtEq, false) => IntCC::UnsignedGreaterThanOrEqual,
+                (BinOp::LtEq, false) => IntCC::UnsignedLessThanOrEqual,
+                _ => unreachable!("not a comparison"),
+            };
+
+            self.builder.ins().icmp(cc, lhs, rhs)
+        };
+
+        RValue::Value(val, types::I8)
+    }
+
+    fn gen_ptr_arithmetic(
+        &mut self,
+        op: BinOp,
+        pointee: &IrType,
+        lhs: Value,
+        rhs: Value,
+        rhs_is_ptr: bool,
+    ) -> RValue {
+        // Pointers to zero-sized types are offset in bytes.
+        let elem_size = pointee.layout(self.ptr_ty).stride().max(1) as i64;
+
+        if rhs_is_ptr {
+            // pointer difference, in elements
+            let diff = self.builder.ins().isub(lhs, rhs);
+            let diff = self.builder.ins().sdiv_imm(diff, elem_size);
+
+            let diff = if self.ptr_ty.bits() < types::I64.bits() {
+                self.builder.ins().sextend(types::I64, diff)
+            } else {
+                diff
+            };
+
+            return RValue::Value(diff, types::I64);
+        }
+
+        let offset = self.builder.ins().imul_imm(rhs, elem_size);
+        let offset = self.int_cast(offset, types::I64, self.ptr_ty);
+
         let val = match op {
-            BinOp::Add => ins.iadd(lhs, rhs),
-            BinOp::Sub => ins.isub(lhs, rhs),
-            BinOp::Mul => ins.imul(lhs, rhs),
-            BinOp::Div => ins.sdiv(lhs, rhs),
-            BinOp::Mod => ins.srem(lhs, rhs),
-            BinOp::BitwiseAnd => ins.band(lhs, rhs),
-            BinOp::BitwiseOr => ins.bor(lhs, rhs),
-            BinOp::BitwiseXor => ins.bxor(lhs, rhs),
+            BinOp::Add => self.builder.ins().iadd(lhs, offset),
+            BinOp::Sub => self.builder.ins().isub(lhs, offset),
+            _ => unreachable!("invalid pointer arithmetic"),
         };
 
-        Continue(RValue::Value(val, types::I64))
+        RValue::Value(val, self.ptr_ty)
+    }
+
+    fn gen_address_of_expr(&mut self, place: &Expr) -> ControlFlow<UnreachableCode, RValue> {
+        match self.gen_place(place)? {
+            Place::Addr(addr) => Continue(RValue::Value(addr, self.ptr_ty)),
+
+            // locals whose address is taken always have a stack slot
+            Place::Local(_) => unreachable!("local without stack slot has its address taken"),
+        }
+    }
+
+    fn gen_deref_expr(&mut self, ptr: &Expr, ty: &IrType) -> ControlFlow<UnreachableCode, RValue> {
+        let addr = match self.gen_expr(ptr)? {
+            RValue::Value(value, _) => value,
+            RValue::Memory(_) | RValue::Void => unreachable!(),
+        };
+
+        self.load(addr, ty)
+    }
+
+    fn gen_cast_expr(&mut self, expr: &Expr, ty: &IrType) -> ControlFlow<UnreachableCode, RValue> {
+        let from_ty = expr.ty.as_ref().unwrap();
+        let value = self.gen_expr(expr)?;
+
+        match (value, from_ty, ty) {
+            // array pointer to slice
+            (RValue::Value(ptr, _), IrType::Ptr(array), IrType::Slice(_)) => {
+                let IrType::Array(_, len) = **array else {
+                    unreachable!();
+                };
+
+                let len = self.builder.ins().iconst(types::I64, len as i64);
+                Continue(self.make_slice(ptr, len, ty))
+            }
+
+            // slice or string to element pointer
+            (RValue::Memory(addr), IrType::Slice(_) | IrType::Str, IrType::Ptr(_)) => {
+                let (ptr, _) = self.load_slice(addr);
+                Continue(RValue::Value(ptr, self.ptr_ty))
+            }
+
+            (RValue::Value(val, from), _, _) => match ty.layout(self.ptr_ty) {
+                TypeLayout::Scalar(to) => {
+                    Continue(RValue::Value(self.scalar_cast(val, from, to), to))
+                }
+                _ => unreachable!("invalid cast"),
+            },
+
+            // casts to the same type
+            (value @ (RValue::Memory(_) | RValue::Void), _, _) => Continue(value),
+        }
+    }
+
+    /// Convert between scalar types. Integers are treated as signed
+    /// when converting to or from floats, which saturate when converted to integers.
+    fn scalar_cast(&mut self, val: Value, from: Type, to: Type) -> Value {
+        match (from.is_float(), to.is_float()) {
+            (false, false) => self.int_cast(val, from, to),
+            (false, true) => self.builder.ins().fcvt_from_sint(to, val),
+            (true, false) => self.builder.ins().fcvt_to_sint_sat(to, val),
+
+            (true, true) => match from.bits().cmp(&to.bits()) {
+                std::cmp::Ordering::Less => self.builder.ins().fpromote(to, val),
+                std::cmp::Ordering::Greater => self.builder.ins().fdemote(to, val),
+                std::cmp::Ordering::Equal => val,
+            },
+        }
+    }
+
+    /// Convert between integer types, zero-extending if needed.
+    fn int_cast(&mut self, val: Value, from: Type, to: Type) -> Value {
+        match from.bits().cmp(&to.bits()) {
+            std::cmp::Ordering::Less => self.builder.ins().uextend(to, val),
+            std::cmp::Ordering::Greater => self.builder.ins().ireduce(to, val),
+            std::cmp::Ordering::Equal => val,
+        }
+    }
+
+    fn gen_call_expr(
+        &mut self,
+        func: FuncId,
+        args: &[Expr],
+    ) -> ControlFlow<UnreachableCode, RValue> {
+        let ret_layout = self.symbols.funcs[func].ret_ty.layout(self.ptr_ty);
+
+        let mut arg_values = vec![];
+
+        // aggregates are returned through a pointer to memory owned by the caller
+        let ret_addr = match &ret_layout {
+            TypeLayout::Aggregate { .. } => {
+                let slot = self.create_stack_slot(&ret_layout);
+                let addr = self.builder.ins().stack_addr(self.ptr_ty, slot, 0);
+                arg_values.push(addr);
+                Some(addr)
+            }
+            _ => None,
+        };
+
+        for arg in args {
+            match self.gen_expr(arg)? {
+                // the callee copies aggregates passed to it, so just pass the address
+                RValue::Value(value, _) | RValue::Memory(value) => arg_values.push(value),
+                RValue::Void => {}
+            }
+        }
+
+        let func_ref = self
+            .module
+            .declare_func_in_func(self.func_ids[func], self.builder.func);
+
+        let call = self.builder.ins().call(func_ref, &arg_values);
+
+        match ret_layout {
+            TypeLayout::Scalar(ty) => {
+                Continue(RValue::Value(self.builder.inst_results(call)[0], ty))
+            }
+            TypeLayout::Aggregate { .. } => Continue(RValue::Memory(ret_addr.unwrap())),
+            TypeLayout::Void => Continue(RValue::Void),
+            TypeLayout::Never => Break(self.gen_unreachable()),
+        }
+    }
+
+    fn gen_array_expr(
+        &mut self,
+        elements: &[Expr],
+        ty: &IrType,
+    ) -> ControlFlow<UnreachableCode, RValue> {
+        let element_layout = ty.element().unwrap().layout(self.ptr_ty);
+
+        let slot = self.create_stack_slot(&ty.layout(self.ptr_ty));
+
+        for (i, element) in elements.iter().enumerate() {
+            let value = self.gen_expr(element)?;
+
+            let addr = self.builder.ins().stack_addr(
+                self.ptr_ty,
+                slot,
+                (i * element_layout.stride()) as i32,
+            );
+            self.store(value, addr, &element_layout);
+        }
+
+        let addr = self.builder.ins().stack_addr(self.ptr_ty, slot, 0);
+        Continue(RValue::Memory(addr))
+    }
+
+    fn gen_len_expr(&mut self, array: &Expr) -> ControlFlow<UnreachableCode, RValue> {
+        let RValue::Memory(addr) = self.gen_expr(array)? else {
+            unreachable!();
+        };
+
+        let len = match array.ty.as_ref().unwrap() {
+            IrType::Array(_, len) => self.builder.ins().iconst(types::I64, *len as i64),
+            IrType::Slice(_) | IrType::Str => self.load_slice(addr).1,
+            _ => unreachable!(),
+        };
+
+        Continue(RValue::Value(len, types::I64))
+    }
+
+    /// Trap with a message if `index` is not less than `len`, treating both as unsigned.
+    fn gen_bounds_check(&mut self, index: Value, len: Value, span: SourceSpan) {
+        if !self.bounds_checks {
+            return;
+        }
+
+        let in_bounds = self.builder.ins().icmp(IntCC::UnsignedLessThan, index, len);
+
+        let ok_block = self.builder.create_block();
+        let fail_block = self.builder.create_block();
+
+        self.builder
+            .ins()
+            .brif(in_bounds, ok_block, &[], fail_block, &[]);
+
+        // only the bounds check can jump to these blocks
+        self.builder.seal_block(ok_block);
+        self.builder.seal_block(fail_block);
+
+        self.builder.switch_to_block(fail_block);
+        self.builder.set_cold_block(fail_block);
+
+        let message = format!("{}: index out of bounds\n", self.describe_span(span));
+        self.gen_panic(&message);
+
+        self.builder.switch_to_block(ok_block);
+    }
+
+    /// Print a message to stderr and abort. Terminates the current block.
+    fn gen_panic(&mut self, message: &str) {
+        let int_ty = types::I32;
+
+        let mut write_sig = self.module.make_signature();
+        write_sig.params.push(AbiParam::new(int_ty));
+        write_sig.params.push(AbiParam::new(self.ptr_ty));
+        write_sig.params.push(AbiParam::new(self.ptr_ty));
+        write_sig.returns.push(AbiParam::new(self.ptr_ty));
+
+        let abort_sig = self.module.make_signature();
+
+        // These are provided by libc, and declaring them is idempotent.
+        let write = self
+            .module
+            .declare_function("write", Linkage::Import, &write_sig)
+            .expect("`write` declared with an incompatible signature");
+        let abort = self
+            .module
+            .declare_function("abort", Linkage::Import, &abort_sig)
+            .expect("`abort` declared with an incompatible signature");
+
+        let write = self.module.declare_func_in_func(write, self.builder.func);
+        let abort = self.module.declare_func_in_func(abort, self.builder.func);
+
+        let fd = self.builder.ins().iconst(int_ty, 2); // stderr
+        let message_addr = self.gen_rodata_addr(message.as_bytes());
+        let message_len = self.builder.ins().iconst(self.ptr_ty, message.len() as i64);
+
+        self.builder
+            .ins()
+            .call(write, &[fd, message_addr, message_len]);
+        self.builder.ins().call(abort, &[]);
+
+        // `abort` doesn't return
+        self.builder.ins().trap(TrapCode::UnreachableCodeReached);
+    }
+
+    /// Describe a span as `name:line:column` for runtime messages.
+    fn describe_span(&self, span: SourceSpan) -> String {
+        let Some(source) = self.source_map.get_source(span.source_id) else {
+            return String::from("<unknown>");
+        };
+
+        match source.byte_to_line_col(span.span.start) {
+            Some((line, col)) => format!("{}:{line}:{col}", source.name_str()),
+            None => source.name_str().to_owned(),
+        }
+    }
+
+    /// Get the address of a read-only data object with the given contents.
+    fn gen_rodata_addr(&mut self, bytes: &[u8]) -> Value {
+        let id = self.rodata.data_id(self.module, bytes);
+        let global = self.module.declare_data_in_func(id, self.builder.func);
+        self.builder.ins().global_value(self.ptr_ty, global)
     }
 
     fn gen_block_expr(&mut self, block: &IrBlock) -> ControlFlow<UnreachableCode, RValue> {
@@ -272,18 +1302,21 @@ impl FuncCodegen<'_> {
         cond: &Expr,
         then: &Expr,
         else_: Option<&Expr>,
-        ty: IrType,
+        ty: &IrType,
     ) -> ControlFlow<UnreachableCode, RValue> {
         let cond = match self.gen_expr(cond)? {
             RValue::Value(value, _) => value,
-            RValue::Void => unreachable!(),
+            RValue::Memory(_) | RValue::Void => unreachable!(),
         };
 
         let next_block = self.builder.create_block();
-        match ty.layout() {
-            TypeLayout::Integer(ty) => {
+        match ty.layout(self.ptr_ty) {
+            TypeLayout::Scalar(ty) => {
                 self.builder.append_block_param(next_block, ty);
             }
+            TypeLayout::Aggregate { .. } => {
+                self.builder.append_block_param(next_block, self.ptr_ty);
+            }
             TypeLayout::Void | TypeLayout::Never => {}
         }
 
@@ -301,7 +1334,7 @@ impl FuncCodegen<'_> {
         // then branch
         self.builder.switch_to_block(then_block);
         match self.gen_expr(then)? {
-            RValue::Value(val, _) => {
+            RValue::Value(val, _) | RValue::Memory(val) => {
                 self.builder.ins().jump(next_block, &[val]);
             }
             RValue::Void => {
@@ -313,7 +1346,7 @@ impl FuncCodegen<'_> {
         self.builder.switch_to_block(else_block);
         if let Some(else_) = else_ {
             match self.gen_expr(else_)? {
-                RValue::Value(val, _) => {
+                RValue::Value(val, _) | RValue::Memory(val) => {
                     self.builder.ins().jump(next_block, &[val]);
                 }
                 RValue::Void => {
@@ -328,12 +1361,110 @@ impl FuncCodegen<'_> {
         self.builder.seal_block(next_block);
         self.builder.switch_to_block(next_block);
 
-        let if_value = match ty.layout() {
-            TypeLayout::Integer(ty) => RValue::Value(self.builder.block_params(next_block)[0], ty),
+        let if_value = match ty.layout(self.ptr_ty) {
+            TypeLayout::Scalar(ty) => RValue::Value(self.builder.block_params(next_block)[0], ty),
+            TypeLayout::Aggregate { .. } => {
+                RValue::Memory(self.builder.block_params(next_block)[0])
+            }
             TypeLayout::Void => RValue::Void,
             TypeLayout::Never => return Break(UnreachableCode),
         };
 
         Continue(if_value)
     }
+
+    /// Load a value of the given type from memory.
+    fn load(&mut self, addr: Value, ty: &IrType) -> ControlFlow<UnreachableCode, RValue> {
+        match ty.layout(self.ptr_ty) {
+            TypeLayout::Scalar(ty) => {
+                let val = self.builder.ins().load(ty, MemFlags::new(), addr, 0);
+                Continue(RValue::Value(val, ty))
+            }
+            TypeLayout::Aggregate { .. } => Continue(RValue::Memory(addr)),
+            TypeLayout::Void => Continue(RValue::Void),
+            TypeLayout::Never => Break(self.gen_unreachable()),
+        }
+    }
+
+    /// Store a value in memory, copying it if it is an aggregate.
+    fn store(&mut self, value: RValue, addr: Value, layout: &TypeLayout) {
+        match value {
+            RValue::Value(value, _) => {
+                self.builder.ins().store(MemFlags::new(), value, addr, 0);
+            }
+            RValue::Memory(src) => self.copy(addr, src, layout),
+            RValue::Void => {}
+        }
+    }
+
+    fn copy(&mut self, dest: Value, src: Value, layout: &TypeLayout) {
+        let config = self.module.target_config();
+        let align = layout.align().min(u8::MAX as usize) as u8;
+
+        self.builder.emit_small_memory_copy(
+            config,
+            dest,
+            src,
+            layout.size() as u64,
+            align,
+            align,
+            false,
+            MemFlags::new(),
+        );
+    }
+
+    /// Build a slice from a pointer and a length, returning its address.
+    fn make_slice(&mut self, ptr: Value, len: Value, ty: &IrType) -> RValue {
+        let slot = self.create_stack_slot(&ty.layout(self.ptr_ty));
+
+        self.builder.ins().stack_store(ptr, slot, 0);
+        self.builder
+            .ins()
+            .stack_store(len, slot, slice_len_offset(self.ptr_ty) as i32);
+
+        RValue::Memory(self.builder.ins().stack_addr(self.ptr_ty, slot, 0))
+    }
+
+    /// Load the pointer and length of the slice at the given address.
+    fn load_slice(&mut self, addr: Value) -> (Value, Value) {
+        let ptr = self
+            .builder
+            .ins()
+            .load(self.ptr_ty, MemFlags::new(), addr, 0);
+        let len = self.builder.ins().load(
+            types::I64,
+            MemFlags::new(),
+            addr,
+            slice_len_offset(self.ptr_ty) as i32,
+        );
+
+        (ptr, len)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bayou_session::{CodegenOptions, OptLevel};
+    use target_lexicon::Triple;
+
+    use super::build_isa;
+
+    #[test]
+    fn verifier_is_on_unless_turned_off() {
+        for opt_level in [OptLevel::O0, OptLevel::O2] {
+            let options = CodegenOptions {
+                opt_level,
+                ..CodegenOptions::default()
+            };
+            let isa = build_isa(Triple::host(), &options, true).unwrap();
+            assert!(isa.flags().enable_verifier(), "{opt_level:?}");
+        }
+
+        let options = CodegenOptions {
+            cranelift_settings: vec![(String::from("enable_verifier"), String::from("false"))],
+            ..CodegenOptions::default()
+        };
+        let isa = build_isa(Triple::host(), &options, true).unwrap();
+        assert!(!isa.flags().enable_verifier());
+    }
 }
@@ -0,0 +1,583 @@
+use std::collections::HashMap;
+
+use bayou_interner::Interner;
+use bayou_ir::symbols::{FunctionSymbol, LocalId, Symbols};
+use bayou_ir::Type as IrType;
+use bayou_session::diagnostics::sources::{Source as _, SourceMap as _};
+use bayou_session::sourcemap::{SourceId, SourceMap, SourceSpan};
+use cranelift::codegen::ir::{Endianness, SourceLoc, StackSlot, Type};
+use cranelift::codegen::isa::TargetIsa;
+use cranelift::codegen::CompiledCode;
+use cranelift_module::FuncId as ClifFuncId;
+use cranelift_object::object::write::{Relocation, SectionId as ObjectSectionId, SymbolId};
+use cranelift_object::object::{RelocationEncoding, RelocationKind, SectionKind};
+use cranelift_object::ObjectProduct;
+use gimli::write::{
+    Address, AttributeValue, DwarfUnit, EndianVec, Expression, FileId, LineProgram, LineString,
+    Range, RangeList, Sections, UnitEntryId, Writer,
+};
+use gimli::{Encoding, Format, LineEncoding, Register, RunTimeEndian, SectionId};
+use target_lexicon::Architecture;
+
+use crate::layout::slice_len_offset;
+use crate::{BackendError, BackendResult};
+
+/// Collects DWARF debug information while functions are compiled,
+/// so that it can be written into the object file at the end.
+pub struct DebugContext {
+    dwarf: DwarfUnit,
+    endian: RunTimeEndian,
+    ptr_ty: Type,
+
+    /// The register that holds the frame pointer, which locals are located relative to.
+    frame_pointer: Option<Register>,
+
+    /// The code ranges of all functions in the unit.
+    ranges: RangeList,
+
+    files: HashMap<SourceId, FileId>,
+    types: HashMap<IrType, UnitEntryId>,
+
+    /// Cranelift source locations are indices into this.
+    spans: Vec<SourceSpan>,
+}
+
+impl DebugContext {
+    /// # Errors
+    ///
+    /// Returns an error if the target doesn't use ELF objects,
+    /// which are the only kind of object that debug information is written to.
+    pub fn new(isa: &dyn TargetIsa, package_name: &str) -> BackendResult<Self> {
+        let triple = isa.triple();
+        if triple.binary_format != target_lexicon::BinaryFormat::Elf {
+            return Err(BackendError::UnsupportedDebugInfo(triple.binary_format));
+        }
+
+        let ptr_ty = isa.pointer_type();
+        let encoding = Encoding {
+            format: Format::Dwarf32,
+            version: 4,
+            address_size: isa.pointer_bytes(),
+        };
+
+        let comp_dir = std::env::current_dir()
+            .map_or_else(|_| String::from("."), |dir| dir.display().to_string());
+
+        let mut dwarf = DwarfUnit::new(encoding);
+        dwarf.unit.line_program = LineProgram::new(
+            encoding,
+            LineEncoding::default(),
+            LineString::new(comp_dir.as_bytes(), encoding, &mut dwarf.line_strings),
+            LineString::new(package_name.as_bytes(), encoding, &mut dwarf.line_strings),
+            None,
+        );
+
+        let producer = format!("bayou {}", env!("CARGO_PKG_VERSION"));
+
+        let root = dwarf.unit.root();
+        let root = dwarf.unit.get_mut(root);
+        root.set(
+            gimli::DW_AT_producer,
+            AttributeValue::String(producer.into_bytes()),
+        );
+        // there is no language code for bayou, and C is the closest
+        root.set(
+            gimli::DW_AT_language,
+            AttributeValue::Language(gimli::DW_LANG_C99),
+        );
+        root.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(package_name.as_bytes().to_vec()),
+        );
+        root.set(
+            gimli::DW_AT_comp_dir,
+            AttributeValue::String(comp_dir.into_bytes()),
+        );
+
+        let frame_pointer = match triple.architecture {
+            Architecture::X86_64 => Some(gimli::X86_64::RBP),
+            Architecture::Aarch64(_) => Some(gimli::AArch64::X29),
+            Architecture::Riscv64(_) => Some(gimli::RiscV::S0),
+            _ => None,
+        };
+
+        Ok(Self {
+            dwarf,
+            endian: match isa.endianness() {
+                Endianness::Little => RunTimeEndian::Little,
+                Endianness::Big => RunTimeEndian::Big,
+            },
+            ptr_ty,
+            frame_pointer,
+
+            ranges: RangeList(vec![]),
+
+            files: HashMap::new(),
+            types: HashMap::new(),
+
+            spans: vec![],
+        })
+    }
+
+    /// Get a Cranelift source location that refers to a span.
+    pub fn source_loc(&mut self, span: SourceSpan) -> SourceLoc {
+        self.spans.push(span);
+        SourceLoc::new(self.spans.len() as u32 - 1)
+    }
+
+    /// Add a compiled function, along with the stack slots of its locals.
+    #[allow(clippy::too_many_arguments)]
+    pub fn define_function(
+        &mut self,
+        func_id: ClifFuncId,
+        func_symbol: &FunctionSymbol,
+        locals: &HashMap<LocalId, StackSlot>,
+        compiled: &CompiledCode,
+        symbols: &Symbols,
+        interner: &Interner,
+        source_map: &SourceMap,
+    ) {
+        let address = Address::Symbol {
+            symbol: func_id.as_u32() as usize,
+            addend: 0,
+        };
+        let size = u64::from(compiled.code_info().total_size);
+
+        self.ranges.0.push(Range::StartLength {
+            begin: address,
+            length: size,
+        });
+
+        // line table
+        let decl = self.line_col(func_symbol.ident.span, source_map);
+
+        let line_program = &mut self.dwarf.unit.line_program;
+        line_program.begin_sequence(Some(address));
+
+        if let Some((file, line, column)) = decl {
+            let row = line_program.row();
+            row.address_offset = 0;
+            row.file = file;
+            row.line = line;
+            row.column = column;
+            line_program.generate_row();
+        }
+
+        for srcloc in compiled.buffer.get_srclocs_sorted() {
+            if srcloc.loc.is_default() {
+                continue;
+            }
+
+            let span = self.spans[srcloc.loc.bits() as usize];
+            let Some((file, line, column)) = self.line_col(span, source_map) else {
+                continue;
+            };
+
+            let line_program = &mut self.dwarf.unit.line_program;
+            let row = line_program.row();
+            row.address_offset = u64::from(srcloc.start);
+            row.file = file;
+            row.line = line;
+            row.column = column;
+            line_program.generate_row();
+        }
+
+        self.dwarf.unit.line_program.end_sequence(size);
+
+        // function entry
+        let ret_ty = self.type_entry(&func_symbol.ret_ty);
+
+        let root = self.dwarf.unit.root();
+        let func_entry_id = self.dwarf.unit.add(root, gimli::DW_TAG_subprogram);
+        let func_entry = self.dwarf.unit.get_mut(func_entry_id);
+
+        let name = &interner[func_symbol.ident.istr];
+        func_entry.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(name.as_bytes().to_vec()),
+        );
+        func_entry.set(gimli::DW_AT_external, AttributeValue::Flag(true));
+        func_entry.set(gimli::DW_AT_low_pc, AttributeValue::Address(address));
+        func_entry.set(gimli::DW_AT_high_pc, AttributeValue::Udata(size));
+
+        if let Some((file, line, _)) = decl {
+            func_entry.set(
+                gimli::DW_AT_decl_file,
+                AttributeValue::FileIndex(Some(file)),
+            );
+            func_entry.set(gimli::DW_AT_decl_line, AttributeValue::Udata(line));
+        }
+
+        if let Some(ret_ty) = ret_ty {
+            func_entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(ret_ty));
+        }
+
+        // locals, in the order they were declared
+        let Some(frame_pointer) = self.frame_pointer else {
+            return;
+        };
+
+        let mut locals: Vec<_> = locals.iter().collect();
+        locals.sort_by_key(|(local, _)| local.0);
+
+        for (&local, &slot) in locals {
+            let local_symbol = &symbols.locals[local];
+
+            let Some(ty) = self.type_entry(&local_symbol.ty) else {
+                continue;
+            };
+
+            // stack slots are relative to the bottom of the fixed frame,
+            // which is `frame_size` bytes below the frame pointer
+            let offset =
+                i64::from(compiled.sized_stackslot_offsets[slot]) - i64::from(compiled.frame_size);
+
+            let mut location = Expression::new();
+            location.op_breg(frame_pointer, offset);
+
+            let tag = if func_symbol.params.contains(&local) {
+                gimli::DW_TAG_formal_parameter
+            } else {
+                gimli::DW_TAG_variable
+            };
+
+            let decl = self.line_col(local_symbol.ident.span, source_map);
+
+            let entry_id = self.dwarf.unit.add(func_entry_id, tag);
+            let entry = self.dwarf.unit.get_mut(entry_id);
+
+            let name = &interner[local_symbol.ident.istr];
+            entry.set(
+                gimli::DW_AT_name,
+                AttributeValue::String(name.as_bytes().to_vec()),
+            );
+            entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(ty));
+            entry.set(gimli::DW_AT_location, AttributeValue::Exprloc(location));
+
+            if let Some((file, line, _)) = decl {
+                entry.set(
+                    gimli::DW_AT_decl_file,
+                    AttributeValue::FileIndex(Some(file)),
+                );
+                entry.set(gimli::DW_AT_decl_line, AttributeValue::Udata(line));
+            }
+        }
+    }
+
+    /// Get the file, line and column of the start of a span.
+    fn line_col(&mut self, span: SourceSpan, source_map: &SourceMap) -> Option<(FileId, u64, u64)> {
+        let source = source_map.get_source(span.source_id)?;
+        let (line, column) = source.byte_to_line_col(span.span.start)?;
+
+        let file = *self.files.entry(span.source_id).or_insert_with(|| {
+            let name = match source.path() {
+                Some(path) => path.display().to_string(),
+                None => source.name_str().to_owned(),
+            };
+
+            let line_program = &mut self.dwarf.unit.line_program;
+            let encoding = line_program.encoding();
+            let directory = line_program.default_directory();
+
+            line_program.add_file(
+                LineString::new(name.into_bytes(), encoding, &mut self.dwarf.line_strings),
+                directory,
+                None,
+            )
+        });
+
+        Some((file, line as u64, column as u64))
+    }
+
+    /// Get the entry that describes a type, or `None` for types without values.
+    fn type_entry(&mut self, ty: &IrType) -> Option<UnitEntryId> {
+        if let Some(&id) = self.types.get(ty) {
+            return Some(id);
+        }
+
+        let root = self.dwarf.unit.root();
+
+        let id = match ty {
+            IrType::Void | IrType::Never => return None,
+
+            IrType::I64 => self.base_type("i64", gimli::DW_ATE_signed, 8),
+            IrType::F32 => self.base_type("f32", gimli::DW_ATE_float, 4),
+            IrType::F64 => self.base_type("f64", gimli::DW_ATE_float, 8),
+            IrType::Bool => self.base_type("bool", gimli::DW_ATE_boolean, 1),
+            IrType::Char => self.base_type("char", gimli::DW_ATE_UTF, 4),
+
+            IrType::Ptr(pointee) => {
+                let pointee = self.type_entry(pointee);
+                self.pointer_type(pointee)
+            }
+
+            IrType::Array(element, len) => {
+                let element = self.type_entry(element)?;
+
+                let id = self.dwarf.unit.add(root, gimli::DW_TAG_array_type);
+                self.dwarf
+                    .unit
+                    .get_mut(id)
+                    .set(gimli::DW_AT_type, AttributeValue::UnitRef(element));
+
+                let subrange = self.dwarf.unit.add(id, gimli::DW_TAG_subrange_type);
+                self.dwarf
+                    .unit
+                    .get_mut(subrange)
+                    .set(gimli::DW_AT_count, AttributeValue::Udata(*len));
+
+                id
+            }
+
+            IrType::Slice(element) => {
+                let ptr = self.type_entry(&(**element).clone().ptr())?;
+                self.slice_type(&ty.to_string(), ptr)
+            }
+
+            IrType::Str => {
+                let byte = self.base_type("u8", gimli::DW_ATE_unsigned_char, 1);
+                let ptr = self.pointer_type(Some(byte));
+                self.slice_type("str", ptr)
+            }
+        };
+
+        self.types.insert(ty.clone(), id);
+        Some(id)
+    }
+
+    /// A pointer type, where pointers to `void` have no pointee type.
+    fn pointer_type(&mut self, pointee: Option<UnitEntryId>) -> UnitEntryId {
+        let root = self.dwarf.unit.root();
+        let id = self.dwarf.unit.add(root, gimli::DW_TAG_pointer_type);
+
+        let entry = self.dwarf.unit.get_mut(id);
+        entry.set(
+            gimli::DW_AT_byte_size,
+            AttributeValue::Udata(self.ptr_ty.bytes().into()),
+        );
+
+        if let Some(pointee) = pointee {
+            entry.set(gimli::DW_AT_type, AttributeValue::UnitRef(pointee));
+        }
+
+        id
+    }
+
+    fn base_type(&mut self, name: &str, encoding: gimli::DwAte, size: u64) -> UnitEntryId {
+        let root = self.dwarf.unit.root();
+        let id = self.dwarf.unit.add(root, gimli::DW_TAG_base_type);
+
+        let entry = self.dwarf.unit.get_mut(id);
+        entry.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(name.as_bytes().to_vec()),
+        );
+        entry.set(gimli::DW_AT_encoding, AttributeValue::Encoding(encoding));
+        entry.set(gimli::DW_AT_byte_size, AttributeValue::Udata(size));
+
+        id
+    }
+
+    /// A struct with a pointer and a length, which is how slices are laid out.
+    fn slice_type(&mut self, name: &str, ptr: UnitEntryId) -> UnitEntryId {
+        let len = self.type_entry(&IrType::I64).unwrap();
+
+        let root = self.dwarf.unit.root();
+        let id = self.dwarf.unit.add(root, gimli::DW_TAG_structure_type);
+
+        let entry = self.dwarf.unit.get_mut(id);
+        entry.set(
+            gimli::DW_AT_name,
+            AttributeValue::String(name.as_bytes().to_vec()),
+        );
+
+        let len_offset = slice_len_offset(self.ptr_ty);
+        entry.set(
+            gimli::DW_AT_byte_size,
+            AttributeValue::Udata(len_offset as u64 + 8),
+        );
+
+        for (name, ty, offset) in [("ptr", ptr, 0), ("len", len, len_offset as u64)] {
+            let member = self.dwarf.unit.add(id, gimli::DW_TAG_member);
+            let member = self.dwarf.unit.get_mut(member);
+
+            member.set(
+                gimli::DW_AT_name,
+                AttributeValue::String(name.as_bytes().to_vec()),
+            );
+            member.set(gimli::DW_AT_type, AttributeValue::UnitRef(ty));
+            member.set(
+                gimli::DW_AT_data_member_location,
+                AttributeValue::Udata(offset),
+            );
+        }
+
+        id
+    }
+
+    /// Write the debug sections into the object, with relocations
+    /// for the addresses of functions and for offsets into other sections.
+    pub fn emit(mut self, product: &mut ObjectProduct) -> BackendResult<()> {
+        let ranges = self.dwarf.unit.ranges.add(self.ranges);
+
+        let root = self.dwarf.unit.root();
+        let root = self.dwarf.unit.get_mut(root);
+        root.set(
+            gimli::DW_AT_low_pc,
+            AttributeValue::Address(Address::Constant(0)),
+        );
+        root.set(gimli::DW_AT_ranges, AttributeValue::RangeListRef(ranges));
+
+        let mut sections = Sections::new(WriterRelocate::new(self.endian));
+        self.dwarf.write(&mut sections)?;
+
+        let mut section_ids: HashMap<SectionId, (ObjectSectionId, SymbolId)> = HashMap::new();
+        sections.for_each_mut(|id, section| -> BackendResult<()> {
+            if section.writer.slice().is_empty() {
+                return Ok(());
+            }
+
+            let object = &mut product.object;
+            let section_id =
+                object.add_section(vec![], id.name().as_bytes().to_vec(), SectionKind::Debug);
+            object
+                .section_mut(section_id)
+                .set_data(section.writer.take(), 1);
+
+            let symbol_id = object.section_symbol(section_id);
+            section_ids.insert(id, (section_id, symbol_id));
+
+            Ok(())
+        })?;
+
+        sections.for_each(|id, section| -> BackendResult<()> {
+            let Some(&(section_id, _)) = section_ids.get(&id) else {
+                return Ok(());
+            };
+
+            for reloc in &section.relocs {
+                let symbol = match reloc.target {
+                    RelocTarget::Section(target) => section_ids[&target].1,
+                    RelocTarget::Func(index) => {
+                        product.function_symbol(ClifFuncId::from_u32(index as u32))
+                    }
+                };
+
+                product
+                    .object
+                    .add_relocation(
+                        section_id,
+                        Relocation {
+                            offset: reloc.offset,
+                            size: reloc.size * 8,
+                            kind: RelocationKind::Absolute,
+                            encoding: RelocationEncoding::Generic,
+                            symbol,
+                            addend: reloc.addend,
+                        },
+                    )
+                    .map_err(BackendError::Object)?;
+            }
+
+            Ok(())
+        })
+    }
+}
+
+#[derive(Clone, Copy)]
+enum RelocTarget {
+    Section(SectionId),
+    /// The index of a Cranelift function ID.
+    Func(usize),
+}
+
+#[derive(Clone, Copy)]
+struct DebugReloc {
+    offset: u64,
+    size: u8,
+    target: RelocTarget,
+    addend: i64,
+}
+
+/// A writer that records relocations instead of writing addresses and
+/// section offsets, which aren't known until the object is linked.
+#[derive(Clone)]
+struct WriterRelocate {
+    writer: EndianVec<RunTimeEndian>,
+    relocs: Vec<DebugReloc>,
+}
+
+impl WriterRelocate {
+    fn new(endian: RunTimeEndian) -> Self {
+        Self {
+            writer: EndianVec::new(endian),
+            relocs: vec![],
+        }
+    }
+}
+
+impl Writer for WriterRelocate {
+    type Endian = RunTimeEndian;
+
+    fn endian(&self) -> Self::Endian {
+        self.writer.endian()
+    }
+
+    fn len(&self) -> usize {
+        self.writer.len()
+    }
+
+    fn write(&mut self, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.writer.write(bytes)
+    }
+
+    fn write_at(&mut self, offset: usize, bytes: &[u8]) -> gimli::write::Result<()> {
+        self.writer.write_at(offset, bytes)
+    }
+
+    fn write_address(&mut self, address: Address, size: u8) -> gimli::write::Result<()> {
+        match address {
+            Address::Constant(value) => self.write_udata(value, size),
+            Address::Symbol { symbol, addend } => {
+                self.relocs.push(DebugReloc {
+                    offset: self.len() as u64,
+                    size,
+                    target: RelocTarget::Func(symbol),
+                    addend,
+                });
+                self.write_udata(0, size)
+            }
+        }
+    }
+
+    fn write_offset(
+        &mut self,
+        value: usize,
+        section: SectionId,
+        size: u8,
+    ) -> gimli::write::Result<()> {
+        self.relocs.push(DebugReloc {
+            offset: self.len() as u64,
+            size,
+            target: RelocTarget::Section(section),
+            addend: value as i64,
+        });
+        self.write_udata(0, size)
+    }
+
+    fn write_offset_at(
+        &mut self,
+        offset: usize,
+        value: usize,
+        section: SectionId,
+        size: u8,
+    ) -> gimli::write::Result<()> {
+        self.relocs.push(DebugReloc {
+            offset: offset as u64,
+            size,
+            target: RelocTarget::Section(section),
+            addend: value as i64,
+        });
+        self.write_udata_at(offset, 0, size)
+    }
+}
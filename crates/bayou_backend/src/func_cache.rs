@@ -0,0 +1,56 @@
+//! Caching compiled functions between builds.
+
+use std::borrow::Cow;
+use std::fmt::Write as _;
+use std::fs;
+use std::path::PathBuf;
+
+use cranelift::codegen::incremental_cache::CacheKvStore;
+
+/// A directory of compiled functions, keyed on hashes of their Cranelift IR and
+/// the target settings, so that functions that haven't changed since the last
+/// build aren't compiled again.
+///
+/// Cached code doesn't depend on where a function is in its package, because
+/// Cranelift keeps the names of other functions and source locations out of the key.
+#[derive(Debug, Clone)]
+pub struct FunctionCache {
+    dir: PathBuf,
+}
+
+impl FunctionCache {
+    /// Use a directory as a cache, which is created when the first function is stored.
+    pub fn new(dir: impl Into<PathBuf>) -> Self {
+        Self { dir: dir.into() }
+    }
+
+    fn entry_path(&self, key: &[u8]) -> PathBuf {
+        let mut name = String::with_capacity(key.len() * 2);
+        for byte in key {
+            write!(name, "{byte:02x}").unwrap();
+        }
+
+        self.dir.join(name)
+    }
+}
+
+impl CacheKvStore for FunctionCache {
+    fn get(&self, key: &[u8]) -> Option<Cow<'_, [u8]>> {
+        fs::read(self.entry_path(key)).ok().map(Cow::Owned)
+    }
+
+    fn insert(&mut self, key: &[u8], val: Vec<u8>) {
+        // the cache is only an optimization, so a function that
+        // can't be stored is compiled again in the next build
+        if fs::create_dir_all(&self.dir).is_ok() {
+            let _ = fs::write(self.entry_path(key), val);
+        }
+    }
+}
+
+/// How many functions were compiled in a package, and how many were reused from the cache.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub struct CodegenStats {
+    pub funcs_compiled: usize,
+    pub funcs_cached: usize,
+}
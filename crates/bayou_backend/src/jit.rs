@@ -0,0 +1,39 @@
+use bayou_ir::symbols::StaticId;
+use bayou_utils::keyvec::KeyVec;
+use cranelift_jit::JITModule;
+
+/// A compiled program loaded into the memory of this process.
+pub struct JitProgram {
+    /// Only `None` while being dropped.
+    pub(crate) module: Option<JITModule>,
+    pub(crate) main: *const u8,
+    pub(crate) statics: KeyVec<StaticId, *mut u8>,
+}
+
+impl JitProgram {
+    /// Call the program's `main` function, returning its result.
+    ///
+    /// # Safety
+    ///
+    /// The program runs in this process, so nothing stops it from corrupting
+    /// memory through raw pointers, or from aborting the process.
+    pub unsafe fn run_main(&self) -> i64 {
+        let main: extern "C" fn() -> i64 = std::mem::transmute(self.main);
+        main()
+    }
+
+    /// Get a pointer to the storage of a `static` item,
+    /// which stays valid for as long as the program is alive.
+    pub fn static_ptr(&self, id: StaticId) -> *mut u8 {
+        self.statics[id]
+    }
+}
+
+impl Drop for JitProgram {
+    fn drop(&mut self) {
+        if let Some(module) = self.module.take() {
+            // SAFETY: the code can't be called after the program is dropped
+            unsafe { module.free_memory() };
+        }
+    }
+}
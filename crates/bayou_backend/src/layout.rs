@@ -1,9 +1,26 @@
-use bayou_ir::ir::Constant;
+use std::collections::HashSet;
+
+use bayou_ir::ir::{Constant, Expr, Package};
+use bayou_ir::visit::{self, Visitor};
 use bayou_ir::Type as IrType;
+use bayou_session::diagnostics::prelude::*;
+use bayou_session::sourcemap::SourceSpan;
 use cranelift::codegen::ir::{types, Type};
 
+/// The largest size of a type, since any value can be put in a stack slot
+/// and Cranelift keeps the size of stack slots in a `u32`.
+pub const MAX_SIZE: usize = u32::MAX as usize;
+
 pub enum TypeLayout {
-    Integer(Type),
+    /// An integer or float that fits in a single value.
+    Scalar(Type),
+
+    /// A type that lives in memory and is passed around by address.
+    Aggregate {
+        size: usize,
+        align: usize,
+    },
+
     Void,
     Never,
 }
@@ -11,36 +28,156 @@ pub enum TypeLayout {
 impl TypeLayout {
     pub fn size(&self) -> usize {
         match self {
-            Self::Integer(ty) => ty.bytes() as usize,
+            Self::Scalar(ty) => ty.bytes() as usize,
+            Self::Aggregate { size, .. } => *size,
             Self::Void | Self::Never => 0,
         }
     }
 
     pub fn align(&self) -> usize {
         match self {
-            Self::Integer(ty) => ty.bytes() as usize,
+            Self::Scalar(ty) => ty.bytes() as usize,
+            Self::Aggregate { align, .. } => *align,
             Self::Void | Self::Never => 1,
         }
     }
+
+    /// The distance between consecutive elements of this type in an array.
+    pub fn stride(&self) -> usize {
+        self.size().next_multiple_of(self.align())
+    }
+}
+
+/// The offset of the length field of a slice. The pointer field is at offset zero.
+pub fn slice_len_offset(ptr_ty: Type) -> usize {
+    (ptr_ty.bytes() as usize).max(8)
 }
 
 pub trait TypeExt {
-    fn layout(&self) -> TypeLayout;
+    /// Get the layout of this type, given the pointer type of the target,
+    /// or `None` if it's larger than [`MAX_SIZE`].
+    fn try_layout(&self, ptr_ty: Type) -> Option<TypeLayout>;
+
+    /// Get the layout of this type, given the pointer type of the target.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the type is too large, which [`check_layouts`] reports before codegen.
+    fn layout(&self, ptr_ty: Type) -> TypeLayout {
+        self.try_layout(ptr_ty)
+            .expect("type sizes should be checked before codegen")
+    }
 }
 
 impl TypeExt for IrType {
-    fn layout(&self) -> TypeLayout {
-        match self {
-            Self::I64 => TypeLayout::Integer(types::I64),
-            Self::Bool => TypeLayout::Integer(types::I8),
+    fn try_layout(&self, ptr_ty: Type) -> Option<TypeLayout> {
+        let layout = match self {
+            Self::I64 => TypeLayout::Scalar(types::I64),
+            Self::F32 => TypeLayout::Scalar(types::F32),
+            Self::F64 => TypeLayout::Scalar(types::F64),
+            Self::Bool => TypeLayout::Scalar(types::I8),
+            Self::Char => TypeLayout::Scalar(types::I32),
             Self::Void => TypeLayout::Void,
             Self::Never => TypeLayout::Never,
+            Self::Ptr(_) => TypeLayout::Scalar(ptr_ty),
+
+            Self::Array(element, len) => {
+                let element = element.try_layout(ptr_ty)?;
+                let size = usize::try_from(*len)
+                    .ok()
+                    .and_then(|len| element.stride().checked_mul(len))
+                    .filter(|&size| size <= MAX_SIZE)?;
+
+                TypeLayout::Aggregate {
+                    size,
+                    align: element.align(),
+                }
+            }
+
+            // pointer followed by an `i64` length
+            Self::Slice(_) | Self::Str => TypeLayout::Aggregate {
+                size: slice_len_offset(ptr_ty) + 8,
+                align: 8,
+            },
+        };
+
+        Some(layout)
+    }
+}
+
+/// A type used in a package that is too large to be laid out.
+pub struct TypeTooLarge {
+    pub ty: IrType,
+    pub span: SourceSpan,
+}
+
+impl IntoDiagnostic<()> for TypeTooLarge {
+    fn into_diagnostic(self, _cx: &()) -> Diagnostic {
+        Diagnostic::error()
+            .with_message(format!("type `{}` is too large", self.ty))
+            .with_snippet(Snippet::primary(
+                "value of this type",
+                self.span.source_id,
+                self.span.span,
+            ))
+            .with_note(format!("values can be at most {MAX_SIZE} bytes"))
+    }
+}
+
+/// Find the types in a package that are too large to be laid out, each
+/// reported once at the first place it is used.
+pub fn check_layouts(package: &Package, ptr_ty: Type) -> Vec<TypeTooLarge> {
+    let mut checker = LayoutChecker {
+        ptr_ty,
+        checked: HashSet::new(),
+        errors: vec![],
+    };
+
+    let symbols = &package.symbols;
+    for func in symbols.funcs.iter() {
+        checker.check(&func.ret_ty, func.ret_ty_span);
+    }
+    for local in symbols.locals.iter() {
+        checker.check(&local.ty, local.ty_span);
+    }
+    for global in symbols.consts.iter().chain(symbols.statics.iter()) {
+        checker.check(&global.ty, global.ty_span);
+    }
+
+    visit::walk_package(&mut checker, &package.ir);
+
+    checker.errors
+}
+
+struct LayoutChecker {
+    ptr_ty: Type,
+    checked: HashSet<IrType>,
+    errors: Vec<TypeTooLarge>,
+}
+
+impl LayoutChecker {
+    fn check(&mut self, ty: &IrType, span: SourceSpan) {
+        if self.checked.insert(ty.clone()) && ty.try_layout(self.ptr_ty).is_none() {
+            self.errors.push(TypeTooLarge {
+                ty: ty.clone(),
+                span,
+            });
         }
     }
 }
 
+impl Visitor<'_> for LayoutChecker {
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let Some(ty) = &expr.ty {
+            self.check(ty, expr.span);
+        }
+
+        visit::walk_expr(self, expr);
+    }
+}
+
 pub trait ConstantAsImm {
-    /// Get this constant as an immediate value.
+    /// Get this constant as an immediate integer value.
     ///
     /// Should be valid for its type.
     fn as_imm(&self) -> Option<i64>;
@@ -51,7 +188,8 @@ impl ConstantAsImm for Constant {
         match self {
             Constant::I64(n) => Some(*n),
             Constant::Bool(b) => Some(*b as i64),
-            Constant::Void => None,
+            Constant::Char(ch) => Some(*ch as i64),
+            Constant::F32(_) | Constant::F64(_) | Constant::Str(_) | Constant::Void => None,
         }
     }
 }
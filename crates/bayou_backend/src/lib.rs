@@ -1,38 +1,101 @@
 use bayou_ir::ir::Package;
-use bayou_session::CodegenSession;
+use bayou_session::{CodegenSession, ErrorsEmitted, Session};
 use codegen::Codegen;
+use cranelift::codegen::ir::Type;
 use cranelift_object::object::write::Object;
-use target_lexicon::Architecture;
+use target_lexicon::{Architecture, Triple};
 
+mod archive;
 mod codegen;
+mod debuginfo;
+mod func_cache;
+mod jit;
 mod layout;
 mod linker;
 
 // Re-exporting `object` here instead of using workspace dependencies
 // so that we stay in sync with the version that cranelift uses.
+pub use archive::write_archive;
 pub use cranelift_object::object;
-pub use linker::{Linker, LinkerError};
+pub use func_cache::{CodegenStats, FunctionCache};
+pub use jit::JitProgram;
+pub use linker::{Linker, LinkerError, LinkerFlavor};
 
 #[derive(thiserror::Error, Debug)]
 pub enum BackendError {
     #[error("unsupported architecture: {0}")]
     UnsupportedArch(Architecture),
 
+    #[error("invalid Cranelift setting `{name}`: {error}")]
+    InvalidSetting {
+        name: String,
+        error: cranelift::prelude::settings::SetError,
+    },
+
     #[error(transparent)]
-    Module(#[from] cranelift_module::ModuleError),
+    Module(Box<cranelift_module::ModuleError>),
+
+    #[error("debug information is not supported for {0} objects")]
+    UnsupportedDebugInfo(target_lexicon::BinaryFormat),
+
+    #[error("error writing debug information: {0}")]
+    DebugInfo(#[from] gimli::write::Error),
+
+    #[error("error writing object: {0}")]
+    Object(cranelift_object::object::write::Error),
+
+    #[error("error reading object: {0}")]
+    ReadObject(cranelift_object::object::read::Error),
 
     #[error(transparent)]
     Codegen(#[from] cranelift::codegen::CodegenError),
 }
 
+impl From<cranelift_module::ModuleError> for BackendError {
+    fn from(err: cranelift_module::ModuleError) -> Self {
+        // Boxed because `ModuleError` is very large.
+        Self::Module(Box::new(err))
+    }
+}
+
 pub type BackendResult<T> = Result<T, BackendError>;
 
+/// Report the types in a package that are too large to be laid out for
+/// `target`, which has to be checked before the package can be compiled.
+pub fn check_layouts<S: Session>(
+    session: &mut S,
+    package: &Package,
+    target: &Triple,
+) -> Result<(), ErrorsEmitted> {
+    let errors = layout::check_layouts(package, Type::triple_pointer_type(target));
+    session.report_all(errors, &())
+}
+
+/// Compile a package into an object, reusing functions from `func_cache` if there is one.
 pub fn run_codegen<S: CodegenSession>(
     session: &mut S,
     package: &Package,
-) -> BackendResult<Object<'static>> {
+    func_cache: Option<FunctionCache>,
+) -> BackendResult<(Object<'static>, CodegenStats)> {
     // TODO: refactor codegen to fit new model
-    let mut codegen = Codegen::new(session.target_triple().clone(), &package.name)?;
-    codegen.compile_package(package)?;
-    Ok(codegen.finish().object)
+    let mut codegen = Codegen::new(
+        session.target_triple().clone(),
+        &package.name,
+        session.codegen_options(),
+    )?;
+    if let Some(func_cache) = func_cache {
+        codegen = codegen.with_function_cache(func_cache);
+    }
+
+    codegen.compile_package(package, session.source_map())?;
+
+    let stats = codegen.stats();
+    Ok((codegen.finish()?.object, stats))
+}
+
+/// Compile a package into memory, ready to be run in this process.
+pub fn run_jit<S: CodegenSession>(session: &mut S, package: &Package) -> BackendResult<JitProgram> {
+    let mut codegen = Codegen::new_jit(session.codegen_options())?;
+    codegen.compile_package(package, session.source_map())?;
+    codegen.finish_jit()
 }
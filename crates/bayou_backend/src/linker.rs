@@ -1,92 +1,502 @@
-use std::ffi::OsStr;
+use std::borrow::Cow;
+use std::ffi::{OsStr, OsString};
+use std::path::{Path, PathBuf};
 use std::process::Command;
 
-use target_lexicon::{Environment, Triple};
+use target_lexicon::{Architecture, BinaryFormat, Environment, OperatingSystem, Triple};
 
 #[derive(thiserror::Error, Debug)]
 pub enum LinkerError {
-    #[error("couldn't run linker: {0}")]
-    Io(#[from] std::io::Error),
+    #[error("couldn't find linker `{0}`")]
+    NotFound(String),
+
+    #[error("couldn't run linker: {error}\ncommand: {command}")]
+    Io {
+        command: String,
+        error: std::io::Error,
+    },
 
     #[error(
-        "linker exited with code {code} and stderr output:\n{}",
+        "linker exited with code {code}\ncommand: {command}\nstderr output:\n{}",
         String::from_utf8_lossy(.stderr)
     )]
-    Exited { code: i32, stderr: Vec<u8> },
+    Exited {
+        command: String,
+        code: i32,
+        stderr: Vec<u8>,
+    },
 
     #[error(
-        "linker terminated with stderr output:\n{}",
-        String::from_utf8_lossy(.stderr))
-    ]
-    Terminated { stderr: Vec<u8> },
+        "linker terminated\ncommand: {command}\nstderr output:\n{}",
+        String::from_utf8_lossy(.stderr)
+    )]
+    Terminated { command: String, stderr: Vec<u8> },
+
+    #[error("`{linker}` can't be used directly for target `{target}`, use a C compiler instead")]
+    UnsupportedTarget { linker: String, target: Triple },
+
+    #[error("couldn't find the C runtime for target `{0}`, use a C compiler instead")]
+    NoCRuntime(Triple),
 }
 
+/// How a linker is invoked, which decides the arguments it's given.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LinkerFlavor {
+    /// A C compiler like `cc`, `gcc` or `clang`, which finds the C runtime itself.
+    Cc,
+
+    /// A linker like `ld`, `ld.lld` or `mold`, which is given the C runtime explicitly.
+    Ld,
+}
+
+impl LinkerFlavor {
+    /// Guess the flavor of a linker from its file name.
+    fn of(program: &Path) -> Self {
+        let name = program_name(program);
+
+        let is_ld = name == "ld"
+            || name == "mold"
+            || name.starts_with("ld.")
+            || name.ends_with("-ld")
+            || name.contains("-ld.");
+
+        if is_ld {
+            Self::Ld
+        } else {
+            Self::Cc
+        }
+    }
+}
+
+/// The file name of a program, without the extension of executables.
+fn program_name(program: &Path) -> String {
+    let file_name = program.file_name().unwrap_or_default().to_string_lossy();
+    file_name
+        .strip_suffix(std::env::consts::EXE_SUFFIX)
+        .unwrap_or(&file_name)
+        .to_owned()
+}
+
+/// The linkers tried by [`Linker::detect`] for the host, in order of preference.
+const DETECTED_LINKERS: &[&str] = &["cc", "clang", "gcc", "ld.lld"];
+
 #[derive(Debug, Clone)]
-pub enum Linker {
-    Gcc,
-    Custom(String, Vec<String>),
+pub struct Linker {
+    program: PathBuf,
+    flavor: LinkerFlavor,
+    args: Vec<String>,
+    sysroot: Option<PathBuf>,
+    target: Triple,
 }
 
 impl Linker {
+    /// Find an installed linker for the target.
+    ///
+    /// For the host, this is the first of `cc`, `clang`, `gcc` and `ld.lld`. For other
+    /// targets, it's a cross compiler like `aarch64-linux-gnu-gcc`, or else `clang`
+    /// or `ld.lld`, which need a sysroot for the target's C runtime.
     pub fn detect(target: &Triple) -> Option<Self> {
-        let host = Triple::host();
+        if target == &Triple::host() {
+            return DETECTED_LINKERS
+                .iter()
+                .find_map(|name| find_program(Path::new(name)))
+                .map(|program| Self::new(program, target.clone()));
+        }
 
-        if target != &host {
-            return None;
+        // cross compilers are prefixed with the full or Debian style target
+        let prefixes = std::iter::once(target.to_string()).chain(multiarch(target));
+
+        for prefix in prefixes {
+            for name in ["gcc", "cc"] {
+                if let Some(program) = find_program(Path::new(&format!("{prefix}-{name}"))) {
+                    return Some(Self::new(program, target.clone()));
+                }
+            }
         }
 
-        if host.environment == Environment::Gnu {
-            Some(Self::Gcc)
-        } else {
-            None
+        if let Some(clang) = find_program(Path::new("clang")) {
+            return Some(Self::new(clang, target.clone()));
         }
+
+        find_program(Path::new("ld.lld")).map(|program| Self::new(program, target.clone()))
+    }
+
+    /// Select a linker by name, which is looked up in `PATH`, or by path.
+    ///
+    /// `lld` is an alias for `ld.lld`, since the generic driver can't be run by that name.
+    pub fn from_name(name: &str, target: &Triple) -> Result<Self, LinkerError> {
+        let name = match name {
+            "lld" => "ld.lld",
+            name => name,
+        };
+
+        let program =
+            find_program(Path::new(name)).ok_or_else(|| LinkerError::NotFound(name.to_owned()))?;
+
+        Ok(Self::new(program, target.clone()))
+    }
+
+    /// Clang can link for any target, so it's told which one
+    /// unless it's the host, which it links for by default.
+    fn new(program: PathBuf, target: Triple) -> Self {
+        let mut args = Vec::new();
+        if program_name(&program).starts_with("clang") && target != Triple::host() {
+            args.push(format!("--target={target}"));
+        }
+
+        Self {
+            flavor: LinkerFlavor::of(&program),
+            program,
+            args,
+            sysroot: None,
+            target,
+        }
+    }
+
+    /// Pass extra arguments to the linker, after the object files.
+    #[must_use]
+    pub fn with_args(mut self, args: impl IntoIterator<Item = String>) -> Self {
+        self.args.extend(args);
+        self
+    }
+
+    /// Link against the C runtime and libraries in a sysroot, which is
+    /// usually needed when cross compiling.
+    #[must_use]
+    pub fn with_sysroot(mut self, sysroot: PathBuf) -> Self {
+        self.sysroot = Some(sysroot);
+        self
+    }
+
+    pub fn program(&self) -> &Path {
+        &self.program
+    }
+
+    pub fn flavor(&self) -> LinkerFlavor {
+        self.flavor
     }
 
+    /// Link object files into an executable.
     pub fn link<P0: AsRef<OsStr>, P1: AsRef<OsStr>>(
         &self,
         obj_files: &[P0],
         output: P1,
     ) -> Result<(), LinkerError> {
-        let mut cmd = match self {
-            Self::Gcc => {
-                let mut cmd = Command::new("gcc");
+        self.run(obj_files, output, false)
+    }
+
+    /// Link object files into a shared library.
+    pub fn link_shared<P0: AsRef<OsStr>, P1: AsRef<OsStr>>(
+        &self,
+        obj_files: &[P0],
+        output: P1,
+    ) -> Result<(), LinkerError> {
+        self.run(obj_files, output, true)
+    }
+
+    fn run<P0: AsRef<OsStr>, P1: AsRef<OsStr>>(
+        &self,
+        obj_files: &[P0],
+        output: P1,
+        shared: bool,
+    ) -> Result<(), LinkerError> {
+        let mut cmd = self.command(obj_files, output, shared)?;
+        let command = display_command(&cmd);
+
+        let output = match cmd.output() {
+            Ok(output) => output,
+            Err(error) => return Err(LinkerError::Io { command, error }),
+        };
+
+        if output.status.success() {
+            Ok(())
+        } else {
+            match output.status.code() {
+                Some(code) => Err(LinkerError::Exited {
+                    command,
+                    code,
+                    stderr: output.stderr,
+                }),
+
+                None => Err(LinkerError::Terminated {
+                    command,
+                    stderr: output.stderr,
+                }),
+            }
+        }
+    }
+
+    /// The command that links object files, without running it.
+    fn command<P0: AsRef<OsStr>, P1: AsRef<OsStr>>(
+        &self,
+        obj_files: &[P0],
+        output: P1,
+        shared: bool,
+    ) -> Result<Command, LinkerError> {
+        let mut cmd = Command::new(&self.program);
+
+        if let Some(sysroot) = &self.sysroot {
+            let mut arg = OsString::from("--sysroot=");
+            arg.push(sysroot);
+            cmd.arg(arg);
+        }
+
+        match self.flavor {
+            LinkerFlavor::Cc => {
+                if shared {
+                    cmd.arg("-shared");
+                }
+
+                cmd.arg("-o");
+                cmd.arg(output);
+
+                cmd.args(obj_files);
+            }
+
+            LinkerFlavor::Ld if shared => {
+                let runtime = self.c_runtime()?;
+
+                cmd.arg("-shared");
 
                 cmd.arg("-o");
                 cmd.arg(output);
 
                 cmd.args(obj_files);
 
-                cmd
+                cmd.arg("-L");
+                cmd.arg(&runtime.lib_dir);
+                cmd.arg("-lc");
             }
 
-            Self::Custom(cmd, args) => {
-                let mut cmd = Command::new(cmd);
-                cmd.args(args);
+            LinkerFlavor::Ld => {
+                let runtime = self.c_runtime()?;
 
                 cmd.arg("-o");
                 cmd.arg(output);
 
+                cmd.arg("--dynamic-linker");
+                cmd.arg(&runtime.dynamic_linker);
+
+                cmd.arg(runtime.lib_dir.join("crt1.o"));
+                cmd.arg(runtime.lib_dir.join("crti.o"));
+
                 cmd.args(obj_files);
 
-                cmd
+                cmd.arg("-L");
+                cmd.arg(&runtime.lib_dir);
+                cmd.arg("-lc");
+
+                cmd.arg(runtime.lib_dir.join("crtn.o"));
             }
-        };
+        }
 
-        let output = cmd.output()?;
+        cmd.args(&self.args);
 
-        if output.status.success() {
-            Ok(())
-        } else {
-            match output.status.code() {
-                Some(code) => Err(LinkerError::Exited {
-                    code,
-                    stderr: output.stderr,
-                }),
+        Ok(cmd)
+    }
 
-                None => Err(LinkerError::Terminated {
-                    stderr: output.stderr,
-                }),
+    fn c_runtime(&self) -> Result<CRuntime, LinkerError> {
+        CRuntime::find(&self.target, self.sysroot.as_deref()).ok_or_else(|| {
+            if self.target.binary_format == BinaryFormat::Elf {
+                LinkerError::NoCRuntime(self.target.clone())
+            } else {
+                LinkerError::UnsupportedTarget {
+                    linker: self.program.display().to_string(),
+                    target: self.target.clone(),
+                }
             }
+        })
+    }
+}
+
+/// The parts of the C runtime that a linker run directly needs to be given,
+/// which a C compiler would otherwise add by itself.
+struct CRuntime {
+    dynamic_linker: PathBuf,
+    lib_dir: PathBuf,
+}
+
+impl CRuntime {
+    /// Find the C runtime of a Linux target, for glibc and musl.
+    fn find(target: &Triple, sysroot: Option<&Path>) -> Option<Self> {
+        let multiarch = multiarch(target)?;
+
+        let dynamic_linker = match (target.architecture, target.environment) {
+            (Architecture::X86_64, Environment::Gnu) => "/lib64/ld-linux-x86-64.so.2".into(),
+            (Architecture::Aarch64(_), Environment::Gnu) => "/lib/ld-linux-aarch64.so.1".into(),
+            (Architecture::Riscv64(_), Environment::Gnu) => {
+                "/lib/ld-linux-riscv64-lp64d.so.1".into()
+            }
+            (_, Environment::Musl) => {
+                let arch = multiarch.split('-').next().unwrap_or_default();
+                format!("/lib/ld-musl-{arch}.so.1").into()
+            }
+            _ => return None,
+        };
+
+        let root = sysroot.unwrap_or(Path::new("/"));
+
+        let mut lib_dirs = vec![
+            root.join("usr/lib").join(&multiarch),
+            root.join("lib").join(&multiarch),
+            // where Debian installs the C runtime for cross compilers
+            root.join("usr").join(&multiarch).join("lib"),
+        ];
+
+        // without a sysroot, these only contain the host's C runtime
+        if sysroot.is_some() || target == &Triple::host() {
+            lib_dirs.extend(["usr/lib64", "usr/lib", "lib"].map(|dir| root.join(dir)));
+        }
+
+        let lib_dir = lib_dirs
+            .into_iter()
+            .find(|dir| dir.join("crt1.o").is_file())?;
+
+        Some(Self {
+            dynamic_linker,
+            lib_dir,
+        })
+    }
+}
+
+/// The Debian style name of a Linux target, like `aarch64-linux-gnu`,
+/// which prefixes cross compilers and names library directories.
+fn multiarch(target: &Triple) -> Option<String> {
+    if target.operating_system != OperatingSystem::Linux
+        || target.environment == Environment::Unknown
+    {
+        return None;
+    }
+
+    let arch = match target.architecture {
+        Architecture::Riscv64(_) => String::from("riscv64"),
+        arch => arch.to_string(),
+    };
+
+    Some(format!("{arch}-linux-{}", target.environment))
+}
+
+/// Find a program in `PATH`, unless it's already a path.
+fn find_program(name: &Path) -> Option<PathBuf> {
+    if name.components().count() > 1 {
+        return name.is_file().then(|| name.to_owned());
+    }
+
+    let mut file_name = name.as_os_str().to_owned();
+    file_name.push(std::env::consts::EXE_SUFFIX);
+
+    std::env::split_paths(&std::env::var_os("PATH")?)
+        .map(|dir| dir.join(&file_name))
+        .find(|path| path.is_file())
+}
+
+/// Format a command like it would be typed into a shell, to reproduce linker errors.
+fn display_command(cmd: &Command) -> String {
+    std::iter::once(cmd.get_program())
+        .chain(cmd.get_args())
+        .map(|arg| shell_quote(&arg.to_string_lossy()).into_owned())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+fn shell_quote(arg: &str) -> Cow<'_, str> {
+    let is_plain = !arg.is_empty()
+        && arg
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || "-_./=+,:@%".contains(ch));
+
+    if is_plain {
+        Cow::Borrowed(arg)
+    } else {
+        Cow::Owned(format!("'{}'", arg.replace('\'', r"'\''")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::path::Path;
+    use std::process::Command;
+
+    use target_lexicon::Triple;
+    use temp_dir::TempDir;
+
+    use super::{display_command, Linker, LinkerFlavor};
+
+    #[test]
+    fn flavor_from_name() {
+        for name in [
+            "cc",
+            "gcc",
+            "clang",
+            "/usr/bin/clang-17",
+            "x86_64-linux-gnu-gcc",
+        ] {
+            assert_eq!(
+                LinkerFlavor::of(Path::new(name)),
+                LinkerFlavor::Cc,
+                "{name}"
+            );
         }
+
+        for name in [
+            "ld",
+            "ld.lld",
+            "ld.bfd",
+            "mold",
+            "/usr/bin/ld.gold",
+            "aarch64-linux-gnu-ld",
+            "aarch64-linux-gnu-ld.bfd",
+        ] {
+            assert_eq!(
+                LinkerFlavor::of(Path::new(name)),
+                LinkerFlavor::Ld,
+                "{name}"
+            );
+        }
+    }
+
+    #[test]
+    fn command_is_quoted() {
+        let mut cmd = Command::new("cc");
+        cmd.args(["-o", "my program", "main.o", "-Wl,--defsym=x=1", "it's", ""]);
+
+        assert_eq!(
+            display_command(&cmd),
+            r"cc -o 'my program' main.o -Wl,--defsym=x=1 'it'\''s' ''"
+        );
+    }
+
+    #[test]
+    fn clang_links_for_the_target() {
+        let dir = TempDir::with_prefix("bayou_test_").unwrap();
+        let target: Triple = "aarch64-unknown-linux-gnu".parse().unwrap();
+
+        let args = |name: &str, target: &Triple| {
+            // linkers chosen by path have to exist, but aren't run
+            let program = dir.child(name);
+            std::fs::write(&program, "").unwrap();
+
+            let linker = Linker::from_name(program.to_str().unwrap(), target).unwrap();
+            let cmd = linker.command(&["main.o"], "main", false).unwrap();
+            cmd.get_args()
+                .map(|arg| arg.to_string_lossy().into_owned())
+                .collect::<Vec<_>>()
+        };
+
+        assert_eq!(
+            args("clang", &target),
+            ["-o", "main", "main.o", "--target=aarch64-unknown-linux-gnu"]
+        );
+        assert_eq!(
+            args("clang-17", &target),
+            ["-o", "main", "main.o", "--target=aarch64-unknown-linux-gnu"]
+        );
+
+        // other C compilers only link for their own target
+        assert_eq!(
+            args("aarch64-linux-gnu-gcc", &target),
+            ["-o", "main", "main.o"]
+        );
+        assert_eq!(args("clang", &Triple::host()), ["-o", "main", "main.o"]);
     }
 }
@@ -0,0 +1,548 @@
+//! The incremental compilation cache, in the build directory of the root package.
+//!
+//! A package is only compiled again if the sources of its modules, the interfaces of
+//! its dependencies or the build options changed since it was cached, and otherwise
+//! its object is reused without even parsing it.
+//!
+//! Packages that are compiled again still reuse what didn't change: the modules whose
+//! sources didn't change aren't lexed and parsed again, from a [`ModuleCache`], and the
+//! functions that didn't change aren't compiled again, from a [`FunctionCache`]. Names
+//! are still resolved and types still checked for the whole package, because those
+//! depend on every module of the package.
+//!
+//! Entries are found by hashes that are stored between builds, so they are
+//! computed with a [`StableHasher`] rather than the standard library's hasher.
+
+use std::collections::HashSet;
+use std::fs;
+use std::hash::{Hash, Hasher};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+use bayou_backend::FunctionCache;
+use bayou_frontend::{CachedModule, ParseCache};
+use bayou_ir::interface::PackageInterface;
+use bayou_session::lints::LintLevels;
+use bayou_session::sourcemap::Source;
+use bayou_session::{CodegenOptions, CrateType};
+use sha2::{Digest, Sha256};
+use target_lexicon::Triple;
+
+/// The directory of the cache, in the build directory.
+pub const CACHE_DIR: &str = "bayou-cache";
+
+/// The version of the format of cache entries, which is
+/// increased whenever entries or their keys change.
+const FORMAT_VERSION: u32 = 3;
+
+/// A hasher whose hashes are the same on every platform and with every Rust release,
+/// unlike [`DefaultHasher`](std::collections::hash_map::DefaultHasher), which is
+/// only meant for hash tables that don't outlive the program.
+///
+/// The `Hash` implementations of the standard library may still change, but then the
+/// compiler is rebuilt, which changes the compiler identity that every key includes.
+#[derive(Default, Clone)]
+pub struct StableHasher(Sha256);
+
+impl StableHasher {
+    /// Hash a value with a new hasher.
+    pub fn hash(value: &impl Hash) -> u64 {
+        let mut hasher = Self::default();
+        value.hash(&mut hasher);
+        hasher.finish()
+    }
+}
+
+impl Hasher for StableHasher {
+    fn finish(&self) -> u64 {
+        let digest = self.0.clone().finalize();
+        u64::from_le_bytes(digest[..8].try_into().unwrap())
+    }
+
+    fn write(&mut self, bytes: &[u8]) {
+        self.0.update(bytes);
+    }
+
+    // integers are written in the same byte order and size on every platform
+
+    fn write_u16(&mut self, n: u16) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u32(&mut self, n: u32) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u64(&mut self, n: u64) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_u128(&mut self, n: u128) {
+        self.write(&n.to_le_bytes());
+    }
+
+    fn write_usize(&mut self, n: usize) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i16(&mut self, n: i16) {
+        self.write_u16(n as u16);
+    }
+
+    fn write_i32(&mut self, n: i32) {
+        self.write_u32(n as u32);
+    }
+
+    fn write_i64(&mut self, n: i64) {
+        self.write_u64(n as u64);
+    }
+
+    fn write_i128(&mut self, n: i128) {
+        self.write_u128(n as u128);
+    }
+
+    fn write_isize(&mut self, n: isize) {
+        self.write_u64(n as u64);
+    }
+}
+
+/// Everything other than its sources that the compiled object of a package depends on.
+#[derive(Hash)]
+pub struct PackageInputs<'a> {
+    pub name: &'a str,
+    pub crate_type: CrateType,
+
+    /// The directory of the root module, as given to the compiler,
+    /// which is where debug information refers to the sources.
+    pub root_dir: &'a Path,
+
+    pub target: &'a Triple,
+    pub options: &'a CodegenOptions,
+    pub lint_levels: &'a LintLevels,
+
+    /// The interfaces of the dependencies, so that a package is compiled
+    /// again if the exported functions it uses change, but not if only
+    /// the bodies of those functions change.
+    pub dependencies: &'a [PackageInterface],
+}
+
+impl PackageInputs<'_> {
+    pub fn fingerprint(&self) -> u64 {
+        StableHasher::hash(self)
+    }
+}
+
+/// A module file and a hash of its contents.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct ModuleHash {
+    pub path: PathBuf,
+    pub hash: u64,
+}
+
+impl ModuleHash {
+    pub fn new(path: PathBuf, source: &str) -> Self {
+        Self {
+            path,
+            hash: StableHasher::hash(&source),
+        }
+    }
+
+    /// Hash the current contents of the module file.
+    fn read(path: &Path) -> Option<Self> {
+        let source = fs::read_to_string(path).ok()?;
+        Some(Self::new(path.to_owned(), &source))
+    }
+}
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct CacheEntry {
+    key: u64,
+
+    /// The modules of the package, which are read again to check if they changed.
+    modules: Vec<ModuleHash>,
+
+    interface: PackageInterface,
+}
+
+/// A parsed module, which is a reference to it when it is stored.
+#[derive(serde::Serialize, serde::Deserialize)]
+struct ModuleEntry<P = CachedModule> {
+    /// The module that was parsed, which is checked as well as the
+    /// name of the entry, in case the names of two entries collide.
+    module: ModuleHash,
+
+    parsed: P,
+}
+
+/// A package reused from the cache.
+pub struct CachedPackage {
+    pub object: Vec<u8>,
+    pub interface: PackageInterface,
+}
+
+#[derive(Debug, Clone)]
+pub struct BuildCache {
+    dir: PathBuf,
+
+    /// Identifies the compiler by its version and the contents of its executable,
+    /// so that the cache is invalidated when the compiler is rebuilt, even if its
+    /// version stays the same.
+    compiler: u64,
+}
+
+impl BuildCache {
+    pub fn new(build_dir: &Path) -> Self {
+        let mut hasher = StableHasher::default();
+        env!("CARGO_PKG_VERSION").hash(&mut hasher);
+
+        // if the executable can't be read, only the version identifies the compiler
+        if let Ok(mut exe) = std::env::current_exe().and_then(fs::File::open) {
+            let _ = std::io::copy(&mut exe, &mut hasher.0);
+        }
+
+        Self {
+            dir: build_dir.join(CACHE_DIR),
+            compiler: hasher.finish(),
+        }
+    }
+
+    /// The cache of compiled functions, which is shared by every package.
+    pub fn function_cache(&self) -> FunctionCache {
+        FunctionCache::new(self.dir.join("functions"))
+    }
+
+    /// The cache of parsed modules, for the package `name` with the `inputs` fingerprint.
+    ///
+    /// The modules are cached along with the inputs of their package, which include the
+    /// interfaces of its dependencies, so that modules are only reused by packages that
+    /// are compiled the same way.
+    pub fn module_cache(&self, name: &str, inputs: u64) -> ModuleCache {
+        ModuleCache {
+            dir: self.dir.join("modules").join(name),
+            key: StableHasher::hash(&(FORMAT_VERSION, self.compiler, inputs)),
+            used: Mutex::default(),
+            hits: AtomicUsize::new(0),
+            misses: AtomicUsize::new(0),
+        }
+    }
+
+    /// Get the object and interface of a package, if nothing it
+    /// depends on changed since it was cached.
+    ///
+    /// Any error reading the cache is treated as the package not being cached.
+    pub fn load(&self, name: &str, inputs: u64) -> Option<CachedPackage> {
+        let json = fs::read(self.entry_path(name)).ok()?;
+        let entry: CacheEntry = serde_json::from_slice(&json).ok()?;
+
+        let modules = entry
+            .modules
+            .iter()
+            .map(|module| ModuleHash::read(&module.path))
+            .collect::<Option<Vec<_>>>()?;
+
+        if self.key(inputs, &modules) != entry.key {
+            return None;
+        }
+
+        Some(CachedPackage {
+            object: fs::read(self.object_path(name)).ok()?,
+            interface: entry.interface,
+        })
+    }
+
+    /// Cache a compiled package, along with the modules it was compiled from.
+    pub fn store(
+        &self,
+        name: &str,
+        inputs: u64,
+        modules: Vec<ModuleHash>,
+        object: &[u8],
+        interface: &PackageInterface,
+    ) -> std::io::Result<()> {
+        let entry_path = self.entry_path(name);
+        fs::create_dir_all(entry_path.parent().unwrap())?;
+
+        // remove the old entry first, so that it can't refer to the new object
+        // if writing the new entry fails
+        if entry_path.exists() {
+            fs::remove_file(&entry_path)?;
+        }
+
+        fs::write(self.object_path(name), object)?;
+
+        let entry = CacheEntry {
+            key: self.key(inputs, &modules),
+            modules,
+            interface: interface.clone(),
+        };
+
+        // the types are always serializable
+        fs::write(entry_path, serde_json::to_string_pretty(&entry).unwrap())
+    }
+
+    fn key(&self, inputs: u64, modules: &[ModuleHash]) -> u64 {
+        StableHasher::hash(&(FORMAT_VERSION, self.compiler, inputs, modules))
+    }
+
+    fn entry_path(&self, name: &str) -> PathBuf {
+        self.dir.join("packages").join(format!("{name}.json"))
+    }
+
+    fn object_path(&self, name: &str) -> PathBuf {
+        self.dir.join("packages").join(format!("{name}.o"))
+    }
+}
+
+/// Modules of a package that were parsed in earlier builds, keyed on the
+/// [`ModuleHash`] of their source and the inputs of their package.
+///
+/// Only modules with a file are cached, and it counts how many modules it had.
+#[derive(Debug)]
+pub struct ModuleCache {
+    dir: PathBuf,
+    key: u64,
+
+    /// The entries that were loaded or stored, which are kept by [`Self::prune`].
+    used: Mutex<HashSet<PathBuf>>,
+
+    hits: AtomicUsize,
+    misses: AtomicUsize,
+}
+
+impl ModuleCache {
+    /// How many modules were reused from the cache.
+    pub fn hits(&self) -> usize {
+        self.hits.load(Ordering::Relaxed)
+    }
+
+    /// How many modules were looked up, whether they were in the cache or not.
+    pub fn lookups(&self) -> usize {
+        self.hits() + self.misses.load(Ordering::Relaxed)
+    }
+
+    /// Remove the entries that weren't used since the cache was created, which are
+    /// modules that changed or were removed, or that were parsed with other inputs.
+    ///
+    /// This is called once the package has been parsed, and only the entries of this
+    /// package are removed, so that packages reused as a whole keep their modules.
+    pub fn prune(&self) {
+        let Ok(entries) = fs::read_dir(&self.dir) else {
+            return;
+        };
+
+        let used = self.used.lock().unwrap();
+        for entry in entries.flatten() {
+            let path = entry.path();
+            if !used.contains(&path) {
+                // the entry is only kept for longer if it can't be removed
+                let _ = fs::remove_file(path);
+            }
+        }
+    }
+
+    fn module_hash(source: &Source) -> Option<ModuleHash> {
+        let path = source.path.as_ref()?.canonicalize().ok()?;
+        Some(ModuleHash::new(path, &source.source))
+    }
+
+    fn entry_path(&self, module: &ModuleHash) -> PathBuf {
+        let hash = StableHasher::hash(&(self.key, module));
+        self.dir.join(format!("{hash:016x}.json"))
+    }
+
+    fn try_load(&self, source: &Source) -> Option<CachedModule> {
+        let module = Self::module_hash(source)?;
+        let entry_path = self.entry_path(&module);
+        let json = fs::read(&entry_path).ok()?;
+        let entry: ModuleEntry = serde_json::from_slice(&json).ok()?;
+
+        if entry.module != module {
+            return None;
+        }
+
+        self.used.lock().unwrap().insert(entry_path);
+        Some(entry.parsed)
+    }
+}
+
+impl ParseCache for ModuleCache {
+    /// Any error reading the cache is treated as the module not being cached.
+    fn load(&self, source: &Source) -> Option<CachedModule> {
+        let parsed = self.try_load(source);
+
+        let counter = if parsed.is_some() {
+            &self.hits
+        } else {
+            &self.misses
+        };
+        counter.fetch_add(1, Ordering::Relaxed);
+
+        parsed
+    }
+
+    /// Errors writing the cache are ignored, since the module is only parsed again.
+    fn store(&self, source: &Source, parsed: &CachedModule) {
+        let Some(module) = Self::module_hash(source) else {
+            return;
+        };
+
+        let entry_path = self.entry_path(&module);
+        let entry = ModuleEntry { module, parsed };
+
+        // the types are always serializable
+        let json = serde_json::to_string(&entry).unwrap();
+        let _ = fs::create_dir_all(&self.dir).and_then(|()| fs::write(&entry_path, json));
+
+        self.used.lock().unwrap().insert(entry_path);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use bayou_frontend::{ast, CachedModule, ParseCache};
+    use bayou_interner::Interner;
+    use bayou_ir::interface::PackageInterface;
+    use bayou_session::sourcemap::Source;
+    use temp_dir::TempDir;
+
+    use super::{BuildCache, ModuleHash, StableHasher};
+
+    fn interface() -> PackageInterface {
+        PackageInterface {
+            name: String::from("math"),
+            funcs: vec![],
+        }
+    }
+
+    #[test]
+    fn changed_modules_miss() {
+        let dir = TempDir::with_prefix("bayou_test_").unwrap();
+        let cache = BuildCache::new(dir.path());
+
+        let main = dir.child("main.by");
+        let sub = dir.child("sub.by");
+        std::fs::write(&main, "submodule sub;").unwrap();
+        std::fs::write(&sub, "func f() {}").unwrap();
+
+        assert!(cache.load("math", 0).is_none());
+
+        let modules = vec![
+            ModuleHash::new(main.clone(), "submodule sub;"),
+            ModuleHash::new(sub.clone(), "func f() {}"),
+        ];
+        cache
+            .store("math", 0, modules, b"object", &interface())
+            .unwrap();
+
+        let cached = cache.load("math", 0).unwrap();
+        assert_eq!(cached.object, b"object");
+        assert_eq!(cached.interface, interface());
+
+        // other inputs, like the build options
+        assert!(cache.load("math", 1).is_none());
+        assert!(cache.load("other", 0).is_none());
+
+        std::fs::write(&sub, "func f() { 1; }").unwrap();
+        assert!(cache.load("math", 0).is_none());
+
+        std::fs::write(&sub, "func f() {}").unwrap();
+        assert!(cache.load("math", 0).is_some());
+
+        std::fs::remove_file(&sub).unwrap();
+        assert!(cache.load("math", 0).is_none());
+    }
+
+    #[test]
+    fn changed_modules_parsed_again() {
+        let dir = TempDir::with_prefix("bayou_test_").unwrap();
+        let cache = BuildCache::new(dir.path());
+
+        let source = |text: &str| Source {
+            name: String::from("main"),
+            source: text.to_owned(),
+            path: Some(dir.child("main.by")),
+        };
+        std::fs::write(dir.child("main.by"), "").unwrap();
+
+        let interner = Interner::new();
+        let f = interner.intern("f");
+        let parsed = CachedModule {
+            interner,
+            ast: ast::Module::default(),
+        };
+
+        let modules = cache.module_cache("main", 0);
+        assert!(modules.load(&source("func f() {}")).is_none());
+        modules.store(&source("func f() {}"), &parsed);
+
+        let cached = modules.load(&source("func f() {}")).unwrap();
+        assert_eq!(cached.interner.get_str(f), Some("f"));
+
+        assert!(modules.load(&source("func f() { 1; }")).is_none());
+        assert_eq!((modules.hits(), modules.lookups()), (1, 3));
+
+        // the inputs of the package, like the interfaces of its dependencies
+        assert!(cache
+            .module_cache("main", 1)
+            .load(&source("func f() {}"))
+            .is_none());
+
+        // sources without a file aren't cached
+        let no_file = Source {
+            path: None,
+            ..source("func g() {}")
+        };
+        modules.store(&no_file, &parsed);
+        assert!(modules.load(&no_file).is_none());
+    }
+
+    #[test]
+    fn unused_modules_pruned() {
+        let dir = TempDir::with_prefix("bayou_test_").unwrap();
+        let cache = BuildCache::new(dir.path());
+
+        let source = |name: &str| Source {
+            name: name.to_owned(),
+            source: String::new(),
+            path: Some(dir.child(format!("{name}.by"))),
+        };
+        let parsed = || CachedModule {
+            interner: Interner::new(),
+            ast: ast::Module::default(),
+        };
+        for name in ["main", "removed"] {
+            std::fs::write(dir.child(format!("{name}.by")), "").unwrap();
+        }
+
+        let modules = cache.module_cache("app", 0);
+        modules.store(&source("main"), &parsed());
+        modules.store(&source("removed"), &parsed());
+
+        // another package isn't affected by pruning this one
+        cache
+            .module_cache("lib", 0)
+            .store(&source("main"), &parsed());
+
+        // the next build only uses `main`
+        let modules = cache.module_cache("app", 0);
+        assert!(modules.load(&source("main")).is_some());
+        modules.prune();
+
+        let modules = cache.module_cache("app", 0);
+        assert!(modules.load(&source("main")).is_some());
+        assert!(modules.load(&source("removed")).is_none());
+        assert!(cache.module_cache("lib", 0).load(&source("main")).is_some());
+    }
+
+    #[test]
+    fn stable_hashes() {
+        // these are stored in the cache, so they must not change between builds of the compiler
+        assert_eq!(StableHasher::hash(&0u64), 0x7a0b_81a1_f570_55af);
+        assert_eq!(StableHasher::hash(&"main"), 0xd108_df9b_a68d_6540);
+        assert_eq!(
+            StableHasher::hash(&(1usize, 2i32, "a")),
+            0x1fd1_7b8c_3d38_9bc1
+        );
+    }
+}
@@ -1,6 +1,7 @@
 use std::path::PathBuf;
 
-use clap::{Parser, Subcommand};
+use bayou_session::{CodegenOptions, CrateType, OptLevel};
+use clap::{Args, Parser, Subcommand, ValueEnum};
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -13,15 +14,250 @@ pub struct Cli {
 pub enum Command {
     /// Build a program.
     Build {
-        /// The input directory.
-        input: PathBuf,
+        /// The package directory. If not specified, the package is found by
+        /// looking for `bayou.toml` in the working directory and its parents.
+        input: Option<PathBuf>,
 
-        /// The output file. If not specified, prints assembly to stdout.
+        /// The output file. If not specified, it's named after the package.
         #[arg(short, long)]
         output: Option<String>,
 
-        /// The target triple.
+        /// The target triple, overriding the target in the manifest.
         #[arg(short, long)]
         target: Option<String>,
+
+        /// The kind of output to produce, overriding the crate type in the manifest.
+        #[arg(long, value_enum)]
+        crate_type: Option<CrateTypeArg>,
+
+        /// Whether to produce the output of the crate type, or stop at its object file,
+        /// which doesn't need a linker for the target.
+        #[arg(long, value_enum, default_value = "link")]
+        emit: EmitArg,
+
+        /// Print how long each package took to compile, and what was reused from the cache.
+        #[arg(long)]
+        timings: bool,
+
+        /// Print internal information about the compiler.
+        #[arg(short = 'Z', value_enum, value_name = "OPTION")]
+        debug: Vec<DebugArg>,
+
+        #[command(flatten)]
+        codegen: CodegenArgs,
+
+        #[command(flatten)]
+        link: LinkArgs,
+    },
+
+    /// Build and run a program.
+    Run {
+        /// The package directory. If not specified, the package is found by
+        /// looking for `bayou.toml` in the working directory and its parents.
+        input: Option<PathBuf>,
+
+        /// Compile into memory and run in-process, instead of linking an executable.
+        #[arg(long)]
+        jit: bool,
+
+        /// Print how long each package took to compile, and what was reused from the cache.
+        #[arg(long)]
+        timings: bool,
+
+        /// Print internal information about the compiler.
+        #[arg(short = 'Z', value_enum, value_name = "OPTION")]
+        debug: Vec<DebugArg>,
+
+        #[command(flatten)]
+        codegen: CodegenArgs,
+
+        #[command(flatten)]
+        link: LinkArgs,
+    },
+
+    /// Run a program with the interpreter, without compiling it.
+    Interp {
+        /// The package directory. If not specified, the package is found by
+        /// looking for `bayou.toml` in the working directory and its parents.
+        input: Option<PathBuf>,
+    },
+
+    /// Start an interactive session, which evaluates items, statements and expressions.
+    Repl {
+        #[command(flatten)]
+        codegen: CodegenArgs,
+    },
+
+    /// Format every module of a package.
+    Fmt {
+        /// The package directory. If not specified, the package is found by
+        /// looking for `bayou.toml` in the working directory and its parents.
+        input: Option<PathBuf>,
+
+        /// Don't write the formatted modules, but list the files that aren't
+        /// formatted and fail if there are any.
+        #[arg(long)]
+        check: bool,
+    },
+
+    /// Start a language server, which editors talk to over stdin and stdout.
+    Lsp,
+
+    /// Create a package in a new directory.
+    New {
+        /// The directory to create, which the package is named after.
+        path: PathBuf,
+    },
+
+    /// Create a package in an existing directory.
+    Init {
+        /// The directory of the package, which is the working directory if not specified.
+        path: Option<PathBuf>,
     },
 }
+
+/// Options for code generation, shared by every command that compiles code.
+#[derive(Args)]
+pub struct CodegenArgs {
+    /// Don't check that array and slice indices are in bounds.
+    #[arg(long)]
+    unchecked_indexing: bool,
+
+    /// The optimization level.
+    #[arg(
+        short = 'O',
+        value_enum,
+        default_value = "2",
+        hide_default_value = true
+    )]
+    opt_level: OptLevelArg,
+
+    /// Generate position independent code, which is the default when building.
+    #[arg(long, overrides_with = "no_pic")]
+    pic: bool,
+
+    /// Don't generate position independent code, which is the default for the JIT.
+    #[arg(long, overrides_with = "pic")]
+    no_pic: bool,
+
+    /// Generate debug information.
+    #[arg(short = 'g')]
+    debug_info: bool,
+
+    /// Set a codegen option, like `-C cranelift.<setting>=<value>`.
+    #[arg(short = 'C', value_name = "OPTION=VALUE", value_parser = parse_codegen_option)]
+    codegen_options: Vec<(String, String)>,
+}
+
+impl From<CodegenArgs> for CodegenOptions {
+    fn from(args: CodegenArgs) -> Self {
+        let pic = match (args.pic, args.no_pic) {
+            (true, _) => Some(true),
+            (_, true) => Some(false),
+            _ => None,
+        };
+
+        Self {
+            unchecked_indexing: args.unchecked_indexing,
+            opt_level: args.opt_level.into(),
+            pic,
+            debug_info: args.debug_info,
+            cranelift_settings: args.codegen_options,
+        }
+    }
+}
+
+/// Options for linking, shared by every command that links an executable.
+///
+/// The linker and sysroot can also be configured per target with the environment
+/// variables `BAYOU_TARGET_<TRIPLE>_LINKER` and `BAYOU_TARGET_<TRIPLE>_SYSROOT`,
+/// where the triple is uppercase with dashes replaced by underscores.
+#[derive(Args)]
+pub struct LinkArgs {
+    /// The linker to use, by name or path, instead of detecting one.
+    #[arg(long, value_name = "NAME|PATH")]
+    pub linker: Option<String>,
+
+    /// The sysroot containing the C runtime and libraries of the target.
+    #[arg(long, value_name = "DIR")]
+    pub sysroot: Option<PathBuf>,
+
+    /// Pass an extra argument to the linker.
+    #[arg(long = "link-arg", value_name = "ARG", allow_hyphen_values = true)]
+    pub link_args: Vec<String>,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+pub enum CrateTypeArg {
+    /// An executable.
+    Bin,
+    /// An object file.
+    Obj,
+    /// A static library, exporting only `export` functions.
+    #[value(name = "staticlib")]
+    StaticLib,
+    /// A shared library, exporting only `export` functions.
+    Cdylib,
+}
+
+impl From<CrateTypeArg> for CrateType {
+    fn from(crate_type: CrateTypeArg) -> Self {
+        match crate_type {
+            CrateTypeArg::Bin => Self::Bin,
+            CrateTypeArg::Obj => Self::Obj,
+            CrateTypeArg::StaticLib => Self::StaticLib,
+            CrateTypeArg::Cdylib => Self::Cdylib,
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum EmitArg {
+    /// The executable or library.
+    Link,
+    /// Only the object file.
+    Obj,
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum DebugArg {
+    /// How many strings, syntax tree and IR nodes each compiled package has,
+    /// and how much memory its interner uses.
+    Stats,
+}
+
+#[derive(Clone, Copy, ValueEnum)]
+enum OptLevelArg {
+    #[value(name = "0")]
+    O0,
+    #[value(name = "1")]
+    O1,
+    #[value(name = "2")]
+    O2,
+    #[value(name = "s")]
+    Os,
+}
+
+impl From<OptLevelArg> for OptLevel {
+    fn from(level: OptLevelArg) -> Self {
+        match level {
+            OptLevelArg::O0 => Self::O0,
+            OptLevelArg::O1 => Self::O1,
+            OptLevelArg::O2 => Self::O2,
+            OptLevelArg::Os => Self::Os,
+        }
+    }
+}
+
+/// Parse a `-C` option, of which only Cranelift settings exist so far.
+fn parse_codegen_option(s: &str) -> Result<(String, String), String> {
+    let (name, value) = s
+        .split_once('=')
+        .ok_or_else(|| format!("expected `OPTION=VALUE`, found `{s}`"))?;
+
+    let setting = name
+        .strip_prefix("cranelift.")
+        .ok_or_else(|| format!("unknown codegen option `{name}`"))?;
+
+    Ok((setting.to_owned(), value.to_owned()))
+}
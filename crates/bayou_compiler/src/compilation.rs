@@ -1,21 +1,46 @@
+use std::time::{Duration, Instant};
+
+use crate::stats::PackageStats;
 use crate::CompilerResult;
 use bayou_backend::object::write::Object;
+use bayou_backend::{CodegenStats, FunctionCache, JitProgram};
+use bayou_frontend::ParseCache;
+use bayou_ir::interface::PackageInterface;
 use bayou_ir::ir::Package;
+use bayou_ir::NodeCounts;
+use bayou_middle::const_eval::ConstEvaluator;
 use bayou_middle::type_check::TypeChecker;
-use bayou_session::CodegenSession;
+use bayou_session::{CodegenSession, CrateType, Session};
+use target_lexicon::Triple;
 
-pub fn compile_package<S: CodegenSession>(
+/// Run the frontend and middle end, producing a type checked package
+/// and statistics about it.
+///
+/// The package can use the exported functions of `dependencies`, and the modules
+/// in `module_cache` that haven't changed aren't parsed again.
+pub fn check_package<S: Session>(
     session: &mut S,
     config: S::PackageConfig,
-) -> CompilerResult<Object<'static>> {
+    dependencies: &[PackageInterface],
+    module_cache: Option<&dyn ParseCache>,
+) -> CompilerResult<(Package, PackageStats)> {
     let mut package_session = session.build_package_session(config);
 
     let (mut module_tree, parsed_modules, errors) =
-        bayou_frontend::load_and_parse_modules(session, &mut package_session);
+        bayou_frontend::load_and_parse_modules(session, &mut package_session, module_cache);
     session.report_all(errors, &package_session.interner)?;
 
-    let (mut ir, mut symbols, errors) =
-        bayou_frontend::lower(&parsed_modules, &mut module_tree, &package_session.interner);
+    let mut ast_counts = NodeCounts::default();
+    for module in &parsed_modules {
+        ast_counts += module.ast.node_counts();
+    }
+
+    let (mut ir, mut symbols, errors) = bayou_frontend::lower(
+        &parsed_modules,
+        &mut module_tree,
+        &package_session.interner,
+        dependencies,
+    );
     session.report_all(errors, &package_session.interner)?;
 
     let type_checker = TypeChecker::new(&mut symbols);
@@ -24,18 +49,82 @@ pub fn compile_package<S: CodegenSession>(
     let type_errors = type_checker.run(&mut ir);
     session.report_all(type_errors, &())?;
 
-    if let Err(err) = bayou_middle::entry_point::check_entrypoint(&ir, &symbols) {
-        session.report(err, &())?;
+    let const_errors = ConstEvaluator::new(&mut symbols, &package_session.interner).run(&ir);
+    session.report_all(const_errors, &package_session.interner)?;
+
+    let lints = bayou_middle::lints::unused_variables(&ir, &symbols, &package_session.interner);
+    session.report_all(lints, &package_session.interner)?;
+
+    // libraries are called into from other code instead
+    if package_session.crate_type == CrateType::Bin {
+        if let Err(err) = bayou_middle::entry_point::check_entrypoint(&ir, &symbols) {
+            session.report(err, &())?;
+        }
     }
 
+    let stats = PackageStats::new(ast_counts, ir.node_counts(), &package_session.interner);
+
     // TODO: remove `Package` type.
     let package = Package {
         name: package_session.name,
+        crate_type: package_session.crate_type,
         ir,
         symbols,
         interner: package_session.interner,
     };
-    let object = bayou_backend::run_codegen(session, &package)?;
 
-    Ok(object)
+    Ok((package, stats))
+}
+
+/// A package compiled into an object.
+pub struct CompiledPackage {
+    pub object: Object<'static>,
+
+    /// The interface of the package, for the packages that depend on it.
+    pub interface: PackageInterface,
+
+    pub check_time: Duration,
+    pub codegen_time: Duration,
+    pub stats: CodegenStats,
+    pub package_stats: PackageStats,
+}
+
+/// Compile a package into an object, reusing the modules in `module_cache` and the
+/// functions in `func_cache` that haven't changed since they were cached.
+pub fn compile_package<S: CodegenSession>(
+    session: &mut S,
+    config: S::PackageConfig,
+    dependencies: &[PackageInterface],
+    module_cache: Option<&dyn ParseCache>,
+    func_cache: Option<FunctionCache>,
+) -> CompilerResult<CompiledPackage> {
+    let start = Instant::now();
+    let (package, package_stats) = check_package(session, config, dependencies, module_cache)?;
+    bayou_backend::check_layouts(session, &package, &session.target_triple().clone())?;
+    let check_time = start.elapsed();
+
+    let start = Instant::now();
+    let (object, stats) = bayou_backend::run_codegen(session, &package, func_cache)?;
+    let codegen_time = start.elapsed();
+
+    Ok(CompiledPackage {
+        object,
+        interface: PackageInterface::new(&package),
+        check_time,
+        codegen_time,
+        stats,
+        package_stats,
+    })
+}
+
+/// Compile a package into memory, so that it can be run without linking.
+pub fn compile_package_jit<S: CodegenSession>(
+    session: &mut S,
+    config: S::PackageConfig,
+) -> CompilerResult<(JitProgram, PackageStats)> {
+    let (package, stats) = check_package(session, config, &[], None)?;
+    bayou_backend::check_layouts(session, &package, &Triple::host())?;
+    let program = bayou_backend::run_jit(session, &package)?;
+
+    Ok((program, stats))
 }
@@ -0,0 +1,107 @@
+//! `bayou fmt`, which formats every module of a package.
+
+use std::collections::VecDeque;
+use std::path::PathBuf;
+
+use bayou_frontend::cst::NodeKind;
+use bayou_frontend::format::format_module;
+use bayou_frontend::token::TokenKind;
+use bayou_frontend::GatherModulesError;
+use bayou_session::module_loader::{ModuleLoader, ModulePath};
+use bayou_session::sourcemap::{Source, SourceSpan};
+use bayou_session::{FullSession, FullSessionConfig, Session};
+
+use crate::{CompilerError, CompilerResult};
+
+/// Format every module of a package in place, or with `check`, only print
+/// the files of the modules that aren't formatted, and fail if there are any.
+///
+/// Modules with syntax errors are reported instead of formatted, and their
+/// submodules aren't found.
+pub fn format_package(
+    session: &mut FullSession,
+    config: FullSessionConfig,
+    check: bool,
+) -> CompilerResult<()> {
+    let package_session = session.build_package_session(config);
+    let interner = &package_session.interner;
+    let loader = &package_session.module_loader;
+
+    let mut errors = vec![];
+    let mut unformatted: Vec<PathBuf> = vec![];
+
+    let mut modules = VecDeque::from([(ModulePath::root(), None)]);
+    while let Some((module_path, span)) = modules.pop_front() {
+        let source = match loader.load_module(&module_path, interner) {
+            Ok(source) => source,
+            Err(err) => {
+                errors.push(GatherModulesError::ModuleLoaderError(err, span));
+                continue;
+            }
+        };
+
+        let Some(file) = loader.module_file(&module_path, interner) else {
+            continue;
+        };
+
+        let (cst, lexer_errors, parse_errors) = bayou_frontend::parse_lossless(&source, interner);
+
+        let source_id = session.source_map_mut().insert(Source {
+            name: module_path.display(interner).to_string(),
+            source: source.clone(),
+            path: Some(file.clone()),
+        });
+
+        if !lexer_errors.is_empty() || !parse_errors.is_empty() {
+            errors.extend(
+                lexer_errors
+                    .into_iter()
+                    .map(|err| GatherModulesError::LexerError(err, source_id)),
+            );
+            errors.extend(
+                parse_errors
+                    .into_iter()
+                    .map(|err| GatherModulesError::ParseError(err, source_id)),
+            );
+
+            continue;
+        }
+
+        let submodules = cst
+            .child_nodes()
+            .filter(|item| item.kind() == NodeKind::Submodule);
+        for submodule in submodules {
+            let ident = submodule
+                .child_tokens()
+                .find_map(|token| match token.token_kind() {
+                    Some(TokenKind::Identifier(istr)) => Some((istr, token.span())),
+                    _ => None,
+                });
+
+            if let Some((istr, span)) = ident {
+                modules.push_back((module_path.join(istr), Some(SourceSpan { span, source_id })));
+            }
+        }
+
+        let formatted = format_module(&cst);
+        if formatted != source {
+            if check {
+                unformatted.push(file);
+            } else {
+                std::fs::write(&file, formatted)?;
+            }
+        }
+    }
+
+    session.report_all(errors, interner)?;
+
+    if unformatted.is_empty() {
+        return Ok(());
+    }
+
+    for file in &unformatted {
+        println!("{}", file.display());
+    }
+
+    Err(CompilerError::Unformatted)
+}
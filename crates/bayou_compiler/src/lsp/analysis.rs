@@ -0,0 +1,294 @@
+//! Checking a package for the language server, keeping what editors ask
+//! about afterwards: the diagnostics, the sources and the type checked IR.
+
+use std::path::PathBuf;
+
+use bayou_frontend::{ModuleTree, ParsedModule};
+use bayou_interner::Interner;
+use bayou_ir::interface::PackageInterface;
+use bayou_ir::ir::{Expr, ExprKind, PackageIr};
+use bayou_ir::symbols::Symbols;
+use bayou_ir::visit::{self, Visitor};
+use bayou_middle::const_eval::ConstEvaluator;
+use bayou_middle::type_check::TypeChecker;
+use bayou_session::diagnostics::span::Span;
+use bayou_session::diagnostics::Diagnostic;
+use bayou_session::lints::LintLevels;
+use bayou_session::module_loader::FsLoader;
+use bayou_session::sourcemap::{SourceId, SourceMap, SourceSpan};
+use bayou_session::{sym, CrateType, ErrorsEmitted, FullSessionConfig, PackageSession, Session};
+
+use crate::compilation::check_package;
+use crate::package_graph::PackageGraph;
+
+/// Session that keeps diagnostics to publish them, instead of printing them.
+#[derive(Debug, Default)]
+pub struct LspSession {
+    pub source_map: SourceMap,
+    pub diagnostics: Vec<Diagnostic>,
+    pub lint_levels: LintLevels,
+}
+
+impl Session for LspSession {
+    type ModuleLoader = FsLoader;
+    type PackageConfig = FullSessionConfig;
+
+    fn build_package_session(
+        &self,
+        FullSessionConfig {
+            name,
+            crate_type,
+            root_dir,
+            root_file,
+        }: Self::PackageConfig,
+    ) -> PackageSession<Self> {
+        PackageSession {
+            name,
+            crate_type,
+            interner: sym::interner(),
+            module_loader: FsLoader {
+                root_dir,
+                root_file,
+                overlays: self.source_map.overlays().clone(),
+            },
+        }
+    }
+
+    fn source_map(&self) -> &SourceMap {
+        &self.source_map
+    }
+
+    fn source_map_mut(&mut self) -> &mut SourceMap {
+        &mut self.source_map
+    }
+
+    fn emit_diagnostic(&mut self, diagnostic: Diagnostic) {
+        self.diagnostics.push(diagnostic);
+    }
+
+    fn lint_levels(&self) -> &LintLevels {
+        &self.lint_levels
+    }
+}
+
+/// A checked package, with its dependencies checked as well.
+pub struct Analysis {
+    pub session: LspSession,
+
+    /// The file of the root module, which diagnostics without a snippet are shown in.
+    pub root_file: PathBuf,
+
+    interner: Interner,
+    module_tree: ModuleTree,
+    modules: Vec<ParsedModule>,
+
+    /// The IR and symbols of the package, unless it had errors before type checking.
+    lowered: Option<(PackageIr, Symbols)>,
+}
+
+impl Analysis {
+    /// Check every package in the graph, loading modules from the overlays of
+    /// `source_map` where it has them.
+    ///
+    /// Dependencies are only checked for their interfaces, so packages that depend on a
+    /// package with errors can't use it. Unlike [`check_package`], the root package is kept
+    /// after type errors, so that the types of the expressions that have them are known.
+    pub fn run(graph: &PackageGraph, source_map: SourceMap) -> Self {
+        let mut session = LspSession {
+            source_map,
+            ..LspSession::default()
+        };
+
+        let (root, dependencies) = graph
+            .nodes()
+            .split_last()
+            .expect("a package graph has the root package");
+
+        let mut interfaces: Vec<Option<PackageInterface>> = vec![];
+        for node in dependencies {
+            session.lint_levels = node.project.manifest.lint_levels();
+
+            let config = node.project.session_config(CrateType::Lib);
+            let dependencies = interfaces_of(&interfaces, &node.dependencies);
+            let interface = check_package(&mut session, config, &dependencies, None)
+                .ok()
+                .map(|(package, _)| PackageInterface::new(&package));
+
+            interfaces.push(interface);
+        }
+
+        session.lint_levels = root.project.manifest.lint_levels();
+
+        let config = root
+            .project
+            .session_config(root.project.manifest.package.crate_type);
+        let dependencies = interfaces_of(&interfaces, &root.dependencies);
+
+        Self::check_root(session, config, &dependencies)
+    }
+
+    fn check_root(
+        mut session: LspSession,
+        config: FullSessionConfig,
+        dependencies: &[PackageInterface],
+    ) -> Self {
+        let root_file = config.root_dir.join(&config.root_file);
+        let mut package_session = session.build_package_session(config);
+
+        let (mut module_tree, modules, errors) =
+            bayou_frontend::load_and_parse_modules(&mut session, &mut package_session, None);
+        let had_errors = session
+            .report_all(errors, &package_session.interner)
+            .is_err();
+
+        let lowered = if had_errors {
+            None
+        } else {
+            let (mut ir, mut symbols, errors) = bayou_frontend::lower(
+                &modules,
+                &mut module_tree,
+                &package_session.interner,
+                dependencies,
+            );
+
+            session
+                .report_all(errors, &package_session.interner)
+                .ok()
+                .map(|()| {
+                    // the errors are in the session's diagnostics
+                    let _ = check_lowered(&mut session, &package_session, &mut ir, &mut symbols);
+                    (ir, symbols)
+                })
+        };
+
+        Self {
+            session,
+            root_file,
+
+            interner: package_session.interner,
+            module_tree,
+            modules,
+
+            lowered,
+        }
+    }
+
+    /// The type of the local declared at `offset`, or else of the
+    /// innermost expression at `offset`, and where it is.
+    pub fn hover(&self, source_id: SourceId, offset: usize) -> Option<(Span, String)> {
+        let (ir, symbols) = self.lowered.as_ref()?;
+
+        let local = symbols
+            .locals
+            .iter()
+            .find(|local| touches(local.ident.span, source_id, offset));
+        if let Some(local) = local {
+            let name = &self.interner[local.ident.istr];
+            return Some((local.ident.span.span, format!("{name}: {}", local.ty)));
+        }
+
+        let expr = innermost_expr(ir, source_id, offset)?;
+        Some((expr.span.span, expr.ty.as_ref()?.to_string()))
+    }
+
+    /// Where the local, global or function used at `offset` is declared, or
+    /// the start of the module declared with `submodule` at `offset`.
+    pub fn definition(&self, source_id: SourceId, offset: usize) -> Option<SourceSpan> {
+        let module = self.modules.iter().find(|module| {
+            self.module_tree
+                .entry(module.module_id)
+                .ident
+                .is_some_and(|ident| touches(ident.span, source_id, offset))
+        });
+        if let Some(module) = module {
+            return Some(SourceSpan::new(Span::empty(0), module.source_id));
+        }
+
+        let (ir, symbols) = self.lowered.as_ref()?;
+        let expr = innermost_expr(ir, source_id, offset)?;
+
+        let ident = match &expr.kind {
+            ExprKind::Var(id) => symbols.locals[*id].ident,
+            ExprKind::Const(id) => symbols.consts[*id].ident,
+            ExprKind::Static(id) => symbols.statics[*id].ident,
+            ExprKind::Call { func, .. } => symbols.funcs[*func].ident,
+            _ => return None,
+        };
+
+        // functions from dependencies have no source, so their spans are where they are used
+        let is_use = ident.span.source_id == expr.span.source_id
+            && expr.span.span.contains(ident.span.span.start);
+
+        (!is_use).then_some(ident.span)
+    }
+}
+
+/// The interfaces of the dependencies of a package that were checked without errors.
+fn interfaces_of(
+    interfaces: &[Option<PackageInterface>],
+    dependencies: &[usize],
+) -> Vec<PackageInterface> {
+    dependencies
+        .iter()
+        .filter_map(|&dependency| interfaces[dependency].clone())
+        .collect()
+}
+
+/// Type check a lowered package and run the checks after type checking,
+/// like [`check_package`] does, stopping at the first check with errors.
+fn check_lowered(
+    session: &mut LspSession,
+    package_session: &PackageSession<LspSession>,
+    ir: &mut PackageIr,
+    symbols: &mut Symbols,
+) -> Result<(), ErrorsEmitted> {
+    let interner = &package_session.interner;
+
+    session.report_all(TypeChecker::new(symbols).run(ir), &())?;
+    session.report_all(ConstEvaluator::new(symbols, interner).run(ir), interner)?;
+    session.report_all(
+        bayou_middle::lints::unused_variables(ir, symbols, interner),
+        interner,
+    )?;
+
+    if package_session.crate_type == CrateType::Bin {
+        if let Err(err) = bayou_middle::entry_point::check_entrypoint(ir, symbols) {
+            session.report(err, &())?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Whether `offset` is in a span or at its end, where the
+/// cursor is after typing the last character of a name.
+fn touches(span: SourceSpan, source_id: SourceId, offset: usize) -> bool {
+    span.source_id == source_id && span.span.start <= offset && offset <= span.span.end
+}
+
+/// The smallest expression at `offset`, which is the innermost
+/// one out of the expressions nested in each other there.
+fn innermost_expr(ir: &PackageIr, source_id: SourceId, offset: usize) -> Option<&Expr> {
+    let mut collector = ExprCollector::default();
+    visit::walk_package(&mut collector, ir);
+
+    // expressions are collected before the expressions in them, so this
+    // picks the inner expression out of expressions with the same span
+    collector
+        .0
+        .into_iter()
+        .rev()
+        .filter(|expr| touches(expr.span, source_id, offset))
+        .min_by_key(|expr| expr.span.span.len())
+}
+
+/// Collects every expression, each before the expressions in it.
+#[derive(Default)]
+struct ExprCollector<'a>(Vec<&'a Expr>);
+
+impl<'a> Visitor<'a> for ExprCollector<'a> {
+    fn visit_expr(&mut self, expr: &'a Expr) {
+        self.0.push(expr);
+        visit::walk_expr(self, expr);
+    }
+}
@@ -0,0 +1,492 @@
+//! `bayou lsp`, a language server that editors talk to over stdio.
+//!
+//! Open documents are overlays in the source map, so that their unsaved changes are
+//! what gets checked. Opening or changing a document checks the package it is in
+//! again, and publishes the diagnostics of every file in the package.
+
+mod analysis;
+#[cfg(test)]
+mod tests;
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use bayou_frontend::cst::{NodeKind, SyntaxNode};
+use bayou_frontend::token::TokenKind;
+use bayou_interner::Interner;
+use bayou_session::diagnostics::sources::SourceMap as _;
+use bayou_session::diagnostics::span::Span;
+use bayou_session::diagnostics::{Severity, SnippetKind, TagKind};
+use bayou_session::sourcemap::{SourceId, SourceMap};
+use lsp_server::{Connection, ErrorCode, Message, Notification, Request, Response};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidCloseTextDocument, DidOpenTextDocument, DidSaveTextDocument,
+    Notification as _, PublishDiagnostics, ShowMessage,
+};
+use lsp_types::request::{DocumentSymbolRequest, GotoDefinition, HoverRequest, Request as _};
+use lsp_types::{
+    DiagnosticRelatedInformation, DiagnosticSeverity, DidChangeTextDocumentParams,
+    DidCloseTextDocumentParams, DidOpenTextDocumentParams, DidSaveTextDocumentParams,
+    DocumentSymbol, DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams,
+    GotoDefinitionResponse, Hover, HoverContents, HoverParams, HoverProviderCapability,
+    LanguageString, Location, MarkedString, MessageType, NumberOrString, OneOf, Position,
+    PublishDiagnosticsParams, Range, ServerCapabilities, ShowMessageParams, SymbolKind,
+    TextDocumentSyncCapability, TextDocumentSyncKind, TextDocumentSyncOptions,
+    TextDocumentSyncSaveOptions, Url,
+};
+
+use crate::manifest::{Project, MANIFEST_FILE};
+use crate::package_graph::PackageGraph;
+use crate::CompilerResult;
+use analysis::Analysis;
+
+/// Run the language server until the editor shuts it down.
+pub fn run() -> CompilerResult<()> {
+    let (connection, io_threads) = Connection::stdio();
+
+    connection.initialize(capabilities())?;
+    Server::new(&connection).main_loop()?;
+
+    // the connection has to be dropped to stop the thread writing to stdout
+    drop(connection);
+    io_threads.join()?;
+
+    Ok(())
+}
+
+fn capabilities() -> serde_json::Value {
+    let capabilities = ServerCapabilities {
+        text_document_sync: Some(TextDocumentSyncCapability::Options(
+            TextDocumentSyncOptions {
+                open_close: Some(true),
+                change: Some(TextDocumentSyncKind::FULL),
+                save: Some(TextDocumentSyncSaveOptions::Supported(true)),
+                ..TextDocumentSyncOptions::default()
+            },
+        )),
+        hover_provider: Some(HoverProviderCapability::Simple(true)),
+        definition_provider: Some(OneOf::Left(true)),
+        document_symbol_provider: Some(OneOf::Left(true)),
+        ..ServerCapabilities::default()
+    };
+
+    serde_json::to_value(capabilities).expect("capabilities can be serialized")
+}
+
+struct Server<'a> {
+    connection: &'a Connection,
+
+    /// The open documents as overlays, without any sources, which
+    /// the source map of every analysis starts as a copy of.
+    documents: SourceMap,
+
+    /// The latest analysis of each package with a document
+    /// that was opened, by the directory of the package.
+    analyses: HashMap<PathBuf, Analysis>,
+}
+
+impl<'a> Server<'a> {
+    fn new(connection: &'a Connection) -> Self {
+        Self {
+            connection,
+            documents: SourceMap::default(),
+            analyses: HashMap::new(),
+        }
+    }
+
+    fn main_loop(&mut self) -> CompilerResult<()> {
+        for message in &self.connection.receiver {
+            match message {
+                Message::Request(request) => {
+                    if self.connection.handle_shutdown(&request)? {
+                        return Ok(());
+                    }
+
+                    let response = self.handle_request(request);
+                    self.send(response.into());
+                }
+
+                Message::Notification(notification) => self.handle_notification(notification),
+                Message::Response(_) => {}
+            }
+        }
+
+        Ok(())
+    }
+
+    fn send(&self, message: Message) {
+        // if the editor has gone, the main loop ends once its messages run out
+        let _ = self.connection.sender.send(message);
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&self, params: N::Params) {
+        self.send(Notification::new(N::METHOD.to_owned(), params).into());
+    }
+
+    fn handle_request(&self, request: Request) -> Response {
+        match request.method.as_str() {
+            HoverRequest::METHOD => self.respond::<HoverRequest>(request, Self::hover),
+            GotoDefinition::METHOD => self.respond::<GotoDefinition>(request, Self::definition),
+            DocumentSymbolRequest::METHOD => {
+                self.respond::<DocumentSymbolRequest>(request, Self::document_symbols)
+            }
+
+            method => Response::new_err(
+                request.id,
+                ErrorCode::MethodNotFound as i32,
+                format!("unknown request `{method}`"),
+            ),
+        }
+    }
+
+    fn respond<R: lsp_types::request::Request>(
+        &self,
+        request: Request,
+        handler: fn(&Self, R::Params) -> R::Result,
+    ) -> Response {
+        match serde_json::from_value(request.params) {
+            Ok(params) => Response::new_ok(request.id, handler(self, params)),
+            Err(err) => {
+                Response::new_err(request.id, ErrorCode::InvalidParams as i32, err.to_string())
+            }
+        }
+    }
+
+    fn handle_notification(&mut self, notification: Notification) {
+        match notification.method.as_str() {
+            DidOpenTextDocument::METHOD => {
+                self.handle::<DidOpenTextDocument>(notification, Self::did_open);
+            }
+            DidChangeTextDocument::METHOD => {
+                self.handle::<DidChangeTextDocument>(notification, Self::did_change);
+            }
+            DidSaveTextDocument::METHOD => {
+                self.handle::<DidSaveTextDocument>(notification, Self::did_save);
+            }
+            DidCloseTextDocument::METHOD => {
+                self.handle::<DidCloseTextDocument>(notification, Self::did_close);
+            }
+
+            // other notifications, like `exit` after a shutdown that
+            // didn't happen, or cancelling requests that are answered in
+            // order, don't need anything done
+            _ => {}
+        }
+    }
+
+    fn handle<N: lsp_types::notification::Notification>(
+        &mut self,
+        notification: Notification,
+        handler: fn(&mut Self, N::Params),
+    ) {
+        if let Ok(params) = serde_json::from_value(notification.params) {
+            handler(self, params);
+        }
+    }
+
+    fn did_open(&mut self, params: DidOpenTextDocumentParams) {
+        let Some(path) = file_path(&params.text_document.uri) else {
+            return;
+        };
+
+        self.documents.set_overlay(&path, params.text_document.text);
+        self.analyse(&path);
+    }
+
+    fn did_change(&mut self, params: DidChangeTextDocumentParams) {
+        let Some(path) = file_path(&params.text_document.uri) else {
+            return;
+        };
+
+        // changes are always the full text, which is the only kind of sync the server supports
+        if let Some(change) = params.content_changes.into_iter().last() {
+            self.documents.set_overlay(&path, change.text);
+            self.analyse(&path);
+        }
+    }
+
+    fn did_save(&mut self, params: DidSaveTextDocumentParams) {
+        if let Some(path) = file_path(&params.text_document.uri) {
+            self.analyse(&path);
+        }
+    }
+
+    fn did_close(&mut self, params: DidCloseTextDocumentParams) {
+        let Some(path) = file_path(&params.text_document.uri) else {
+            return;
+        };
+
+        self.documents.remove_overlay(&path);
+        self.analyse(&path);
+    }
+
+    /// Check the package that a file is in, and publish its diagnostics.
+    fn analyse(&mut self, path: &Path) {
+        let dir = package_dir(path);
+
+        let graph = match Project::find(Some(dir.clone())).and_then(PackageGraph::load) {
+            Ok(graph) => graph,
+            Err(err) => {
+                self.notify::<ShowMessage>(ShowMessageParams {
+                    typ: MessageType::ERROR,
+                    message: err.to_string(),
+                });
+                return;
+            }
+        };
+
+        let analysis = Analysis::run(&graph, self.documents.clone());
+        let previous = self.analyses.insert(dir.clone(), analysis);
+
+        let mut diagnostics = lsp_diagnostics(&self.analyses[&dir]);
+
+        // files that are no longer in the package, or are but
+        // couldn't be loaded, keep their diagnostics unless cleared
+        if let Some(previous) = previous {
+            for uri in lsp_diagnostics(&previous).into_keys() {
+                diagnostics.entry(uri).or_default();
+            }
+        }
+
+        for (uri, diagnostics) in diagnostics {
+            self.notify::<PublishDiagnostics>(PublishDiagnosticsParams {
+                uri,
+                diagnostics,
+                version: None,
+            });
+        }
+    }
+
+    /// The analysis that has the source of a file, and its ID in the analysis.
+    fn find_source(&self, uri: &Url) -> Option<(&Analysis, SourceId)> {
+        let path = file_path(uri)?;
+
+        self.analyses.values().find_map(|analysis| {
+            let source_id = analysis.session.source_map.find_path(&path)?;
+            Some((analysis, source_id))
+        })
+    }
+
+    fn hover(&self, params: HoverParams) -> Option<Hover> {
+        let document = params.text_document_position_params;
+        let (analysis, source_id) = self.find_source(&document.text_document.uri)?;
+        let text = source_text(&analysis.session.source_map, source_id)?;
+
+        let (span, ty) = analysis.hover(source_id, offset(text, document.position))?;
+
+        Some(Hover {
+            contents: HoverContents::Scalar(MarkedString::LanguageString(LanguageString {
+                language: "bayou".to_owned(),
+                value: ty,
+            })),
+            range: Some(range(text, span)),
+        })
+    }
+
+    fn definition(&self, params: GotoDefinitionParams) -> Option<GotoDefinitionResponse> {
+        let document = params.text_document_position_params;
+        let (analysis, source_id) = self.find_source(&document.text_document.uri)?;
+        let text = source_text(&analysis.session.source_map, source_id)?;
+
+        let target = analysis.definition(source_id, offset(text, document.position))?;
+        let location = location(&analysis.session.source_map, target.source_id, target.span)?;
+
+        Some(GotoDefinitionResponse::Scalar(location))
+    }
+
+    /// The items of a document, read from its overlay if it is open, which are
+    /// found from its syntax tree so that they are there even with errors.
+    fn document_symbols(&self, params: DocumentSymbolParams) -> Option<DocumentSymbolResponse> {
+        let path = file_path(&params.text_document.uri)?;
+        let text = match self.documents.overlays().get(&path) {
+            Some(text) => text.clone(),
+            None => std::fs::read_to_string(&path).ok()?,
+        };
+
+        let (cst, _, _) = bayou_frontend::parse_lossless(&text, &Interner::new());
+        let symbols = cst
+            .child_nodes()
+            .filter_map(|item| item_symbol(&text, &item))
+            .collect();
+
+        Some(DocumentSymbolResponse::Nested(symbols))
+    }
+}
+
+/// The file that a URI refers to, with symlinks resolved if the file exists,
+/// like the paths of modules in packages found from the file.
+fn file_path(uri: &Url) -> Option<PathBuf> {
+    let path = uri.to_file_path().ok()?;
+    Some(path.canonicalize().unwrap_or(path))
+}
+
+/// The directory of the package that a file is in, which is the closest directory
+/// with a manifest, or the directory of the file if there is no manifest.
+fn package_dir(path: &Path) -> PathBuf {
+    let parent = path.parent().unwrap_or(path);
+
+    parent
+        .ancestors()
+        .find(|dir| dir.join(MANIFEST_FILE).is_file())
+        .unwrap_or(parent)
+        .to_owned()
+}
+
+fn source_text(source_map: &SourceMap, source_id: SourceId) -> Option<&str> {
+    Some(&source_map.get_source(source_id)?.as_source().source)
+}
+
+/// The diagnostics of every file in an analysis, including
+/// files without diagnostics, to clear their old ones.
+fn lsp_diagnostics(analysis: &Analysis) -> HashMap<Url, Vec<lsp_types::Diagnostic>> {
+    let source_map = &analysis.session.source_map;
+
+    let mut files: HashMap<Url, Vec<lsp_types::Diagnostic>> = source_map
+        .sources()
+        .filter_map(|source| Url::from_file_path(source.path.as_ref()?).ok())
+        .map(|uri| (uri, vec![]))
+        .collect();
+
+    for diagnostic in &analysis.session.diagnostics {
+        let primary = diagnostic
+            .snippets
+            .iter()
+            .find(|snippet| snippet.kind() == SnippetKind::Primary)
+            .or(diagnostic.snippets.first());
+
+        let primary_location = match primary {
+            Some(snippet) => location(source_map, snippet.source_id(), snippet.span()),
+            None => Url::from_file_path(&analysis.root_file)
+                .ok()
+                .map(|uri| Location::new(uri, Range::default())),
+        };
+
+        let Some(primary_location) = primary_location else {
+            continue;
+        };
+
+        let mut message = diagnostic
+            .message
+            .clone()
+            .or_else(|| primary.map(|snippet| snippet.label().to_owned()))
+            .unwrap_or_default();
+
+        for (kind, tag) in &diagnostic.tags {
+            let kind = match kind {
+                TagKind::Note => "note",
+                TagKind::Suggestion => "help",
+            };
+            message.push_str(&format!("\n{kind}: {tag}"));
+        }
+
+        let related_information = diagnostic
+            .snippets
+            .iter()
+            .filter(|snippet| snippet.kind() == SnippetKind::Secondary)
+            .filter_map(|snippet| {
+                Some(DiagnosticRelatedInformation {
+                    location: location(source_map, snippet.source_id(), snippet.span())?,
+                    message: snippet.label().to_owned(),
+                })
+            })
+            .collect();
+
+        let severity = match diagnostic.severity {
+            Severity::Warning => DiagnosticSeverity::WARNING,
+            Severity::Error => DiagnosticSeverity::ERROR,
+        };
+
+        files
+            .entry(primary_location.uri)
+            .or_default()
+            .push(lsp_types::Diagnostic {
+                range: primary_location.range,
+                severity: Some(severity),
+                code: diagnostic.id.clone().map(NumberOrString::String),
+                source: Some("bayou".to_owned()),
+                message,
+                related_information: Some(related_information),
+                ..lsp_types::Diagnostic::default()
+            });
+    }
+
+    files
+}
+
+fn location(source_map: &SourceMap, source_id: SourceId, span: Span) -> Option<Location> {
+    let source = source_map.get_source(source_id)?.as_source();
+    let uri = Url::from_file_path(source.path.as_ref()?).ok()?;
+
+    Some(Location::new(uri, range(&source.source, span)))
+}
+
+/// The symbol of an item, named after the first identifier in it.
+fn item_symbol(text: &str, item: &SyntaxNode) -> Option<DocumentSymbol> {
+    let kind = match item.kind() {
+        NodeKind::Submodule => SymbolKind::MODULE,
+        NodeKind::FuncDecl | NodeKind::ExternFuncDecl => SymbolKind::FUNCTION,
+        NodeKind::ConstDecl => SymbolKind::CONSTANT,
+        NodeKind::StaticDecl => SymbolKind::VARIABLE,
+        _ => return None,
+    };
+
+    let ident = item
+        .child_tokens()
+        .find(|token| matches!(token.token_kind(), Some(TokenKind::Identifier(_))))?;
+
+    #[allow(deprecated)]
+    Some(DocumentSymbol {
+        name: ident.text().to_owned(),
+        detail: None,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: range(text, item.span()),
+        selection_range: range(text, ident.span()),
+        children: None,
+    })
+}
+
+fn range(text: &str, span: Span) -> Range {
+    Range::new(position(text, span.start), position(text, span.end))
+}
+
+/// The position of a byte offset, with the column in UTF-16 code
+/// units, which is what editors count in unless told otherwise.
+fn position(text: &str, offset: usize) -> Position {
+    let before = &text[..offset.min(text.len())];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+
+    let line = before.matches('\n').count();
+    let character = before[line_start..].encode_utf16().count();
+
+    Position::new(line as u32, character as u32)
+}
+
+/// The byte offset of a position, which is clamped to the end of its line.
+fn offset(text: &str, position: Position) -> usize {
+    let line_start = if position.line == 0 {
+        Some(0)
+    } else {
+        text.match_indices('\n')
+            .nth(position.line as usize - 1)
+            .map(|(newline, _)| newline + 1)
+    };
+
+    let Some(line_start) = line_start else {
+        return text.len();
+    };
+
+    let mut offset = line_start;
+    let mut character = 0;
+    for ch in text[line_start..].chars() {
+        if ch == '\n' || character >= position.character as usize {
+            break;
+        }
+
+        offset += ch.len_utf8();
+        character += ch.len_utf16();
+    }
+
+    offset
+}
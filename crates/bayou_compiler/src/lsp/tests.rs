@@ -0,0 +1,25 @@
+use lsp_types::Position;
+
+use super::{offset, position};
+
+#[test]
+fn positions_count_utf16() {
+    let text = "let a = 1;\nlet λ = \"😀\" + b;\n";
+
+    let b = text.find('b').unwrap();
+    assert_eq!(position(text, b), Position::new(1, 15));
+    assert_eq!(offset(text, Position::new(1, 15)), b);
+
+    for (byte, _) in text.char_indices() {
+        assert_eq!(offset(text, position(text, byte)), byte);
+    }
+}
+
+#[test]
+fn offsets_clamped() {
+    let text = "ab\ncd";
+
+    assert_eq!(offset(text, Position::new(0, 10)), 2);
+    assert_eq!(offset(text, Position::new(5, 0)), text.len());
+    assert_eq!(position(text, 100), Position::new(1, 2));
+}
@@ -1,24 +1,61 @@
+mod cache;
 mod cli;
 mod compilation;
+mod format;
+mod lsp;
+mod manifest;
+mod metadata;
+mod package_graph;
+mod repl;
+mod stats;
+#[cfg(test)]
+mod tests;
+mod timings;
 
+use std::ffi::OsStr;
+use std::path::{Path, PathBuf};
+use std::process::ExitStatus;
 use std::str::FromStr;
+use std::time::Instant;
 
 use bayou_backend::Linker;
+use bayou_ir::interface::PackageInterface;
+use bayou_middle::interp::Interpreter;
 use bayou_session::FullSession;
-use bayou_session::FullSessionConfig;
+use bayou_session::Session as _;
+use bayou_session::{CodegenOptions, CrateType};
+use cache::{BuildCache, ModuleHash, PackageInputs};
 use clap::Parser as _;
-use cli::{Cli, Command};
-use target_lexicon::Triple;
+use cli::{Cli, Command, DebugArg, EmitArg, LinkArgs};
+use manifest::Project;
+use metadata::{metadata_path, read_metadata, write_metadata};
+use package_graph::PackageGraph;
+use stats::Stats;
+use target_lexicon::{BinaryFormat, OperatingSystem, Triple};
 use temp_dir::TempDir;
-use temp_file::TempFileBuilder;
+use timings::{PackageTiming, Timings};
 
-use crate::compilation::compile_package;
+use crate::compilation::{check_package, compile_package, compile_package_jit};
+
+/// The stack size of the interpreter thread, which recurses for every
+/// nested call and expression in the interpreted program.
+const INTERP_STACK_SIZE: usize = 1 << 30;
+
+/// The call depth limit of the interpreter, which is plenty for
+/// programs that use recursion instead of loops.
+const INTERP_CALL_DEPTH_LIMIT: usize = 100_000;
 
 #[derive(thiserror::Error, Debug)]
 enum CompilerError {
     #[error(transparent)]
     Io(#[from] std::io::Error),
 
+    #[error(transparent)]
+    Manifest(#[from] manifest::ManifestError),
+
+    #[error(transparent)]
+    Metadata(#[from] metadata::MetadataError),
+
     #[error(transparent)]
     InvalidTarget(#[from] target_lexicon::ParseError),
 
@@ -28,12 +65,24 @@ enum CompilerError {
     #[error("error writing object: {0}")]
     ObjectError(#[from] bayou_backend::object::write::Error),
 
-    #[error("no linker found for given target and host")]
-    NoLinker,
+    #[error(
+        "no linker found for target `{0}`, select one with `--linker` \
+        or `BAYOU_TARGET_<TRIPLE>_LINKER`"
+    )]
+    NoLinker(Triple),
 
     #[error(transparent)]
     LinkerError(#[from] bayou_backend::LinkerError),
 
+    #[error("packages with dependencies can't be run with {0} yet")]
+    DependenciesUnsupported(&'static str),
+
+    #[error("language server error: {0}")]
+    Lsp(#[from] lsp_server::ProtocolError),
+
+    #[error("some modules aren't formatted")]
+    Unformatted,
+
     #[error("errors while compiling")]
     HadErrors,
 }
@@ -46,9 +95,17 @@ impl From<bayou_session::ErrorsEmitted> for CompilerError {
 
 type CompilerResult<T> = Result<T, CompilerError>;
 
+/// What the object file of a build is turned into.
+enum BuildProduct {
+    Object,
+    Archive,
+    Linked(Linker),
+}
+
 fn main() {
     if let Err(err) = run() {
         eprintln!("{err}");
+        std::process::exit(1);
     }
 }
 
@@ -60,56 +117,448 @@ fn run() -> CompilerResult<()> {
             input,
             output,
             target,
+            crate_type,
+            emit,
+            timings,
+            debug,
+            codegen,
+            link,
         } => {
-            let name: String = input
-                .file_name()
-                .unwrap_or_default()
-                .to_string_lossy()
-                .replace(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'), "");
+            let mut report = Timings::start();
+            let mut stats = Stats::default();
+            let graph = PackageGraph::load(Project::find(input)?)?;
+            let project = graph.root();
+            let name = project.name().to_owned();
 
-            let target = match target {
+            let crate_type = crate_type.map_or(project.manifest.package.crate_type, Into::into);
+
+            let target = match target.or_else(|| project.manifest.package.target.clone()) {
                 Some(s) => Triple::from_str(&s)?,
                 None => Triple::host(),
             };
 
-            let linker = Linker::detect(&target).ok_or(CompilerError::NoLinker)?;
+            // objects and static libraries are written without linking
+            let product = match (emit, crate_type) {
+                (EmitArg::Obj, _) | (_, CrateType::Obj | CrateType::Lib) => BuildProduct::Object,
+                (EmitArg::Link, CrateType::StaticLib) => BuildProduct::Archive,
+                (EmitArg::Link, CrateType::Bin | CrateType::Cdylib) => {
+                    BuildProduct::Linked(select_linker(link, &target)?)
+                }
+            };
+
+            let output = output.unwrap_or_else(|| {
+                let file_type = match product {
+                    BuildProduct::Object => CrateType::Obj,
+                    BuildProduct::Archive | BuildProduct::Linked(_) => crate_type,
+                };
 
-            let mut session = FullSession::new(target);
+                output_file_name(&name, file_type, &target)
+            });
 
-            let output = output.unwrap_or_else(|| name.clone());
+            let mut session = FullSession::new(target, codegen.into());
+            let compiled = compile_graph(
+                &mut session,
+                &graph,
+                crate_type,
+                true,
+                &mut report,
+                &mut stats,
+            )?;
 
-            // compilation
-            let object = {
-                println!("compiling project `{name}`");
+            match product {
+                // an object file only has the root package, and
+                // its dependencies are left in the build directory
+                BuildProduct::Object => {
+                    std::fs::write(&output, &compiled.root)?;
+                    write_metadata(
+                        &metadata_path(Path::new(&output)),
+                        &compiled.interface,
+                        &session.target_triple,
+                    )?;
+                }
 
-                compile_package(
+                BuildProduct::Archive => {
+                    let mut members = vec![(format!("{name}.o"), compiled.root.clone())];
+                    for (name, object_file) in &compiled.dependencies {
+                        members.push((format!("{name}.o"), std::fs::read(object_file)?));
+                    }
+
+                    std::fs::write(output, bayou_backend::write_archive(&members)?)?;
+                }
+
+                BuildProduct::Linked(linker) => {
+                    println!("linking");
+
+                    let start = Instant::now();
+                    link_graph(&compiled, &name, &linker, crate_type, output)?;
+                    report.set_link(start.elapsed());
+                }
+            }
+
+            if timings {
+                report.print();
+            }
+
+            if debug.contains(&DebugArg::Stats) {
+                stats.print();
+            }
+
+            Ok(())
+        }
+
+        Command::Run {
+            input,
+            jit,
+            timings,
+            debug,
+            codegen,
+            link,
+        } => {
+            let mut report = Timings::start();
+            let mut stats = Stats::default();
+            let graph = PackageGraph::load(Project::find(input)?)?;
+            let name = graph.root().name().to_owned();
+
+            let target = Triple::host();
+            let mut session = FullSession::new(target.clone(), codegen.into());
+
+            let exit_code = if jit {
+                if graph.has_dependencies() {
+                    return Err(CompilerError::DependenciesUnsupported("the JIT"));
+                }
+
+                let project = graph.root();
+                session.lint_levels = project.manifest.lint_levels();
+
+                let (program, package_stats) =
+                    compile_package_jit(&mut session, project.session_config(CrateType::Bin))?;
+                stats.add_package(&name, package_stats);
+
+                if debug.contains(&DebugArg::Stats) {
+                    stats.print();
+                }
+
+                // SAFETY: running the program is what the user asked for,
+                // and it can't do anything worse than a compiled executable
+                (unsafe { program.run_main() }) as i32
+            } else {
+                let linker = select_linker(link, &target)?;
+                let compiled = compile_graph(
                     &mut session,
-                    FullSessionConfig {
-                        name: name.clone(),
-                        root_dir: input,
-                    },
-                )?
-            };
+                    &graph,
+                    CrateType::Bin,
+                    false,
+                    &mut report,
+                    &mut stats,
+                )?;
 
-            // emit and link objects
-            {
                 let tmp_dir = TempDir::with_prefix("bayou_")?;
+                let executable = tmp_dir.child(&name);
 
-                let tmp_file = TempFileBuilder::new()
-                    .in_dir(tmp_dir.path())
-                    .prefix(name)
-                    .suffix(".o")
-                    .build()?;
+                let start = Instant::now();
+                link_graph(&compiled, &name, &linker, CrateType::Bin, &executable)?;
+                report.set_link(start.elapsed());
 
-                println!("writing object");
-                let object_data = object.write()?;
-                std::fs::write(tmp_file.path(), object_data)?;
+                // printed before running, so that it isn't mixed with the output of the program
+                if timings {
+                    report.print();
+                }
 
-                println!("linking");
-                linker.link(&[tmp_file.path()], output)?;
-            }
+                if debug.contains(&DebugArg::Stats) {
+                    stats.print();
+                }
+
+                let status = std::process::Command::new(&executable).status()?;
+                exit_code(status)
+            };
 
+            std::process::exit(exit_code);
+        }
+
+        Command::Repl { codegen } => repl::Repl::new(codegen.into()).run(),
+
+        Command::New { path } => {
+            let name = manifest::new_package(&path)?;
+            println!("created package `{name}`");
             Ok(())
         }
+
+        Command::Init { path } => {
+            let name = manifest::init_package(&path.unwrap_or_else(|| PathBuf::from(".")))?;
+            println!("created package `{name}`");
+            Ok(())
+        }
+
+        Command::Fmt { input, check } => {
+            let project = Project::find(input)?;
+            let mut session = FullSession::new(Triple::host(), CodegenOptions::default());
+
+            format::format_package(&mut session, project.session_config(CrateType::Bin), check)
+        }
+
+        Command::Lsp => lsp::run(),
+
+        Command::Interp { input } => {
+            let project = Project::find(input)?;
+            if !project.manifest.dependencies.is_empty() {
+                return Err(CompilerError::DependenciesUnsupported("the interpreter"));
+            }
+
+            let mut session = FullSession::new(Triple::host(), CodegenOptions::default());
+            session.lint_levels = project.manifest.lint_levels();
+
+            let (package, _) = check_package(
+                &mut session,
+                project.session_config(CrateType::Bin),
+                &[],
+                None,
+            )?;
+
+            let (result, package) = std::thread::Builder::new()
+                .stack_size(INTERP_STACK_SIZE)
+                .spawn(move || {
+                    let result = Interpreter::new(&package.ir, &package.symbols, &package.interner)
+                        .with_call_depth_limit(INTERP_CALL_DEPTH_LIMIT)
+                        .run_main();
+
+                    (result, package)
+                })?
+                .join()
+                .expect("interpreter panicked");
+
+            match result {
+                Ok(exit_code) => std::process::exit(exit_code as i32),
+                Err(errors) => {
+                    session.report_all(errors, &package.interner)?;
+                    Ok(())
+                }
+            }
+        }
+    }
+}
+
+/// Use the linker given on the command line or configured for the target,
+/// or detect one.
+fn select_linker(args: LinkArgs, target: &Triple) -> CompilerResult<Linker> {
+    let name = args.linker.or_else(|| target_env_var(target, "LINKER"));
+    let sysroot = args
+        .sysroot
+        .or_else(|| target_env_var(target, "SYSROOT").map(PathBuf::from));
+
+    let mut linker = match name {
+        Some(name) => Linker::from_name(&name, target)?,
+        None => Linker::detect(target).ok_or_else(|| CompilerError::NoLinker(target.clone()))?,
+    };
+
+    if let Some(sysroot) = sysroot {
+        linker = linker.with_sysroot(sysroot);
+    }
+
+    Ok(linker.with_args(args.link_args))
+}
+
+/// Get a setting for a target from the environment, like `BAYOU_TARGET_X86_64_UNKNOWN_LINUX_GNU_LINKER`.
+fn target_env_var(target: &Triple, setting: &str) -> Option<String> {
+    let target = target.to_string().to_uppercase().replace(['-', '.'], "_");
+    std::env::var(format!("BAYOU_TARGET_{target}_{setting}")).ok()
+}
+
+/// A compiled package and the object files of its dependencies.
+struct CompiledGraph {
+    root: Vec<u8>,
+    interface: PackageInterface,
+
+    /// The names and object files of the dependencies, in the build directory.
+    dependencies: Vec<(String, PathBuf)>,
+}
+
+/// Compile every package in the graph after the packages it depends on. The root
+/// package is compiled as `crate_type`, and its dependencies are compiled into
+/// objects and metadata in the build directory, to be linked with it.
+///
+/// Packages that haven't changed since they were last compiled are reused
+/// from the incremental compilation cache, and so aren't in `stats`.
+fn compile_graph(
+    session: &mut FullSession,
+    graph: &PackageGraph,
+    crate_type: CrateType,
+    print_progress: bool,
+    timings: &mut Timings,
+    stats: &mut Stats,
+) -> CompilerResult<CompiledGraph> {
+    let build_dir = graph.build_dir();
+    if graph.has_dependencies() {
+        std::fs::create_dir_all(&build_dir)?;
+    }
+
+    let cache = BuildCache::new(&build_dir);
+    let mut dependency_objects: Vec<(String, PathBuf)> = vec![];
+
+    for (index, node) in graph.nodes().iter().enumerate() {
+        let project = &node.project;
+        let is_root = index == graph.nodes().len() - 1;
+        let crate_type = if is_root { crate_type } else { CrateType::Lib };
+
+        // dependencies are used through their metadata instead of their sources
+        let dependencies = node
+            .dependencies
+            .iter()
+            .map(|&dependency| {
+                let (_, object_file) = &dependency_objects[dependency];
+                read_metadata(&metadata_path(object_file), &session.target_triple)
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        session.lint_levels = project.manifest.lint_levels();
+
+        let config = project.session_config(crate_type);
+        let inputs = PackageInputs {
+            name: project.name(),
+            crate_type,
+            root_dir: &config.root_dir,
+            target: &session.target_triple,
+            options: &session.codegen_options,
+            lint_levels: &session.lint_levels,
+            dependencies: &dependencies,
+        }
+        .fingerprint();
+
+        let start = Instant::now();
+
+        let (object, interface) = if let Some(cached) = cache.load(project.name(), inputs) {
+            timings.add_package(project.name(), PackageTiming::Cached(start.elapsed()));
+            stats.add_cached_package(project.name());
+            (cached.object, cached.interface)
+        } else {
+            if print_progress {
+                let version = &project.manifest.package.version;
+                println!("compiling project `{}` v{version}", project.name());
+            }
+
+            let first_source = session.source_map.sources().count();
+            let diagnostic_count = session.diagnostic_count;
+
+            let module_cache = cache.module_cache(project.name(), inputs);
+            let compiled = compile_package(
+                session,
+                config,
+                &dependencies,
+                Some(&module_cache),
+                Some(cache.function_cache()),
+            )?;
+            module_cache.prune();
+            let object = compiled.object.write()?;
+
+            // packages with warnings aren't cached, so that the warnings are shown every build
+            if session.diagnostic_count == diagnostic_count {
+                let modules = session
+                    .source_map
+                    .sources()
+                    .skip(first_source)
+                    .filter_map(|source| {
+                        let path = source.path.as_ref()?.canonicalize().ok()?;
+                        Some(ModuleHash::new(path, &source.source))
+                    })
+                    .collect();
+
+                cache.store(
+                    project.name(),
+                    inputs,
+                    modules,
+                    &object,
+                    &compiled.interface,
+                )?;
+            }
+
+            timings.add_package(
+                project.name(),
+                PackageTiming::Compiled {
+                    check: compiled.check_time,
+                    codegen: compiled.codegen_time,
+                    modules_cached: module_cache.hits(),
+                    modules: module_cache.lookups(),
+                    stats: compiled.stats,
+                },
+            );
+            stats.add_package(project.name(), compiled.package_stats);
+
+            (object, compiled.interface)
+        };
+
+        if is_root {
+            return Ok(CompiledGraph {
+                root: object,
+                interface,
+                dependencies: dependency_objects,
+            });
+        }
+
+        let object_file = build_dir.join(format!("{}.o", project.name()));
+        std::fs::write(&object_file, object)?;
+        write_metadata(
+            &metadata_path(&object_file),
+            &interface,
+            &session.target_triple,
+        )?;
+
+        dependency_objects.push((project.name().to_owned(), object_file));
     }
+
+    unreachable!("the root package is the last package in the graph")
+}
+
+/// Link a package with its dependencies into an executable,
+/// or a shared library for `cdylib` packages.
+fn link_graph(
+    compiled: &CompiledGraph,
+    name: &str,
+    linker: &Linker,
+    crate_type: CrateType,
+    output: impl AsRef<OsStr>,
+) -> CompilerResult<()> {
+    let tmp_dir = TempDir::with_prefix("bayou_")?;
+
+    let root_file = tmp_dir.child(format!("{name}.o"));
+    std::fs::write(&root_file, &compiled.root)?;
+
+    let object_files: Vec<_> = std::iter::once(&root_file)
+        .chain(compiled.dependencies.iter().map(|(_, file)| file))
+        .collect();
+
+    if crate_type == CrateType::Cdylib {
+        linker.link_shared(&object_files, output)?;
+    } else {
+        linker.link(&object_files, output)?;
+    }
+
+    Ok(())
+}
+
+/// The default name of the output file, following the conventions of the target.
+fn output_file_name(name: &str, crate_type: CrateType, target: &Triple) -> String {
+    let is_windows = target.operating_system == OperatingSystem::Windows;
+
+    match crate_type {
+        CrateType::Bin => name.to_owned(),
+        CrateType::Obj | CrateType::Lib if is_windows => format!("{name}.obj"),
+        CrateType::Obj | CrateType::Lib => format!("{name}.o"),
+        CrateType::StaticLib if is_windows => format!("{name}.lib"),
+        CrateType::StaticLib => format!("lib{name}.a"),
+        CrateType::Cdylib if is_windows => format!("{name}.dll"),
+        CrateType::Cdylib if target.binary_format == BinaryFormat::Macho => {
+            format!("lib{name}.dylib")
+        }
+        CrateType::Cdylib => format!("lib{name}.so"),
+    }
+}
+
+/// Get the exit code of a finished process, which on Unix is 128 plus
+/// the signal number if it was killed by a signal, like in a shell.
+fn exit_code(status: ExitStatus) -> i32 {
+    #[cfg(unix)]
+    if let Some(signal) = std::os::unix::process::ExitStatusExt::signal(&status) {
+        return 128 + signal;
+    }
+
+    status.code().unwrap_or(1)
 }
@@ -0,0 +1,359 @@
+//! Package manifests, which are `bayou.toml` files in the directory of a package.
+
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bayou_middle::lints::LINTS;
+use bayou_session::lints::{LintLevel, LintLevels, WARNINGS};
+use bayou_session::module_loader::DEFAULT_ROOT_FILE;
+use bayou_session::{CrateType, FullSessionConfig};
+
+pub const MANIFEST_FILE: &str = "bayou.toml";
+
+/// The root module of new packages.
+const MAIN_TEMPLATE: &str = r#"extern func write(fd: i64, buf: *void, len: i64) -> i64;
+
+func print(s: str) {
+    write(1, s as *void, s.len);
+}
+
+func main() -> i64 {
+    print("Hello, world!\n");
+    0
+}
+"#;
+
+#[derive(thiserror::Error, Debug)]
+pub enum ManifestError {
+    #[error("couldn't access `{}`: {error}", path.display())]
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error("invalid manifest `{}`: {error}", path.display())]
+    Parse {
+        path: PathBuf,
+        error: toml::de::Error,
+    },
+
+    #[error("invalid package name `{0}`, which should be an identifier")]
+    InvalidName(String),
+
+    #[error("unknown lint `{0}`")]
+    UnknownLint(String),
+
+    #[error("couldn't find `{MANIFEST_FILE}` in `{}` or any parent directory", .0.display())]
+    NotFound(PathBuf),
+
+    #[error("`{}` already exists", .0.display())]
+    AlreadyExists(PathBuf),
+
+    #[error("dependency `{expected}` in `{}` is a package named `{found}`", path.display())]
+    DependencyName {
+        expected: String,
+        found: String,
+        path: PathBuf,
+    },
+
+    #[error("cyclic dependency between packages: {}", .0.join(" -> "))]
+    DependencyCycle(Vec<String>),
+
+    #[error("different packages named `{0}` in the dependency graph")]
+    DuplicatePackage(String),
+}
+
+pub type ManifestResult<T> = Result<T, ManifestError>;
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Manifest {
+    pub package: PackageManifest,
+
+    /// Levels of lints by name, or of every lint with `warnings`.
+    #[serde(default)]
+    pub lints: BTreeMap<String, LintLevel>,
+
+    /// Packages that this package can use the exported functions of, by name.
+    #[serde(default)]
+    pub dependencies: BTreeMap<String, Dependency>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields, rename_all = "kebab-case")]
+pub struct PackageManifest {
+    pub name: String,
+    pub version: String,
+
+    /// The file of the root module, relative to the manifest.
+    #[serde(default = "default_entry")]
+    pub entry: PathBuf,
+
+    #[serde(default)]
+    pub crate_type: CrateType,
+
+    /// The target triple to build for, instead of the host.
+    pub target: Option<String>,
+}
+
+#[derive(Debug, Clone, serde::Deserialize)]
+#[serde(deny_unknown_fields)]
+pub struct Dependency {
+    /// The directory of the package, relative to the manifest.
+    pub path: PathBuf,
+}
+
+fn default_entry() -> PathBuf {
+    PathBuf::from(DEFAULT_ROOT_FILE)
+}
+
+impl Manifest {
+    pub fn load(path: &Path) -> ManifestResult<Self> {
+        let source = fs::read_to_string(path).map_err(|error| ManifestError::Io {
+            path: path.to_owned(),
+            error,
+        })?;
+
+        let manifest: Self = toml::from_str(&source).map_err(|error| ManifestError::Parse {
+            path: path.to_owned(),
+            error,
+        })?;
+
+        if !is_valid_name(&manifest.package.name) {
+            return Err(ManifestError::InvalidName(manifest.package.name));
+        }
+
+        if let Some(name) = manifest
+            .dependencies
+            .keys()
+            .find(|name| !is_valid_name(name))
+        {
+            return Err(ManifestError::InvalidName(name.clone()));
+        }
+
+        if let Some(lint) = manifest
+            .lints
+            .keys()
+            .find(|lint| *lint != WARNINGS && !LINTS.contains(&lint.as_str()))
+        {
+            return Err(ManifestError::UnknownLint(lint.clone()));
+        }
+
+        Ok(manifest)
+    }
+
+    /// The manifest of a package without one, which is named after its directory.
+    fn for_directory(dir: &Path) -> Self {
+        Self {
+            package: PackageManifest {
+                name: package_name(dir),
+                version: String::from("0.0.0"),
+                entry: default_entry(),
+                crate_type: CrateType::Bin,
+                target: None,
+            },
+            lints: BTreeMap::new(),
+            dependencies: BTreeMap::new(),
+        }
+    }
+
+    pub fn lint_levels(&self) -> LintLevels {
+        let mut levels = LintLevels::default();
+
+        for (lint, &level) in &self.lints {
+            levels.set(lint.clone(), level);
+        }
+
+        levels
+    }
+}
+
+/// A package to compile, in the directory of its manifest.
+#[derive(Debug, Clone)]
+pub struct Project {
+    pub dir: PathBuf,
+    pub manifest: Manifest,
+}
+
+impl Project {
+    /// Load the package in a directory, which doesn't need a manifest, or without a
+    /// directory, the package with the closest manifest to the working directory.
+    pub fn find(dir: Option<PathBuf>) -> ManifestResult<Self> {
+        let Some(dir) = dir else {
+            let working_dir = std::env::current_dir().map_err(|error| ManifestError::Io {
+                path: PathBuf::from("."),
+                error,
+            })?;
+
+            let dir = working_dir
+                .ancestors()
+                .find(|dir| dir.join(MANIFEST_FILE).is_file())
+                .ok_or_else(|| ManifestError::NotFound(working_dir.clone()))?;
+
+            return Ok(Self {
+                dir: dir.to_owned(),
+                manifest: Manifest::load(&dir.join(MANIFEST_FILE))?,
+            });
+        };
+
+        let manifest_path = dir.join(MANIFEST_FILE);
+        let manifest = if manifest_path.is_file() {
+            Manifest::load(&manifest_path)?
+        } else {
+            Manifest::for_directory(&dir)
+        };
+
+        Ok(Self { dir, manifest })
+    }
+
+    pub fn name(&self) -> &str {
+        &self.manifest.package.name
+    }
+
+    pub fn session_config(&self, crate_type: CrateType) -> FullSessionConfig {
+        let entry = self.dir.join(&self.manifest.package.entry);
+
+        FullSessionConfig {
+            name: self.manifest.package.name.clone(),
+            crate_type,
+            root_dir: entry.parent().map(Path::to_owned).unwrap_or_default(),
+            root_file: entry.file_name().map(PathBuf::from).unwrap_or_default(),
+        }
+    }
+}
+
+/// Create a new package in a directory that doesn't exist yet.
+pub fn new_package(dir: &Path) -> ManifestResult<String> {
+    if dir.exists() {
+        return Err(ManifestError::AlreadyExists(dir.to_owned()));
+    }
+
+    init_package(dir)
+}
+
+/// Create a package in a directory, keeping its root module if it already exists,
+/// and return the name of the package.
+pub fn init_package(dir: &Path) -> ManifestResult<String> {
+    let manifest_path = dir.join(MANIFEST_FILE);
+    if manifest_path.exists() {
+        return Err(ManifestError::AlreadyExists(manifest_path));
+    }
+
+    let name = package_name(dir);
+    if !is_valid_name(&name) {
+        return Err(ManifestError::InvalidName(name));
+    }
+
+    let write = |path: PathBuf, contents: &str| {
+        fs::write(&path, contents).map_err(|error| ManifestError::Io { path, error })
+    };
+
+    fs::create_dir_all(dir).map_err(|error| ManifestError::Io {
+        path: dir.to_owned(),
+        error,
+    })?;
+
+    write(
+        manifest_path,
+        &format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n"),
+    )?;
+
+    let main_path = dir.join(DEFAULT_ROOT_FILE);
+    if !main_path.exists() {
+        write(main_path, MAIN_TEMPLATE)?;
+    }
+
+    Ok(name)
+}
+
+/// Derive a package name from its directory, keeping only valid identifier characters.
+fn package_name(dir: &Path) -> String {
+    // the name of `.` is the name of the working directory
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_owned());
+
+    dir.file_name()
+        .unwrap_or_default()
+        .to_string_lossy()
+        .replace(|ch: char| !(ch.is_ascii_alphanumeric() || ch == '_'), "")
+}
+
+fn is_valid_name(name: &str) -> bool {
+    name.starts_with(|ch: char| ch.is_ascii_alphabetic() || ch == '_')
+        && name
+            .chars()
+            .all(|ch| ch.is_ascii_alphanumeric() || ch == '_')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{is_valid_name, Manifest, ManifestError};
+
+    fn parse(source: &str) -> Result<Manifest, ManifestError> {
+        let dir = temp_dir::TempDir::with_prefix("bayou_test_").unwrap();
+        let path = dir.child("bayou.toml");
+        std::fs::write(&path, source).unwrap();
+
+        Manifest::load(&path)
+    }
+
+    #[test]
+    fn full_manifest() {
+        let manifest = parse(
+            r#"
+            [package]
+            name = "squares"
+            version = "1.2.0"
+            entry = "src/lib.by"
+            crate-type = "staticlib"
+            target = "aarch64-unknown-linux-gnu"
+
+            [lints]
+            warnings = "deny"
+            unused_variables = "allow"
+
+            [dependencies]
+            shapes = { path = "../shapes" }
+            "#,
+        )
+        .unwrap();
+
+        insta::assert_debug_snapshot!(manifest);
+    }
+
+    #[test]
+    fn invalid_manifests() {
+        let unknown_lint =
+            "[package]\nname = \"a\"\nversion = \"0.1.0\"\n[lints]\nunused = \"warn\"";
+        assert!(
+            matches!(parse(unknown_lint), Err(ManifestError::UnknownLint(lint)) if lint == "unused")
+        );
+
+        let invalid_name = "[package]\nname = \"1a\"\nversion = \"0.1.0\"";
+        assert!(matches!(
+            parse(invalid_name),
+            Err(ManifestError::InvalidName(_))
+        ));
+
+        let unknown_field = "[package]\nname = \"a\"\nversion = \"0.1.0\"\nedition = \"2024\"";
+        assert!(matches!(
+            parse(unknown_field),
+            Err(ManifestError::Parse { .. })
+        ));
+
+        let missing_version = "[package]\nname = \"a\"";
+        assert!(matches!(
+            parse(missing_version),
+            Err(ManifestError::Parse { .. })
+        ));
+    }
+
+    #[test]
+    fn package_names() {
+        assert!(is_valid_name("hello_world2"));
+        assert!(is_valid_name("_a"));
+        assert!(!is_valid_name(""));
+        assert!(!is_valid_name("2d"));
+        assert!(!is_valid_name("hello-world"));
+    }
+}
@@ -0,0 +1,188 @@
+//! Package metadata files, which hold the interface of a compiled package next to its
+//! object file, so that packages depending on it can be compiled without its sources.
+
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use bayou_ir::interface::PackageInterface;
+use target_lexicon::Triple;
+
+pub const METADATA_EXTENSION: &str = "bmeta";
+
+/// The version of the format of metadata files, which
+/// is increased whenever the interface types change.
+const FORMAT_VERSION: u32 = 1;
+
+#[derive(thiserror::Error, Debug)]
+pub enum MetadataError {
+    #[error("couldn't access `{}`: {error}", path.display())]
+    Io {
+        path: PathBuf,
+        error: std::io::Error,
+    },
+
+    #[error("invalid metadata `{}`: {error}", path.display())]
+    Parse {
+        path: PathBuf,
+        error: serde_json::Error,
+    },
+
+    #[error(
+        "metadata `{}` has format version {found} instead of {FORMAT_VERSION}, \
+        and needs to be rebuilt",
+        path.display()
+    )]
+    Version { path: PathBuf, found: u32 },
+
+    #[error("metadata `{}` is for target `{found}` instead of `{expected}`", path.display())]
+    Target {
+        path: PathBuf,
+        found: String,
+        expected: Triple,
+    },
+}
+
+type MetadataResult<T> = Result<T, MetadataError>;
+
+#[derive(serde::Serialize, serde::Deserialize)]
+struct Metadata<I> {
+    format_version: u32,
+    compiler_version: String,
+    target: String,
+
+    interface: I,
+}
+
+/// The path of the metadata file of an object file.
+pub fn metadata_path(object_path: &Path) -> PathBuf {
+    object_path.with_extension(METADATA_EXTENSION)
+}
+
+pub fn write_metadata(
+    path: &Path,
+    interface: &PackageInterface,
+    target: &Triple,
+) -> MetadataResult<()> {
+    let metadata = Metadata {
+        format_version: FORMAT_VERSION,
+        compiler_version: env!("CARGO_PKG_VERSION").to_owned(),
+        target: target.to_string(),
+        interface,
+    };
+
+    // the types are always serializable
+    let json = serde_json::to_string_pretty(&metadata).unwrap();
+
+    fs::write(path, json).map_err(|error| MetadataError::Io {
+        path: path.to_owned(),
+        error,
+    })
+}
+
+/// Read the interface from a metadata file, which has to be for `target`.
+pub fn read_metadata(path: &Path, target: &Triple) -> MetadataResult<PackageInterface> {
+    let json = fs::read_to_string(path).map_err(|error| MetadataError::Io {
+        path: path.to_owned(),
+        error,
+    })?;
+
+    let parse_error = |error| MetadataError::Parse {
+        path: path.to_owned(),
+        error,
+    };
+
+    // check the version before the rest, which might have a different format
+    #[derive(serde::Deserialize)]
+    struct Version {
+        format_version: u32,
+    }
+
+    let version: Version = serde_json::from_str(&json).map_err(parse_error)?;
+    if version.format_version != FORMAT_VERSION {
+        return Err(MetadataError::Version {
+            path: path.to_owned(),
+            found: version.format_version,
+        });
+    }
+
+    let metadata: Metadata<PackageInterface> = serde_json::from_str(&json).map_err(parse_error)?;
+
+    if metadata.target != target.to_string() {
+        return Err(MetadataError::Target {
+            path: path.to_owned(),
+            found: metadata.target,
+            expected: target.clone(),
+        });
+    }
+
+    Ok(metadata.interface)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::str::FromStr;
+
+    use bayou_ir::interface::{FuncInterface, PackageInterface};
+    use bayou_ir::Type;
+    use target_lexicon::Triple;
+    use temp_dir::TempDir;
+
+    use super::{metadata_path, read_metadata, write_metadata, MetadataError};
+
+    fn interface() -> PackageInterface {
+        PackageInterface {
+            name: String::from("shapes"),
+            funcs: vec![FuncInterface {
+                name: String::from("area"),
+                module: vec![String::from("rect")],
+                symbol: String::from("area"),
+                params: vec![Type::Slice(Box::new(Type::F64)), Type::I64.ptr()],
+                ret_ty: Type::Array(Box::new(Type::Bool), 2),
+            }],
+        }
+    }
+
+    #[test]
+    fn round_trip() {
+        let dir = TempDir::with_prefix("bayou_test_").unwrap();
+        let path = metadata_path(&dir.child("shapes.o"));
+        assert_eq!(path, dir.child("shapes.bmeta"));
+
+        let target = Triple::host();
+        write_metadata(&path, &interface(), &target).unwrap();
+
+        assert_eq!(read_metadata(&path, &target).unwrap(), interface());
+    }
+
+    #[test]
+    fn invalid_metadata() {
+        let dir = TempDir::with_prefix("bayou_test_").unwrap();
+        let path = dir.child("shapes.bmeta");
+
+        let target = Triple::from_str("aarch64-unknown-linux-gnu").unwrap();
+        write_metadata(&path, &interface(), &target).unwrap();
+
+        let other_target = Triple::from_str("x86_64-unknown-linux-gnu").unwrap();
+        assert!(matches!(
+            read_metadata(&path, &other_target),
+            Err(MetadataError::Target { found, .. }) if found == "aarch64-unknown-linux-gnu"
+        ));
+
+        std::fs::write(&path, r#"{ "format_version": 0, "interface": null }"#).unwrap();
+        assert!(matches!(
+            read_metadata(&path, &target),
+            Err(MetadataError::Version { found: 0, .. })
+        ));
+
+        std::fs::write(&path, "{}").unwrap();
+        assert!(matches!(
+            read_metadata(&path, &target),
+            Err(MetadataError::Parse { .. })
+        ));
+
+        assert!(matches!(
+            read_metadata(&dir.child("missing.bmeta"), &target),
+            Err(MetadataError::Io { .. })
+        ));
+    }
+}
@@ -0,0 +1,233 @@
+//! The graph of a package and the packages it depends on.
+
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
+use crate::manifest::{Manifest, ManifestError, ManifestResult, Project, MANIFEST_FILE};
+
+/// The directory in the root package that dependencies are compiled into.
+pub const BUILD_DIR: &str = "target";
+
+/// A package in a [`PackageGraph`].
+#[derive(Debug, Clone)]
+pub struct GraphNode {
+    pub project: Project,
+
+    /// The indices of the direct dependencies of the package,
+    /// which are always before it in the graph.
+    pub dependencies: Vec<usize>,
+}
+
+/// Packages in topological order, so that every package comes
+/// after the packages it depends on, and the root package is last.
+#[derive(Debug, Clone)]
+pub struct PackageGraph {
+    nodes: Vec<GraphNode>,
+}
+
+impl PackageGraph {
+    /// Load the dependencies of a package, and their dependencies.
+    pub fn load(root: Project) -> ManifestResult<Self> {
+        let mut loader = GraphLoader::default();
+        loader.visit(root)?;
+
+        Ok(Self {
+            nodes: loader.nodes,
+        })
+    }
+
+    pub fn nodes(&self) -> &[GraphNode] {
+        &self.nodes
+    }
+
+    pub fn root(&self) -> &Project {
+        // there is always at least the root package
+        &self.nodes.last().unwrap().project
+    }
+
+    pub fn has_dependencies(&self) -> bool {
+        self.nodes.len() > 1
+    }
+
+    pub fn build_dir(&self) -> PathBuf {
+        self.root().dir.join(BUILD_DIR)
+    }
+}
+
+#[derive(Default)]
+struct GraphLoader {
+    nodes: Vec<GraphNode>,
+
+    /// The index of every loaded package, by its canonical directory.
+    indices: HashMap<PathBuf, usize>,
+
+    /// The directories and names of the packages being loaded,
+    /// from the root to the current package.
+    stack: Vec<(PathBuf, String)>,
+}
+
+impl GraphLoader {
+    /// Load a package after its dependencies, returning its index.
+    fn visit(&mut self, project: Project) -> ManifestResult<usize> {
+        let dir = canonicalize(&project.dir)?;
+
+        if let Some(&index) = self.indices.get(&dir) {
+            return Ok(index);
+        }
+
+        if let Some(start) = self.stack.iter().position(|(other, _)| *other == dir) {
+            let cycle = self.stack[start..]
+                .iter()
+                .map(|(_, name)| name.clone())
+                .chain([project.name().to_owned()])
+                .collect();
+
+            return Err(ManifestError::DependencyCycle(cycle));
+        }
+
+        self.stack.push((dir.clone(), project.name().to_owned()));
+
+        let mut dependencies = vec![];
+        for (name, dependency) in &project.manifest.dependencies {
+            let dependency_dir = project.dir.join(&dependency.path);
+            let manifest_path = dependency_dir.join(MANIFEST_FILE);
+            let manifest = Manifest::load(&manifest_path)?;
+
+            if manifest.package.name != *name {
+                return Err(ManifestError::DependencyName {
+                    expected: name.clone(),
+                    found: manifest.package.name,
+                    path: manifest_path,
+                });
+            }
+
+            dependencies.push(self.visit(Project {
+                dir: dependency_dir,
+                manifest,
+            })?);
+        }
+
+        self.stack.pop();
+
+        // packages are linked together, so their names have to be unique
+        if self
+            .nodes
+            .iter()
+            .any(|node| node.project.name() == project.name())
+        {
+            return Err(ManifestError::DuplicatePackage(project.name().to_owned()));
+        }
+
+        let index = self.nodes.len();
+        self.indices.insert(dir, index);
+        self.nodes.push(GraphNode {
+            project,
+            dependencies,
+        });
+
+        Ok(index)
+    }
+}
+
+fn canonicalize(dir: &Path) -> ManifestResult<PathBuf> {
+    dir.canonicalize().map_err(|error| ManifestError::Io {
+        path: dir.to_owned(),
+        error,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use temp_dir::TempDir;
+
+    use super::PackageGraph;
+    use crate::manifest::{ManifestError, Project};
+
+    /// Write the manifests of packages given as names and dependencies.
+    fn write_packages(dir: &TempDir, packages: &[(&str, &[&str])]) {
+        for (name, dependencies) in packages {
+            let mut manifest = format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n");
+
+            manifest.push_str("[dependencies]\n");
+            for dependency in *dependencies {
+                manifest.push_str(&format!(
+                    "{dependency} = {{ path = \"../{dependency}\" }}\n"
+                ));
+            }
+
+            std::fs::create_dir(dir.child(name)).unwrap();
+            std::fs::write(dir.child(name).join("bayou.toml"), manifest).unwrap();
+        }
+    }
+
+    fn load(dir: &TempDir, root: &str) -> Result<PackageGraph, ManifestError> {
+        PackageGraph::load(Project::find(Some(dir.child(root)))?)
+    }
+
+    #[test]
+    fn topological_order() {
+        let dir = TempDir::with_prefix("bayou_test_").unwrap();
+        write_packages(
+            &dir,
+            &[
+                ("app", &["shapes", "colors"]),
+                ("shapes", &["math"]),
+                ("colors", &["math"]),
+                ("math", &[]),
+            ],
+        );
+
+        let graph = load(&dir, "app").unwrap();
+
+        let names: Vec<_> = graph
+            .nodes()
+            .iter()
+            .map(|node| node.project.name())
+            .collect();
+        // dependencies are visited in the order of their names
+        assert_eq!(names, ["math", "colors", "shapes", "app"]);
+
+        let dependencies: Vec<_> = graph
+            .nodes()
+            .iter()
+            .map(|node| node.dependencies.as_slice())
+            .collect();
+        assert_eq!(dependencies, [&[][..], &[0], &[0], &[1, 2]]);
+
+        assert_eq!(graph.root().name(), "app");
+        assert!(graph.has_dependencies());
+    }
+
+    #[test]
+    fn invalid_graphs() {
+        let dir = TempDir::with_prefix("bayou_test_").unwrap();
+        write_packages(
+            &dir,
+            &[("a", &["b"]), ("b", &["c"]), ("c", &["a"]), ("d", &["d"])],
+        );
+
+        assert!(matches!(
+            load(&dir, "a"),
+            Err(ManifestError::DependencyCycle(cycle)) if cycle == ["a", "b", "c", "a"]
+        ));
+        assert!(matches!(
+            load(&dir, "d"),
+            Err(ManifestError::DependencyCycle(cycle)) if cycle == ["d", "d"]
+        ));
+
+        // the dependency `e` is the package `a`
+        write_packages(&dir, &[("mismatch", &[])]);
+        std::fs::write(
+            dir.child("mismatch/bayou.toml"),
+            "[package]\nname = \"mismatch\"\nversion = \"0.1.0\"\n\
+            [dependencies]\ne = { path = \"../a\" }\n",
+        )
+        .unwrap();
+
+        assert!(matches!(
+            load(&dir, "mismatch"),
+            Err(ManifestError::DependencyName { expected, found, .. })
+                if expected == "e" && found == "a"
+        ));
+    }
+}
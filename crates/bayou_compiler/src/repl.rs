@@ -0,0 +1,633 @@
+use std::collections::{HashMap, HashSet};
+use std::io::{BufRead, IsTerminal, Write};
+
+use bayou_backend::JitProgram;
+use bayou_frontend::ast;
+use bayou_frontend::{ModuleTree, ParsedModule};
+use bayou_interner::{Interner, Istr};
+use bayou_ir::ir::{self, Package};
+use bayou_ir::symbols::{FuncId, StaticId};
+use bayou_ir::{Ident, Type};
+use bayou_middle::const_eval::ConstEvaluator;
+use bayou_middle::interp::{size_of, stride_of, PTR_SIZE};
+use bayou_middle::type_check::TypeChecker;
+use bayou_session::diagnostics::span::Span;
+use bayou_session::diagnostics::Diagnostic;
+use bayou_session::sourcemap::{Source, SourceSpan};
+use bayou_session::{sym, CodegenOptions, CrateType, FullSession, Session};
+use target_lexicon::Triple;
+
+use crate::CompilerResult;
+
+/// The name of the function that runs the statements of an input,
+/// which can't clash with user items since it isn't a valid identifier.
+const ENTRY_NAME: &str = "<repl>";
+
+/// Memory owned by the REPL, which JIT compiled code reads and writes through raw addresses.
+struct Storage(Box<[u64]>);
+
+impl Storage {
+    fn new(ty: &Type) -> Self {
+        Self(vec![0; size_of(ty).div_ceil(8).max(1)].into_boxed_slice())
+    }
+
+    fn addr(&mut self) -> i64 {
+        self.0.as_mut_ptr() as i64
+    }
+
+    fn region(&mut self) -> Region {
+        Region {
+            ptr: self.0.as_mut_ptr().cast(),
+            len: size_of_val(&*self.0),
+        }
+    }
+}
+
+/// Memory that the program of an input can write to, which is copied
+/// back from the process that the program runs in.
+#[derive(Clone, Copy)]
+struct Region {
+    ptr: *mut u8,
+    len: usize,
+}
+
+/// A `let` from a previous input.
+///
+/// Each input runs as a separate function, so locals are copied in from their
+/// storage before the input's statements, and copied back out after them.
+/// This means pointers to them don't stay valid across inputs.
+struct ReplLocal {
+    ident: Istr,
+    ty: Type,
+    storage: Storage,
+}
+
+/// An interactive session, where every input is compiled with the JIT
+/// together with the items of all the previous inputs.
+pub struct Repl {
+    session: FullSession,
+    interner: Interner,
+
+    /// The items of previous inputs, one module per input.
+    modules: Vec<ParsedModule>,
+    locals: Vec<ReplLocal>,
+
+    /// The contents of statics after the last run, so that they can be restored
+    /// when everything is compiled again.
+    statics: HashMap<Istr, (Type, Vec<u8>)>,
+
+    /// Kept alive because values can point into their memory, like string literals do.
+    programs: Vec<JitProgram>,
+
+    /// Storage of locals that have been shadowed, which pointers may still refer to.
+    shadowed: Vec<Storage>,
+
+    inputs: usize,
+}
+
+impl Repl {
+    pub fn new(options: CodegenOptions) -> Self {
+        Self {
+            session: FullSession::new(Triple::host(), options),
+            interner: sym::interner(),
+
+            modules: vec![],
+            locals: vec![],
+            statics: HashMap::new(),
+
+            programs: vec![],
+            shadowed: vec![],
+
+            inputs: 0,
+        }
+    }
+
+    /// Read inputs line by line until the end of stdin.
+    pub fn run(mut self) -> CompilerResult<()> {
+        let stdin = std::io::stdin();
+        let interactive = stdin.is_terminal();
+
+        let mut lines = stdin.lock().lines();
+        loop {
+            if interactive {
+                print!(">> ");
+                std::io::stdout().flush()?;
+            }
+
+            let Some(line) = lines.next() else {
+                break;
+            };
+            let line = line?;
+
+            if line.trim().is_empty() {
+                continue;
+            }
+
+            if let Some((value, ty)) = self.eval(&line) {
+                println!("{value}: {ty}");
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Compile and run a line of input, returning the formatted value and type
+    /// of its final expression, if it had one. Errors in the input, including
+    /// errors from the backend and traps while running it, are reported to the
+    /// session instead of being returned.
+    fn eval(&mut self, line: &str) -> Option<(String, Type)> {
+        self.inputs += 1;
+        let name = format!("[{}]", self.inputs);
+        let source_id = self.session.source_map.insert(Source::new(name, line));
+
+        let (input, errors) = bayou_frontend::parse_repl_input(line, source_id, &self.interner);
+        if self.session.report_all(errors, &self.interner).is_err() {
+            return None;
+        }
+
+        // items of this input replace earlier items with the same name
+        let defined: HashSet<Istr> = input.items.iter().filter_map(item_ident).collect();
+
+        let mut modules = self.modules.clone();
+        for module in &mut modules {
+            module
+                .ast
+                .items
+                .retain(|item| item_ident(item).is_none_or(|ident| !defined.contains(&ident)));
+        }
+
+        let module_tree = ModuleTree::new();
+        let mut module = ParsedModule {
+            module_id: module_tree.root_id(),
+            source_id,
+            ast: ast::Module { items: input.items },
+        };
+
+        let has_entry = !input.statements.is_empty() || input.final_expr.is_some();
+        let entry_istr = self.interner.intern(ENTRY_NAME);
+
+        let mut new_locals = vec![];
+        if has_entry {
+            let (entry, locals) = self.entry_func(entry_istr, input.statements, input.final_expr);
+            module.ast.items.push(ast::Item::FuncDecl(entry));
+            new_locals = locals;
+        }
+
+        modules.push(module);
+
+        let mut package = self.check(&modules, module_tree)?;
+
+        if bayou_backend::check_layouts(&mut self.session, &package, &Triple::host()).is_err() {
+            return None;
+        }
+
+        // the entry function isn't kept for later inputs
+        if has_entry {
+            modules.last_mut().unwrap().ast.items.pop();
+        }
+
+        if !has_entry {
+            // redefined statics start over from their new initializers
+            for ident in &defined {
+                self.statics.remove(ident);
+            }
+
+            self.modules = modules;
+            return None;
+        }
+
+        let entry_id = package
+            .symbols
+            .funcs
+            .iter()
+            .position(|func| func.ident.istr == entry_istr)
+            .map(FuncId)
+            .unwrap();
+        package.ir.main_func = Some(entry_id);
+
+        let mut result = store_final_expr(&mut package.ir, entry_id);
+
+        package.interner = std::mem::take(&mut self.interner);
+        let program = bayou_backend::run_jit(&mut self.session, &package);
+        self.interner = std::mem::take(&mut package.interner);
+
+        let program = match program {
+            Ok(program) => program,
+            Err(err) => {
+                let diagnostic = Diagnostic::error().with_message(err.to_string());
+                self.session.emit_diagnostic(diagnostic);
+                return None;
+            }
+        };
+
+        self.restore_statics(&package, &program, &defined);
+
+        // everything that the input can write to, other than memory of its own
+        let mut regions: Vec<Region> = self
+            .locals
+            .iter_mut()
+            .chain(&mut new_locals)
+            .map(|local| &mut local.storage)
+            .chain(&mut self.shadowed)
+            .chain(result.as_mut().map(|(_, storage)| storage))
+            .map(Storage::region)
+            .collect();
+
+        for (id, symbol) in package.symbols.statics.iter().enumerate() {
+            regions.push(Region {
+                ptr: program.static_ptr(StaticId(id)),
+                len: size_of(&symbol.ty),
+            });
+        }
+
+        // SAFETY: running the input is what the user asked for, and the REPL's storage
+        // that it accesses is kept alive for the whole session. A trap in the input
+        // undoes everything it did, like an error compiling it would
+        if let Err(message) = unsafe { run_isolated(&program, &regions) } {
+            let diagnostic = Diagnostic::error().with_message(message);
+            self.session.emit_diagnostic(diagnostic);
+            return None;
+        }
+
+        self.save_statics(&package, &program);
+        self.programs.push(program);
+
+        self.modules = modules;
+        self.commit_locals(new_locals);
+
+        result.map(|(ty, mut storage)| {
+            // SAFETY: the storage was just written by the program, with a value of this type
+            let value = unsafe { format_value(storage.addr() as *const u8, &ty) };
+            (value, ty)
+        })
+    }
+
+    /// Lower and type check the items of all inputs, and evaluate their constants.
+    fn check(&mut self, modules: &[ParsedModule], mut module_tree: ModuleTree) -> Option<Package> {
+        let (mut ir, mut symbols, errors) =
+            bayou_frontend::lower(modules, &mut module_tree, &self.interner, &[]);
+        if self.session.report_all(errors, &self.interner).is_err() {
+            return None;
+        }
+
+        let type_errors = TypeChecker::new(&mut symbols).run(&mut ir);
+        if self.session.report_all(type_errors, &()).is_err() {
+            return None;
+        }
+
+        let const_errors = ConstEvaluator::new(&mut symbols, &self.interner).run(&ir);
+        if self
+            .session
+            .report_all(const_errors, &self.interner)
+            .is_err()
+        {
+            return None;
+        }
+
+        // the interner is moved in for code generation
+        Some(Package {
+            name: String::from("repl"),
+            crate_type: CrateType::Bin,
+            ir,
+            symbols,
+            interner: Interner::default(),
+        })
+    }
+
+    /// Build the function that runs the statements of an input,
+    /// along with the locals that it declares.
+    fn entry_func(
+        &mut self,
+        ident: Istr,
+        statements: Vec<ast::Stmt>,
+        final_expr: Option<ast::Expr>,
+    ) -> (ast::FuncDecl, Vec<ReplLocal>) {
+        let span = Span::empty(0);
+        let expr = |kind| ast::Expr::new(kind, span);
+
+        // the address of a local's storage, as a pointer of its type
+        let storage_ptr = |addr: i64, ty: &Type| {
+            expr(ast::ExprKind::Deref(Box::new(expr(ast::ExprKind::Cast {
+                expr: Box::new(expr(ast::ExprKind::Integer(addr))),
+                ty: ty.clone().ptr(),
+            }))))
+        };
+
+        let mut body = vec![];
+
+        for local in &mut self.locals {
+            body.push(ast::Stmt::Assign {
+                ident: Ident {
+                    istr: local.ident,
+                    span,
+                },
+                ty: local.ty.clone(),
+                expr: storage_ptr(local.storage.addr(), &local.ty),
+            });
+        }
+
+        // locals declared at the top level of this input, where later ones shadow earlier ones
+        let mut declared: Vec<(Istr, Type)> = vec![];
+        for statement in &statements {
+            if let ast::Stmt::Assign { ident, ty, .. } = statement {
+                declared.retain(|(istr, _)| *istr != ident.istr);
+                declared.push((ident.istr, ty.clone()));
+            }
+        }
+
+        let declared_idents: Vec<Istr> = declared.iter().map(|(ident, _)| *ident).collect();
+        let mut locals: Vec<ReplLocal> = declared
+            .into_iter()
+            .map(|(ident, ty)| ReplLocal {
+                storage: Storage::new(&ty),
+                ident,
+                ty,
+            })
+            .collect();
+
+        body.extend(statements);
+
+        // previous locals that weren't shadowed keep their storage
+        let unshadowed = self
+            .locals
+            .iter_mut()
+            .filter(|local| !declared_idents.contains(&local.ident));
+
+        for local in unshadowed.chain(&mut locals) {
+            body.push(ast::Stmt::Store {
+                place: storage_ptr(local.storage.addr(), &local.ty),
+                expr: expr(ast::ExprKind::Var(Ident {
+                    istr: local.ident,
+                    span,
+                })),
+            });
+        }
+
+        // replaced by a store of the value once its type is known
+        if let Some(final_expr) = final_expr {
+            body.push(ast::Stmt::Drop {
+                expr: final_expr,
+                had_semicolon: true,
+            });
+        }
+
+        let func = ast::FuncDecl {
+            ident: Ident { istr: ident, span },
+            params: vec![],
+
+            ret_ty: Type::I64,
+            ret_ty_span: span,
+
+            block: ast::Block {
+                statements: body,
+                final_expr: expr(ast::ExprKind::Integer(0)),
+                span,
+            },
+
+            is_exported: false,
+        };
+
+        (func, locals)
+    }
+
+    /// Add the locals declared by the last input, which shadow previous ones.
+    fn commit_locals(&mut self, locals: Vec<ReplLocal>) {
+        for local in locals {
+            if let Some(index) = self.locals.iter().position(|old| old.ident == local.ident) {
+                let old = self.locals.remove(index);
+                self.shadowed.push(old.storage);
+            }
+
+            self.locals.push(local);
+        }
+    }
+
+    /// Copy the contents of statics from the previous run into the new program,
+    /// unless they were just defined again.
+    fn restore_statics(&self, package: &Package, program: &JitProgram, defined: &HashSet<Istr>) {
+        for (id, symbol) in package.symbols.statics.iter().enumerate() {
+            let ident = symbol.ident.istr;
+            if defined.contains(&ident) {
+                continue;
+            }
+
+            if let Some((ty, bytes)) = self.statics.get(&ident) {
+                if *ty == symbol.ty {
+                    let ptr = program.static_ptr(StaticId(id));
+
+                    // SAFETY: the static has the same type, so it's the same size
+                    unsafe { std::ptr::copy_nonoverlapping(bytes.as_ptr(), ptr, bytes.len()) };
+                }
+            }
+        }
+    }
+
+    /// Remember the contents of statics after a run.
+    fn save_statics(&mut self, package: &Package, program: &JitProgram) {
+        for (id, symbol) in package.symbols.statics.iter().enumerate() {
+            let ptr = program.static_ptr(StaticId(id));
+
+            // SAFETY: the program's statics stay alive as long as it does
+            let bytes = unsafe { std::slice::from_raw_parts(ptr, size_of(&symbol.ty)) }.to_vec();
+
+            self.statics
+                .insert(symbol.ident.istr, (symbol.ty.clone(), bytes));
+        }
+    }
+}
+
+/// Run the program of an input in a child process, so that a trap in it can't end the
+/// session, and copy what it wrote to `regions` back. Returns what stopped the
+/// program if it didn't finish.
+///
+/// # Safety
+///
+/// The program must only write to its own memory and to `regions`.
+#[cfg(unix)]
+unsafe fn run_isolated(program: &JitProgram, regions: &[Region]) -> Result<(), String> {
+    use std::fs::File;
+    use std::io::Read;
+    use std::os::fd::FromRawFd;
+
+    // output buffered before forking would be written by both processes
+    std::io::stdout().flush().map_err(|err| err.to_string())?;
+
+    let mut fds = [0; 2];
+    if libc::pipe(fds.as_mut_ptr()) != 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+    let [read_fd, write_fd] = fds;
+
+    let pid = libc::fork();
+    if pid < 0 {
+        libc::close(read_fd);
+        libc::close(write_fd);
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+
+    if pid == 0 {
+        libc::close(read_fd);
+        program.run_main();
+
+        let mut pipe = File::from_raw_fd(write_fd);
+        for region in regions {
+            let bytes = std::slice::from_raw_parts(region.ptr, region.len);
+            if pipe.write_all(bytes).is_err() {
+                libc::_exit(1);
+            }
+        }
+
+        // the program can print through C's buffered output
+        libc::fflush(std::ptr::null_mut());
+        libc::_exit(0);
+    }
+
+    libc::close(write_fd);
+    let mut bytes = vec![];
+    let read = File::from_raw_fd(read_fd).read_to_end(&mut bytes);
+
+    let mut status = 0;
+    if libc::waitpid(pid, &mut status, 0) < 0 {
+        return Err(std::io::Error::last_os_error().to_string());
+    }
+
+    if libc::WIFSIGNALED(status) {
+        let signal = libc::WTERMSIG(status);
+        let name = std::ffi::CStr::from_ptr(libc::strsignal(signal)).to_string_lossy();
+        return Err(format!("the input was stopped by signal {signal} ({name})"));
+    }
+
+    let total: usize = regions.iter().map(|region| region.len).sum();
+    if read.is_err() || libc::WEXITSTATUS(status) != 0 || bytes.len() != total {
+        return Err(String::from("the input couldn't be run"));
+    }
+
+    let mut offset = 0;
+    for region in regions {
+        let written = &bytes[offset..offset + region.len];
+        std::ptr::copy_nonoverlapping(written.as_ptr(), region.ptr, region.len);
+        offset += region.len;
+    }
+
+    Ok(())
+}
+
+/// Run the program of an input in this process, where a trap ends the session.
+///
+/// # Safety
+///
+/// The program must only write to its own memory and to `regions`.
+#[cfg(not(unix))]
+unsafe fn run_isolated(program: &JitProgram, _regions: &[Region]) -> Result<(), String> {
+    program.run_main();
+    Ok(())
+}
+
+fn item_ident(item: &ast::Item) -> Option<Istr> {
+    match item {
+        ast::Item::FuncDecl(decl) => Some(decl.ident.istr),
+        ast::Item::ExternFuncDecl(decl) => Some(decl.ident.istr),
+        ast::Item::Const(decl) | ast::Item::Static(decl) => Some(decl.ident.istr),
+        ast::Item::Submodule(_) | ast::Item::ParseError => None,
+    }
+}
+
+/// Make the entry function store the value of the input's final expression,
+/// returning its type and where it will be stored.
+fn store_final_expr(ir: &mut ir::PackageIr, entry_id: FuncId) -> Option<(Type, Storage)> {
+    let entry = ir.items.iter_mut().find_map(|item| match item {
+        ir::Item::FuncDecl(decl) if decl.id == entry_id => Some(decl),
+        _ => None,
+    })?;
+
+    let Some(ir::Stmt::Drop(expr)) = entry.block.statements.last() else {
+        return None;
+    };
+
+    let ty = expr.ty.clone()?;
+    if matches!(ty, Type::Void | Type::Never) {
+        return None;
+    }
+
+    let Some(ir::Stmt::Drop(expr)) = entry.block.statements.pop() else {
+        unreachable!()
+    };
+
+    let mut storage = Storage::new(&ty);
+    let span = SourceSpan::new(Span::empty(0), expr.span.source_id);
+
+    let addr = ir::Expr {
+        kind: ir::ExprKind::Constant(ir::Constant::I64(storage.addr())),
+        span,
+        ty: Some(Type::I64),
+    };
+
+    let ptr = ir::Expr {
+        kind: ir::ExprKind::Cast {
+            expr: Box::new(addr),
+            ty: ty.clone().ptr(),
+        },
+        span,
+        ty: Some(ty.clone().ptr()),
+    };
+
+    let place = ir::Expr {
+        kind: ir::ExprKind::Deref(Box::new(ptr)),
+        span,
+        ty: Some(ty.clone()),
+    };
+
+    entry.block.statements.push(ir::Stmt::Store { place, expr });
+
+    Some((ty, storage))
+}
+
+/// Format a value in the memory of this process.
+///
+/// # Safety
+///
+/// `ptr` must point to a valid value of the given type.
+unsafe fn format_value(ptr: *const u8, ty: &Type) -> String {
+    match ty {
+        Type::I64 => ptr.cast::<i64>().read_unaligned().to_string(),
+        Type::F32 => format!("{:?}", ptr.cast::<f32>().read_unaligned()),
+        Type::F64 => format!("{:?}", ptr.cast::<f64>().read_unaligned()),
+        Type::Bool => (ptr.read() != 0).to_string(),
+        Type::Char => match char::from_u32(ptr.cast::<u32>().read_unaligned()) {
+            Some(ch) => format!("{ch:?}"),
+            None => String::from("<invalid char>"),
+        },
+        Type::Ptr(_) => format!("{:#x}", ptr.cast::<usize>().read_unaligned()),
+
+        Type::Str => {
+            let (data, len) = read_slice(ptr);
+            let bytes = std::slice::from_raw_parts(data, len);
+            format!("{:?}", String::from_utf8_lossy(bytes))
+        }
+
+        Type::Array(element, len) => format_elements(ptr, element, *len as usize),
+
+        Type::Slice(element) => {
+            let (data, len) = read_slice(ptr);
+            format_elements(data, element, len)
+        }
+
+        Type::Void | Type::Never => String::new(),
+    }
+}
+
+unsafe fn read_slice(ptr: *const u8) -> (*const u8, usize) {
+    let data = ptr.cast::<*const u8>().read_unaligned();
+    let len = ptr.add(PTR_SIZE).cast::<usize>().read_unaligned();
+    (data, len)
+}
+
+unsafe fn format_elements(ptr: *const u8, element: &Type, len: usize) -> String {
+    let stride = stride_of(element);
+
+    let elements: Vec<_> = (0..len)
+        .map(|i| format_value(ptr.add(i * stride), element))
+        .collect();
+
+    format!("[{}]", elements.join(", "))
+}
@@ -0,0 +1,105 @@
+//! The report printed with `-Z stats`, of how big each compiled package is in the
+//! compiler's data structures, for tuning the compiler on large builds.
+
+use std::fmt;
+
+use bayou_interner::{Interner, InternerStats};
+use bayou_ir::NodeCounts;
+
+pub struct PackageStats {
+    pub ast: NodeCounts,
+    pub ir: NodeCounts,
+    pub interner: InternerStats,
+
+    /// The strings of [`InternerStats::longest`].
+    pub longest_strings: Vec<String>,
+}
+
+impl PackageStats {
+    pub fn new(ast: NodeCounts, ir: NodeCounts, interner: &Interner) -> Self {
+        let stats = interner.stats();
+        let longest_strings = stats
+            .longest
+            .iter()
+            .map(|&istr| interner[istr].to_owned())
+            .collect();
+
+        Self {
+            ast,
+            ir,
+            interner: stats,
+            longest_strings,
+        }
+    }
+}
+
+#[derive(Default)]
+pub struct Stats {
+    /// The packages in the order they were compiled, without stats
+    /// for packages that were reused from the cache.
+    packages: Vec<(String, Option<PackageStats>)>,
+}
+
+impl Stats {
+    pub fn add_package(&mut self, name: impl Into<String>, stats: PackageStats) {
+        self.packages.push((name.into(), Some(stats)));
+    }
+
+    pub fn add_cached_package(&mut self, name: impl Into<String>) {
+        self.packages.push((name.into(), None));
+    }
+
+    pub fn print(&self) {
+        eprint!("{self}");
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "stats:")?;
+
+        for (name, stats) in &self.packages {
+            // packages reused from the cache aren't compiled, so they have no stats
+            let Some(stats) = stats else {
+                writeln!(f, "  {name}  cached")?;
+                continue;
+            };
+
+            let interner = &stats.interner;
+
+            writeln!(f, "  {name}")?;
+            writeln!(f, "    ast          {}", DisplayCounts(stats.ast))?;
+            writeln!(f, "    ir           {}", DisplayCounts(stats.ir))?;
+            writeln!(
+                f,
+                "    interner     {} strings, {} bytes of strings, {} bytes allocated",
+                interner.strings, interner.string_bytes, interner.arena_bytes
+            )?;
+            writeln!(
+                f,
+                "    hash table   {} strings, capacity for {}",
+                interner.table_len, interner.table_capacity
+            )?;
+            writeln!(f, "    longest      {:?}", stats.longest_strings)?;
+        }
+
+        Ok(())
+    }
+}
+
+struct DisplayCounts(NodeCounts);
+
+impl fmt::Display for DisplayCounts {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let NodeCounts {
+            items,
+            statements,
+            exprs,
+        } = self.0;
+
+        write!(
+            f,
+            "{items} items, {statements} statements, {exprs} expressions"
+        )
+    }
+}
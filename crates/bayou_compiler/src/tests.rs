@@ -0,0 +1,188 @@
+use bayou_ir::interface::{FuncInterface, PackageInterface};
+use bayou_ir::mangle::mangle;
+use bayou_ir::Type;
+use bayou_middle::interp::Interpreter;
+use bayou_session::{Session, TestSession, TestSessionConfig};
+use bayou_utils::assert_yaml_snapshot_with_source;
+use target_lexicon::Triple;
+
+use crate::compilation::check_package;
+
+/// The stdout output and exit code of a program.
+type Outcome = (String, i32);
+
+fn interpret(source: &str) -> Result<Outcome, TestSession> {
+    let mut session = TestSession::new();
+    let config = TestSessionConfig::new(
+        "test_package",
+        [(String::from("package"), String::from(source))],
+    );
+
+    let Ok((package, _)) = check_package(&mut session, config, &[], None) else {
+        return Err(session);
+    };
+
+    let mut stdout = vec![];
+    let result = Interpreter::new(&package.ir, &package.symbols, &package.interner)
+        .with_output(&mut stdout, std::io::sink())
+        .run_main();
+
+    match result {
+        // exit codes are truncated to a byte, like they are by the OS
+        Ok(exit_code) => Ok((String::from_utf8(stdout).unwrap(), exit_code as u8 as i32)),
+        Err(errors) => {
+            let _ = session.report_all(errors, &package.interner);
+            Err(session)
+        }
+    }
+}
+
+macro_rules! assert_interp_errors {
+    ($source:expr) => {{
+        let source = $source;
+        let Err(session) = interpret(source) else {
+            panic!("program ran without errors");
+        };
+
+        assert_yaml_snapshot_with_source!(source => session.diagnostics);
+    }};
+}
+
+/// Check a program which has no errors, snapshotting its warnings.
+macro_rules! assert_warnings {
+    ($source:expr) => {{
+        let source = $source;
+        let mut session = TestSession::new();
+        let config = TestSessionConfig::new(
+            "test_package",
+            [(String::from("package"), String::from(source))],
+        );
+
+        assert!(check_package(&mut session, config, &[], None).is_ok());
+        assert_yaml_snapshot_with_source!(source => session.diagnostics);
+    }};
+}
+
+#[test]
+fn interp_const_calls_function() {
+    let source = "
+        func fact(n: i64) -> i64 { if n == 0 then 1 else n * fact(n - 1) }
+        const F: i64 = fact(5);
+        func main() -> i64 { F }
+    ";
+
+    assert_eq!(interpret(source).ok(), Some((String::new(), 120)));
+}
+
+#[test]
+fn interp_division_by_zero() {
+    assert_interp_errors!("func main() -> i64 { let a: i64 = 0; 10 / a }");
+}
+
+#[test]
+fn interp_overflow() {
+    assert_interp_errors!("func main() -> i64 { let a: i64 = 9223372036854775807; a + 1 }");
+}
+
+#[test]
+fn interp_index_out_of_bounds() {
+    assert_interp_errors!("func main() -> i64 { let a: [i64; 2] = [1, 2]; let i: i64 = 2; a[i] }");
+}
+
+#[test]
+fn interp_use_after_free() {
+    assert_interp_errors!("func f() -> *i64 { let x: i64 = 1; &x } func main() -> i64 { *f() }");
+}
+
+#[test]
+fn interp_unsupported_extern() {
+    assert_interp_errors!(
+        "extern func puts(s: *void) -> i64; func main() -> i64 { puts(\"a\" as *void) }"
+    );
+}
+
+#[test]
+fn const_static_not_const() {
+    assert_interp_errors!("static S: i64 = 1; const C: i64 = S; func main() -> i64 { C }");
+}
+
+#[test]
+fn const_static_array() {
+    assert_interp_errors!(
+        "const C: [i64; 2] = [1, 2]; static S: [i64; 1] = [C[0]]; func main() -> i64 { 0 }"
+    );
+}
+
+/// Check a package that depends on a package `math` exporting `square`.
+fn check_with_math_dependency(source: &str) -> TestSession {
+    let math = PackageInterface {
+        name: String::from("math"),
+        funcs: vec![FuncInterface {
+            name: String::from("square"),
+            module: vec![],
+            symbol: mangle("math", [], "square"),
+            params: vec![Type::I64],
+            ret_ty: Type::I64,
+        }],
+    };
+
+    let mut session = TestSession::new();
+    let config = TestSessionConfig::new(
+        "test_package",
+        [(String::from("package"), String::from(source))],
+    );
+
+    assert!(check_package(&mut session, config, &[math], None).is_err());
+    session
+}
+
+#[test]
+fn package_name_errors() {
+    let source = "
+        func square(x: i64) -> i64 { x }
+        func main() -> i64 { colors::red() + math::cube(2) + math::square(2) }
+    ";
+
+    let session = check_with_math_dependency(source);
+    assert_yaml_snapshot_with_source!(source => session.diagnostics);
+}
+
+#[test]
+fn package_func_type_error() {
+    let source = "func main() -> i64 { math::square(true) }";
+
+    let session = check_with_math_dependency(source);
+    assert_yaml_snapshot_with_source!(source => session.diagnostics);
+}
+
+#[test]
+fn type_too_large() {
+    let source = "
+        func first(a: *[[i64; 4294967296]; 2]) -> i64 { (*a)[0][0] }
+        func main() -> i64 { 0 }
+    ";
+
+    let mut session = TestSession::new();
+    let config = TestSessionConfig::new(
+        "test_package",
+        [(String::from("package"), String::from(source))],
+    );
+
+    let (package, _) = check_package(&mut session, config, &[], None).unwrap();
+    assert!(bayou_backend::check_layouts(&mut session, &package, &Triple::host()).is_err());
+    assert_yaml_snapshot_with_source!(source => session.diagnostics);
+}
+
+#[test]
+fn unused_variables() {
+    assert_warnings!(
+        "func f(unused: i64, used: i64, _ignored: i64) -> i64 {
+            let assigned: i64 = 1;
+            assigned = 2;
+            let read_in_block: i64 = 3;
+            let x: i64 = if used > 0 then { read_in_block } else 0;
+            x
+        }
+        func main() -> i64 { f(1, 2, 3) }"
+    );
+}
@@ -0,0 +1,97 @@
+//! The report printed with `--timings`, of how long each step of a build took
+//! and what was reused from the incremental compilation cache.
+
+use std::fmt;
+use std::time::{Duration, Instant};
+
+use bayou_backend::CodegenStats;
+
+pub enum PackageTiming {
+    /// The package was reused from the cache without being compiled.
+    Cached(Duration),
+
+    Compiled {
+        check: Duration,
+        codegen: Duration,
+
+        /// How many of the modules of the package were parsed in an earlier build.
+        modules_cached: usize,
+        modules: usize,
+
+        stats: CodegenStats,
+    },
+}
+
+pub struct Timings {
+    start: Instant,
+    packages: Vec<(String, PackageTiming)>,
+    link: Option<Duration>,
+}
+
+impl Timings {
+    pub fn start() -> Self {
+        Self {
+            start: Instant::now(),
+            packages: vec![],
+            link: None,
+        }
+    }
+
+    pub fn add_package(&mut self, name: impl Into<String>, timing: PackageTiming) {
+        self.packages.push((name.into(), timing));
+    }
+
+    pub fn set_link(&mut self, time: Duration) {
+        self.link = Some(time);
+    }
+
+    /// Print the report, with the time since the build started as the total.
+    pub fn print(&self) {
+        eprint!("{self}");
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let width = self
+            .packages
+            .iter()
+            .map(|(name, _)| name.len())
+            .chain(["linking".len()])
+            .max()
+            .unwrap_or_default();
+
+        writeln!(f, "timings:")?;
+
+        for (name, timing) in &self.packages {
+            match timing {
+                PackageTiming::Cached(time) => {
+                    writeln!(f, "  {name:width$}  cached ({time:.2?})")?;
+                }
+
+                PackageTiming::Compiled {
+                    check,
+                    codegen,
+                    modules_cached,
+                    modules,
+                    stats,
+                } => {
+                    let funcs = stats.funcs_compiled + stats.funcs_cached;
+                    writeln!(
+                        f,
+                        "  {name:width$}  checked in {check:.2?} ({modules_cached} of {modules} \
+                        modules cached), codegen in {codegen:.2?}, \
+                        {} of {funcs} functions cached",
+                        stats.funcs_cached
+                    )?;
+                }
+            }
+        }
+
+        if let Some(link) = self.link {
+            writeln!(f, "  {:width$}  {link:.2?}", "linking")?;
+        }
+
+        writeln!(f, "  {:width$}  {:.2?}", "total", self.start.elapsed())
+    }
+}
@@ -0,0 +1,58 @@
+//! Helpers shared by the integration tests, which each use some of them.
+#![allow(dead_code)]
+
+use std::path::Path;
+use std::process::{Command, Output};
+
+/// The compiler, as a command to add arguments to.
+pub fn compiler() -> Command {
+    Command::new(env!("CARGO_BIN_EXE_bayou_compiler"))
+}
+
+/// Run the compiler with `args` in `working_dir`, where it finds the package.
+pub fn run_compiler(args: &[&str], working_dir: &Path) -> Output {
+    compiler()
+        .args(args)
+        .current_dir(working_dir)
+        .output()
+        .unwrap()
+}
+
+/// Build the package in `working_dir` with extra arguments, which has to succeed.
+pub fn build(args: &[&str], working_dir: &Path) -> Output {
+    let output = compiler()
+        .arg("build")
+        .args(args)
+        .current_dir(working_dir)
+        .output()
+        .unwrap();
+    assert!(output.status.success(), "{output:?}");
+
+    output
+}
+
+/// Write a package into a directory named after it in `dir`, with a
+/// manifest listing its path dependencies next to it.
+pub fn write_package(dir: &Path, name: &str, dependencies: &[&str], source: &str) {
+    let package_dir = dir.join(name);
+    std::fs::create_dir_all(&package_dir).unwrap();
+
+    let mut manifest = format!("[package]\nname = \"{name}\"\nversion = \"0.1.0\"\n");
+    manifest.push_str("[dependencies]\n");
+    for dependency in dependencies {
+        manifest.push_str(&format!(
+            "{dependency} = {{ path = \"../{dependency}\" }}\n"
+        ));
+    }
+
+    std::fs::write(package_dir.join("bayou.toml"), manifest).unwrap();
+    std::fs::write(package_dir.join("main.by"), source).unwrap();
+}
+
+pub fn stdout(output: &Output) -> String {
+    String::from_utf8(output.stdout.clone()).unwrap()
+}
+
+pub fn stderr(output: &Output) -> String {
+    String::from_utf8(output.stderr.clone()).unwrap()
+}
@@ -0,0 +1,101 @@
+//! Building libraries and object files, and using them from C.
+
+mod common;
+
+use std::path::{Path, PathBuf};
+use std::process::Command;
+
+use bayou_backend::object::read::{File, Object as _, ObjectSymbol as _};
+use temp_dir::TempDir;
+
+use common::{build, write_package};
+
+const LIBRARY: &str = "
+    func square(x: i64) -> i64 { x * x }
+
+    static COUNT: i64 = 0;
+
+    export func sum_of_squares(a: i64, b: i64) -> i64 {
+        COUNT = COUNT + 1;
+        square(a) + square(b) + COUNT
+    }
+";
+
+const C_PROGRAM: &str = "
+    long sum_of_squares(long a, long b);
+    int main(void) { return sum_of_squares(3, 4) + sum_of_squares(1, 1); }
+";
+
+/// Build [`LIBRARY`] as a package named `squares`, returning the output path.
+fn build_library(dir: &TempDir, crate_type: &str, output: &str) -> PathBuf {
+    write_package(dir.path(), "squares", &[], LIBRARY);
+
+    let output = dir.child(output);
+    build(
+        &["--crate-type", crate_type, "-o", output.to_str().unwrap()],
+        &dir.child("squares"),
+    );
+
+    output
+}
+
+/// Compile [`C_PROGRAM`] with extra arguments and run it, returning its exit code.
+fn run_c_program(dir: &TempDir, args: &[&Path]) -> Option<i32> {
+    let c_file = dir.child("main.c");
+    std::fs::write(&c_file, C_PROGRAM).unwrap();
+
+    let executable = dir.child("main");
+    let status = Command::new("cc")
+        .arg("-o")
+        .arg(&executable)
+        .arg(&c_file)
+        .args(args)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    Command::new(&executable)
+        .env("LD_LIBRARY_PATH", dir.path())
+        .status()
+        .unwrap()
+        .code()
+}
+
+#[test]
+fn object_exports_only_export_functions() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    let object_file = build_library(&dir, "obj", "squares.o");
+
+    let data = std::fs::read(&object_file).unwrap();
+    let object = File::parse(&*data).unwrap();
+
+    let is_global = |name: &str| {
+        object
+            .symbols()
+            .find(|symbol| symbol.name() == Ok(name))
+            .map(|symbol| symbol.is_global())
+    };
+
+    assert_eq!(is_global("sum_of_squares"), Some(true));
+    assert_eq!(is_global("_B7squares6square"), Some(false));
+    assert_eq!(is_global("_B7squares5COUNT"), Some(false));
+
+    // 25 + 1 and 2 + 2
+    assert_eq!(run_c_program(&dir, &[&object_file]), Some(30));
+}
+
+#[test]
+fn static_library() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    let library = build_library(&dir, "staticlib", "libsquares.a");
+
+    assert_eq!(run_c_program(&dir, &[&library]), Some(30));
+}
+
+#[test]
+fn shared_library() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    let library = build_library(&dir, "cdylib", "libsquares.so");
+
+    assert_eq!(run_c_program(&dir, &[&library]), Some(30));
+}
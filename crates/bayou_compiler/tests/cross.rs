@@ -0,0 +1,154 @@
+//! Compiling for targets other than the host, without running the results.
+
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+use bayou_backend::object::read::{File, Object as _};
+use bayou_backend::object::{Architecture, BinaryFormat};
+use temp_dir::TempDir;
+
+use common::{compiler, write_package};
+
+const PROGRAM: &str = "func main() -> i64 { 6 * 7 }";
+
+/// Build [`PROGRAM`] with the given arguments, writing the output to `output`.
+fn build_program(args: &[&str], output: &Path, envs: &[(&str, &std::ffi::OsStr)]) -> Output {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(dir.path(), "program", &[], PROGRAM);
+
+    compiler()
+        .arg("build")
+        .args(args)
+        .arg("-o")
+        .arg(output)
+        .current_dir(dir.child("program"))
+        .envs(envs.iter().copied())
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn objects_for_foreign_targets() {
+    let targets = [
+        (
+            "x86_64-unknown-linux-gnu",
+            Architecture::X86_64,
+            BinaryFormat::Elf,
+        ),
+        (
+            "aarch64-unknown-linux-gnu",
+            Architecture::Aarch64,
+            BinaryFormat::Elf,
+        ),
+        (
+            "riscv64gc-unknown-linux-gnu",
+            Architecture::Riscv64,
+            BinaryFormat::Elf,
+        ),
+        (
+            "s390x-unknown-linux-gnu",
+            Architecture::S390x,
+            BinaryFormat::Elf,
+        ),
+        (
+            "x86_64-unknown-linux-musl",
+            Architecture::X86_64,
+            BinaryFormat::Elf,
+        ),
+        (
+            "aarch64-apple-darwin",
+            Architecture::Aarch64,
+            BinaryFormat::MachO,
+        ),
+        (
+            "x86_64-pc-windows-gnu",
+            Architecture::X86_64,
+            BinaryFormat::Coff,
+        ),
+    ];
+
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+
+    for (target, arch, format) in targets {
+        let object_file = dir.child(format!("{target}.o"));
+        let output = build_program(&["--emit=obj", "--target", target], &object_file, &[]);
+        assert!(output.status.success(), "{target}");
+
+        let data = std::fs::read(&object_file).unwrap();
+        let object = File::parse(&*data).unwrap();
+
+        assert_eq!(object.architecture(), arch, "{target}");
+        assert_eq!(object.format(), format, "{target}");
+        assert_eq!(
+            object.is_little_endian(),
+            arch != Architecture::S390x,
+            "{target}"
+        );
+    }
+}
+
+#[test]
+fn configured_linker_for_target() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+
+    let output = build_program(
+        &["--target", "aarch64-unknown-linux-gnu"],
+        &dir.child("main"),
+        &[(
+            "BAYOU_TARGET_AARCH64_UNKNOWN_LINUX_GNU_LINKER",
+            "no_such_cross_linker".as_ref(),
+        )],
+    );
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "couldn't find linker `no_such_cross_linker`\n"
+    );
+}
+
+/// A cross compiler named after the target is found in `PATH`,
+/// and given the sysroot configured for the target.
+#[cfg(unix)]
+#[test]
+fn cross_compiler_is_detected() {
+    use std::os::unix::fs::PermissionsExt;
+
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+
+    // records its arguments instead of linking
+    let cross_gcc = dir.child("aarch64-linux-gnu-gcc");
+    let args_file = dir.child("args");
+    std::fs::write(
+        &cross_gcc,
+        format!("#!/bin/sh\necho \"$@\" > '{}'\n", args_file.display()),
+    )
+    .unwrap();
+    std::fs::set_permissions(&cross_gcc, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+    let path = std::env::join_paths(
+        std::iter::once(dir.path().to_owned())
+            .chain(std::env::split_paths(&std::env::var_os("PATH").unwrap())),
+    )
+    .unwrap();
+
+    let executable = dir.child("main");
+    let output = build_program(
+        &["--target", "aarch64-unknown-linux-gnu"],
+        &executable,
+        &[
+            ("PATH", &path),
+            (
+                "BAYOU_TARGET_AARCH64_UNKNOWN_LINUX_GNU_SYSROOT",
+                "/opt/aarch64".as_ref(),
+            ),
+        ],
+    );
+    assert!(output.status.success());
+
+    let args = std::fs::read_to_string(args_file).unwrap();
+    let expected = format!("--sysroot=/opt/aarch64 -o {} ", executable.display());
+    assert!(args.starts_with(&expected), "{args}");
+}
@@ -0,0 +1,188 @@
+//! Building packages with path dependencies on other packages.
+
+mod common;
+
+use std::process::Command;
+
+use temp_dir::TempDir;
+
+use common::{run_compiler, write_package};
+
+/// Write an `app` package using `shapes`, which both use `math`.
+fn write_packages(dir: &TempDir) {
+    write_package(
+        dir.path(),
+        "math",
+        &[],
+        "export func square(x: i64) -> i64 { x * x }
+        func helper() -> i64 { 1 }",
+    );
+
+    write_package(
+        dir.path(),
+        "shapes",
+        &["math"],
+        "export func area(w: i64, h: i64) -> i64 { w * h }
+        export func square_area(side: i64) -> i64 { math::square(side) }
+        func helper() -> i64 { 2 }",
+    );
+
+    write_package(
+        dir.path(),
+        "app",
+        &["shapes", "math"],
+        "func helper() -> i64 { 3 }
+        export func app_main() -> i64 {
+            shapes::square_area(4) + shapes::area(2, 3) + math::square(2) + helper()
+        }
+        func main() -> i64 { app_main() }",
+    );
+}
+
+#[test]
+fn executable_links_dependencies() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_packages(&dir);
+
+    let app_dir = dir.child("app");
+    let output = run_compiler(&["build"], &app_dir);
+    assert!(output.status.success());
+
+    // dependencies are compiled first, and only once
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "compiling project `math` v0.1.0\n\
+        compiling project `shapes` v0.1.0\n\
+        compiling project `app` v0.1.0\n\
+        linking\n"
+    );
+
+    // dependencies are compiled into objects with metadata next to them
+    for file in ["math.o", "math.bmeta", "shapes.o", "shapes.bmeta"] {
+        assert!(app_dir.join("target").join(file).is_file(), "{file}");
+    }
+
+    let metadata = std::fs::read_to_string(app_dir.join("target/shapes.bmeta")).unwrap();
+    assert!(
+        metadata.contains(r#""symbol": "_B6shapes11square_area""#),
+        "{metadata}"
+    );
+
+    // 16 + 6 + 4 + 3
+    let status = Command::new(app_dir.join("app")).status().unwrap();
+    assert_eq!(status.code(), Some(29));
+
+    let output = run_compiler(&["run"], &app_dir);
+    assert_eq!(output.status.code(), Some(29));
+
+    let output = run_compiler(&["run", "--jit"], &app_dir);
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "packages with dependencies can't be run with the JIT yet\n"
+    );
+}
+
+#[test]
+fn static_library_contains_dependencies() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_packages(&dir);
+
+    let app_dir = dir.child("app");
+    let output = run_compiler(&["build", "--crate-type=staticlib"], &app_dir);
+    assert!(output.status.success());
+
+    let c_file = dir.child("main.c");
+    std::fs::write(
+        &c_file,
+        "long app_main(void); int main(void) { return app_main(); }",
+    )
+    .unwrap();
+
+    let executable = dir.child("main");
+    let status = Command::new("cc")
+        .arg("-o")
+        .arg(&executable)
+        .arg(&c_file)
+        .arg(app_dir.join("libapp.a"))
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    let status = Command::new(&executable).status().unwrap();
+    assert_eq!(status.code(), Some(29));
+}
+
+#[test]
+fn same_names_in_packages_and_modules() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(dir.path(), "left", &[], "export func value() -> i64 { 1 }");
+    write_package(
+        dir.path(),
+        "right",
+        &[],
+        "export func value() -> i64 { 10 }",
+    );
+    write_package(
+        dir.path(),
+        "app",
+        &["left", "right"],
+        "submodule util;
+        func value() -> i64 { 100 }
+        func main() -> i64 { left::value() + right::value() + value() }",
+    );
+
+    let app_dir = dir.child("app");
+    std::fs::write(app_dir.join("util.by"), "func value() -> i64 { 1000 }").unwrap();
+
+    let output = run_compiler(&["build"], &app_dir);
+    assert!(output.status.success(), "{output:?}");
+
+    let status = Command::new(app_dir.join("app")).status().unwrap();
+    assert_eq!(status.code(), Some(111));
+}
+
+#[test]
+fn same_named_statics() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(
+        dir.path(),
+        "counter",
+        &[],
+        "static COUNTER: i64 = 1;
+        export func count() -> i64 { COUNTER }",
+    );
+    // `stdout` is also a static of libc
+    write_package(
+        dir.path(),
+        "app",
+        &["counter"],
+        "submodule util;
+        static COUNTER: i64 = 10;
+        static stdout: i64 = 100;
+        func main() -> i64 { counter::count() + COUNTER + stdout }",
+    );
+
+    let app_dir = dir.child("app");
+    std::fs::write(app_dir.join("util.by"), "static COUNTER: i64 = 1000;").unwrap();
+
+    let output = run_compiler(&["build"], &app_dir);
+    assert!(output.status.success(), "{output:?}");
+
+    let status = Command::new(app_dir.join("app")).status().unwrap();
+    assert_eq!(status.code(), Some(111));
+}
+
+#[test]
+fn dependency_cycle() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(dir.path(), "a", &["b"], "func main() -> i64 { 0 }");
+    write_package(dir.path(), "b", &["a"], "");
+
+    let output = run_compiler(&["build"], &dir.child("a"));
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(
+        String::from_utf8(output.stderr).unwrap(),
+        "cyclic dependency between packages: a -> b -> a\n"
+    );
+}
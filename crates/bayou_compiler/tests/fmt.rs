@@ -0,0 +1,96 @@
+//! Formatting modules with `bayou fmt`.
+
+mod common;
+
+use std::path::Path;
+use std::process::Output;
+
+use bayou_interner::Interner;
+use temp_dir::TempDir;
+
+use common::compiler;
+
+fn format(source: &str) -> String {
+    let interner = Interner::new();
+
+    let (cst, lexer_errors, parse_errors) = bayou_frontend::parse_lossless(source, &interner);
+    assert!(lexer_errors.is_empty() && parse_errors.is_empty());
+
+    bayou_frontend::format::format_module(&cst)
+}
+
+fn fmt(args: &[&str], package_dir: &Path) -> Output {
+    compiler()
+        .arg("fmt")
+        .args(args)
+        .arg(package_dir)
+        .output()
+        .unwrap()
+}
+
+#[test]
+fn samples_formatted() {
+    let samples_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../samples");
+
+    for entry in std::fs::read_dir(samples_dir).unwrap() {
+        let path = entry.unwrap().path();
+        let name = path.file_stem().unwrap().to_str().unwrap().to_owned();
+
+        let source = std::fs::read_to_string(&path).unwrap();
+        let formatted = format(&source);
+
+        assert_eq!(
+            format(&formatted),
+            formatted,
+            "{}: formatting isn't idempotent",
+            path.display()
+        );
+
+        insta::assert_snapshot!(name, formatted);
+    }
+}
+
+#[test]
+fn check_then_format() {
+    let package_dir = TempDir::with_prefix("bayou_test_").unwrap();
+    std::fs::write(
+        package_dir.child("main.by"),
+        "submodule util;\nfunc main()->i64{util::one()} // the answer\n",
+    )
+    .unwrap();
+    std::fs::write(
+        package_dir.child("util.by"),
+        "func one() -> i64 {\n    1\n}\n",
+    )
+    .unwrap();
+
+    let check = fmt(&["--check"], package_dir.path());
+    assert!(!check.status.success());
+
+    // only the unformatted module is listed, and nothing is written
+    let listed = String::from_utf8(check.stdout).unwrap();
+    assert_eq!(listed.lines().count(), 1);
+    assert!(listed.trim_end().ends_with("main.by"));
+
+    assert!(fmt(&[], package_dir.path()).status.success());
+    assert_eq!(
+        std::fs::read_to_string(package_dir.child("main.by")).unwrap(),
+        "submodule util;\n\nfunc main() -> i64 {\n    util::one()\n} // the answer\n"
+    );
+
+    assert!(fmt(&["--check"], package_dir.path()).status.success());
+}
+
+#[test]
+fn syntax_errors_not_formatted() {
+    let package_dir = TempDir::with_prefix("bayou_test_").unwrap();
+    let source = "func main()->i64{ 1 +}";
+    std::fs::write(package_dir.child("main.by"), source).unwrap();
+
+    let output = fmt(&[], package_dir.path());
+    assert!(!output.status.success());
+    assert_eq!(
+        std::fs::read_to_string(package_dir.child("main.by")).unwrap(),
+        source
+    );
+}
@@ -0,0 +1,181 @@
+//! Reusing packages and functions from the incremental compilation cache.
+
+mod common;
+
+use std::process::{Command, Output};
+
+use temp_dir::TempDir;
+
+use common::{build, stderr, stdout, write_package};
+
+/// The timings report, without the durations.
+fn timings(output: &Output) -> Vec<String> {
+    let stderr = stderr(output);
+    let report = stderr.split("timings:\n").nth(1).unwrap();
+
+    report
+        .lines()
+        .filter(|line| !line.contains("linking") && !line.contains("total"))
+        .map(|line| {
+            let words: Vec<_> = line.split_whitespace().collect();
+            match words[1] {
+                "cached" => format!("{} cached", words[0]),
+                _ => {
+                    let (_, modules) = line.split_once('(').unwrap();
+                    let (modules, _) = modules.split_once(')').unwrap();
+                    let (_, funcs) = line.rsplit_once(", ").unwrap();
+                    format!("{} compiled, {modules}, {funcs}", words[0])
+                }
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn unchanged_packages_are_cached() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(
+        dir.path(),
+        "math",
+        &[],
+        "export func square(x: i64) -> i64 { x * x }",
+    );
+    write_package(
+        dir.path(),
+        "app",
+        &["math"],
+        "func helper() -> i64 { 1 }
+        func main() -> i64 { math::square(3) + helper() }",
+    );
+
+    let app_dir = dir.child("app");
+    let output = build(&["--timings"], &app_dir);
+    assert_eq!(
+        timings(&output),
+        [
+            "math compiled, 0 of 1 modules cached, 0 of 1 functions cached",
+            "app compiled, 0 of 1 modules cached, 0 of 2 functions cached"
+        ]
+    );
+
+    let output = build(&["--timings"], &app_dir);
+    assert_eq!(stdout(&output), "linking\n");
+    assert_eq!(timings(&output), ["math cached", "app cached"]);
+
+    // only the changed function is compiled again
+    std::fs::write(
+        app_dir.join("main.by"),
+        "func helper() -> i64 { 1 }
+        func main() -> i64 { math::square(3) + helper() + 1 }",
+    )
+    .unwrap();
+
+    let output = build(&["--timings"], &app_dir);
+    assert_eq!(stdout(&output), "compiling project `app` v0.1.0\nlinking\n");
+    assert_eq!(
+        timings(&output),
+        [
+            "math cached",
+            "app compiled, 0 of 1 modules cached, 1 of 2 functions cached"
+        ]
+    );
+
+    let status = Command::new(app_dir.join("app")).status().unwrap();
+    assert_eq!(status.code(), Some(11));
+
+    // packages using a dependency aren't compiled again if its interface stays the same
+    std::fs::write(
+        dir.child("math/main.by"),
+        "export func square(x: i64) -> i64 { x * x * 1 }",
+    )
+    .unwrap();
+
+    let output = build(&["--timings"], &app_dir);
+    assert_eq!(
+        timings(&output),
+        [
+            "math compiled, 0 of 1 modules cached, 0 of 1 functions cached",
+            "app cached"
+        ]
+    );
+
+    // but they are if it changes
+    std::fs::write(
+        dir.child("math/main.by"),
+        "export func square(x: i64, y: i64) -> i64 { x * y }",
+    )
+    .unwrap();
+    std::fs::write(
+        app_dir.join("main.by"),
+        "func helper() -> i64 { 1 }
+        func main() -> i64 { math::square(3, 3) + helper() + 1 }",
+    )
+    .unwrap();
+
+    let output = build(&["--timings"], &app_dir);
+    assert_eq!(
+        timings(&output),
+        [
+            "math compiled, 0 of 1 modules cached, 0 of 1 functions cached",
+            "app compiled, 0 of 1 modules cached, 1 of 2 functions cached"
+        ]
+    );
+
+    let status = Command::new(app_dir.join("app")).status().unwrap();
+    assert_eq!(status.code(), Some(11));
+}
+
+#[test]
+fn warnings_are_shown_every_build() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(
+        dir.path(),
+        "warn",
+        &[],
+        "func main() -> i64 { let x: i64 = 1; 0 }",
+    );
+
+    for cached in 0..2 {
+        let output = build(&["--timings"], &dir.child("warn"));
+        let stderr = stderr(&output);
+        assert!(stderr.contains("unused variable"), "{stderr}");
+        assert_eq!(
+            timings(&output),
+            [format!(
+                "warn compiled, {cached} of 1 modules cached, {cached} of 1 functions cached"
+            )]
+        );
+    }
+}
+
+#[test]
+fn unchanged_modules_are_cached() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(
+        dir.path(),
+        "app",
+        &[],
+        "submodule util;
+        func main() -> i64 { 7 }",
+    );
+    let app_dir = dir.child("app");
+    std::fs::write(app_dir.join("util.by"), "func one() -> i64 { 1 }").unwrap();
+
+    let output = build(&["--timings"], &app_dir);
+    assert_eq!(
+        timings(&output),
+        ["app compiled, 0 of 2 modules cached, 0 of 2 functions cached"]
+    );
+
+    // only the changed module is parsed again
+    std::fs::write(app_dir.join("util.by"), "func one() -> i64 { 2 - 1 }").unwrap();
+
+    let output = build(&["--timings"], &app_dir);
+    assert_eq!(
+        timings(&output),
+        ["app compiled, 1 of 2 modules cached, 1 of 2 functions cached"]
+    );
+
+    let status = Command::new(app_dir.join("app")).status().unwrap();
+    assert_eq!(status.code(), Some(7));
+}
@@ -0,0 +1,67 @@
+//! Selecting and configuring the linker from the command line.
+
+mod common;
+
+use std::process::{Command, Output};
+
+use temp_dir::TempDir;
+
+use common::{run_compiler, write_package};
+
+const PROGRAM: &str = "func main() -> i64 { 6 * 7 }";
+
+/// Run the compiler on a package containing [`PROGRAM`].
+fn run_program(args: &[&str]) -> Output {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(dir.path(), "program", &[], PROGRAM);
+
+    run_compiler(args, &dir.child("program"))
+}
+
+fn is_installed(program: &str) -> bool {
+    Command::new(program)
+        .arg("--version")
+        .output()
+        .is_ok_and(|output| output.status.success())
+}
+
+#[test]
+fn detected_linker() {
+    let output = run_program(&["run"]);
+    assert_eq!(output.status.code(), Some(42));
+}
+
+#[test]
+fn linker_run_directly() {
+    if !is_installed("ld") {
+        return;
+    }
+
+    let output = run_program(&["run", "--linker=ld"]);
+    assert_eq!(output.status.code(), Some(42));
+}
+
+#[test]
+fn missing_linker() {
+    let output = run_program(&["run", "--linker=no_such_linker"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+    assert_eq!(stderr, "couldn't find linker `no_such_linker`\n");
+}
+
+#[test]
+fn errors_show_command() {
+    let output = run_program(&["run", "--linker=cc", "--link-arg", "-lno_such_library"]);
+    let stderr = String::from_utf8(output.stderr).unwrap();
+
+    assert_eq!(output.status.code(), Some(1));
+
+    let command = stderr
+        .lines()
+        .find_map(|line| line.strip_prefix("command: "))
+        .unwrap();
+
+    assert!(command.starts_with('/'), "{command}");
+    assert!(command.ends_with(" -lno_such_library"), "{command}");
+}
@@ -0,0 +1,332 @@
+//! Talking to `bayou lsp` like an editor does.
+
+mod common;
+
+use std::io::{BufReader, Write};
+use std::path::Path;
+use std::process::{Child, ChildStdin, ChildStdout, Stdio};
+
+use lsp_server::{Message, Notification, Request, RequestId};
+use lsp_types::notification::{
+    DidChangeTextDocument, DidOpenTextDocument, Exit, Initialized, Notification as _,
+    PublishDiagnostics,
+};
+use lsp_types::request::{
+    DocumentSymbolRequest, GotoDefinition, HoverRequest, Initialize, Shutdown,
+};
+use lsp_types::{
+    DiagnosticSeverity, DidChangeTextDocumentParams, DidOpenTextDocumentParams,
+    DocumentSymbolParams, DocumentSymbolResponse, GotoDefinitionParams, GotoDefinitionResponse,
+    HoverContents, HoverParams, InitializeParams, MarkedString, Position, Range, SymbolKind,
+    TextDocumentContentChangeEvent, TextDocumentIdentifier, TextDocumentItem,
+    TextDocumentPositionParams, Url, VersionedTextDocumentIdentifier,
+};
+use temp_dir::TempDir;
+
+use common::compiler;
+
+struct Client {
+    server: Child,
+    stdin: ChildStdin,
+    stdout: BufReader<ChildStdout>,
+    next_id: i32,
+}
+
+impl Client {
+    fn start() -> Self {
+        let mut server = compiler()
+            .arg("lsp")
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped())
+            .spawn()
+            .unwrap();
+
+        let mut client = Self {
+            stdin: server.stdin.take().unwrap(),
+            stdout: BufReader::new(server.stdout.take().unwrap()),
+            server,
+            next_id: 0,
+        };
+
+        client.request::<Initialize>(InitializeParams::default());
+        client.notify::<Initialized>(lsp_types::InitializedParams {});
+
+        client
+    }
+
+    fn send(&mut self, message: Message) {
+        message.write(&mut self.stdin).unwrap();
+        self.stdin.flush().unwrap();
+    }
+
+    fn receive(&mut self) -> Message {
+        Message::read(&mut self.stdout)
+            .unwrap()
+            .expect("the server closed stdout")
+    }
+
+    /// Send a request and wait for its response, skipping notifications until then.
+    fn request<R: lsp_types::request::Request>(&mut self, params: R::Params) -> R::Result {
+        self.next_id += 1;
+        let id = RequestId::from(self.next_id);
+        self.send(Request::new(id.clone(), R::METHOD.to_owned(), params).into());
+
+        loop {
+            if let Message::Response(response) = self.receive() {
+                assert_eq!(response.id, id);
+                assert!(response.error.is_none(), "{:?}", response.error);
+
+                return serde_json::from_value(response.result.unwrap_or_default()).unwrap();
+            }
+        }
+    }
+
+    fn notify<N: lsp_types::notification::Notification>(&mut self, params: N::Params) {
+        self.send(Notification::new(N::METHOD.to_owned(), params).into());
+    }
+
+    /// Wait for the next diagnostics published for a file.
+    fn diagnostics(&mut self, uri: &Url) -> Vec<lsp_types::Diagnostic> {
+        loop {
+            let Message::Notification(notification) = self.receive() else {
+                continue;
+            };
+
+            if notification.method == PublishDiagnostics::METHOD {
+                let params: lsp_types::PublishDiagnosticsParams =
+                    serde_json::from_value(notification.params).unwrap();
+
+                if params.uri == *uri {
+                    return params.diagnostics;
+                }
+            }
+        }
+    }
+
+    fn open(&mut self, uri: &Url, text: &str) {
+        self.notify::<DidOpenTextDocument>(DidOpenTextDocumentParams {
+            text_document: TextDocumentItem::new(
+                uri.clone(),
+                "bayou".to_owned(),
+                1,
+                text.to_owned(),
+            ),
+        });
+    }
+
+    fn change(&mut self, uri: &Url, version: i32, text: &str) {
+        self.notify::<DidChangeTextDocument>(DidChangeTextDocumentParams {
+            text_document: VersionedTextDocumentIdentifier::new(uri.clone(), version),
+            content_changes: vec![TextDocumentContentChangeEvent {
+                range: None,
+                range_length: None,
+                text: text.to_owned(),
+            }],
+        });
+    }
+
+    fn shutdown(mut self) {
+        self.request::<Shutdown>(());
+        self.notify::<Exit>(());
+
+        assert!(self.server.wait().unwrap().success());
+    }
+}
+
+fn file_uri(dir: &Path, file: &str) -> Url {
+    Url::from_file_path(dir.canonicalize().unwrap().join(file)).unwrap()
+}
+
+fn position_params(uri: &Url, position: Position) -> TextDocumentPositionParams {
+    TextDocumentPositionParams::new(TextDocumentIdentifier::new(uri.clone()), position)
+}
+
+const MAIN: &str = "\
+submodule util;
+
+const TWO: i64 = 2;
+
+func double(n: i64) -> i64 {
+    n * TWO
+}
+
+func main() -> i64 {
+    let x: i64 = 20;
+    double(x) + 2
+}
+";
+
+fn write_package() -> TempDir {
+    let package_dir = TempDir::with_prefix("bayou_test_").unwrap();
+    std::fs::write(package_dir.child("main.by"), MAIN).unwrap();
+    std::fs::write(
+        package_dir.child("util.by"),
+        "func one() -> i64 {\n    1\n}\n",
+    )
+    .unwrap();
+
+    package_dir
+}
+
+#[test]
+fn diagnostics_of_unsaved_changes() {
+    let package_dir = write_package();
+    let main = file_uri(package_dir.path(), "main.by");
+
+    let mut client = Client::start();
+
+    client.open(&main, MAIN);
+    assert_eq!(client.diagnostics(&main), vec![]);
+
+    // the file on disk is still fine, but the buffer isn't
+    let changed = MAIN.replace("let x: i64 = 20;", "let x: i64 = true;");
+    client.change(&main, 2, &changed);
+
+    let diagnostics = client.diagnostics(&main);
+    assert_eq!(diagnostics.len(), 1);
+    assert_eq!(diagnostics[0].severity, Some(DiagnosticSeverity::ERROR));
+    assert_eq!(
+        diagnostics[0].range,
+        Range::new(Position::new(9, 17), Position::new(9, 21))
+    );
+
+    client.change(&main, 3, MAIN);
+    assert_eq!(client.diagnostics(&main), vec![]);
+
+    client.shutdown();
+}
+
+#[test]
+fn hover_types() {
+    let package_dir = write_package();
+    let main = file_uri(package_dir.path(), "main.by");
+
+    let mut client = Client::start();
+    client.open(&main, MAIN);
+
+    let mut hover = |position| {
+        let hover = client
+            .request::<HoverRequest>(HoverParams {
+                text_document_position_params: position_params(&main, position),
+                work_done_progress_params: Default::default(),
+            })
+            .unwrap();
+
+        let HoverContents::Scalar(MarkedString::LanguageString(contents)) = hover.contents else {
+            panic!("unexpected hover contents {:?}", hover.contents);
+        };
+
+        (contents.value, hover.range.unwrap())
+    };
+
+    // the call to `double`
+    assert_eq!(
+        hover(Position::new(10, 6)),
+        (
+            "i64".to_owned(),
+            Range::new(Position::new(10, 4), Position::new(10, 13))
+        )
+    );
+
+    // the declaration of `x`
+    assert_eq!(
+        hover(Position::new(9, 8)),
+        (
+            "x: i64".to_owned(),
+            Range::new(Position::new(9, 8), Position::new(9, 9))
+        )
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn go_to_definition() {
+    let package_dir = write_package();
+    let main = file_uri(package_dir.path(), "main.by");
+
+    let mut client = Client::start();
+    client.open(&main, MAIN);
+
+    let mut definition = |position| {
+        let response = client
+            .request::<GotoDefinition>(GotoDefinitionParams {
+                text_document_position_params: position_params(&main, position),
+                work_done_progress_params: Default::default(),
+                partial_result_params: Default::default(),
+            })
+            .unwrap();
+
+        let GotoDefinitionResponse::Scalar(location) = response else {
+            panic!("unexpected definition {response:?}");
+        };
+
+        (location.uri, location.range.start)
+    };
+
+    // `x` in `double(x)` is the local declared by `let`
+    assert_eq!(
+        definition(Position::new(10, 11)),
+        (main.clone(), Position::new(9, 8))
+    );
+
+    // `double` in `double(x)`
+    assert_eq!(
+        definition(Position::new(10, 5)),
+        (main.clone(), Position::new(4, 5))
+    );
+
+    // `TWO` in `n * TWO`
+    assert_eq!(
+        definition(Position::new(5, 9)),
+        (main.clone(), Position::new(2, 6))
+    );
+
+    // `util` in `submodule util;`
+    assert_eq!(
+        definition(Position::new(0, 12)),
+        (file_uri(package_dir.path(), "util.by"), Position::new(0, 0))
+    );
+
+    client.shutdown();
+}
+
+#[test]
+fn document_symbols() {
+    let package_dir = write_package();
+    let main = file_uri(package_dir.path(), "main.by");
+
+    let mut client = Client::start();
+
+    // symbols come from the syntax tree, so they are there in spite of errors
+    client.open(&main, &MAIN.replace("n * TWO", "n * THREE"));
+
+    let response = client
+        .request::<DocumentSymbolRequest>(DocumentSymbolParams {
+            text_document: TextDocumentIdentifier::new(main.clone()),
+            work_done_progress_params: Default::default(),
+            partial_result_params: Default::default(),
+        })
+        .unwrap();
+
+    let DocumentSymbolResponse::Nested(symbols) = response else {
+        panic!("unexpected symbols {response:?}");
+    };
+
+    let symbols: Vec<_> = symbols
+        .iter()
+        .map(|symbol| (symbol.name.as_str(), symbol.kind))
+        .collect();
+
+    assert_eq!(
+        symbols,
+        [
+            ("util", SymbolKind::MODULE),
+            ("TWO", SymbolKind::CONSTANT),
+            ("double", SymbolKind::FUNCTION),
+            ("main", SymbolKind::FUNCTION),
+        ]
+    );
+
+    client.shutdown();
+}
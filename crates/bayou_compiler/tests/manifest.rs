@@ -0,0 +1,75 @@
+//! Packages created with `bayou new` and configured with `bayou.toml`.
+
+mod common;
+
+use std::process::Command;
+
+use temp_dir::TempDir;
+
+use common::run_compiler;
+
+#[test]
+fn new_package_is_found_from_subdirectory() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+
+    let output = run_compiler(&["new", "hello"], dir.path());
+    assert!(output.status.success());
+    assert_eq!(
+        String::from_utf8(output.stdout).unwrap(),
+        "created package `hello`\n"
+    );
+
+    let subdir = dir.child("hello/nested/deeper");
+    std::fs::create_dir_all(&subdir).unwrap();
+
+    let output = run_compiler(&["run"], &subdir);
+    assert_eq!(output.status.code(), Some(0));
+    assert_eq!(String::from_utf8(output.stdout).unwrap(), "Hello, world!\n");
+
+    // the directory exists now
+    let output = run_compiler(&["new", "hello"], dir.path());
+    assert_eq!(output.status.code(), Some(1));
+}
+
+#[test]
+fn manifest_settings() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+
+    std::fs::create_dir(dir.child("src")).unwrap();
+    std::fs::write(
+        dir.child("src/entry.by"),
+        "func main() -> i64 { let unused: i64 = 1; 42 }",
+    )
+    .unwrap();
+
+    let manifest = |lint_level: &str| {
+        let manifest = format!(
+            "[package]\n\
+            name = \"configured\"\n\
+            version = \"0.1.0\"\n\
+            entry = \"src/entry.by\"\n\
+            crate-type = \"obj\"\n\
+            [lints]\n\
+            unused_variables = \"{lint_level}\"\n"
+        );
+        std::fs::write(dir.child("bayou.toml"), manifest).unwrap();
+    };
+
+    manifest("deny");
+    let output = run_compiler(&["build"], dir.path());
+    assert_eq!(output.status.code(), Some(1));
+    assert!(!dir.child("configured.o").exists());
+
+    manifest("allow");
+    let output = run_compiler(&["build"], dir.path());
+    assert!(output.status.success());
+    assert!(output.stderr.is_empty());
+    assert!(dir.child("configured.o").exists());
+
+    // the crate type can be overridden
+    let output = run_compiler(&["build", "--crate-type=bin"], dir.path());
+    assert!(output.status.success());
+
+    let output = Command::new(dir.child("configured")).status().unwrap();
+    assert_eq!(output.code(), Some(42));
+}
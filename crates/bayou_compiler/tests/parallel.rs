@@ -0,0 +1,90 @@
+//! Compiling modules and functions on multiple threads.
+
+mod common;
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+use temp_dir::TempDir;
+
+use common::{compiler, write_package};
+
+/// Write the submodules of a package, which are nested and each
+/// have a few functions, returning the source of its root module.
+fn write_submodules(dir: &Path) -> String {
+    let mut main = String::new();
+
+    for module in 0..8 {
+        writeln!(main, "submodule m{module};").unwrap();
+
+        let mut source = String::new();
+        for submodule in 0..4 {
+            writeln!(source, "submodule s{submodule};").unwrap();
+
+            let submodule_dir = dir.join(format!("m{module}"));
+            std::fs::create_dir_all(&submodule_dir).unwrap();
+            std::fs::write(
+                submodule_dir.join(format!("s{submodule}.by")),
+                format!(
+                    "func g{module}_{submodule}(x: i64) -> i64 {{ x * {submodule} + {module} }}
+                    export func f{module}_{submodule}(x: i64) -> i64 {{
+                        g{module}_{submodule}(x) + g{module}_{submodule}(x + 1)
+                    }}"
+                ),
+            )
+            .unwrap();
+        }
+
+        std::fs::write(dir.join(format!("m{module}.by")), source).unwrap();
+    }
+
+    main.push_str(
+        "func square(x: i64) -> i64 { x * x }
+        func main() -> i64 { square(4) + square(2) }",
+    );
+    main
+}
+
+fn build_object(dir: &Path, threads: &str) -> Vec<u8> {
+    // don't reuse anything from the last build
+    let _ = std::fs::remove_dir_all(dir.join("target"));
+
+    let output = dir.join("out.o");
+    let status = compiler()
+        .args(["build", "-g", "--crate-type=obj", "-o"])
+        .arg(&output)
+        .current_dir(dir)
+        .env("BAYOU_THREADS", threads)
+        .status()
+        .unwrap();
+    assert!(status.success());
+
+    std::fs::read(output).unwrap()
+}
+
+#[test]
+fn output_is_the_same_with_any_number_of_threads() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    let package_dir = dir.child("nested");
+    std::fs::create_dir(&package_dir).unwrap();
+
+    let main = write_submodules(&package_dir);
+    write_package(dir.path(), "nested", &[], &main);
+
+    let single_threaded = build_object(&package_dir, "1");
+    for threads in ["2", "8"] {
+        assert!(
+            build_object(&package_dir, threads) == single_threaded,
+            "output differs with {threads} threads"
+        );
+    }
+
+    let output = compiler()
+        .arg("run")
+        .current_dir(&package_dir)
+        .env("BAYOU_THREADS", "8")
+        .output()
+        .unwrap();
+
+    assert_eq!(output.status.code(), Some(20));
+}
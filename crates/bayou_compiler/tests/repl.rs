@@ -0,0 +1,95 @@
+//! Sessions of the REPL, fed through stdin.
+
+mod common;
+
+use std::io::Write;
+use std::process::Stdio;
+
+use common::compiler;
+
+/// Run a REPL session with the given lines of input, returning its stdout.
+fn repl(lines: &[&str]) -> String {
+    let mut child = compiler()
+        .arg("repl")
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()
+        .unwrap();
+
+    let mut stdin = child.stdin.take().unwrap();
+    for line in lines {
+        writeln!(stdin, "{line}").unwrap();
+    }
+    drop(stdin);
+
+    let output = child.wait_with_output().unwrap();
+    assert!(output.status.success());
+
+    String::from_utf8(output.stdout).unwrap()
+}
+
+#[test]
+fn locals_and_functions_persist() {
+    let output = repl(&[
+        "func sq(x: i64) -> i64 { x * x }",
+        "let a: i64 = sq(4);",
+        "a = a + 1;",
+        "a",
+        "func sq(x: i64) -> i64 { x + x }",
+        "sq(a) == 34",
+    ]);
+
+    assert_eq!(output, "17: i64\ntrue: bool\n");
+}
+
+#[test]
+fn values_are_formatted_by_type() {
+    let output = repl(&["[1.5, 2.0]", "'x'", "\"hi\"", "1 == 2"]);
+
+    assert_eq!(
+        output,
+        "[1.5, 2.0]: [f64; 2]\n'x': char\n\"hi\": str\nfalse: bool\n"
+    );
+}
+
+#[test]
+fn statics_persist() {
+    let output = repl(&[
+        "static S: i64 = 1;",
+        "S = S + 10;",
+        "S",
+        "static S: i64 = 2;",
+        "S",
+    ]);
+
+    assert_eq!(output, "11: i64\n2: i64\n");
+}
+
+#[test]
+fn errors_dont_end_the_session() {
+    let output = repl(&["let a: i64 = 1;", "a + b", "let a: i64 = b;", "1 +", "a"]);
+    assert_eq!(output, "1: i64\n");
+
+    // runtime traps
+    let output = repl(&[
+        "let a: i64 = 1;",
+        "let zero: i64 = 0;",
+        "a / zero",
+        "*(zero as *i64)",
+        "[1, 2][a + 5]",
+        // what an input did before trapping is undone
+        "a = 2; a / zero",
+        "a",
+    ]);
+    assert_eq!(output, "1: i64\n");
+
+    // backend errors
+    let output = repl(&[
+        "func one() -> i64 { 1 }",
+        // declared with the mangled name of `one`, but with another signature
+        "extern func _B4repl3one(x: i64) -> i64; _B4repl3one(2)",
+        "one()",
+    ]);
+    assert_eq!(output, "1: i64\n");
+}
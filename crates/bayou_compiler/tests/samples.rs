@@ -0,0 +1,59 @@
+//! Every sample should behave the same with each way of running a program.
+
+mod common;
+
+use std::path::Path;
+
+use temp_dir::TempDir;
+
+use common::compiler;
+
+/// The stdout output and exit code of a program.
+type Outcome = (String, Option<i32>);
+
+fn outcome(args: &[&str], package_dir: &Path) -> Outcome {
+    let output = compiler().args(args).arg(package_dir).output().unwrap();
+
+    (
+        String::from_utf8(output.stdout).unwrap(),
+        output.status.code(),
+    )
+}
+
+#[test]
+fn samples_agree() {
+    let samples_dir = Path::new(env!("CARGO_MANIFEST_DIR")).join("../../samples");
+
+    for entry in std::fs::read_dir(samples_dir).unwrap() {
+        let path = entry.unwrap().path();
+
+        let package_dir = TempDir::with_prefix("bayou_test_").unwrap();
+        std::fs::copy(&path, package_dir.child("main.by")).unwrap();
+
+        let interpreted = outcome(&["interp"], package_dir.path());
+        let compiled = outcome(&["run"], package_dir.path());
+        let jit = outcome(&["run", "--jit"], package_dir.path());
+        let unoptimized = outcome(&["run", "-O0"], package_dir.path());
+        let debug_info = outcome(&["run", "-g"], package_dir.path());
+
+        assert_eq!(
+            interpreted,
+            compiled,
+            "{}: interpreted vs compiled",
+            path.display()
+        );
+        assert_eq!(jit, compiled, "{}: JIT vs compiled", path.display());
+        assert_eq!(
+            unoptimized,
+            compiled,
+            "{}: unoptimized vs compiled",
+            path.display()
+        );
+        assert_eq!(
+            debug_info,
+            compiled,
+            "{}: with debug info vs compiled",
+            path.display()
+        );
+    }
+}
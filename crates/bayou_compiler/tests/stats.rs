@@ -0,0 +1,53 @@
+//! The report printed with `-Z stats`.
+
+mod common;
+
+use std::path::Path;
+
+use temp_dir::TempDir;
+
+use common::{build, stderr, write_package};
+
+fn stats(working_dir: &Path) -> String {
+    let output = build(&["-Z", "stats"], working_dir);
+    stderr(&output).split("stats:\n").nth(1).unwrap().to_owned()
+}
+
+#[test]
+fn stats_of_compiled_packages() {
+    let dir = TempDir::with_prefix("bayou_test_").unwrap();
+    write_package(
+        dir.path(),
+        "counted",
+        &[],
+        "const A_VERY_LONG_CONSTANT_NAME: i64 = 1 + 2;
+        func main() -> i64 {
+            let x: i64 = A_VERY_LONG_CONSTANT_NAME;
+            x * 2
+        }",
+    );
+    let package_dir = dir.child("counted");
+
+    let report = stats(&package_dir);
+    let lines: Vec<&str> = report.lines().map(str::trim).collect();
+
+    assert_eq!(lines[0], "counted");
+    assert_eq!(
+        lines[1],
+        "ast          2 items, 1 statements, 7 expressions"
+    );
+    assert_eq!(
+        lines[2],
+        "ir           2 items, 1 statements, 7 expressions"
+    );
+    assert!(lines[3].starts_with("interner"), "{report}");
+    assert!(lines[4].starts_with("hash table"), "{report}");
+    assert!(lines[4].contains("strings, capacity for"), "{report}");
+    assert!(
+        lines[5].starts_with(r#"longest      ["A_VERY_LONG_CONSTANT_NAME","#),
+        "{report}"
+    );
+
+    // nothing is compiled the second time
+    assert_eq!(stats(&package_dir), "  counted  cached\n");
+}
@@ -169,6 +169,22 @@ impl<S: SourceMap> Snippet<S> {
     pub fn secondary(label: impl Into<String>, source_id: S::SourceId, span: impl AsSpan) -> Self {
         Self::new(SnippetKind::Secondary, label, source_id, span)
     }
+
+    pub fn label(&self) -> &str {
+        &self.label
+    }
+
+    pub fn kind(&self) -> SnippetKind {
+        self.kind
+    }
+
+    pub fn source_id(&self) -> S::SourceId {
+        self.source_id
+    }
+
+    pub fn span(&self) -> Span {
+        self.span
+    }
 }
 
 #[cfg_attr(feature = "serialize", derive(serde::Serialize))]
@@ -1,4 +1,5 @@
-use bayou_ir::{BinOp, Ident, Type, UnOp};
+use bayou_interner::{Istr, Remap};
+use bayou_ir::{BinOp, F64Bits, Ident, NodeCounts, Type, UnOp};
 use bayou_session::diagnostics::span::Span;
 
 use crate::Node;
@@ -8,26 +9,117 @@ pub struct Module {
     pub items: Vec<Item>,
 }
 
+impl Module {
+    /// Count the items, statements and expressions in the module.
+    pub fn node_counts(&self) -> NodeCounts {
+        let mut counts = NodeCounts::default();
+
+        for item in &self.items {
+            counts.items += 1;
+            match item {
+                Item::FuncDecl(func_decl) => func_decl.block.count_nodes(&mut counts),
+                Item::Const(decl) | Item::Static(decl) => decl.expr.count_nodes(&mut counts),
+                Item::Submodule(_) | Item::ExternFuncDecl(_) | Item::ParseError => {}
+            }
+        }
+
+        counts
+    }
+
+    /// Give the identifiers and strings in the module the IDs that the
+    /// interner it was parsed with was merged into another interner with.
+    pub fn remap(&mut self, remap: &Remap) {
+        for item in &mut self.items {
+            match item {
+                Item::Submodule(ident) => ident.istr = remap[ident.istr],
+                Item::FuncDecl(func_decl) => {
+                    func_decl.ident.istr = remap[func_decl.ident.istr];
+                    for param in &mut func_decl.params {
+                        param.ident.istr = remap[param.ident.istr];
+                    }
+                    func_decl.block.remap(remap);
+                }
+                Item::ExternFuncDecl(func_decl) => {
+                    func_decl.ident.istr = remap[func_decl.ident.istr];
+                    for param in &mut func_decl.params {
+                        param.ident.istr = remap[param.ident.istr];
+                    }
+                }
+                Item::Const(decl) | Item::Static(decl) => {
+                    decl.ident.istr = remap[decl.ident.istr];
+                    decl.expr.remap(remap);
+                }
+                Item::ParseError => {}
+            }
+        }
+    }
+}
+
+/// A line of input to the REPL.
+#[derive(Node!, Default)]
+pub struct ReplInput {
+    pub items: Vec<Item>,
+    pub statements: Vec<Stmt>,
+    pub final_expr: Option<Expr>,
+}
+
 #[derive(Node!)]
 pub enum Item {
     Submodule(Ident),
     FuncDecl(FuncDecl),
+    ExternFuncDecl(ExternFuncDecl),
+    Const(GlobalVarDecl),
+    Static(GlobalVarDecl),
     ParseError,
 }
 
 #[derive(Node!)]
 pub struct FuncDecl {
     pub ident: Ident,
+    pub params: Vec<Param>,
 
     pub ret_ty: Type,
     pub ret_ty_span: Span,
 
     pub block: Block,
+
+    /// Whether this function is declared with `export`, which makes it
+    /// visible to code linking against a library.
+    pub is_exported: bool,
+}
+
+#[derive(Node!)]
+pub struct ExternFuncDecl {
+    pub ident: Ident,
+    pub params: Vec<Param>,
+
+    pub ret_ty: Type,
+    pub ret_ty_span: Span,
+}
+
+/// A `const` or `static` item.
+#[derive(Node!)]
+pub struct GlobalVarDecl {
+    pub ident: Ident,
+
+    pub ty: Type,
+    pub ty_span: Span,
+
+    pub expr: Expr,
+}
+
+#[derive(Node!)]
+pub struct Param {
+    pub ident: Ident,
+
+    pub ty: Type,
+    pub ty_span: Span,
 }
 
 #[derive(Node!)]
 pub enum Stmt {
     Assign { ident: Ident, ty: Type, expr: Expr },
+    Store { place: Expr, expr: Expr },
     Drop { expr: Expr, had_semicolon: bool },
     Return(Expr),
 
@@ -42,6 +134,45 @@ pub struct Block {
     pub span: Span,
 }
 
+impl Block {
+    fn count_nodes(&self, counts: &mut NodeCounts) {
+        for stmt in &self.statements {
+            counts.statements += 1;
+            match stmt {
+                Stmt::Assign { expr, .. } | Stmt::Drop { expr, .. } | Stmt::Return(expr) => {
+                    expr.count_nodes(counts);
+                }
+                Stmt::Store { place, expr } => {
+                    place.count_nodes(counts);
+                    expr.count_nodes(counts);
+                }
+                Stmt::ParseError => {}
+            }
+        }
+
+        self.final_expr.count_nodes(counts);
+    }
+
+    fn remap(&mut self, remap: &Remap) {
+        for stmt in &mut self.statements {
+            match stmt {
+                Stmt::Assign { ident, expr, .. } => {
+                    ident.istr = remap[ident.istr];
+                    expr.remap(remap);
+                }
+                Stmt::Drop { expr, .. } | Stmt::Return(expr) => expr.remap(remap),
+                Stmt::Store { place, expr } => {
+                    place.remap(remap);
+                    expr.remap(remap);
+                }
+                Stmt::ParseError => {}
+            }
+        }
+
+        self.final_expr.remap(remap);
+    }
+}
+
 #[derive(Node!)]
 pub struct Expr {
     pub kind: ExprKind,
@@ -57,7 +188,10 @@ impl Expr {
 #[derive(Node!)]
 pub enum ExprKind {
     Integer(i64),
+    Float(F64Bits),
     Bool(bool),
+    Char(char),
+    String(Istr),
 
     Var(Ident),
 
@@ -72,6 +206,33 @@ pub enum ExprKind {
         rhs: Box<Expr>,
     },
 
+    AddressOf(Box<Expr>),
+    Deref(Box<Expr>),
+
+    Cast {
+        expr: Box<Expr>,
+        ty: Type,
+    },
+
+    Call {
+        /// The dependency that the function is from, as in `package::func()`.
+        package: Option<Ident>,
+        func: Ident,
+        args: Vec<Expr>,
+    },
+
+    Array(Vec<Expr>),
+
+    Index {
+        base: Box<Expr>,
+        index: Box<Expr>,
+    },
+
+    Field {
+        expr: Box<Expr>,
+        field: Ident,
+    },
+
     Block(Box<Block>),
 
     If {
@@ -85,6 +246,121 @@ pub enum ExprKind {
     ParseError,
 }
 
+impl Expr {
+    fn count_nodes(&self, counts: &mut NodeCounts) {
+        counts.exprs += 1;
+
+        match &self.kind {
+            ExprKind::Integer(_)
+            | ExprKind::Float(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Char(_)
+            | ExprKind::String(_)
+            | ExprKind::Var(_)
+            | ExprKind::Void
+            | ExprKind::ParseError => {}
+
+            ExprKind::UnOp { expr, .. }
+            | ExprKind::AddressOf(expr)
+            | ExprKind::Deref(expr)
+            | ExprKind::Cast { expr, .. }
+            | ExprKind::Field { expr, .. } => expr.count_nodes(counts),
+
+            ExprKind::BinOp { lhs, rhs, .. } => {
+                lhs.count_nodes(counts);
+                rhs.count_nodes(counts);
+            }
+
+            ExprKind::Index { base, index } => {
+                base.count_nodes(counts);
+                index.count_nodes(counts);
+            }
+
+            ExprKind::Call { args: exprs, .. } | ExprKind::Array(exprs) => {
+                for expr in exprs {
+                    expr.count_nodes(counts);
+                }
+            }
+
+            ExprKind::Block(block) => block.count_nodes(counts),
+
+            ExprKind::If { cond, then, else_ } => {
+                cond.count_nodes(counts);
+                then.count_nodes(counts);
+                if let Some(else_) = else_ {
+                    else_.count_nodes(counts);
+                }
+            }
+        }
+    }
+}
+
+impl Expr {
+    fn remap(&mut self, remap: &Remap) {
+        match &mut self.kind {
+            ExprKind::Integer(_)
+            | ExprKind::Float(_)
+            | ExprKind::Bool(_)
+            | ExprKind::Char(_)
+            | ExprKind::Void
+            | ExprKind::ParseError => {}
+
+            ExprKind::String(istr) => *istr = remap[*istr],
+            ExprKind::Var(ident) => ident.istr = remap[ident.istr],
+
+            ExprKind::UnOp { expr, .. }
+            | ExprKind::AddressOf(expr)
+            | ExprKind::Deref(expr)
+            | ExprKind::Cast { expr, .. } => expr.remap(remap),
+
+            ExprKind::Field { expr, field } => {
+                expr.remap(remap);
+                field.istr = remap[field.istr];
+            }
+
+            ExprKind::BinOp { lhs, rhs, .. } => {
+                lhs.remap(remap);
+                rhs.remap(remap);
+            }
+
+            ExprKind::Index { base, index } => {
+                base.remap(remap);
+                index.remap(remap);
+            }
+
+            ExprKind::Call {
+                package,
+                func,
+                args,
+            } => {
+                if let Some(package) = package {
+                    package.istr = remap[package.istr];
+                }
+                func.istr = remap[func.istr];
+                for arg in args {
+                    arg.remap(remap);
+                }
+            }
+
+            ExprKind::Array(exprs) => {
+                for expr in exprs {
+                    expr.remap(remap);
+                }
+            }
+
+            ExprKind::Block(block) => block.remap(remap),
+
+            ExprKind::If { cond, then, else_ } => {
+                cond.remap(remap);
+                then.remap(remap);
+                if let Some(else_) = else_ {
+                    else_.remap(remap);
+                }
+            }
+        }
+    }
+}
+
 impl ExprKind {
     /// Whether a semicolon is optional after an expression statement
     /// of this kind.
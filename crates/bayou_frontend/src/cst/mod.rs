@@ -0,0 +1,425 @@
+//! A concrete syntax tree, which has every token of a module in order and
+//! the text between them as trivia, so that it can be turned back into the
+//! exact source code. Whitespace, comments and invalid text are only told
+//! apart if the lexer kept trivia.
+//!
+//! The tree is made of green nodes, which own their children but don't
+//! know where they are, and is read through [`SyntaxNode`]s, which are
+//! made on demand and know their position and parent. The [`ast`](crate::ast)
+//! is derived from the syntax tree.
+
+#[cfg(test)]
+mod tests;
+
+mod to_ast;
+
+use std::fmt;
+use std::sync::Arc;
+
+use bayou_session::diagnostics::span::Span;
+
+use crate::token::{TokenKind, TriviaKind};
+use crate::NodeCopy;
+
+#[derive(NodeCopy!)]
+pub enum NodeKind {
+    Module,
+    ReplInput,
+
+    Submodule,
+    /// A function declaration, with `export` if it has it.
+    FuncDecl,
+    ExternFuncDecl,
+    ConstDecl,
+    StaticDecl,
+
+    ParamList,
+    Param,
+    /// The return type of a function, which is empty if it has no `->`.
+    RetType,
+
+    /// A type that is a single keyword, or `!`.
+    PrimitiveType,
+    PtrType,
+    SliceType,
+    ArrayType,
+
+    Block,
+
+    LetStmt,
+    ReturnStmt,
+    StoreStmt,
+    ExprStmt,
+
+    /// A literal, or `void`.
+    Literal,
+    VarExpr,
+    CallExpr,
+    UnaryExpr,
+    AddressOfExpr,
+    DerefExpr,
+    ParenExpr,
+    ArrayExpr,
+    IfExpr,
+    BinaryExpr,
+    IndexExpr,
+    FieldExpr,
+    CastExpr,
+
+    /// Tokens that the parser reported an error in.
+    Error,
+}
+
+#[derive(NodeCopy!)]
+pub enum SyntaxTokenKind {
+    Token(TokenKind),
+    Trivia(TriviaKind),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenNode {
+    kind: NodeKind,
+    len: usize,
+    children: Vec<GreenElement>,
+}
+
+impl GreenNode {
+    pub fn new(kind: NodeKind, children: Vec<GreenElement>) -> Self {
+        let len = children.iter().map(GreenElement::len).sum();
+        Self {
+            kind,
+            len,
+            children,
+        }
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.kind
+    }
+
+    /// The length of the node's text in bytes.
+    pub fn len(&self) -> usize {
+        self.len
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len == 0
+    }
+
+    pub fn children(&self) -> &[GreenElement] {
+        &self.children
+    }
+}
+
+impl fmt::Display for GreenNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for child in &self.children {
+            match child {
+                GreenElement::Node(node) => write!(f, "{node}")?,
+                GreenElement::Token(token) => f.write_str(token.text())?,
+            }
+        }
+
+        Ok(())
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum GreenElement {
+    Node(Arc<GreenNode>),
+    Token(GreenToken),
+}
+
+impl GreenElement {
+    pub fn len(&self) -> usize {
+        match self {
+            GreenElement::Node(node) => node.len(),
+            GreenElement::Token(token) => token.text().len(),
+        }
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct GreenToken {
+    kind: SyntaxTokenKind,
+    text: Box<str>,
+}
+
+impl GreenToken {
+    pub fn new(kind: SyntaxTokenKind, text: &str) -> Self {
+        Self {
+            kind,
+            text: text.into(),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxTokenKind {
+        self.kind
+    }
+
+    pub fn text(&self) -> &str {
+        &self.text
+    }
+}
+
+/// A node of a syntax tree, at a position in the source code.
+///
+/// Displaying a node gives its text, including trivia.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxNode(Arc<NodeData>);
+
+#[derive(PartialEq, Eq)]
+struct NodeData {
+    green: Arc<GreenNode>,
+    offset: usize,
+    parent: Option<SyntaxNode>,
+}
+
+impl SyntaxNode {
+    pub fn new_root(green: Arc<GreenNode>) -> Self {
+        Self(Arc::new(NodeData {
+            green,
+            offset: 0,
+            parent: None,
+        }))
+    }
+
+    pub fn kind(&self) -> NodeKind {
+        self.0.green.kind()
+    }
+
+    pub fn green(&self) -> &Arc<GreenNode> {
+        &self.0.green
+    }
+
+    pub fn parent(&self) -> Option<&SyntaxNode> {
+        self.0.parent.as_ref()
+    }
+
+    /// The span of all of the node's text, including trivia.
+    pub fn text_span(&self) -> Span {
+        Span::new(self.0.offset, self.0.offset + self.0.green.len())
+    }
+
+    /// The span from the start of the node's first token to the end of its
+    /// last, without trivia. Nodes without any tokens have an empty span at
+    /// their end.
+    pub fn span(&self) -> Span {
+        token_bounds(&self.0.green, self.0.offset)
+            .unwrap_or_else(|| Span::empty(self.text_span().end))
+    }
+
+    pub fn children(&self) -> impl Iterator<Item = SyntaxElement> + '_ {
+        self.0
+            .green
+            .children()
+            .iter()
+            .enumerate()
+            .scan(self.0.offset, |offset, (index, child)| {
+                let element = match child {
+                    GreenElement::Node(node) => {
+                        SyntaxElement::Node(SyntaxNode(Arc::new(NodeData {
+                            green: node.clone(),
+                            offset: *offset,
+                            parent: Some(self.clone()),
+                        })))
+                    }
+                    GreenElement::Token(_) => SyntaxElement::Token(SyntaxToken {
+                        parent: self.clone(),
+                        index,
+                        offset: *offset,
+                    }),
+                };
+
+                *offset += child.len();
+                Some(element)
+            })
+    }
+
+    pub fn child_nodes(&self) -> impl Iterator<Item = SyntaxNode> + '_ {
+        self.children().filter_map(|element| match element {
+            SyntaxElement::Node(node) => Some(node),
+            SyntaxElement::Token(_) => None,
+        })
+    }
+
+    /// The tokens that are children of this node, without trivia.
+    pub fn child_tokens(&self) -> impl Iterator<Item = SyntaxToken> + '_ {
+        self.children().filter_map(|element| match element {
+            SyntaxElement::Token(token) if !token.is_trivia() => Some(token),
+            _ => None,
+        })
+    }
+
+    /// Every token in the node and the nodes in it, including trivia, in order.
+    pub fn descendant_tokens(&self) -> Vec<SyntaxToken> {
+        let mut tokens = vec![];
+        for child in self.children() {
+            match child {
+                SyntaxElement::Node(node) => tokens.extend(node.descendant_tokens()),
+                SyntaxElement::Token(token) => tokens.push(token),
+            }
+        }
+
+        tokens
+    }
+}
+
+/// The span from the start of the first token in a node to the end of its last token.
+fn token_bounds(green: &GreenNode, mut offset: usize) -> Option<Span> {
+    let mut bounds: Option<Span> = None;
+
+    for child in green.children() {
+        let span = match child {
+            GreenElement::Node(node) => token_bounds(node, offset),
+            GreenElement::Token(token) => match token.kind() {
+                SyntaxTokenKind::Token(_) => Some(Span::new(offset, offset + token.text().len())),
+                SyntaxTokenKind::Trivia(_) => None,
+            },
+        };
+
+        if let Some(span) = span {
+            bounds = Some(bounds.map_or(span, |bounds| bounds.union(span)));
+        }
+
+        offset += child.len();
+    }
+
+    bounds
+}
+
+impl fmt::Display for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0.green)
+    }
+}
+
+/// Shows the tree with a node or token on each line.
+impl fmt::Debug for SyntaxNode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        fn write_node(f: &mut fmt::Formatter<'_>, node: &SyntaxNode, depth: usize) -> fmt::Result {
+            let indent = "  ".repeat(depth);
+            let span = node.text_span();
+            writeln!(f, "{indent}{:?} {}..{}", node.kind(), span.start, span.end)?;
+
+            for child in node.children() {
+                match child {
+                    SyntaxElement::Node(node) => write_node(f, &node, depth + 1)?,
+                    SyntaxElement::Token(token) => writeln!(f, "{indent}  {token:?}")?,
+                }
+            }
+
+            Ok(())
+        }
+
+        write_node(f, self, 0)
+    }
+}
+
+/// A token in a syntax tree, which may be trivia.
+#[derive(Clone, PartialEq, Eq)]
+pub struct SyntaxToken {
+    parent: SyntaxNode,
+    index: usize,
+    offset: usize,
+}
+
+impl SyntaxToken {
+    fn green(&self) -> &GreenToken {
+        match &self.parent.green().children()[self.index] {
+            GreenElement::Token(token) => token,
+            GreenElement::Node(_) => unreachable!("syntax token refers to a node"),
+        }
+    }
+
+    pub fn kind(&self) -> SyntaxTokenKind {
+        self.green().kind()
+    }
+
+    /// The kind of the token, if it isn't trivia.
+    pub fn token_kind(&self) -> Option<TokenKind> {
+        match self.kind() {
+            SyntaxTokenKind::Token(kind) => Some(kind),
+            SyntaxTokenKind::Trivia(_) => None,
+        }
+    }
+
+    pub fn is_trivia(&self) -> bool {
+        matches!(self.kind(), SyntaxTokenKind::Trivia(_))
+    }
+
+    pub fn text(&self) -> &str {
+        self.green().text()
+    }
+
+    pub fn span(&self) -> Span {
+        Span::new(self.offset, self.offset + self.text().len())
+    }
+
+    pub fn parent(&self) -> &SyntaxNode {
+        &self.parent
+    }
+}
+
+impl fmt::Debug for SyntaxToken {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let span = self.span();
+        if let SyntaxTokenKind::Trivia(kind) = self.kind() {
+            write!(f, "{kind:?} ")?;
+        }
+
+        write!(f, "{:?} {}..{}", self.text(), span.start, span.end)
+    }
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SyntaxElement {
+    Node(SyntaxNode),
+    Token(SyntaxToken),
+}
+
+/// Builds a green tree from tokens in order, wrapping them in
+/// nodes once the parser knows what they are.
+#[derive(Debug, Clone, Default)]
+pub(crate) struct Builder {
+    children: Vec<GreenElement>,
+    text_len: usize,
+}
+
+/// The position in a [`Builder`] where a node can start.
+#[derive(Debug, Clone, Copy)]
+pub(crate) struct Checkpoint(usize);
+
+impl Builder {
+    pub fn token(&mut self, kind: SyntaxTokenKind, text: &str) {
+        self.text_len += text.len();
+        self.children
+            .push(GreenElement::Token(GreenToken::new(kind, text)));
+    }
+
+    /// The length of the text so far.
+    pub fn text_len(&self) -> usize {
+        self.text_len
+    }
+
+    pub fn checkpoint(&self) -> Checkpoint {
+        Checkpoint(self.children.len())
+    }
+
+    /// Wrap everything since the checkpoint in a node.
+    pub fn wrap(&mut self, checkpoint: Checkpoint, kind: NodeKind) {
+        let children = self.children.split_off(checkpoint.0);
+        self.children
+            .push(GreenElement::Node(Arc::new(GreenNode::new(kind, children))));
+    }
+
+    /// Wrap everything in a root node.
+    pub fn finish(mut self, kind: NodeKind) -> SyntaxNode {
+        let children = std::mem::take(&mut self.children);
+        SyntaxNode::new_root(Arc::new(GreenNode::new(kind, children)))
+    }
+}
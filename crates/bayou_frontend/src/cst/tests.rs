@@ -0,0 +1,76 @@
+use bayou_interner::Interner;
+
+use super::SyntaxNode;
+use crate::ast::Module;
+use crate::lexer::Lexer;
+use crate::parser::Parser;
+
+fn parse_lossless(source: &str) -> SyntaxNode {
+    let interner = Interner::new();
+
+    let (tokens, _) = Lexer::new(source, &interner).with_trivia().lex();
+    let (cst, _) = Parser::new(tokens).parse_cst();
+    cst
+}
+
+/// The AST from a lossless syntax tree, and the AST from the parser without trivia.
+fn parse_both(source: &str) -> (Module, Module) {
+    let interner = Interner::new();
+
+    let (tokens, _) = Lexer::new(source, &interner).with_trivia().lex();
+    let (cst, _) = Parser::new(tokens).parse_cst();
+
+    let (tokens, _) = Lexer::new(source, &interner).lex();
+    let (module, _) = Parser::new(tokens).parse();
+
+    (Module::from_cst(&cst), module)
+}
+
+const SOURCES: &[&str] = &[
+    "// a comment\nfunc main() -> i64 {\n    let x: i64 = 1; // one\n    x + 2\n}\n",
+    "submodule a;\nexport func f(p: *i64, xs: []i64) -> [i64; 2] { [xs[0], *p] }\n",
+    "extern func malloc(size: i64) -> *void;\nconst N: i64 = 4 * (2 + 1);\n",
+    "func f(x: f64) -> bool { if x > 0.0 then { true } else false }\n",
+    "func main() -> i64 { return }  // missing semicolon",
+    "func main( { RETURN 0; }\n\n  func g() -> $ { 1 }",
+    "func main() -> i64 { ; } static S: = 1; export extern func f();",
+];
+
+#[test]
+fn round_trip() {
+    for source in SOURCES {
+        assert_eq!(parse_lossless(source).to_string(), *source);
+    }
+}
+
+#[test]
+fn round_trip_without_trivia() {
+    let interner = Interner::new();
+
+    for source in SOURCES {
+        let (tokens, _) = Lexer::new(source, &interner).lex();
+        let (cst, _) = Parser::new(tokens).parse_cst();
+
+        assert_eq!(cst.to_string(), *source);
+    }
+}
+
+#[test]
+fn same_ast_with_trivia() {
+    for source in SOURCES {
+        let (from_cst, module) = parse_both(source);
+        assert_eq!(from_cst, module, "{source:?}");
+    }
+}
+
+#[test]
+fn tree() {
+    let source = "func main() -> i64 {\n    // comment\n    -x[0] as i64 # \n}";
+
+    insta::with_settings!({
+        info => &source,
+        omit_expression => true,
+    }, {
+        insta::assert_snapshot!(format!("{:?}", parse_lossless(source)));
+    });
+}
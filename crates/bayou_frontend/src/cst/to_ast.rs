@@ -0,0 +1,512 @@
+//! Deriving the AST from a syntax tree.
+//!
+//! The parser has already reported every syntax error, so this only has to
+//! decide what an `Error` node stands for where it is. Every other node has
+//! the nodes and tokens it needs, apart from the ones that the parser
+//! recovers from being missing.
+
+use bayou_ir::{Ident, Type, UnOp};
+use bayou_session::diagnostics::span::Span;
+
+use super::{NodeKind, SyntaxElement, SyntaxNode, SyntaxToken};
+use crate::ast::*;
+use crate::token::{Keyword, TokenKind};
+
+impl Module {
+    /// Derive the AST of a module from its syntax tree.
+    pub fn from_cst(node: &SyntaxNode) -> Self {
+        // items that failed to parse before their keyword aren't in the AST
+        let items = node.child_nodes().filter_map(|node| item(&node)).collect();
+        Self { items }
+    }
+}
+
+impl ReplInput {
+    /// Derive the AST of a line of input to the REPL from its syntax tree.
+    pub fn from_cst(node: &SyntaxNode) -> Self {
+        let mut input = ReplInput::default();
+        let end = node.span().end;
+
+        for child in node.child_nodes() {
+            match child.kind() {
+                // items that failed to parse aren't in the AST, but statements are
+                NodeKind::Error if starts_with_item_keyword(&child) => {}
+
+                NodeKind::Error
+                | NodeKind::LetStmt
+                | NodeKind::ReturnStmt
+                | NodeKind::StoreStmt
+                | NodeKind::ExprStmt => match statement(&child) {
+                    Stmt::Drop {
+                        expr,
+                        had_semicolon: false,
+                    } if child.span().end == end => {
+                        input.final_expr = Some(expr);
+                    }
+
+                    // the parser reported the missing semicolon
+                    Stmt::Drop {
+                        had_semicolon: false,
+                        expr,
+                    } if !expr.kind.stmt_semicolon_is_optional() => {}
+
+                    statement => input.statements.push(statement),
+                },
+
+                _ => input.items.extend(item(&child)),
+            }
+        }
+
+        input
+    }
+}
+
+fn starts_with_item_keyword(node: &SyntaxNode) -> bool {
+    node.child_tokens().next().is_some_and(|token| {
+        matches!(
+            token.token_kind(),
+            Some(TokenKind::Keyword(
+                Keyword::Submodule
+                    | Keyword::Func
+                    | Keyword::Extern
+                    | Keyword::Export
+                    | Keyword::Const
+                    | Keyword::Static
+            ))
+        )
+    })
+}
+
+fn item(node: &SyntaxNode) -> Option<Item> {
+    if node.kind() == NodeKind::Error {
+        return None;
+    }
+
+    // items that failed to parse after their keyword are
+    // parse errors, with an `Error` node in them
+    if has_error(node) {
+        return Some(Item::ParseError);
+    }
+
+    let item = match node.kind() {
+        NodeKind::Submodule => Item::Submodule(first_ident(node)),
+
+        NodeKind::FuncDecl => {
+            let (ret_ty, ret_ty_span) = ret_type(&child(node, NodeKind::RetType));
+
+            Item::FuncDecl(FuncDecl {
+                ident: first_ident(node),
+                params: params(node),
+
+                ret_ty,
+                ret_ty_span,
+
+                block: block(&child(node, NodeKind::Block)),
+
+                is_exported: has_token(node, TokenKind::Keyword(Keyword::Export)),
+            })
+        }
+
+        NodeKind::ExternFuncDecl => {
+            let (ret_ty, ret_ty_span) = ret_type(&child(node, NodeKind::RetType));
+
+            Item::ExternFuncDecl(ExternFuncDecl {
+                ident: first_ident(node),
+                params: params(node),
+
+                ret_ty,
+                ret_ty_span,
+            })
+        }
+
+        NodeKind::ConstDecl => Item::Const(global_var_decl(node)),
+        NodeKind::StaticDecl => Item::Static(global_var_decl(node)),
+
+        _ => return None,
+    };
+
+    Some(item)
+}
+
+fn global_var_decl(node: &SyntaxNode) -> GlobalVarDecl {
+    let mut nodes = node.child_nodes();
+    let ty_node = nodes.next().expect("global variable without a type");
+    let expr_node = nodes.next().expect("global variable without a value");
+
+    GlobalVarDecl {
+        ident: first_ident(node),
+
+        ty: ty(&ty_node),
+        ty_span: ty_node.span(),
+
+        expr: expr(&expr_node),
+    }
+}
+
+fn params(node: &SyntaxNode) -> Vec<Param> {
+    child(node, NodeKind::ParamList)
+        .child_nodes()
+        .map(|param| {
+            let ty_node = param
+                .child_nodes()
+                .next()
+                .expect("parameter without a type");
+
+            Param {
+                ident: first_ident(&param),
+
+                ty: ty(&ty_node),
+                ty_span: ty_node.span(),
+            }
+        })
+        .collect()
+}
+
+/// The return type, and the span of everything after the arrow, including
+/// tokens that were skipped after a type that failed to parse.
+fn ret_type(node: &SyntaxNode) -> (Type, Span) {
+    if !has_token(node, TokenKind::Arrow) {
+        return (Type::Void, node.span());
+    }
+
+    let mut ret_ty = Type::Void;
+    let mut span: Option<Span> = None;
+
+    for element in node.children() {
+        let element_span = match element {
+            SyntaxElement::Node(node) => {
+                if node.kind() != NodeKind::Error {
+                    ret_ty = ty(&node);
+                }
+
+                node.span()
+            }
+
+            SyntaxElement::Token(token)
+                if token.is_trivia() || token.token_kind() == Some(TokenKind::Arrow) =>
+            {
+                continue;
+            }
+
+            SyntaxElement::Token(token) => token.span(),
+        };
+
+        span = Some(span.map_or(element_span, |span| span.union(element_span)));
+    }
+
+    (ret_ty, span.unwrap_or_else(|| Span::empty(node.span().end)))
+}
+
+fn ty(node: &SyntaxNode) -> Type {
+    let inner = || {
+        ty(&node
+            .child_nodes()
+            .next()
+            .expect("type without an inner type"))
+    };
+
+    match node.kind() {
+        NodeKind::PrimitiveType => match first_token(node).token_kind() {
+            Some(TokenKind::Keyword(Keyword::I64)) => Type::I64,
+            Some(TokenKind::Keyword(Keyword::F32)) => Type::F32,
+            Some(TokenKind::Keyword(Keyword::F64)) => Type::F64,
+            Some(TokenKind::Keyword(Keyword::Bool)) => Type::Bool,
+            Some(TokenKind::Keyword(Keyword::Char)) => Type::Char,
+            Some(TokenKind::Keyword(Keyword::Str)) => Type::Str,
+            Some(TokenKind::Keyword(Keyword::Void)) => Type::Void,
+            Some(TokenKind::Bang) => Type::Never,
+            other => unreachable!("{other:?} isn't a primitive type"),
+        },
+
+        NodeKind::PtrType => inner().ptr(),
+        NodeKind::SliceType => Type::Slice(Box::new(inner())),
+        NodeKind::ArrayType => {
+            let len = node
+                .child_tokens()
+                .find_map(|token| match token.token_kind() {
+                    Some(TokenKind::Integer(n)) => Some(n as u64),
+                    _ => None,
+                })
+                .expect("array type without a length");
+
+            Type::Array(Box::new(inner()), len)
+        }
+
+        other => unreachable!("{other:?} isn't a type"),
+    }
+}
+
+fn block(node: &SyntaxNode) -> Block {
+    let mut statements = vec![];
+    let mut final_expr = None;
+
+    let children: Vec<SyntaxElement> = node
+        .children()
+        .filter(|element| !matches!(element, SyntaxElement::Token(token) if token.is_trivia()))
+        .collect();
+
+    for (index, element) in children.iter().enumerate() {
+        let SyntaxElement::Node(stmt_node) = element else {
+            continue;
+        };
+
+        match statement(stmt_node) {
+            // expressions like `if` don't need a semicolon to be a statement,
+            // but are still the final expression at the end of a block
+            Stmt::Drop {
+                expr,
+                had_semicolon: false,
+            } if !expr.kind.stmt_semicolon_is_optional()
+                || matches!(
+                    children.get(index + 1),
+                    Some(SyntaxElement::Token(token))
+                        if token.token_kind() == Some(TokenKind::RBrace)
+                ) =>
+            {
+                final_expr = Some(expr);
+                break;
+            }
+
+            statement => statements.push(statement),
+        }
+    }
+
+    let final_expr = final_expr.unwrap_or_else(|| {
+        let rbrace = node
+            .child_tokens()
+            .filter(|token| token.token_kind() == Some(TokenKind::RBrace))
+            .last()
+            .expect("block without a closing brace");
+
+        Expr::new(ExprKind::Void, rbrace.span())
+    });
+
+    Block {
+        statements,
+        final_expr,
+
+        span: node.span(),
+    }
+}
+
+fn statement(node: &SyntaxNode) -> Stmt {
+    let nodes: Vec<SyntaxNode> = node.child_nodes().collect();
+
+    match node.kind() {
+        NodeKind::ReturnStmt => match nodes.first() {
+            Some(expr_node) => Stmt::Return(expr(expr_node)),
+            None => Stmt::Return(Expr::new(ExprKind::Void, first_token(node).span())),
+        },
+
+        NodeKind::LetStmt => Stmt::Assign {
+            ident: first_ident(node),
+            ty: ty(&nodes[0]),
+            expr: expr(&nodes[1]),
+        },
+
+        NodeKind::StoreStmt => Stmt::Store {
+            place: expr(&nodes[0]),
+            expr: expr(&nodes[1]),
+        },
+
+        NodeKind::ExprStmt => Stmt::Drop {
+            expr: expr(&nodes[0]),
+            had_semicolon: has_token(node, TokenKind::Semicolon),
+        },
+
+        _ => Stmt::ParseError,
+    }
+}
+
+fn expr(node: &SyntaxNode) -> Expr {
+    let nodes: Vec<SyntaxNode> = node.child_nodes().collect();
+
+    let (kind, span) = match node.kind() {
+        NodeKind::Literal => {
+            let token = first_token(node);
+            let kind = match token.token_kind() {
+                Some(TokenKind::Integer(n)) => ExprKind::Integer(n),
+                Some(TokenKind::Float(f)) => ExprKind::Float(f),
+                Some(TokenKind::Bool(b)) => ExprKind::Bool(b),
+                Some(TokenKind::Char(ch)) => ExprKind::Char(ch),
+                Some(TokenKind::String(istr)) => ExprKind::String(istr),
+                Some(TokenKind::Keyword(Keyword::Void)) => ExprKind::Void,
+                other => unreachable!("{other:?} isn't a literal"),
+            };
+
+            (kind, token.span())
+        }
+
+        NodeKind::VarExpr => {
+            let ident = first_ident(node);
+            (ExprKind::Var(ident), ident.span)
+        }
+
+        NodeKind::CallExpr => {
+            let mut idents = idents(node);
+            let first = idents.next().expect("call without a function");
+            let (package, func) = match idents.next() {
+                Some(func) => (Some(first), func),
+                None => (None, first),
+            };
+
+            let args = nodes.iter().map(expr).collect();
+            let rparen = node.child_tokens().last().expect("call without tokens");
+
+            let kind = ExprKind::Call {
+                package,
+                func,
+                args,
+            };
+            (kind, first.span.union(rparen.span()))
+        }
+
+        NodeKind::UnaryExpr => {
+            let op_token = first_token(node);
+            let op = match op_token.token_kind() {
+                Some(TokenKind::Sub) => UnOp::Negate,
+                Some(TokenKind::BitwiseInvert) => UnOp::BitwiseInvert,
+                other => unreachable!("{other:?} isn't a unary operator"),
+            };
+
+            let kind = ExprKind::UnOp {
+                op,
+                expr: Box::new(expr(&nodes[0])),
+            };
+
+            // the span of a unary operation is only its operator
+            (kind, op_token.span())
+        }
+
+        NodeKind::AddressOfExpr => {
+            let expr = expr(&nodes[0]);
+            let span = first_token(node).span().union(expr.span);
+            (ExprKind::AddressOf(Box::new(expr)), span)
+        }
+
+        NodeKind::DerefExpr => {
+            let expr = expr(&nodes[0]);
+            let span = first_token(node).span().union(expr.span);
+            (ExprKind::Deref(Box::new(expr)), span)
+        }
+
+        // parentheses aren't in the AST
+        NodeKind::ParenExpr => return expr(&nodes[0]),
+
+        NodeKind::ArrayExpr => {
+            let elements = nodes.iter().map(expr).collect();
+            (ExprKind::Array(elements), node.span())
+        }
+
+        NodeKind::Block => (ExprKind::Block(Box::new(block(node))), node.span()),
+
+        NodeKind::IfExpr => {
+            let kind = ExprKind::If {
+                cond: Box::new(expr(&nodes[0])),
+                then: Box::new(expr(&nodes[1])),
+                else_: nodes.get(2).map(|else_| Box::new(expr(else_))),
+            };
+
+            (kind, node.span())
+        }
+
+        NodeKind::BinaryExpr => {
+            let op = node
+                .child_tokens()
+                .find_map(|token| token.token_kind()?.bin_op())
+                .expect("binary expression without an operator");
+
+            let lhs = expr(&nodes[0]);
+            let rhs = expr(&nodes[1]);
+
+            let span = lhs.span.union(rhs.span);
+            let kind = ExprKind::BinOp {
+                op,
+                lhs: Box::new(lhs),
+                rhs: Box::new(rhs),
+            };
+            (kind, span)
+        }
+
+        NodeKind::IndexExpr => {
+            let base = expr(&nodes[0]);
+            let index = expr(&nodes[1]);
+
+            let rbracket = node.child_tokens().last().expect("index without tokens");
+            let span = base.span.union(rbracket.span());
+            let kind = ExprKind::Index {
+                base: Box::new(base),
+                index: Box::new(index),
+            };
+            (kind, span)
+        }
+
+        NodeKind::FieldExpr => {
+            let expr = expr(&nodes[0]);
+            let field = first_ident(node);
+
+            let span = expr.span.union(field.span);
+            let kind = ExprKind::Field {
+                expr: Box::new(expr),
+                field,
+            };
+            (kind, span)
+        }
+
+        NodeKind::CastExpr => {
+            let expr = expr(&nodes[0]);
+            let ty_node = &nodes[1];
+
+            let span = expr.span.union(ty_node.span());
+            let kind = ExprKind::Cast {
+                expr: Box::new(expr),
+                ty: ty(ty_node),
+            };
+            (kind, span)
+        }
+
+        _ => (ExprKind::ParseError, node.span()),
+    };
+
+    Expr::new(kind, span)
+}
+
+fn child(node: &SyntaxNode, kind: NodeKind) -> SyntaxNode {
+    node.child_nodes()
+        .find(|child| child.kind() == kind)
+        .unwrap_or_else(|| panic!("{:?} without {kind:?}", node.kind()))
+}
+
+fn has_error(node: &SyntaxNode) -> bool {
+    node.child_nodes()
+        .any(|child| child.kind() == NodeKind::Error)
+}
+
+fn has_token(node: &SyntaxNode, kind: TokenKind) -> bool {
+    node.child_tokens()
+        .any(|token| token.token_kind() == Some(kind))
+}
+
+fn first_token(node: &SyntaxNode) -> SyntaxToken {
+    node.child_tokens()
+        .next()
+        .unwrap_or_else(|| panic!("{:?} without tokens", node.kind()))
+}
+
+/// The identifiers that are children of a node.
+fn idents(node: &SyntaxNode) -> impl Iterator<Item = Ident> + '_ {
+    node.child_tokens()
+        .filter_map(|token| match token.token_kind() {
+            Some(TokenKind::Identifier(istr)) => Some(Ident {
+                istr,
+                span: token.span(),
+            }),
+            _ => None,
+        })
+}
+
+fn first_ident(node: &SyntaxNode) -> Ident {
+    idents(node)
+        .next()
+        .unwrap_or_else(|| panic!("{:?} without an identifier", node.kind()))
+}
@@ -0,0 +1,334 @@
+//! Formatting modules with a deterministic layout, from their lossless syntax tree.
+//!
+//! Every token is printed in order, and only the whitespace between them is
+//! changed. Statements and items go on their own lines, with blocks indented
+//! by four spaces, and everything else goes on one line, since there's no
+//! maximum width yet. Comments are kept, at the end of the line they were on,
+//! or on their own line, and so are single blank lines between items and
+//! between statements. A comment in a list of parameters, arguments or array
+//! elements breaks the list over several lines, which are indented one level
+//! deeper than the line the list starts on.
+
+#[cfg(test)]
+mod tests;
+
+use crate::cst::{NodeKind, SyntaxElement, SyntaxNode, SyntaxToken, SyntaxTokenKind};
+use crate::token::{TokenKind, TriviaKind};
+
+const INDENT: &str = "    ";
+
+/// Format a module from its lossless syntax tree, which should have no
+/// syntax errors, because the tokens around an error are left as they are.
+pub fn format_module(node: &SyntaxNode) -> String {
+    let mut printer = Printer::default();
+    printer.node(node);
+
+    let mut output = printer.output;
+    output.truncate(output.trim_end().len());
+    output.push('\n');
+    output
+}
+
+/// What goes before the next token, where later requests only make it wider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Default)]
+enum Separator {
+    #[default]
+    None,
+    Space,
+    Line,
+    /// A line break, and a blank line if there was one in the source.
+    LineOrBlank,
+    Blank,
+}
+
+#[derive(Default)]
+struct Printer {
+    output: String,
+    indent: usize,
+
+    /// The indentation of the line being printed, which blocks and
+    /// lists that start on it are indented relative to.
+    line_indent: usize,
+
+    separator: Separator,
+
+    /// The line breaks in the source since the last token or comment.
+    newlines: usize,
+
+    /// Whether the last thing printed was a comment on its own line.
+    after_comment_line: bool,
+}
+
+impl Printer {
+    fn node(&mut self, node: &SyntaxNode) {
+        match node.kind() {
+            NodeKind::Module => self.module(node),
+            NodeKind::Block => self.block(node),
+            kind => self.inline(node, kind),
+        }
+    }
+
+    /// Print items with a line between them, and a blank line around functions.
+    fn module(&mut self, node: &SyntaxNode) {
+        for child in node.children() {
+            match child {
+                SyntaxElement::Token(token) if token.is_trivia() => self.trivia(&token),
+                SyntaxElement::Token(token) => self.token(&token),
+
+                SyntaxElement::Node(item) => {
+                    // a comment above a function stays with it
+                    let is_func = item.kind() == NodeKind::FuncDecl;
+                    if is_func && !self.after_comment_line {
+                        self.separate(Separator::Blank);
+                    }
+
+                    self.node(&item);
+
+                    self.separate(if is_func {
+                        Separator::Blank
+                    } else {
+                        Separator::LineOrBlank
+                    });
+                }
+            }
+        }
+    }
+
+    /// Print a block with a statement on each line, or as `{}` if it's empty.
+    fn block(&mut self, node: &SyntaxNode) {
+        let is_empty = node.children().all(|child| match child {
+            SyntaxElement::Node(_) => false,
+            SyntaxElement::Token(token) => {
+                token.kind() != SyntaxTokenKind::Trivia(TriviaKind::Comment)
+            }
+        });
+        let outer_indent = self.indent;
+
+        for child in node.children() {
+            match child {
+                SyntaxElement::Token(token) if token.is_trivia() => self.trivia(&token),
+
+                SyntaxElement::Token(token) if token.token_kind() == Some(TokenKind::LBrace) => {
+                    self.token(&token);
+
+                    if !is_empty {
+                        self.indent = self.line_indent + 1;
+                        self.separate(Separator::Line);
+                    }
+                }
+
+                SyntaxElement::Token(token) if token.token_kind() == Some(TokenKind::RBrace) => {
+                    if !is_empty {
+                        self.indent = outer_indent;
+                        // no blank line before the closing brace
+                        self.separator = Separator::Line;
+                    }
+
+                    self.token(&token);
+                }
+
+                SyntaxElement::Token(token) => self.token(&token),
+
+                SyntaxElement::Node(statement) => {
+                    self.node(&statement);
+                    self.separate(Separator::LineOrBlank);
+                }
+            }
+        }
+    }
+
+    /// Print a node on one line, with spaces between its tokens where they belong.
+    fn inline(&mut self, node: &SyntaxNode, kind: NodeKind) {
+        let is_list = matches!(
+            kind,
+            NodeKind::ParamList | NodeKind::CallExpr | NodeKind::ArrayExpr
+        );
+        let (outer_indent, outer_line_indent) = (self.indent, self.line_indent);
+
+        let mut prev: Option<SyntaxElement> = None;
+
+        for child in node.children() {
+            match &child {
+                SyntaxElement::Token(token) if token.is_trivia() => {
+                    self.trivia(token);
+                    continue;
+                }
+
+                // like the return type of a function without one
+                SyntaxElement::Node(node) if !has_tokens(node) => continue,
+
+                _ => {}
+            }
+
+            if let Some(prev) = &prev {
+                if space_between(kind, prev, &child) {
+                    self.separate(Separator::Space);
+                }
+            }
+
+            let delimiter = match &child {
+                SyntaxElement::Token(token) if is_list => token.token_kind(),
+                _ => None,
+            };
+            let closes_list = matches!(delimiter, Some(TokenKind::RParen | TokenKind::RBracket));
+
+            // the closing delimiter goes back to the indentation outside the list
+            if closes_list {
+                self.indent = outer_indent;
+            }
+
+            match &child {
+                SyntaxElement::Node(node) => self.node(node),
+                SyntaxElement::Token(token) => self.token(token),
+            }
+
+            match delimiter {
+                // lines broken in the list are one level deeper than the line it starts on
+                Some(TokenKind::LParen | TokenKind::LBracket) => {
+                    self.indent = self.line_indent + 1;
+                }
+
+                // and what follows the list is indented like that line, such as
+                // the body of a function with its parameters on several lines
+                _ if closes_list => self.line_indent = outer_line_indent,
+
+                _ => {}
+            }
+
+            prev = Some(child);
+        }
+
+        self.indent = outer_indent;
+    }
+
+    fn separate(&mut self, separator: Separator) {
+        self.separator = self.separator.max(separator);
+    }
+
+    fn token(&mut self, token: &SyntaxToken) {
+        self.write_separator();
+        self.output.push_str(token.text());
+        self.after_comment_line = false;
+    }
+
+    fn trivia(&mut self, token: &SyntaxToken) {
+        match token.kind() {
+            SyntaxTokenKind::Trivia(TriviaKind::Comment) => {
+                let comment = token.text().trim_end();
+
+                if self.newlines == 0 && !self.output.is_empty() {
+                    // a comment at the end of a line stays there
+                    self.output.push(' ');
+                    self.output.push_str(comment);
+                } else {
+                    let separator = self.separator;
+                    self.separate(Separator::Line);
+                    self.write_separator();
+                    self.output.push_str(comment);
+
+                    // the separator after the comment is like the one before it,
+                    // without a blank line after a comment above an item
+                    self.separator = separator.min(Separator::LineOrBlank);
+                    self.after_comment_line = true;
+                }
+
+                // the next token can't be on the comment's line
+                self.separate(Separator::Line);
+                self.newlines = 0;
+            }
+
+            SyntaxTokenKind::Trivia(_) => {
+                self.newlines += token.text().matches('\n').count();
+            }
+
+            SyntaxTokenKind::Token(_) => unreachable!("token isn't trivia"),
+        }
+    }
+
+    fn write_separator(&mut self) {
+        let separator = match self.separator {
+            Separator::LineOrBlank if self.newlines >= 2 => Separator::Blank,
+            Separator::LineOrBlank => Separator::Line,
+            separator => separator,
+        };
+
+        // nothing goes before the first token
+        if !self.output.is_empty() {
+            match separator {
+                Separator::None | Separator::LineOrBlank => {}
+                Separator::Space => self.output.push(' '),
+                Separator::Line | Separator::Blank => {
+                    if separator == Separator::Blank {
+                        self.output.push('\n');
+                    }
+
+                    self.output.push('\n');
+                    for _ in 0..self.indent {
+                        self.output.push_str(INDENT);
+                    }
+                    self.line_indent = self.indent;
+                }
+            }
+        }
+
+        self.separator = Separator::None;
+        self.newlines = 0;
+    }
+}
+
+fn has_tokens(node: &SyntaxNode) -> bool {
+    node.descendant_tokens()
+        .iter()
+        .any(|token| !token.is_trivia())
+}
+
+/// Whether there is a space between two tokens or nodes in a node of `kind`.
+fn space_between(kind: NodeKind, prev: &SyntaxElement, next: &SyntaxElement) -> bool {
+    let token_kind = |element: &SyntaxElement| match element {
+        SyntaxElement::Token(token) => token.token_kind(),
+        SyntaxElement::Node(_) => None,
+    };
+
+    let prev_token = token_kind(prev);
+    let next_token = token_kind(next);
+
+    if matches!(
+        next_token,
+        Some(
+            TokenKind::Comma
+                | TokenKind::Semicolon
+                | TokenKind::Colon
+                | TokenKind::DoubleColon
+                | TokenKind::Dot
+                | TokenKind::RParen
+                | TokenKind::RBracket
+        )
+    ) {
+        return false;
+    }
+
+    if matches!(
+        prev_token,
+        Some(TokenKind::LParen | TokenKind::LBracket | TokenKind::DoubleColon | TokenKind::Dot)
+    ) {
+        return false;
+    }
+
+    match kind {
+        // `f(x)` and `package::f(x)`
+        NodeKind::CallExpr => next_token != Some(TokenKind::LParen),
+        // `xs[i]`
+        NodeKind::IndexExpr => next_token != Some(TokenKind::LBracket),
+        // `func f(x: i64)`
+        NodeKind::FuncDecl | NodeKind::ExternFuncDecl => {
+            !matches!(next, SyntaxElement::Node(node) if node.kind() == NodeKind::ParamList)
+        }
+        // `-x`, `&x`, `*x` and `*i64`
+        NodeKind::UnaryExpr | NodeKind::AddressOfExpr | NodeKind::DerefExpr | NodeKind::PtrType => {
+            prev_token.is_none()
+        }
+        // `[]i64`
+        NodeKind::SliceType => prev_token != Some(TokenKind::RBracket),
+        _ => true,
+    }
+}
@@ -0,0 +1,92 @@
+use bayou_interner::Interner;
+
+use super::format_module;
+use crate::parse_lossless;
+
+fn format(source: &str) -> String {
+    let interner = Interner::new();
+
+    let (cst, lexer_errors, parse_errors) = parse_lossless(source, &interner);
+    assert!(lexer_errors.is_empty(), "lexer errors in formatter tests");
+    assert!(parse_errors.is_empty(), "parse errors in formatter tests");
+
+    format_module(&cst)
+}
+
+macro_rules! assert_format {
+    ($source:expr) => {{
+        let source = $source;
+        let formatted = format(source);
+        assert_eq!(format(&formatted), formatted, "formatting isn't idempotent");
+
+        insta::with_settings!({
+            info => &source,
+            omit_expression => true,
+        }, {
+            insta::assert_snapshot!(formatted);
+        });
+    }};
+}
+
+#[test]
+fn layout() {
+    assert_format!(
+        "submodule a;   submodule b;
+        extern func f(x:i64,p:*void)->[]i64;
+        const N :i64=4*(2+1);static S:[i64;2]=[1,2];
+        func main()->i64{let x:i64=-N;x=x+1; a::g(x,&x)[0] as i64+*p.len}"
+    );
+}
+
+#[test]
+fn if_and_blocks() {
+    assert_format!(
+        "func f(x: f64) -> bool { if x > 0.0 then { let y: f64 = x; y > 1.0 } else false }
+        func g() {} export func h() { { 1 }; if true then {} else {} }"
+    );
+}
+
+#[test]
+fn blank_lines() {
+    assert_format!(
+        "const A: i64 = 1;\nconst B: i64 = 2;\n\n\n\nstatic C: i64 = 3;\nfunc main() -> i64 {\n\n    let x: i64 = 1;\n\n\n    let y: i64 = 2;\n\n    x + y\n\n}\n\n\n"
+    );
+}
+
+#[test]
+fn comments() {
+    assert_format!(
+        "// the entry point
+func main() -> i64 { // a trailing comment
+    // before a statement
+    let x: i64 = 1;   // after a statement
+
+    // before the final expression
+    f(x, // in the arguments
+      2) // after the final expression
+    // before the closing brace
+}
+// at the end"
+    );
+}
+
+#[test]
+fn multi_line_lists() {
+    assert_format!(
+        "func add(
+    x: i64, // the first number
+    // the second number
+    y: i64
+) -> i64 {
+    let sum: i64 = add(x, // first
+        add(y, // nested
+          1),
+        // last
+        2);
+    let a: [i64; 2] = [1, // one
+        2];
+    if x > 0 then { add(1, // one
+        { 2 }) } else 0
+}"
+    );
+}
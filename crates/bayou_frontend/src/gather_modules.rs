@@ -1,11 +1,12 @@
-use bayou_interner::Interner;
+use bayou_interner::{Interner, SyncInterner};
 use bayou_ir::IdentWithSource;
 use bayou_session::{
-    diagnostics::{prelude::*, sources::Source as _},
+    diagnostics::prelude::*,
     module_loader::{ModuleLoader, ModuleLoaderError, ModulePath},
     sourcemap::{Source, SourceId, SourceSpan},
-    PackageSession, Session,
+    sym, PackageSession, Session,
 };
+use bayou_utils::parallel;
 
 use crate::{
     ast,
@@ -23,7 +24,10 @@ pub enum GatherModulesError {
     LexerError(LexerError, SourceId),
     ParseError(ParseError, SourceId),
 
-    DuplicateGlobal(IdentWithSource),
+    DuplicateGlobal {
+        first: IdentWithSource,
+        second: IdentWithSource,
+    },
 }
 
 impl IntoDiagnostic<Interner> for GatherModulesError {
@@ -47,9 +51,21 @@ impl IntoDiagnostic<Interner> for GatherModulesError {
             Self::LexerError(err, source_id) => err.into_diagnostic(&source_id),
             Self::ParseError(err, source_id) => err.into_diagnostic(&source_id),
 
-            Self::DuplicateGlobal(_err) => {
-                // TODO: store source spans in duplicate global error
-                todo!()
+            Self::DuplicateGlobal { first, second } => {
+                let ident_str = &interner[first.istr];
+
+                Diagnostic::error()
+                    .with_message(format!("duplicate global `{ident_str}`"))
+                    .with_snippet(Snippet::secondary(
+                        "first definition",
+                        first.span.source_id,
+                        first.span.span,
+                    ))
+                    .with_snippet(Snippet::primary(
+                        "second definition",
+                        second.span.source_id,
+                        second.span.span,
+                    ))
             }
         }
     }
@@ -63,130 +79,244 @@ pub struct ParsedModule {
     pub ast: ast::Module,
 }
 
+/// A module parsed without errors, with the interner that its identifiers
+/// and strings are in, rather than the interner of its package.
+#[derive(Debug, serde::Serialize, serde::Deserialize)]
+pub struct CachedModule {
+    pub interner: Interner,
+    pub ast: ast::Module,
+}
+
+/// Where modules parsed in earlier builds are kept, so that
+/// modules that didn't change aren't lexed and parsed again.
+pub trait ParseCache: Sync {
+    /// Get the module parsed from `source` in an earlier build.
+    fn load(&self, source: &Source) -> Option<CachedModule>;
+
+    /// Keep a module that was parsed from `source`.
+    fn store(&self, source: &Source, module: &CachedModule);
+}
+
 pub struct ModuleGatherer<'a, S: Session> {
     session: &'a mut S,
     package_session: &'a mut PackageSession<S>,
+    cache: Option<&'a dyn ParseCache>,
+    threads: usize,
 
     errors: Vec<GatherModulesError>,
 }
 
+/// A module that was loaded, lexed and parsed, but isn't in the source map yet.
+struct LoadedModule {
+    source: Source,
+    module: CachedModule,
+
+    lexer_errors: Vec<LexerError>,
+    parse_errors: Vec<ParseError>,
+}
+
 impl<'a, S: Session> ModuleGatherer<'a, S> {
     pub fn new(session: &'a mut S, package_session: &'a mut PackageSession<S>) -> Self {
         Self {
             session,
             package_session,
+            cache: None,
+            threads: parallel::thread_count(),
 
             errors: vec![],
         }
     }
 
+    /// Reuse the modules in `cache` that haven't changed, and
+    /// add the modules that are parsed without errors to it.
+    #[must_use]
+    pub fn with_cache(mut self, cache: &'a dyn ParseCache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Load modules on `threads` threads, rather than on [`parallel::thread_count`],
+    /// so that tests don't depend on the number of CPUs.
+    #[cfg(test)]
+    #[must_use]
+    pub fn with_threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+
+    /// Load every module of the package, starting from the root module.
+    ///
+    /// Modules are loaded a level of the module tree at a time, because the submodules
+    /// of a module are only known once it is parsed. The modules in each level are
+    /// loaded, lexed and parsed in parallel.
+    ///
+    /// Each module is parsed with its own interner, which is merged into the interner of
+    /// the package in the order of the modules, so that strings get the same IDs no
+    /// matter which threads parse which modules first.
     pub fn run(mut self) -> (ModuleTree, Vec<ParsedModule>, Vec<GatherModulesError>) {
+        let interner = SyncInterner::from(std::mem::take(&mut self.package_session.interner));
+
         let mut module_tree = ModuleTree::new();
         let mut parsed_modules = vec![];
 
         let mut modules_to_load = vec![(module_tree.root_id(), None)];
 
-        while let Some((module_id, span)) = modules_to_load.pop() {
-            let module_path = &module_tree.entry(module_id).path;
-
-            let Some((source_id, ast)) = self.parse_module(module_path, span) else {
-                continue;
+        while !modules_to_load.is_empty() {
+            let loaded = {
+                let module_tree = &module_tree;
+                let module_loader = &self.package_session.module_loader;
+                let cache = self.cache;
+
+                parallel::map_with_threads(
+                    self.threads,
+                    &modules_to_load,
+                    || (),
+                    |(), &(module_id, _)| {
+                        let path = &module_tree.entry(module_id).path;
+                        load_module(module_loader, path, &interner, cache)
+                    },
+                )
             };
 
-            let submodule_names = ast.items.iter().filter_map(|item| match item {
-                ast::Item::Submodule(name) => Some(name.with_source(source_id)),
-                _ => None,
-            });
-
-            for submodule_name in submodule_names {
-                // A submodule of the root module can't be called `main` because it would
-                // clash with the root module. For now just check no modules are called `main`.
-                // TODO: handle cyclic modules properly?
-                if &self.package_session.interner[submodule_name.istr] == "main" {
-                    self.errors
-                        .push(GatherModulesError::InvalidModuleName(submodule_name));
-                    continue;
-                }
-
-                let submodule_id = match module_tree.insert_module(module_id, submodule_name) {
-                    Ok(id) => id,
-
-                    Err(GlobalId::Module(first_module_id)) => {
-                        // module must have an identifier, otherwise there would be no error
-                        let first_module_ident = module_tree.entry(first_module_id).ident.unwrap();
+            let mut submodules_to_load = vec![];
 
+            for ((module_id, span), loaded) in modules_to_load.drain(..).zip(loaded) {
+                let module = match loaded {
+                    Ok(module) => module,
+                    Err(err) => {
                         self.errors
-                            .push(GatherModulesError::DuplicateGlobal(first_module_ident));
+                            .push(GatherModulesError::ModuleLoaderError(err, span));
+                        continue;
+                    }
+                };
 
+                let mut ast = module.module.ast;
+                ast.remap(&interner.merge(&module.module.interner));
+
+                let source_id = self.session.source_map_mut().insert(module.source);
+                self.errors.extend(
+                    module
+                        .lexer_errors
+                        .into_iter()
+                        .map(|err| GatherModulesError::LexerError(err, source_id)),
+                );
+                self.errors.extend(
+                    module
+                        .parse_errors
+                        .into_iter()
+                        .map(|err| GatherModulesError::ParseError(err, source_id)),
+                );
+
+                let submodule_names = ast.items.iter().filter_map(|item| match item {
+                    ast::Item::Submodule(name) => Some(name.with_source(source_id)),
+                    _ => None,
+                });
+
+                for submodule_name in submodule_names {
+                    // A submodule of the root module can't be called `main` because it would
+                    // clash with the root module. For now just check no modules are called `main`.
+                    // TODO: handle cyclic modules properly?
+                    if submodule_name.istr == sym::MAIN {
+                        self.errors
+                            .push(GatherModulesError::InvalidModuleName(submodule_name));
                         continue;
                     }
 
-                    Err(_) => unreachable!(),
-                };
+                    let submodule_id = match module_tree.insert_module(module_id, submodule_name) {
+                        Ok(id) => id,
 
-                modules_to_load.push((submodule_id, Some(submodule_name.span)));
+                        Err(GlobalId::Module(first_module_id)) => {
+                            // module must have an identifier, otherwise there would be no error
+                            let first_module_ident =
+                                module_tree.entry(first_module_id).ident.unwrap();
+
+                            self.errors.push(GatherModulesError::DuplicateGlobal {
+                                first: first_module_ident,
+                                second: submodule_name,
+                            });
+
+                            continue;
+                        }
+
+                        Err(_) => unreachable!(),
+                    };
+
+                    submodules_to_load.push((submodule_id, Some(submodule_name.span)));
+                }
+
+                parsed_modules.push(ParsedModule {
+                    module_id,
+                    source_id,
+                    ast,
+                });
             }
 
-            parsed_modules.push(ParsedModule {
-                module_id,
-                source_id,
-                ast,
-            });
+            modules_to_load = submodules_to_load;
         }
 
+        self.package_session.interner = interner.into_interner();
+
         (module_tree, parsed_modules, self.errors)
     }
+}
 
-    fn parse_module(
-        &mut self,
-        module_path: &ModulePath,
-        def_source_span: Option<SourceSpan>,
-    ) -> Option<(SourceId, ast::Module)> {
-        let source_string = match self
-            .package_session
-            .module_loader
-            .load_module(module_path, &self.package_session.interner)
-        {
-            Ok(s) => s,
-            Err(err) => {
-                self.errors
-                    .push(GatherModulesError::ModuleLoaderError(err, def_source_span));
-                return None;
-            }
-        };
-
-        let (source_id, source) = self.session.source_map_mut().insert_and_get(Source {
-            name: module_path
-                .display(&self.package_session.interner)
-                .to_string(),
-            source: source_string,
+/// Load, lex and parse a module, or get it from the cache if it is there,
+/// which can happen on any thread.
+fn load_module(
+    module_loader: &impl ModuleLoader,
+    module_path: &ModulePath,
+    interner: &SyncInterner,
+    cache: Option<&dyn ParseCache>,
+) -> Result<LoadedModule, ModuleLoaderError> {
+    let source = Source {
+        name: module_path.display(interner).to_string(),
+        source: module_loader.load_module(module_path, interner)?,
+        path: module_loader.module_file(module_path, interner),
+    };
+
+    if let Some(module) = cache.and_then(|cache| cache.load(&source)) {
+        return Ok(LoadedModule {
+            source,
+            module,
+
+            lexer_errors: vec![],
+            parse_errors: vec![],
         });
+    }
 
-        let (tokens, lexer_errors) =
-            Lexer::new(source.source_str(), &self.package_session.interner).lex();
-        self.errors.extend(
-            lexer_errors
-                .into_iter()
-                .map(|err| GatherModulesError::LexerError(err, source_id)),
-        );
+    let module_interner = Interner::new();
+    let (tokens, lexer_errors) = Lexer::new(&source.source, &module_interner).lex();
+    let (ast, parse_errors) = Parser::new(tokens).parse();
 
-        let (ast, parse_errors) = Parser::new(tokens).parse();
-        self.errors.extend(
-            parse_errors
-                .into_iter()
-                .map(|err| GatherModulesError::ParseError(err, source_id)),
-        );
+    let module = CachedModule {
+        interner: module_interner,
+        ast,
+    };
 
-        Some((source_id, ast))
+    if let Some(cache) = cache {
+        if lexer_errors.is_empty() && parse_errors.is_empty() {
+            cache.store(&source, &module);
+        }
     }
+
+    Ok(LoadedModule {
+        source,
+        module,
+
+        lexer_errors,
+        parse_errors,
+    })
 }
 
 #[cfg(test)]
 mod tests {
+    use std::collections::HashMap;
+
     use bayou_ir::{ir::PackageIr, symbols::Symbols};
     use bayou_session::{Session, TestSession, TestSessionConfig};
     use bayou_utils::assert_yaml_snapshot_with_source;
 
+    use super::ModuleGatherer;
     use crate::NameError;
 
     fn lower(source: &str) -> (PackageIr, Symbols, Vec<NameError>) {
@@ -197,14 +327,14 @@ mod tests {
         ));
 
         let (mut module_tree, modules, errors) =
-            crate::load_and_parse_modules(&mut session, &mut package_session);
+            crate::load_and_parse_modules(&mut session, &mut package_session, None);
 
         assert!(
             errors.is_empty(),
             "non-lowering errors while testing lowering"
         );
 
-        crate::lower(&modules, &mut module_tree, &package_session.interner)
+        crate::lower(&modules, &mut module_tree, &package_session.interner, &[])
     }
 
     macro_rules! assert_lower {
@@ -218,4 +348,64 @@ mod tests {
     fn basic_lower() {
         assert_lower!("func main() -> i64 { return 0; }");
     }
+
+    #[test]
+    fn global_vars() {
+        assert_lower!(
+            "const N: i64 = 2; static X: i64 = N; func main() -> i64 { let N: i64 = X; N }"
+        );
+    }
+
+    #[test]
+    fn address_taken() {
+        assert_lower!("func main() -> i64 { let a: i64 = 0; let b: *i64 = &a; *b }");
+    }
+
+    #[test]
+    fn deterministic_interning() {
+        let names = ["a", "b", "c", "d", "e", "f", "g", "h"];
+
+        let mut modules: HashMap<_, _> = names
+            .iter()
+            .map(|name| {
+                let source = format!(
+                    "func {name}_func(x_{name}: i64) -> i64 {{ let y_{name}: i64 = x_{name}; y_{name} }}"
+                );
+                (format!("package::{name}"), source)
+            })
+            .collect();
+
+        let submodules: String = names
+            .iter()
+            .map(|name| format!("submodule {name};"))
+            .collect();
+        modules.insert(
+            String::from("package"),
+            format!("{submodules} func main() -> i64 {{ 0 }}"),
+        );
+
+        let gather_and_lower = || {
+            let mut session = TestSession::new();
+            let mut package_session = session
+                .build_package_session(TestSessionConfig::new("test_package", modules.clone()));
+
+            // modules in the same level are lexed on different threads, in any order
+            let (mut module_tree, modules, errors) =
+                ModuleGatherer::new(&mut session, &mut package_session)
+                    .with_threads(8)
+                    .run();
+            assert!(errors.is_empty());
+
+            let interner = &package_session.interner;
+            let (ir, symbols, errors) = crate::lower(&modules, &mut module_tree, interner, &[]);
+            assert!(errors.is_empty());
+
+            serde_json::to_string(&(interner, ir, symbols)).unwrap()
+        };
+
+        let first = gather_and_lower();
+        for _ in 0..20 {
+            assert_eq!(gather_and_lower(), first);
+        }
+    }
 }
@@ -3,7 +3,8 @@ mod tests;
 
 use std::str::Chars;
 
-use bayou_interner::Interner;
+use bayou_interner::Intern;
+use bayou_ir::F64Bits;
 use bayou_session::diagnostics::prelude::*;
 use bayou_utils::peek::Peek;
 
@@ -23,8 +24,29 @@ pub enum LexerErrorKind {
     #[error("integer overflow")]
     IntegerOverflow,
 
+    #[error("float literal out of range")]
+    FloatOutOfRange,
+
     #[error("digit {digit:?} is invalid for base {base}")]
     IntegerDigitWrongBase { base: u32, digit: char },
+
+    #[error("unterminated string literal")]
+    UnterminatedString,
+
+    #[error("unterminated character literal")]
+    UnterminatedChar,
+
+    #[error("empty character literal")]
+    EmptyChar,
+
+    #[error("character literal may only contain one character")]
+    CharTooLong,
+
+    #[error("unknown escape sequence `\\{0}`")]
+    InvalidEscape(char),
+
+    #[error("invalid unicode escape")]
+    InvalidUnicodeEscape,
 }
 
 impl IntoDiagnostic<SourceId> for LexerError {
@@ -42,9 +64,13 @@ impl IntoDiagnostic<SourceId> for LexerError {
 pub type LexerResult<T> = Result<T, LexerErrorKind>;
 
 pub struct Lexer<'sess> {
-    interner: &'sess Interner,
+    interner: &'sess dyn Intern,
     errors: Vec<LexerError>,
 
+    /// Whether to keep trivia, which is only needed for lossless syntax trees.
+    keep_trivia: bool,
+    trivia: Vec<Trivia>,
+
     all: &'sess str,
     chars: Chars<'sess>,
 
@@ -52,11 +78,14 @@ pub struct Lexer<'sess> {
 }
 
 impl<'sess> Lexer<'sess> {
-    pub fn new(source: &'sess str, interner: &'sess Interner) -> Self {
+    pub fn new(source: &'sess str, interner: &'sess dyn Intern) -> Self {
         Self {
             interner,
             errors: vec![],
 
+            keep_trivia: false,
+            trivia: vec![],
+
             all: source,
             chars: source.chars(),
 
@@ -64,14 +93,24 @@ impl<'sess> Lexer<'sess> {
         }
     }
 
-    pub fn lex(mut self) -> (TokenIter, Vec<LexerError>) {
+    /// Keep whitespace, comments and invalid text as trivia between
+    /// the tokens, so that a lossless syntax tree can be parsed.
+    #[must_use]
+    pub fn with_trivia(mut self) -> Self {
+        self.keep_trivia = true;
+        self
+    }
+
+    pub fn lex(mut self) -> (TokenIter<'sess>, Vec<LexerError>) {
         let mut tokens = vec![];
         while let Some(token) = self.lex_token() {
             tokens.push(token);
         }
 
         let iter = TokenIter {
+            source: self.all,
             tokens: tokens.into_iter(),
+            trivia: self.trivia.into_iter(),
             prev_span: Span::empty(0),
             eof_span: Span::empty(self.chars.as_str().len()),
         };
@@ -98,22 +137,37 @@ impl<'sess> Lexer<'sess> {
             let kind = match self.chars.next()? {
                 // comment
                 '/' if self.chars.eat('/') => {
-                    while !matches!(self.chars.next(), Some('\n') | None) {}
+                    while !matches!(self.chars.peek(), Some('\n') | None) {
+                        self.chars.next();
+                    }
+
+                    self.push_trivia(TriviaKind::Comment);
                     continue;
                 }
 
-                ch if ch.is_ascii_whitespace() => continue,
+                ch if ch.is_ascii_whitespace() => {
+                    while self.chars.peek().is_some_and(|ch| ch.is_ascii_whitespace()) {
+                        self.chars.next();
+                    }
+
+                    self.push_trivia(TriviaKind::Whitespace);
+                    continue;
+                }
 
                 '{' => TokenKind::LBrace,
                 '}' => TokenKind::RBrace,
                 '(' => TokenKind::LParen,
                 ')' => TokenKind::RParen,
+                '[' => TokenKind::LBracket,
+                ']' => TokenKind::RBracket,
 
                 '.' => TokenKind::Dot,
+                ':' if self.chars.eat(':') => TokenKind::DoubleColon,
                 ':' => TokenKind::Colon,
                 ',' => TokenKind::Comma,
                 ';' => TokenKind::Semicolon,
-                '!' => TokenKind::Bang, // will break `!=`
+                '!' if self.chars.eat('=') => TokenKind::NotEq,
+                '!' => TokenKind::Bang,
                 '-' if self.chars.eat('>') => TokenKind::Arrow,
 
                 '+' => TokenKind::Add,
@@ -122,8 +176,14 @@ impl<'sess> Lexer<'sess> {
                 '/' => TokenKind::Div,
                 '%' => TokenKind::Mod,
 
+                '=' if self.chars.eat('=') => TokenKind::EqEq,
                 '=' => TokenKind::Assign,
 
+                '>' if self.chars.eat('=') => TokenKind::GtEq,
+                '>' => TokenKind::Gt,
+                '<' if self.chars.eat('=') => TokenKind::LtEq,
+                '<' => TokenKind::Lt,
+
                 '&' => TokenKind::BitwiseAnd,
                 '|' => TokenKind::BitwiseOr,
                 '^' => TokenKind::BitwiseXor,
@@ -133,8 +193,12 @@ impl<'sess> Lexer<'sess> {
                 '0' if self.chars.eat('o') => try_lex!(self.lex_integer(0, 8)),
                 '0' if self.chars.eat('b') => try_lex!(self.lex_integer(0, 2)),
 
+                '0'..='9' if self.at_float() => self.lex_float(),
                 ch @ '0'..='9' => try_lex!(self.lex_integer(ch as i64 - 48, 10)),
 
+                '"' => try_lex!(self.lex_string()),
+                '\'' => try_lex!(self.lex_char()),
+
                 ch if is_ident_start(ch) => self.lex_alpha(),
 
                 ch => {
@@ -174,6 +238,137 @@ impl<'sess> Lexer<'sess> {
             .ok_or(LexerErrorKind::IntegerOverflow)
     }
 
+    /// Whether the number that the current token starts with is a float,
+    /// which means that it has a fractional part or an exponent.
+    fn at_float(&self) -> bool {
+        float_len(&self.all[self.token_start..]).is_some()
+    }
+
+    fn lex_float(&mut self) -> TokenKind {
+        let len = float_len(&self.all[self.token_start..]).unwrap();
+
+        let s: String = self.all[self.token_start..self.token_start + len]
+            .chars()
+            .filter(|&ch| ch != '_')
+            .collect();
+
+        // skip the rest of the literal (the first digit was already consumed)
+        for _ in 1..len {
+            self.chars.next();
+        }
+
+        // the literal has the right syntax, but out of range values parse as infinity
+        let value: f64 = s.parse().expect("invalid float literal");
+        if value.is_infinite() {
+            self.report_literal_error(LexerErrorKind::FloatOutOfRange);
+        }
+
+        TokenKind::Float(F64Bits::new(value))
+    }
+
+    fn lex_string(&mut self) -> LexerResult<TokenKind> {
+        let mut s = String::new();
+
+        loop {
+            match self.chars.next() {
+                Some('"') => break,
+                Some('\\') => {
+                    if let Some(ch) = self.lex_escape() {
+                        s.push(ch);
+                    }
+                }
+                Some(ch) => s.push(ch),
+                None => return Err(LexerErrorKind::UnterminatedString),
+            }
+        }
+
+        Ok(TokenKind::String(self.interner.intern(&s)))
+    }
+
+    fn lex_char(&mut self) -> LexerResult<TokenKind> {
+        let ch = match self.chars.next() {
+            Some('\'') => {
+                self.report_literal_error(LexerErrorKind::EmptyChar);
+                return Ok(TokenKind::Char(char::REPLACEMENT_CHARACTER));
+            }
+            Some('\\') => self.lex_escape(),
+            Some('\n') | None => return Err(LexerErrorKind::UnterminatedChar),
+            Some(ch) => Some(ch),
+        };
+
+        if !self.chars.eat('\'') {
+            // skip to the end of the literal, if it looks like there is one
+            while !matches!(self.chars.peek(), Some('\'' | '\n') | None) {
+                self.chars.next();
+            }
+
+            if !self.chars.eat('\'') {
+                return Err(LexerErrorKind::UnterminatedChar);
+            }
+
+            self.report_literal_error(LexerErrorKind::CharTooLong);
+        }
+
+        // an invalid escape or length has already been reported
+        Ok(TokenKind::Char(ch.unwrap_or(char::REPLACEMENT_CHARACTER)))
+    }
+
+    /// Lex an escape sequence, after the backslash.
+    ///
+    /// Invalid escapes are reported here, so that the rest of
+    /// the literal can still be lexed.
+    fn lex_escape(&mut self) -> Option<char> {
+        let escape_start = self.byte_pos() - 1;
+
+        let result = match self.chars.next() {
+            Some('n') => Ok('\n'),
+            Some('t') => Ok('\t'),
+            Some('r') => Ok('\r'),
+            Some('0') => Ok('\0'),
+            Some('\\') => Ok('\\'),
+            Some('"') => Ok('"'),
+            Some('\'') => Ok('\''),
+            Some('u') => self.lex_unicode_escape(),
+            Some(ch) => Err(LexerErrorKind::InvalidEscape(ch)),
+
+            // the literal is unterminated, which is reported by the caller
+            None => return None,
+        };
+
+        result
+            .map_err(|kind| {
+                let span = Span::new(escape_start, self.byte_pos());
+                self.errors.push(LexerError { kind, span });
+            })
+            .ok()
+    }
+
+    /// Lex a unicode escape of the form `{XXXX}`, after the `\u`.
+    fn lex_unicode_escape(&mut self) -> LexerResult<char> {
+        if !self.chars.eat('{') {
+            return Err(LexerErrorKind::InvalidUnicodeEscape);
+        }
+
+        let mut n: u32 = 0;
+        let mut digits = 0;
+
+        while let Some(ch) = self.chars.peek() {
+            let Some(digit) = ch.to_digit(16) else {
+                break;
+            };
+            self.chars.next();
+
+            n = n.saturating_mul(16).saturating_add(digit);
+            digits += 1;
+        }
+
+        if !self.chars.eat('}') || !(1..=6).contains(&digits) {
+            return Err(LexerErrorKind::InvalidUnicodeEscape);
+        }
+
+        char::from_u32(n).ok_or(LexerErrorKind::InvalidUnicodeEscape)
+    }
+
     fn lex_alpha(&mut self) -> TokenKind {
         while matches!(self.chars.peek(), Some(ch) if is_ident(ch)) {
             self.chars.next();
@@ -184,13 +379,22 @@ impl<'sess> Lexer<'sess> {
         match s {
             "submodule" => TokenKind::Keyword(Keyword::Submodule),
             "func" => TokenKind::Keyword(Keyword::Func),
+            "extern" => TokenKind::Keyword(Keyword::Extern),
+            "export" => TokenKind::Keyword(Keyword::Export),
+            "const" => TokenKind::Keyword(Keyword::Const),
+            "static" => TokenKind::Keyword(Keyword::Static),
             "return" => TokenKind::Keyword(Keyword::Return),
             "let" => TokenKind::Keyword(Keyword::Let),
             "if" => TokenKind::Keyword(Keyword::If),
             "then" => TokenKind::Keyword(Keyword::Then),
             "else" => TokenKind::Keyword(Keyword::Else),
+            "as" => TokenKind::Keyword(Keyword::As),
             "i64" => TokenKind::Keyword(Keyword::I64),
+            "f32" => TokenKind::Keyword(Keyword::F32),
+            "f64" => TokenKind::Keyword(Keyword::F64),
             "bool" => TokenKind::Keyword(Keyword::Bool),
+            "char" => TokenKind::Keyword(Keyword::Char),
+            "str" => TokenKind::Keyword(Keyword::Str),
             "void" => TokenKind::Keyword(Keyword::Void),
             "true" => TokenKind::Bool(true),
             "false" => TokenKind::Bool(false),
@@ -205,12 +409,73 @@ impl<'sess> Lexer<'sess> {
         self.all.len() - self.chars.as_str().len()
     }
 
+    /// Report an error for the current token, which is skipped.
     fn report_error(&mut self, kind: LexerErrorKind) {
         let span = Span::new(self.token_start, self.byte_pos());
         self.errors.push(LexerError { kind, span });
+
+        self.push_trivia(TriviaKind::Invalid);
+    }
+
+    /// Report an error for the current token, which is a literal that is still
+    /// lexed, so that the parser doesn't report a missing expression as well.
+    fn report_literal_error(&mut self, kind: LexerErrorKind) {
+        let span = Span::new(self.token_start, self.byte_pos());
+        self.errors.push(LexerError { kind, span });
+    }
+
+    /// Make the current token trivia, if trivia is being kept.
+    fn push_trivia(&mut self, kind: TriviaKind) {
+        if self.keep_trivia {
+            let span = Span::new(self.token_start, self.byte_pos());
+            self.trivia.push(Trivia { kind, span });
+        }
     }
 }
 
+/// Get the length of the float literal at the start of `s`, if there is one.
+///
+/// Float literals are decimal, and have a fractional part, an exponent, or both
+/// (`1.5`, `1e10`, `2.5E-3`). Integers aren't matched.
+fn float_len(s: &str) -> Option<usize> {
+    let bytes = s.as_bytes();
+
+    let digits = |start: usize| {
+        let mut end = start;
+        if !bytes.get(end).is_some_and(u8::is_ascii_digit) {
+            return None;
+        }
+        while bytes
+            .get(end)
+            .is_some_and(|b| b.is_ascii_digit() || *b == b'_')
+        {
+            end += 1;
+        }
+        Some(end)
+    };
+
+    let mut len = digits(0)?;
+    let mut is_float = false;
+
+    // a digit is required after the dot, so that `0.len` isn't a float
+    if bytes.get(len) == Some(&b'.') {
+        if let Some(end) = digits(len + 1) {
+            len = end;
+            is_float = true;
+        }
+    }
+
+    if matches!(bytes.get(len), Some(b'e' | b'E')) {
+        let sign_len = matches!(bytes.get(len + 1), Some(b'+' | b'-')) as usize;
+        if let Some(end) = digits(len + 1 + sign_len) {
+            len = end;
+            is_float = true;
+        }
+    }
+
+    is_float.then_some(len)
+}
+
 fn is_ident_start(ch: char) -> bool {
     ch.is_ascii_alphabetic() || ch == '_'
 }
@@ -220,13 +485,32 @@ fn is_ident(ch: char) -> bool {
 }
 
 #[derive(Debug, Clone)]
-pub struct TokenIter {
+pub struct TokenIter<'src> {
+    source: &'src str,
     tokens: std::vec::IntoIter<Token>,
+
+    /// The trivia between the tokens, if the lexer kept it.
+    trivia: std::vec::IntoIter<Trivia>,
+
     prev_span: Span,
     eof_span: Span,
 }
 
-impl TokenIter {
+impl<'src> TokenIter<'src> {
+    /// The source code that the tokens were lexed from.
+    pub fn source(&self) -> &'src str {
+        self.source
+    }
+
+    /// Take the next trivia, if it is before `pos`.
+    pub fn next_trivia_before(&mut self, pos: usize) -> Option<Trivia> {
+        if self.trivia.as_slice().first()?.span.start < pos {
+            self.trivia.next()
+        } else {
+            None
+        }
+    }
+
     pub fn prev_span(&self) -> Span {
         self.prev_span
     }
@@ -240,7 +524,7 @@ impl TokenIter {
     }
 }
 
-impl Iterator for TokenIter {
+impl Iterator for TokenIter<'_> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Self::Item> {
@@ -250,7 +534,7 @@ impl Iterator for TokenIter {
     }
 }
 
-impl Peek for TokenIter {
+impl Peek for TokenIter<'_> {
     fn peek(&self) -> Option<Self::Item> {
         self.tokens.as_slice().first().copied()
     }
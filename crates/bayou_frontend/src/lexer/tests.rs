@@ -2,6 +2,7 @@ use bayou_interner::Interner;
 use bayou_utils::assert_yaml_snapshot_with_source;
 
 use super::{Lexer, LexerError};
+use crate::parser::Parser;
 use crate::token::Token;
 
 fn lex(source: &str) -> (Vec<Token>, Vec<LexerError>) {
@@ -44,3 +45,60 @@ fn no_newlines() {
 fn spaces() {
     assert_lex!("  func  main  (  )  ->  i64  {  return  0  ;  }");
 }
+
+#[test]
+fn string() {
+    assert_lex!(r#""hello" "\n\t\\\"\u{1F600}""#);
+}
+
+#[test]
+fn string_invalid_escape() {
+    assert_lex!(r#""\q\u{110000}\u{}" "ok""#);
+}
+
+#[test]
+fn string_unterminated() {
+    assert_lex!(r#"func "unterminated"#);
+}
+
+#[test]
+fn char() {
+    assert_lex!(r"'a' '\n' '\'' '\u{e9}'");
+}
+
+#[test]
+fn char_invalid() {
+    assert_lex!(r"'' 'ab' 'a");
+}
+
+#[test]
+fn float() {
+    assert_lex!("1.5 1e10 2.5E-3 1_000.0 1. 0.len 3e");
+}
+
+#[test]
+fn float_out_of_range() {
+    assert_lex!("1e999 1e-999");
+}
+
+#[test]
+fn invalid_literals_are_still_expressions() {
+    let interner = Interner::new();
+
+    let source = "func main() -> i64 { f('', 'ab', 1e999) }";
+    let (tokens, lexer_errors) = Lexer::new(source, &interner).lex();
+    assert_eq!(lexer_errors.len(), 3);
+
+    let (_, parse_errors) = Parser::new(tokens).parse();
+    assert!(parse_errors.is_empty(), "{parse_errors:?}");
+}
+
+#[test]
+fn comparison_operators() {
+    assert_lex!("== != < > <= >= = !");
+}
+
+#[test]
+fn double_colon() {
+    assert_lex!("math::square : ::");
+}
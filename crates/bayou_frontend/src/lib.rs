@@ -4,6 +4,9 @@ extern crate macro_rules_attribute;
 mod lexer;
 mod parser;
 
+pub mod cst;
+pub mod format;
+
 mod gather_modules;
 mod module_tree;
 
@@ -12,26 +15,33 @@ mod lower;
 pub mod ast;
 pub mod token;
 
+use bayou_ir::interface::PackageInterface;
 use bayou_ir::symbols::Symbols;
-pub use gather_modules::GatherModulesError;
-use gather_modules::{ModuleGatherer, ParsedModule};
+use gather_modules::ModuleGatherer;
+pub use gather_modules::{CachedModule, GatherModulesError, ParseCache, ParsedModule};
 pub use lexer::{LexerError, LexerErrorKind, LexerResult, TokenIter};
+use lower::Imports;
 pub use lower::NameError;
 pub use parser::ParseError;
 
-use ast::Module;
+use ast::{Module, ReplInput};
 use bayou_interner::Interner;
+use bayou_session::sourcemap::SourceId;
 use bayou_session::{PackageSession, Session};
+use cst::SyntaxNode;
 use lexer::Lexer;
-use module_tree::ModuleTree;
+pub use module_tree::{ModuleId, ModuleTree};
 use parser::Parser;
 
 derive_alias! {
-    #[derive(Node!)] = #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize)];
+    #[derive(Node!)] = #[derive(Debug, Clone, PartialEq, Eq, serde::Serialize, serde::Deserialize)];
     #[derive(NodeCopy!)] = #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)];
 }
 
-pub fn lex(source: &str, interner: &Interner) -> (TokenIter, Vec<LexerError>) {
+pub fn lex<'src>(
+    source: &'src str,
+    interner: &'src Interner,
+) -> (TokenIter<'src>, Vec<LexerError>) {
     Lexer::new(source, interner).lex()
 }
 
@@ -39,17 +49,62 @@ pub fn parse(tokens: TokenIter) -> (Module, Vec<ParseError>) {
     Parser::new(tokens).parse()
 }
 
+/// Lex and parse a module into a lossless syntax tree, which keeps the
+/// whitespace and comments, for tools that need to give back the source code.
+pub fn parse_lossless(
+    source: &str,
+    interner: &Interner,
+) -> (SyntaxNode, Vec<LexerError>, Vec<ParseError>) {
+    let (tokens, lexer_errors) = Lexer::new(source, interner).with_trivia().lex();
+    let (cst, parse_errors) = Parser::new(tokens).parse_cst();
+
+    (cst, lexer_errors, parse_errors)
+}
+
+/// Lex and parse a line of input to the REPL.
+pub fn parse_repl_input(
+    source: &str,
+    source_id: SourceId,
+    interner: &Interner,
+) -> (ReplInput, Vec<GatherModulesError>) {
+    let (tokens, lexer_errors) = lex(source, interner);
+    let (input, parse_errors) = Parser::new(tokens).parse_repl_input();
+
+    let errors = lexer_errors
+        .into_iter()
+        .map(|err| GatherModulesError::LexerError(err, source_id))
+        .chain(
+            parse_errors
+                .into_iter()
+                .map(|err| GatherModulesError::ParseError(err, source_id)),
+        )
+        .collect();
+
+    (input, errors)
+}
+
+/// Load and parse every module of a package, reusing the modules in
+/// `cache` that haven't changed since they were cached.
 pub fn load_and_parse_modules<S: Session>(
     session: &mut S,
     package_session: &mut PackageSession<S>,
+    cache: Option<&dyn ParseCache>,
 ) -> (ModuleTree, Vec<ParsedModule>, Vec<GatherModulesError>) {
-    ModuleGatherer::new(session, package_session).run()
+    let gatherer = ModuleGatherer::new(session, package_session);
+
+    match cache {
+        Some(cache) => gatherer.with_cache(cache).run(),
+        None => gatherer.run(),
+    }
 }
 
+/// Lower parsed modules into IR, resolving names, including the
+/// names of functions from the interfaces of dependencies.
 pub fn lower(
     modules: &[ParsedModule],
     module_tree: &mut ModuleTree,
     interner: &Interner,
+    dependencies: &[PackageInterface],
 ) -> (
     bayou_ir::ir::PackageIr,
     bayou_ir::symbols::Symbols,
@@ -59,6 +114,22 @@ pub fn lower(
 
     let mut symbols = Symbols::default();
     let mut package_ir = bayou_ir::ir::PackageIr::default();
+    let mut imports = Imports::new(dependencies);
+
+    // declare everything first, so that items can refer
+    // to items in modules that are lowered after them
+    for module in modules {
+        lower::ModuleLowerer::new(
+            module,
+            module_tree,
+            &mut symbols,
+            &mut package_ir,
+            &mut errors,
+            interner,
+            &mut imports,
+        )
+        .declare_globals();
+    }
 
     for module in modules {
         lower::ModuleLowerer::new(
@@ -68,6 +139,7 @@ pub fn lower(
             &mut package_ir,
             &mut errors,
             interner,
+            &mut imports,
         )
         .run();
     }
@@ -1,8 +1,13 @@
+use std::collections::HashMap;
+
 use bayou_interner::{Interner, Istr};
+use bayou_ir::interface::PackageInterface;
 use bayou_ir::symbols::*;
 use bayou_ir::{ir, Ident, IdentWithSource, Type};
 use bayou_session::diagnostics::prelude::*;
+use bayou_session::module_loader::ModulePath;
 use bayou_session::sourcemap::SourceSpan;
+use bayou_session::sym;
 
 use crate::ast;
 use crate::gather_modules::ParsedModule;
@@ -11,6 +16,14 @@ use crate::module_tree::{get_global_ident, GlobalId, ModuleTree};
 #[derive(Debug, Clone, Copy, serde::Serialize)]
 pub enum NameError {
     LocalUndefined(IdentWithSource),
+    FuncUndefined(IdentWithSource),
+    UnknownField(IdentWithSource),
+    PackageUndefined(IdentWithSource),
+
+    FuncNotExported {
+        package: IdentWithSource,
+        func: IdentWithSource,
+    },
 
     DuplicateGlobal {
         first: IdentWithSource,
@@ -48,6 +61,72 @@ impl IntoDiagnostic<Interner> for NameError {
                         ident.span.span,
                     ))
             }
+
+            Self::UnknownField(ident) => {
+                let ident_str = &interner[ident.istr];
+                Diagnostic::error()
+                    .with_message(format!("unknown field `{ident_str}`"))
+                    .with_snippet(Snippet::primary(
+                        "unknown field here",
+                        ident.span.source_id,
+                        ident.span.span,
+                    ))
+            }
+
+            Self::FuncUndefined(ident) => {
+                let ident_str = &interner[ident.istr];
+                Diagnostic::error()
+                    .with_message(format!("undefined function `{ident_str}`"))
+                    .with_snippet(Snippet::primary(
+                        "undefined function here",
+                        ident.span.source_id,
+                        ident.span.span,
+                    ))
+            }
+
+            Self::PackageUndefined(ident) => {
+                let ident_str = &interner[ident.istr];
+                Diagnostic::error()
+                    .with_message(format!("undefined package `{ident_str}`"))
+                    .with_snippet(Snippet::primary(
+                        "not a dependency of this package",
+                        ident.span.source_id,
+                        ident.span.span,
+                    ))
+            }
+
+            Self::FuncNotExported { package, func } => {
+                let package_str = &interner[package.istr];
+                let func_str = &interner[func.istr];
+                Diagnostic::error()
+                    .with_message(format!(
+                        "package `{package_str}` has no exported function `{func_str}`"
+                    ))
+                    .with_snippet(Snippet::primary(
+                        "undefined function here",
+                        func.span.source_id,
+                        func.span.span,
+                    ))
+                    .with_note("only functions declared with `export func` can be used")
+            }
+        }
+    }
+}
+
+/// The functions of dependencies that a package can use.
+pub struct Imports<'a> {
+    dependencies: &'a [PackageInterface],
+
+    /// Functions that have already been used, by the index of the
+    /// dependency and the index of the function in its interface.
+    funcs: HashMap<(usize, usize), FuncId>,
+}
+
+impl<'a> Imports<'a> {
+    pub fn new(dependencies: &'a [PackageInterface]) -> Self {
+        Self {
+            dependencies,
+            funcs: HashMap::new(),
         }
     }
 }
@@ -67,6 +146,8 @@ pub struct ModuleLowerer<'a, 'b> {
 
     interner: &'a Interner,
 
+    imports: &'b mut Imports<'a>,
+
     local_stack: Vec<LocalEntry>,
 }
 
@@ -80,6 +161,8 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
         errors: &'b mut Vec<NameError>,
 
         interner: &'a Interner,
+
+        imports: &'b mut Imports<'a>,
     ) -> Self {
         Self {
             module,
@@ -91,12 +174,15 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
 
             interner,
 
+            imports,
+
             local_stack: vec![],
         }
     }
 
+    /// Lower the items of the module, after the globals
+    /// of every module have been declared.
     pub fn run(mut self) {
-        self.declare_globals();
         self.lower_module();
 
         if self.module.module_id == self.module_tree.root_id() {
@@ -105,40 +191,117 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
     }
 
     fn get_main_func(&mut self) {
-        let main_istr = self.interner.intern("main");
-
         self.package_ir.main_func = self
             .module_tree
             .entry(self.module.module_id)
             .globals
-            .get(&main_istr)
+            .get(&sym::MAIN)
             .and_then(|id| id.as_func());
     }
 
-    fn declare_globals(&mut self) {
+    pub fn declare_globals(&mut self) {
         for item in &self.module.ast.items {
             match item {
-                ast::Item::FuncDecl(func_decl) => self.declare_global_func(FunctionSymbol {
-                    ident: func_decl.ident.with_source(self.module.source_id),
+                ast::Item::FuncDecl(func_decl) => {
+                    let symbol = self.func_symbol(
+                        func_decl.ident,
+                        &func_decl.params,
+                        &func_decl.ret_ty,
+                        func_decl.ret_ty_span,
+                        false,
+                        func_decl.is_exported,
+                    );
+                    self.declare_global_func(symbol);
+                }
+
+                ast::Item::ExternFuncDecl(func_decl) => {
+                    let symbol = self.func_symbol(
+                        func_decl.ident,
+                        &func_decl.params,
+                        &func_decl.ret_ty,
+                        func_decl.ret_ty_span,
+                        true,
+                        false,
+                    );
+                    self.declare_global_func(symbol);
+                }
+
+                ast::Item::Const(decl) => {
+                    let id = self.symbols.consts.insert(self.global_var_symbol(decl));
+                    self.declare_global(self.symbols.consts[id].ident, GlobalId::Const(id));
+                }
 
-                    ret_ty: func_decl.ret_ty,
-                    ret_ty_span: SourceSpan::new(func_decl.ret_ty_span, self.module.source_id),
-                }),
+                ast::Item::Static(decl) => {
+                    let id = self.symbols.statics.insert(self.global_var_symbol(decl));
+                    self.declare_global(self.symbols.statics[id].ident, GlobalId::Static(id));
+                }
 
                 ast::Item::Submodule(_) | ast::Item::ParseError => {}
             }
         }
     }
 
+    fn global_var_symbol(&self, decl: &ast::GlobalVarDecl) -> GlobalVarSymbol {
+        GlobalVarSymbol {
+            ident: decl.ident.with_source(self.module.source_id),
+            module: self.module_tree.entry(self.module.module_id).path.clone(),
+
+            ty: decl.ty.clone(),
+            ty_span: SourceSpan::new(decl.ty_span, self.module.source_id),
+
+            value: None,
+        }
+    }
+
+    fn func_symbol(
+        &mut self,
+        ident: Ident,
+        params: &[ast::Param],
+        ret_ty: &Type,
+        ret_ty_span: Span,
+        is_extern: bool,
+        is_exported: bool,
+    ) -> FunctionSymbol {
+        let params = params
+            .iter()
+            .map(|param| {
+                self.symbols.locals.insert(LocalSymbol {
+                    ident: param.ident.with_source(self.module.source_id),
+
+                    ty: param.ty.clone(),
+                    ty_span: SourceSpan::new(param.ty_span, self.module.source_id),
+
+                    address_taken: false,
+                })
+            })
+            .collect();
+
+        FunctionSymbol {
+            ident: ident.with_source(self.module.source_id),
+            module: self.module_tree.entry(self.module.module_id).path.clone(),
+
+            params,
+
+            ret_ty: ret_ty.clone(),
+            ret_ty_span: SourceSpan::new(ret_ty_span, self.module.source_id),
+
+            is_extern,
+            is_exported,
+        }
+    }
+
     fn declare_global_func(&mut self, symbol: FunctionSymbol) {
         let ident = symbol.ident;
 
         let func_id = self.symbols.funcs.insert(symbol);
+        self.declare_global(ident, GlobalId::Func(func_id));
+    }
 
+    fn declare_global(&mut self, ident: IdentWithSource, global: GlobalId) {
         if let Err(first_global_id) = self
             .module_tree
             .entry_mut(self.module.module_id)
-            .insert_global(ident.istr, GlobalId::Func(func_id))
+            .insert_global(ident.istr, global)
         {
             self.errors.push(NameError::DuplicateGlobal {
                 // global must have an identifier, otherwise there would be no error
@@ -157,34 +320,69 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
                         self.package_ir.items.push(ir::Item::FuncDecl(func_decl));
                     }
                 }
-                ast::Item::Submodule(_) | ast::Item::ParseError => {}
+                ast::Item::Const(decl) => {
+                    let id = self.lookup_global_item(decl.ident, GlobalId::as_const);
+                    if let Some((id, expr)) = id.zip(self.lower_global_var_decl(decl)) {
+                        self.package_ir
+                            .items
+                            .push(ir::Item::Const(ir::GlobalVarDecl { id, expr }));
+                    }
+                }
+                ast::Item::Static(decl) => {
+                    let id = self.lookup_global_item(decl.ident, GlobalId::as_static);
+                    if let Some((id, expr)) = id.zip(self.lower_global_var_decl(decl)) {
+                        self.package_ir
+                            .items
+                            .push(ir::Item::Static(ir::GlobalVarDecl { id, expr }));
+                    }
+                }
+                ast::Item::ExternFuncDecl(_) | ast::Item::Submodule(_) | ast::Item::ParseError => {}
             }
         }
     }
 
-    fn lower_func_decl(&mut self, func_decl: &ast::FuncDecl) -> Option<ir::FuncDecl> {
-        self.clear_locals();
+    /// Get the ID of an item declared in this module.
+    ///
+    /// Returns `None` if the name was taken by a different kind of
+    /// item, which has already been reported as a duplicate.
+    fn lookup_global_item<T>(&self, ident: Ident, as_kind: fn(GlobalId) -> Option<T>) -> Option<T> {
+        as_kind(self.module_tree.entry(self.module.module_id).globals[&ident.istr])
+    }
 
-        // no parameters for now
+    fn lower_global_var_decl(&mut self, decl: &ast::GlobalVarDecl) -> Option<ir::Expr> {
+        // initializers can't refer to locals
+        self.clear_locals();
+        self.lower_expr(&decl.expr)
+    }
 
-        let block = self.lower_block_expr(&func_decl.block)?;
+    fn lower_func_decl(&mut self, func_decl: &ast::FuncDecl) -> Option<ir::FuncDecl> {
+        self.clear_locals();
 
         let id = self.module_tree.entry(self.module.module_id).globals[&func_decl.ident.istr]
             .as_func()
             .unwrap();
 
+        for &param in &self.symbols.funcs[id].params {
+            self.local_stack.push(LocalEntry {
+                ident_str: self.symbols.locals[param].ident.istr,
+                id: param,
+            });
+        }
+
+        let block = self.lower_block_expr(&func_decl.block)?;
+
         Some(ir::FuncDecl { id, block })
     }
 
     fn lower_expr(&mut self, expr: &ast::Expr) -> Option<ir::Expr> {
         let expr_kind = match &expr.kind {
             ast::ExprKind::Integer(n) => ir::ExprKind::Constant(ir::Constant::I64(*n)),
+            ast::ExprKind::Float(f) => ir::ExprKind::Constant(ir::Constant::F64(*f)),
             ast::ExprKind::Bool(b) => ir::ExprKind::Constant(ir::Constant::Bool(*b)),
+            ast::ExprKind::Char(ch) => ir::ExprKind::Constant(ir::Constant::Char(*ch)),
+            ast::ExprKind::String(istr) => ir::ExprKind::Constant(ir::Constant::Str(*istr)),
 
-            ast::ExprKind::Var(ident) => {
-                let id = self.lookup_local(*ident)?;
-                ir::ExprKind::Var(id)
-            }
+            ast::ExprKind::Var(ident) => self.lookup_var(*ident)?,
 
             ast::ExprKind::UnOp { op, expr } => {
                 let expr = self.lower_expr(expr)?;
@@ -206,6 +404,78 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
                 }
             }
 
+            ast::ExprKind::AddressOf(expr) => {
+                let expr = self.lower_expr(expr)?;
+
+                if let ir::ExprKind::Var(local) = expr.kind {
+                    self.symbols.locals[local].address_taken = true;
+                }
+
+                ir::ExprKind::AddressOf(Box::new(expr))
+            }
+
+            ast::ExprKind::Deref(expr) => {
+                let expr = self.lower_expr(expr)?;
+                ir::ExprKind::Deref(Box::new(expr))
+            }
+
+            ast::ExprKind::Cast { expr, ty } => {
+                let expr = self.lower_expr(expr)?;
+                ir::ExprKind::Cast {
+                    expr: Box::new(expr),
+                    ty: ty.clone(),
+                }
+            }
+
+            ast::ExprKind::Call {
+                package,
+                func,
+                args,
+            } => {
+                let func = match package {
+                    Some(package) => self.lookup_package_func(*package, *func),
+                    None => self.lookup_func(*func),
+                };
+
+                // lower all arguments before using `?`
+                let args: Vec<_> = args.iter().map(|arg| self.lower_expr(arg)).collect();
+
+                ir::ExprKind::Call {
+                    func: func?,
+                    args: args.into_iter().collect::<Option<_>>()?,
+                }
+            }
+
+            ast::ExprKind::Array(elements) => {
+                // lower all elements before using `?`
+                let elements: Vec<_> = elements.iter().map(|e| self.lower_expr(e)).collect();
+                ir::ExprKind::Array(elements.into_iter().collect::<Option<_>>()?)
+            }
+
+            ast::ExprKind::Index { base, index } => {
+                let base = self.lower_expr(base);
+                let index = self.lower_expr(index);
+
+                ir::ExprKind::Index {
+                    base: Box::new(base?),
+                    index: Box::new(index?),
+                }
+            }
+
+            ast::ExprKind::Field { expr, field } => {
+                let expr = self.lower_expr(expr);
+
+                // arrays and slices are the only types with fields for now
+                if &self.interner[field.istr] != "len" {
+                    self.errors.push(NameError::UnknownField(
+                        field.with_source(self.module.source_id),
+                    ));
+                    return None;
+                }
+
+                ir::ExprKind::Len(Box::new(expr?))
+            }
+
             ast::ExprKind::Block(block) => {
                 let lowered_block = self.lower_block_expr(block)?;
                 ir::ExprKind::Block(Box::new(lowered_block))
@@ -247,7 +517,7 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
                 match stmt {
                     ast::Stmt::Assign { ident, ty, expr } => {
                         let expr = lowerer.lower_expr(expr);
-                        let local_id = lowerer.declare_local(*ident, *ty);
+                        let local_id = lowerer.declare_local(*ident, ty.clone());
 
                         if let Some(expr) = expr {
                             lowered_stmts.push(ir::Stmt::Assign {
@@ -257,6 +527,15 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
                         }
                     }
 
+                    ast::Stmt::Store { place, expr } => {
+                        let place = lowerer.lower_expr(place);
+                        let expr = lowerer.lower_expr(expr);
+
+                        if let (Some(place), Some(expr)) = (place, expr) {
+                            lowered_stmts.push(ir::Stmt::Store { place, expr });
+                        }
+                    }
+
                     ast::Stmt::Drop {
                         expr,
                         had_semicolon: _,
@@ -295,6 +574,8 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
             ident,
             ty,
             ty_span: ident.span,
+
+            address_taken: false,
         });
 
         self.local_stack.push(LocalEntry {
@@ -305,15 +586,47 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
         id
     }
 
-    fn lookup_local(&mut self, ident: Ident) -> Option<LocalId> {
-        let id = self
+    /// Look up a local, or failing that a `const` or `static`.
+    fn lookup_var(&mut self, ident: Ident) -> Option<ir::ExprKind> {
+        let local = self
             .local_stack
             .iter()
             .rev()
             .find_map(|entry| (entry.ident_str == ident.istr).then_some(entry.id));
 
+        if let Some(id) = local {
+            return Some(ir::ExprKind::Var(id));
+        }
+
+        let global = self
+            .module_tree
+            .entry(self.module.module_id)
+            .globals
+            .get(&ident.istr);
+
+        match global {
+            Some(GlobalId::Const(id)) => Some(ir::ExprKind::Const(*id)),
+            Some(GlobalId::Static(id)) => Some(ir::ExprKind::Static(*id)),
+
+            _ => {
+                self.errors.push(NameError::LocalUndefined(
+                    ident.with_source(self.module.source_id),
+                ));
+                None
+            }
+        }
+    }
+
+    fn lookup_func(&mut self, ident: Ident) -> Option<FuncId> {
+        let id = self
+            .module_tree
+            .entry(self.module.module_id)
+            .globals
+            .get(&ident.istr)
+            .and_then(|id| id.as_func());
+
         if id.is_none() {
-            self.errors.push(NameError::LocalUndefined(
+            self.errors.push(NameError::FuncUndefined(
                 ident.with_source(self.module.source_id),
             ));
         }
@@ -321,6 +634,80 @@ impl<'a, 'b> ModuleLowerer<'a, 'b> {
         id
     }
 
+    /// Look up an exported function of a dependency, declaring it
+    /// as an external function the first time it is used.
+    fn lookup_package_func(&mut self, package: Ident, func: Ident) -> Option<FuncId> {
+        let source_id = self.module.source_id;
+        let package_str = &self.interner[package.istr];
+        let func_str = &self.interner[func.istr];
+
+        let Some(package_index) = self
+            .imports
+            .dependencies
+            .iter()
+            .position(|dependency| dependency.name == package_str)
+        else {
+            self.errors
+                .push(NameError::PackageUndefined(package.with_source(source_id)));
+            return None;
+        };
+
+        let interface = &self.imports.dependencies[package_index];
+
+        let Some(func_index) = interface.funcs.iter().position(|f| f.name == func_str) else {
+            self.errors.push(NameError::FuncNotExported {
+                package: package.with_source(source_id),
+                func: func.with_source(source_id),
+            });
+            return None;
+        };
+
+        if let Some(&id) = self.imports.funcs.get(&(package_index, func_index)) {
+            return Some(id);
+        }
+
+        let func_interface = &interface.funcs[func_index];
+
+        // imported functions are declared by their symbol name, and have
+        // no source, so spans point to where they are used
+        let symbol = IdentWithSource {
+            istr: self.interner.intern(&func_interface.symbol),
+            span: SourceSpan::new(func.span, source_id),
+        };
+
+        let params = func_interface
+            .params
+            .iter()
+            .map(|ty| {
+                self.symbols.locals.insert(LocalSymbol {
+                    ident: symbol,
+                    ty: ty.clone(),
+                    ty_span: symbol.span,
+                    address_taken: false,
+                })
+            })
+            .collect();
+
+        let module: Vec<_> = func_interface
+            .module
+            .iter()
+            .map(|name| self.interner.intern(name))
+            .collect();
+
+        let id = self.symbols.funcs.insert(FunctionSymbol {
+            ident: symbol,
+            module: ModulePath::new(module),
+            params,
+            ret_ty: func_interface.ret_ty.clone(),
+            ret_ty_span: symbol.span,
+            is_extern: true,
+            is_exported: false,
+        });
+
+        self.imports.funcs.insert((package_index, func_index), id);
+        Some(id)
+    }
+
     fn clear_locals(&mut self) {
         self.local_stack.clear();
     }
@@ -2,7 +2,7 @@ use std::{collections::HashMap, ops::Deref};
 
 use bayou_interner::Istr;
 use bayou_ir::{
-    symbols::{FuncId, Symbols},
+    symbols::{ConstId, FuncId, StaticId, Symbols},
     IdentWithSource,
 };
 use bayou_session::module_loader::ModulePath;
@@ -50,7 +50,7 @@ impl ModuleTree {
         &self.entries[id]
     }
 
-    pub fn entry_mut(&mut self, id: ModuleId) -> ModuleEntryMut {
+    pub fn entry_mut(&mut self, id: ModuleId) -> ModuleEntryMut<'_> {
         ModuleEntryMut {
             inner: &mut self.entries[id],
         }
@@ -111,13 +111,29 @@ impl ModuleEntryMut<'_> {
 pub enum GlobalId {
     Module(ModuleId),
     Func(FuncId),
+    Const(ConstId),
+    Static(StaticId),
 }
 
 impl GlobalId {
     pub fn as_func(self) -> Option<FuncId> {
         match self {
-            Self::Module(_) => None,
             Self::Func(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn as_const(self) -> Option<ConstId> {
+        match self {
+            Self::Const(id) => Some(id),
+            _ => None,
+        }
+    }
+
+    pub fn as_static(self) -> Option<StaticId> {
+        match self {
+            Self::Static(id) => Some(id),
+            _ => None,
         }
     }
 }
@@ -130,5 +146,7 @@ pub fn get_global_ident(
     match global {
         GlobalId::Module(id) => modules.entry(id).ident,
         GlobalId::Func(id) => Some(symbols.funcs[id].ident),
+        GlobalId::Const(id) => Some(symbols.consts[id].ident),
+        GlobalId::Static(id) => Some(symbols.statics[id].ident),
     }
 }
@@ -1,11 +1,12 @@
-use bayou_ir::Ident;
-use bayou_ir::{BinOp, UnOp};
+use bayou_ir::BinOp;
 use bayou_utils::peek::Peek;
 
-use super::{ParseResult, Parser, Transpose};
-use crate::ast::*;
+use super::{ParseResult, Parser};
+use crate::cst::NodeKind;
 use crate::token::*;
 
+// Some precedence levels are for operators that haven't been implemented yet.
+#[allow(dead_code)]
 #[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
 enum Prec {
     Lowest,
@@ -23,9 +24,11 @@ enum Prec {
     Term,
     Factor,
 
+    Cast,
     Unary,
-    // Field,
-    // Call,
+
+    /// Indexing and field access.
+    Postfix,
 }
 
 fn should_parse_binop_in_prec(binop: BinOp, in_prec: Prec) -> bool {
@@ -37,17 +40,14 @@ fn binop_prec(binop: BinOp) -> Prec {
     match binop {
         // BinOp::LogicalOr => Prec::LogicalOr,
         // BinOp::LogicalAnd => Prec::LogicalAnd,
-
-        // BinOp::Equal | BinOp::NotEqual => Prec::Equality,
-        // BinOp::Gt | BinOp::Lt | BinOp::GtEq | BinOp::LtEq => Prec::Comparison,
+        BinOp::Eq | BinOp::NotEq => Prec::Equality,
+        BinOp::Gt | BinOp::Lt | BinOp::GtEq | BinOp::LtEq => Prec::Comparison,
         BinOp::BitwiseAnd => Prec::BitwiseAnd,
         BinOp::BitwiseXor => Prec::BitwiseXor,
         BinOp::BitwiseOr => Prec::BitwiseOr,
 
         BinOp::Add | BinOp::Sub => Prec::Term,
         BinOp::Mul | BinOp::Div | BinOp::Mod => Prec::Factor,
-        // BinOp::Field => Prec::Field,
-        // BinOp::Call | BinOp::Index => Prec::Call,
     }
 }
 
@@ -56,165 +56,182 @@ fn binop_is_r_assoc(_binop: BinOp) -> bool {
     false
 }
 
-impl Parser {
+impl Parser<'_> {
     /// Always makes progress.
-    pub fn parse_expr(&mut self) -> ParseResult<Expr> {
+    ///
+    /// Returns the kind of the expression, which for an expression in
+    /// parentheses is the kind of the expression inside them.
+    pub fn parse_expr(&mut self) -> ParseResult<NodeKind> {
         self.parse_prec(Prec::Lowest)
     }
 
     /// Always makes progress.
-    fn parse_prec(&mut self, prec: Prec) -> ParseResult<Expr> {
-        let mut expr = self.parse_lhs()?;
-
-        while let Some(op) = self.peek_bin_op(prec) {
-            // `get_op` doesn't consume a token because
-            // some (as of yet unimplemented) operations need to consume
-            // the token themselves
-            self.tokens.next();
-
-            let rhs = self.parse_prec(binop_prec(op))?;
-
-            let span = expr.span.union(rhs.span);
-            expr = Expr::new(
-                ExprKind::BinOp {
-                    op,
-                    lhs: Box::new(expr),
-                    rhs: Box::new(rhs),
-                },
-                span,
-            );
+    fn parse_prec(&mut self, prec: Prec) -> ParseResult<NodeKind> {
+        let checkpoint = self.checkpoint();
+        let mut kind = self.parse_lhs()?;
+
+        loop {
+            if prec < Prec::Postfix && self.eat_kind(TokenKind::LBracket) {
+                self.parse_or_recover(Self::parse_expr, |parser| {
+                    parser.seek(&[TokenKind::RBracket]);
+                });
+
+                self.expect(TokenKind::RBracket)?;
+                kind = NodeKind::IndexExpr;
+            } else if prec < Prec::Postfix && self.eat_kind(TokenKind::Dot) {
+                self.parse_ident()?;
+                kind = NodeKind::FieldExpr;
+            } else if prec < Prec::Cast && self.eat_kind(TokenKind::Keyword(Keyword::As)) {
+                self.parse_type()?;
+                kind = NodeKind::CastExpr;
+            } else {
+                let Some(op) = self.peek_bin_op(prec) else {
+                    break;
+                };
+
+                // `get_op` doesn't consume a token because
+                // some (as of yet unimplemented) operations need to consume
+                // the token themselves
+                self.bump();
+
+                self.parse_prec(binop_prec(op))?;
+                kind = NodeKind::BinaryExpr;
+            }
+
+            self.builder.wrap(checkpoint, kind);
         }
 
-        Ok(expr)
+        Ok(kind)
     }
 
     /// Always makes progress.
-    fn parse_lhs(&mut self) -> ParseResult<Expr> {
-        match self.tokens.peek() {
-            Some(Token {
-                kind: TokenKind::Integer(n),
-                span,
-            }) => {
-                self.tokens.next();
-                Ok(Expr::new(ExprKind::Integer(n), span))
-            }
+    fn parse_lhs(&mut self) -> ParseResult<NodeKind> {
+        let checkpoint = self.checkpoint();
 
+        let kind = match self.tokens.peek() {
             Some(Token {
-                kind: TokenKind::Bool(b),
-                span,
+                kind:
+                    TokenKind::Integer(_)
+                    | TokenKind::Float(_)
+                    | TokenKind::Bool(_)
+                    | TokenKind::Char(_)
+                    | TokenKind::String(_)
+                    | TokenKind::Keyword(Keyword::Void),
+                ..
             }) => {
-                self.tokens.next();
-                Ok(Expr::new(ExprKind::Bool(b), span))
+                self.bump();
+                NodeKind::Literal
             }
 
             Some(Token {
-                kind: TokenKind::Identifier(istr),
-                span,
+                kind: TokenKind::Identifier(_),
+                ..
             }) => {
-                self.tokens.next();
-                // TODO: rely on expression span instead of storing in ident??
-                Ok(Expr::new(ExprKind::Var(Ident { istr, span }), span))
+                self.bump();
+
+                // only functions from other packages can be referred to with a path
+                let is_call = if self.eat_kind(TokenKind::DoubleColon) {
+                    self.parse_ident()?;
+                    self.expect(TokenKind::LParen)?;
+                    true
+                } else {
+                    self.eat_kind(TokenKind::LParen)
+                };
+
+                if is_call {
+                    self.parse_delimited(TokenKind::RParen)?;
+                    NodeKind::CallExpr
+                } else {
+                    NodeKind::VarExpr
+                }
             }
 
-            Some(t) if t.kind == TokenKind::Keyword(Keyword::Void) => {
-                self.tokens.next();
-                Ok(Expr::new(ExprKind::Void, t.span))
+            Some(t) if matches!(t.kind, TokenKind::Sub | TokenKind::BitwiseInvert) => {
+                self.bump();
+                self.parse_prec(Prec::Unary)?;
+                NodeKind::UnaryExpr
             }
 
-            Some(t) if t.kind == TokenKind::Sub => {
-                self.tokens.next();
-
-                let expr = self.parse_prec(Prec::Unary)?;
-                Ok(Expr::new(
-                    ExprKind::UnOp {
-                        op: UnOp::Negate,
-                        expr: Box::new(expr),
-                    },
-                    t.span,
-                ))
+            Some(t) if t.kind == TokenKind::BitwiseAnd => {
+                self.bump();
+                self.parse_prec(Prec::Unary)?;
+                NodeKind::AddressOfExpr
             }
 
-            Some(t) if t.kind == TokenKind::BitwiseInvert => {
-                self.tokens.next();
-
-                let expr = self.parse_prec(Prec::Unary)?;
-                Ok(Expr::new(
-                    ExprKind::UnOp {
-                        op: UnOp::BitwiseInvert,
-                        expr: Box::new(expr),
-                    },
-                    t.span,
-                ))
+            Some(t) if t.kind == TokenKind::Mul => {
+                self.bump();
+                self.parse_prec(Prec::Unary)?;
+                NodeKind::DerefExpr
             }
 
             Some(t) if t.kind == TokenKind::LParen => {
-                self.tokens.next();
+                self.bump();
 
-                let expr = self.parse_or_recover(Self::parse_expr, |parser, span| {
-                    parser.seek(&[TokenKind::RParen]);
-                    Expr::new(ExprKind::ParseError, span)
-                });
+                let inner = self
+                    .parse_or_recover(Self::parse_expr, |parser| {
+                        parser.seek(&[TokenKind::RParen]);
+                    })
+                    .unwrap_or(NodeKind::Error);
 
                 self.expect(TokenKind::RParen)?;
 
-                Ok(expr)
+                self.builder.wrap(checkpoint, NodeKind::ParenExpr);
+                return Ok(inner);
+            }
+
+            Some(t) if t.kind == TokenKind::LBracket => {
+                self.bump();
+                self.parse_delimited(TokenKind::RBracket)?;
+                NodeKind::ArrayExpr
             }
 
             Some(t) if t.kind == TokenKind::LBrace => {
-                let (block, span) = self.parse_spanned(Self::parse_block).transpose()?;
-                Ok(Expr::new(ExprKind::Block(Box::new(block)), span))
+                self.parse_block()?;
+                return Ok(NodeKind::Block);
             }
 
             Some(t) if t.kind == TokenKind::Keyword(Keyword::If) => {
-                let (kind, span) = self
-                    .parse_spanned(|parser| {
-                        parser.tokens.next();
-
-                        // TODO: recover to `then`
-                        let cond = parser.parse_expr()?;
-
-                        parser.expect(TokenKind::Keyword(Keyword::Then))?;
-                        let then = parser.parse_expr()?;
-
-                        let else_ = if parser.eat_kind(TokenKind::Keyword(Keyword::Else)) {
-                            Some(parser.parse_expr()?)
-                        } else {
-                            None
-                        };
-
-                        Ok(ExprKind::If {
-                            cond: Box::new(cond),
-                            then: Box::new(then),
-                            else_: else_.map(Box::new),
-                        })
-                    })
-                    .transpose()?;
+                self.bump();
+
+                // TODO: recover to `then`
+                self.parse_expr()?;
+
+                self.expect(TokenKind::Keyword(Keyword::Then))?;
+                self.parse_expr()?;
+
+                if self.eat_kind(TokenKind::Keyword(Keyword::Else)) {
+                    self.parse_expr()?;
+                }
 
-                Ok(Expr::new(kind, span))
+                NodeKind::IfExpr
             }
 
             other => {
-                self.tokens.next();
-                Err(self.error_expected("an expression", other))
+                self.bump();
+                return Err(self.error_expected("an expression", other));
+            }
+        };
+
+        self.builder.wrap(checkpoint, kind);
+        Ok(kind)
+    }
+
+    /// Parse a comma separated list of expressions, after the opening delimiter.
+    fn parse_delimited(&mut self, close: TokenKind) -> ParseResult<()> {
+        while self.tokens.peek().is_some_and(|t| t.kind != close) {
+            self.parse_expr()?;
+
+            if !self.eat_kind(TokenKind::Comma) {
+                break;
             }
         }
+
+        self.expect(close)?;
+        Ok(())
     }
 
     fn peek_bin_op(&self, prec: Prec) -> Option<BinOp> {
-        let op = match self.tokens.peek().map(|t| t.kind)? {
-            TokenKind::Add => BinOp::Add,
-            TokenKind::Sub => BinOp::Sub,
-            TokenKind::Mul => BinOp::Mul,
-            TokenKind::Div => BinOp::Div,
-            TokenKind::Mod => BinOp::Mod,
-
-            TokenKind::BitwiseAnd => BinOp::BitwiseAnd,
-            TokenKind::BitwiseOr => BinOp::BitwiseOr,
-            TokenKind::BitwiseXor => BinOp::BitwiseXor,
-
-            _ => return None,
-        };
-
+        let op = self.tokens.peek()?.kind.bin_op()?;
         should_parse_binop_in_prec(op, prec).then_some(op)
     }
 }
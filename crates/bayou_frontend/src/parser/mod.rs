@@ -3,14 +3,14 @@ mod tests;
 
 mod expr;
 
-use bayou_ir::{Ident, Type};
 use bayou_session::diagnostics::prelude::*;
 use bayou_session::diagnostics::span::Span;
 use bayou_utils::peek::Peek;
 
-use crate::ast::*;
+use crate::ast::{Module, ReplInput};
+use crate::cst::{Builder, Checkpoint, NodeKind, SyntaxNode, SyntaxTokenKind};
 use crate::lexer::TokenIter;
-use crate::token::{Keyword, Token, TokenKind};
+use crate::token::{Keyword, Token, TokenKind, TriviaKind};
 
 #[derive(serde::Serialize, Debug, Clone)]
 pub struct ParseError {
@@ -32,281 +32,467 @@ impl IntoDiagnostic<SourceId> for ParseError {
 
 pub type ParseResult<T> = Result<T, ParseError>;
 
+/// How a statement ended, which decides whether it is the final
+/// expression of a block.
+enum StmtEnd {
+    Complete,
+
+    /// An expression statement without a semicolon.
+    Expr {
+        semicolon_optional: bool,
+    },
+}
+
+const ITEM_KEYWORDS: [TokenKind; 6] = [
+    TokenKind::Keyword(Keyword::Submodule),
+    TokenKind::Keyword(Keyword::Func),
+    TokenKind::Keyword(Keyword::Extern),
+    TokenKind::Keyword(Keyword::Export),
+    TokenKind::Keyword(Keyword::Const),
+    TokenKind::Keyword(Keyword::Static),
+];
+
+/// Parses tokens into a [syntax tree](crate::cst), which the AST is derived from.
+///
+/// Nodes are only made once what is in them has been parsed, so anything
+/// that failed to parse is left in an `Error` node.
 #[derive(Debug, Clone)]
-pub struct Parser {
-    tokens: TokenIter,
+pub struct Parser<'src> {
+    tokens: TokenIter<'src>,
+    builder: Builder,
     errors: Vec<ParseError>,
 }
 
-impl Parser {
-    pub fn new(tokens: TokenIter) -> Self {
+impl<'src> Parser<'src> {
+    pub fn new(tokens: TokenIter<'src>) -> Self {
         Self {
             tokens,
+            builder: Builder::default(),
             errors: vec![],
         }
     }
 
-    pub fn parse(mut self) -> (Module, Vec<ParseError>) {
-        let module = self.parse_module();
-        (module, self.errors)
+    pub fn parse(self) -> (Module, Vec<ParseError>) {
+        let (cst, errors) = self.parse_cst();
+        (Module::from_cst(&cst), errors)
     }
 
-    fn parse_module(&mut self) -> Module {
-        let mut items = vec![];
+    /// Parse a module into a syntax tree, which is lossless
+    /// if the lexer kept trivia.
+    pub fn parse_cst(mut self) -> (SyntaxNode, Vec<ParseError>) {
+        while !self.tokens.at_end() {
+            self.parse_or_recover(Self::parse_item, |parser| {
+                parser.seek(&ITEM_KEYWORDS);
+            });
+        }
+
+        self.finish(NodeKind::Module)
+    }
 
+    /// Parse a line of input to the REPL, which can mix items and statements,
+    /// optionally followed by an expression.
+    pub fn parse_repl_input(mut self) -> (ReplInput, Vec<ParseError>) {
         while !self.tokens.at_end() {
-            match self.parse_item() {
-                Ok(item) => {
-                    items.push(item);
-                }
+            if self.at_item() {
+                self.parse_or_recover(Self::parse_item, |parser| {
+                    parser.seek_and_consume(&[TokenKind::Semicolon]);
+                });
+
+                continue;
+            }
 
-                Err(err) => {
-                    self.report(err);
-                    self.seek(&[
-                        TokenKind::Keyword(Keyword::Submodule),
-                        TokenKind::Keyword(Keyword::Func),
-                    ]);
+            match self.parse_statement_or_recover() {
+                StmtEnd::Expr { .. } if self.tokens.at_end() => {}
+
+                StmtEnd::Expr {
+                    semicolon_optional: false,
+                } => {
+                    let next = self.tokens.peek();
+                    self.report(self.error_expected_kind(TokenKind::Semicolon, next));
                 }
+
+                _ => {}
             }
         }
 
-        Module { items }
+        let (cst, errors) = self.finish(NodeKind::ReplInput);
+        (ReplInput::from_cst(&cst), errors)
     }
 
-    fn parse_item(&mut self) -> ParseResult<Item> {
-        match self.tokens.next() {
+    fn finish(mut self, kind: NodeKind) -> (SyntaxNode, Vec<ParseError>) {
+        self.push_trivia(usize::MAX);
+        (self.builder.finish(kind), self.errors)
+    }
+
+    fn at_item(&mut self) -> bool {
+        self.tokens
+            .peek()
+            .is_some_and(|t| ITEM_KEYWORDS.contains(&t.kind))
+    }
+
+    fn parse_item(&mut self) -> ParseResult<()> {
+        let checkpoint = self.checkpoint();
+
+        let kind = match self.bump() {
             Some(t) if t.kind == TokenKind::Keyword(Keyword::Submodule) => {
-                let ident = self.parse_ident()?;
+                self.parse_ident()?;
                 self.expect(TokenKind::Semicolon)?;
-                Ok(Item::Submodule(ident))
+                NodeKind::Submodule
             }
 
             Some(t) if t.kind == TokenKind::Keyword(Keyword::Func) => {
-                let item = self.parse_or_recover(
-                    |parser| parser.parse_func_decl().map(Item::FuncDecl),
-                    |_, _| Item::ParseError,
-                );
-                Ok(item)
+                self.parse_or_recover(Self::parse_func_decl, |_| {});
+                NodeKind::FuncDecl
             }
 
-            other => Err(self.error_expected("an item", other)),
-        }
-    }
+            Some(t) if t.kind == TokenKind::Keyword(Keyword::Export) => {
+                self.expect(TokenKind::Keyword(Keyword::Func))?;
 
-    fn parse_func_decl(&mut self) -> ParseResult<FuncDecl> {
-        let ident = self.parse_ident()?;
+                self.parse_or_recover(Self::parse_func_decl, |_| {});
+                NodeKind::FuncDecl
+            }
 
-        self.expect_or_recover(TokenKind::LParen);
-        self.expect_or_recover(TokenKind::RParen); // TODO: change when parsing parameters?
-
-        let (ret_ty, ret_ty_span) = if self.eat_kind(TokenKind::Arrow) {
-            self.parse_spanned(|parser| {
-                parser.parse_or_recover(Self::parse_type, |parser, _| {
-                    parser.seek(&[TokenKind::LBrace]);
-                    Type::Void
-                })
-            })
-        } else {
-            (Type::Void, Span::empty(self.tokens.peek_span().start))
+            Some(t) if t.kind == TokenKind::Keyword(Keyword::Extern) => {
+                self.expect(TokenKind::Keyword(Keyword::Func))?;
+
+                self.parse_or_recover(Self::parse_extern_func_decl, |parser| {
+                    parser.seek_and_consume(&[TokenKind::Semicolon]);
+                });
+                NodeKind::ExternFuncDecl
+            }
+
+            Some(t) if t.kind == TokenKind::Keyword(Keyword::Const) => {
+                self.parse_or_recover(Self::parse_global_var_decl, |parser| {
+                    parser.seek_and_consume(&[TokenKind::Semicolon]);
+                });
+                NodeKind::ConstDecl
+            }
+
+            Some(t) if t.kind == TokenKind::Keyword(Keyword::Static) => {
+                self.parse_or_recover(Self::parse_global_var_decl, |parser| {
+                    parser.seek_and_consume(&[TokenKind::Semicolon]);
+                });
+                NodeKind::StaticDecl
+            }
+
+            other => return Err(self.error_expected("an item", other)),
         };
 
-        let block = self.parse_block()?;
+        self.builder.wrap(checkpoint, kind);
+        Ok(())
+    }
+
+    fn parse_func_decl(&mut self) -> ParseResult<()> {
+        self.parse_func_signature()?;
+        self.parse_block()
+    }
 
-        Ok(FuncDecl {
-            ident,
+    fn parse_extern_func_decl(&mut self) -> ParseResult<()> {
+        self.parse_func_signature()?;
+        self.expect(TokenKind::Semicolon)?;
+        Ok(())
+    }
 
-            ret_ty,
-            ret_ty_span,
+    fn parse_global_var_decl(&mut self) -> ParseResult<()> {
+        self.parse_ident()?;
 
-            block,
-        })
+        self.expect(TokenKind::Colon)?;
+        self.parse_type()?;
+
+        self.expect(TokenKind::Assign)?;
+        self.parse_expr()?;
+
+        self.expect(TokenKind::Semicolon)?;
+        Ok(())
     }
 
-    fn parse_type(&mut self) -> ParseResult<Type> {
-        match self.tokens.peek() {
-            Some(t) if t.kind == TokenKind::Keyword(Keyword::I64) => {
-                self.tokens.next();
-                Ok(Type::I64)
-            }
-            Some(t) if t.kind == TokenKind::Keyword(Keyword::Bool) => {
-                self.tokens.next();
-                Ok(Type::Bool)
-            }
-            Some(t) if t.kind == TokenKind::Keyword(Keyword::Void) => {
-                self.tokens.next();
-                Ok(Type::Void)
-            }
-            Some(t) if t.kind == TokenKind::Bang => {
-                self.tokens.next();
-                Ok(Type::Never)
+    fn parse_func_signature(&mut self) -> ParseResult<()> {
+        self.parse_ident()?;
+
+        let checkpoint = self.checkpoint();
+        self.expect_or_recover(TokenKind::LParen);
+
+        while matches!(
+            self.tokens.peek(),
+            Some(Token {
+                kind: TokenKind::Identifier(_),
+                ..
+            })
+        ) {
+            self.parse_param()?;
+
+            if !self.eat_kind(TokenKind::Comma) {
+                break;
             }
+        }
+
+        self.expect_or_recover(TokenKind::RParen);
+        self.builder.wrap(checkpoint, NodeKind::ParamList);
 
-            other => Err(self.error_expected("a type", other)),
+        // the return type node is empty if there is no arrow
+        let checkpoint = self.checkpoint();
+        if self.eat_kind(TokenKind::Arrow) {
+            self.parse_or_recover(Self::parse_type, |parser| {
+                parser.seek(&[TokenKind::LBrace, TokenKind::Semicolon]);
+            });
         }
+        self.builder.wrap(checkpoint, NodeKind::RetType);
+
+        Ok(())
     }
 
-    fn parse_block(&mut self) -> ParseResult<Block> {
-        let ((statements, final_expr), span) = self
-            .parse_spanned(|parser| -> ParseResult<_> {
-                parser.expect(TokenKind::LBrace)?;
+    fn parse_param(&mut self) -> ParseResult<()> {
+        let checkpoint = self.checkpoint();
 
-                let mut statements = vec![];
-                let mut final_expr = None;
+        self.parse_ident()?;
+        self.expect(TokenKind::Colon)?;
+        self.parse_type()?;
 
-                // TODO: significantly improve recoverable parsing
+        self.builder.wrap(checkpoint, NodeKind::Param);
+        Ok(())
+    }
 
-                while parser
-                    .tokens
-                    .peek()
-                    .is_some_and(|t| t.kind != TokenKind::RBrace)
-                {
-                    let statement = parser.parse_statement_or_recover();
-
-                    match statement {
-                        Stmt::Drop {
-                            expr,
-                            had_semicolon: false,
-                        } if !expr.kind.stmt_semicolon_is_optional() => {
-                            final_expr = Some(expr);
-                            break;
-                        }
+    fn parse_type(&mut self) -> ParseResult<()> {
+        let checkpoint = self.checkpoint();
+
+        let kind = match self.tokens.peek() {
+            Some(t)
+                if matches!(
+                    t.kind,
+                    TokenKind::Keyword(
+                        Keyword::I64
+                            | Keyword::F32
+                            | Keyword::F64
+                            | Keyword::Bool
+                            | Keyword::Char
+                            | Keyword::Str
+                            | Keyword::Void
+                    ) | TokenKind::Bang
+                ) =>
+            {
+                self.bump();
+                NodeKind::PrimitiveType
+            }
+            Some(t) if t.kind == TokenKind::Mul => {
+                self.bump();
+                self.parse_type()?;
+                NodeKind::PtrType
+            }
+            Some(t) if t.kind == TokenKind::LBracket => {
+                self.bump();
 
-                        _ => {
-                            statements.push(statement);
-                        }
+                if self.eat_kind(TokenKind::RBracket) {
+                    self.parse_type()?;
+                    NodeKind::SliceType
+                } else {
+                    self.parse_type()?;
+                    self.expect(TokenKind::Semicolon)?;
+
+                    match self.bump() {
+                        Some(Token {
+                            kind: TokenKind::Integer(_),
+                            ..
+                        }) => {}
+                        other => return Err(self.error_expected("an array length", other)),
                     }
+
+                    self.expect(TokenKind::RBracket)?;
+                    NodeKind::ArrayType
                 }
+            }
+
+            other => return Err(self.error_expected("a type", other)),
+        };
 
-                let rbrace = parser.expect(TokenKind::RBrace)?;
+        self.builder.wrap(checkpoint, kind);
+        Ok(())
+    }
 
-                let final_expr =
-                    final_expr.unwrap_or_else(|| Expr::new(ExprKind::Void, rbrace.span));
+    fn parse_block(&mut self) -> ParseResult<()> {
+        let checkpoint = self.checkpoint();
+        self.expect(TokenKind::LBrace)?;
+
+        // TODO: significantly improve recoverable parsing
+
+        while self
+            .tokens
+            .peek()
+            .is_some_and(|t| t.kind != TokenKind::RBrace)
+        {
+            match self.parse_statement_or_recover() {
+                // expressions like `if` don't need a semicolon to be a statement,
+                // but are still the final expression at the end of a block
+                StmtEnd::Expr { semicolon_optional }
+                    if !semicolon_optional
+                        || self
+                            .tokens
+                            .peek()
+                            .is_some_and(|t| t.kind == TokenKind::RBrace) =>
+                {
+                    break;
+                }
 
-                Ok((statements, final_expr))
-            })
-            .transpose()?;
+                _ => {}
+            }
+        }
 
-        Ok(Block {
-            statements,
-            final_expr,
+        self.expect(TokenKind::RBrace)?;
 
-            span,
-        })
+        self.builder.wrap(checkpoint, NodeKind::Block);
+        Ok(())
     }
 
     /// Always makes progress.
-    fn parse_statement_or_recover(&mut self) -> Stmt {
-        self.parse_or_recover(Self::parse_statement, |parser, _| {
+    fn parse_statement_or_recover(&mut self) -> StmtEnd {
+        self.parse_or_recover(Self::parse_statement, |parser| {
             parser.seek_and_consume(&[TokenKind::Semicolon]);
-            Stmt::ParseError
         })
+        .unwrap_or(StmtEnd::Complete)
     }
 
     /// Always makes progress.
-    fn parse_statement(&mut self) -> ParseResult<Stmt> {
+    fn parse_statement(&mut self) -> ParseResult<StmtEnd> {
+        let checkpoint = self.checkpoint();
+
         match self.tokens.peek() {
             Some(token) if token.kind == TokenKind::Keyword(Keyword::Return) => {
-                self.tokens.next();
-
-                let expr = if self.eat_kind(TokenKind::Semicolon) {
-                    Expr::new(
-                        ExprKind::Void,
-                        // `return` span
-                        token.span,
-                    )
-                } else {
+                self.bump();
+
+                if !self.eat_kind(TokenKind::Semicolon) {
                     match self.tokens.peek() {
                         // handle case of `return }`
                         Some(brace_token) if brace_token.kind == TokenKind::RBrace => {
                             self.report(
                                 self.error_expected_kind(TokenKind::Semicolon, Some(brace_token)),
                             );
-
-                            Expr::new(
-                                ExprKind::Void,
-                                // `return` span
-                                token.span,
-                            )
                         }
 
                         _ => {
-                            let expr = self.parse_expr()?;
-
+                            self.parse_expr()?;
                             self.expect_or_recover(TokenKind::Semicolon);
-                            expr
                         }
                     }
-                };
+                }
 
-                Ok(Stmt::Return(expr))
+                self.builder.wrap(checkpoint, NodeKind::ReturnStmt);
+                Ok(StmtEnd::Complete)
             }
 
             Some(token) if token.kind == TokenKind::Keyword(Keyword::Let) => {
-                self.tokens.next();
+                self.bump();
 
-                let ident = self.parse_ident()?;
+                self.parse_ident()?;
                 self.expect(TokenKind::Colon)?;
-                let ty = self.parse_type()?;
+                self.parse_type()?;
                 self.expect(TokenKind::Assign)?;
-                let expr = self.parse_expr()?;
+                self.parse_expr()?;
                 self.expect_or_recover(TokenKind::Semicolon);
 
-                Ok(Stmt::Assign { ident, ty, expr })
+                self.builder.wrap(checkpoint, NodeKind::LetStmt);
+                Ok(StmtEnd::Complete)
             }
 
             _ => {
-                let expr = self.parse_expr()?;
+                let kind = self.parse_expr()?;
+
+                if self.eat_kind(TokenKind::Assign) {
+                    self.parse_expr()?;
+                    self.expect_or_recover(TokenKind::Semicolon);
+
+                    self.builder.wrap(checkpoint, NodeKind::StoreStmt);
+                    return Ok(StmtEnd::Complete);
+                }
 
                 let had_semicolon = self.eat_kind(TokenKind::Semicolon);
+                self.builder.wrap(checkpoint, NodeKind::ExprStmt);
 
-                Ok(Stmt::Drop {
-                    expr,
-                    had_semicolon,
-                })
+                if had_semicolon {
+                    Ok(StmtEnd::Complete)
+                } else {
+                    Ok(StmtEnd::Expr {
+                        semicolon_optional: matches!(kind, NodeKind::Block | NodeKind::IfExpr),
+                    })
+                }
             }
         }
     }
 
-    fn parse_ident(&mut self) -> ParseResult<Ident> {
-        match self.tokens.next() {
+    fn parse_ident(&mut self) -> ParseResult<()> {
+        match self.bump() {
             Some(Token {
-                kind: TokenKind::Identifier(istr),
-                span,
-            }) => Ok(Ident { istr, span }),
+                kind: TokenKind::Identifier(_),
+                ..
+            }) => Ok(()),
 
             other => Err(self.error_expected("an identifier", other)),
         }
     }
 
+    /// Parse a node, or if that fails, report the error, put what was
+    /// parsed in an `Error` node and recover after it.
     fn parse_or_recover<T>(
         &mut self,
         parse: impl FnOnce(&mut Self) -> ParseResult<T>,
-        recover: impl FnOnce(&mut Self, Span) -> T,
-    ) -> T {
-        let (result, span) = self.parse_spanned(parse);
+        recover: impl FnOnce(&mut Self),
+    ) -> Option<T> {
+        let checkpoint = self.checkpoint();
 
-        match result {
-            Ok(node) => node,
+        match parse(self) {
+            Ok(node) => Some(node),
             Err(err) => {
                 self.report(err);
-                recover(self, span)
+                self.builder.wrap(checkpoint, NodeKind::Error);
+
+                recover(self);
+                None
             }
         }
     }
 
-    fn parse_spanned<T>(&mut self, f: impl FnOnce(&mut Self) -> T) -> (T, Span) {
-        let span_start = self.tokens.peek_span();
-        let node = f(self);
-        let span_end = self.tokens.prev_span();
+    /// Where the next node starts, after the trivia before its first token.
+    fn checkpoint(&mut self) -> Checkpoint {
+        let pos = self.tokens.peek().map_or(usize::MAX, |t| t.span.start);
+
+        self.push_trivia(pos);
+        self.builder.checkpoint()
+    }
+
+    /// Add the trivia before `pos` to the tree.
+    fn push_trivia(&mut self, pos: usize) {
+        let pos = pos.min(self.tokens.source().len());
+
+        while let Some(trivia) = self.tokens.next_trivia_before(pos) {
+            let text = &self.tokens.source()[trivia.span.start..trivia.span.end];
+            self.builder
+                .token(SyntaxTokenKind::Trivia(trivia.kind), text);
+        }
+
+        // the text between tokens is in the tree even if the lexer didn't
+        // keep it as trivia, so that positions in the tree are right
+        let start = self.builder.text_len();
+        if start < pos {
+            let text = &self.tokens.source()[start..pos];
+            self.builder
+                .token(SyntaxTokenKind::Trivia(TriviaKind::Skipped), text);
+        }
+    }
+
+    /// Consume the next token, adding it to the tree.
+    fn bump(&mut self) -> Option<Token> {
+        let token = self.tokens.peek()?;
+        self.push_trivia(token.span.start);
+
+        self.tokens.next();
+        let text = &self.tokens.source()[token.span.start..token.span.end];
+        self.builder.token(SyntaxTokenKind::Token(token.kind), text);
 
-        let span = Span::new(span_start.start, span_end.end.max(span_start.start));
-        (node, span)
+        Some(token)
     }
 
     fn expect(&mut self, kind: TokenKind) -> ParseResult<Token> {
         match self.tokens.peek() {
             Some(t) if t.kind == kind => {
-                self.tokens.next();
+                self.bump();
                 Ok(t)
             }
 
@@ -323,7 +509,7 @@ impl Parser {
     fn eat_kind(&mut self, kind: TokenKind) -> bool {
         match self.tokens.peek() {
             Some(t) if t.kind == kind => {
-                self.tokens.next();
+                self.bump();
                 true
             }
             _ => false,
@@ -332,7 +518,7 @@ impl Parser {
 
     fn seek_and_consume(&mut self, kinds: &[TokenKind]) {
         if self.seek(kinds) {
-            self.tokens.next();
+            self.bump();
         }
     }
 
@@ -350,7 +536,7 @@ impl Parser {
                 }
 
                 Some(token) if token.kind == TokenKind::LBrace => {
-                    self.tokens.next();
+                    self.bump();
 
                     brace_depth += 1;
 
@@ -363,14 +549,14 @@ impl Parser {
                         return false;
                     }
 
-                    self.tokens.next();
+                    self.bump();
 
                     brace_depth -= 1;
                     paren_depth = paren_depth_stack.pop().unwrap_or(0);
                 }
 
                 Some(token) if token.kind == TokenKind::LParen => {
-                    self.tokens.next();
+                    self.bump();
                     paren_depth += 1;
                 }
 
@@ -379,12 +565,12 @@ impl Parser {
                         return false;
                     }
 
-                    self.tokens.next();
+                    self.bump();
                     paren_depth -= 1;
                 }
 
                 Some(_) => {
-                    self.tokens.next();
+                    self.bump();
                 }
 
                 None => {
@@ -415,17 +601,3 @@ impl Parser {
         }
     }
 }
-
-pub trait Transpose {
-    type Transposed;
-
-    fn transpose(self) -> Self::Transposed;
-}
-
-impl<T, E> Transpose for (Result<T, E>, Span) {
-    type Transposed = Result<(T, Span), E>;
-
-    fn transpose(self) -> Self::Transposed {
-        self.0.map(|inner| (inner, self.1))
-    }
-}
@@ -2,7 +2,7 @@ use bayou_interner::Interner;
 use bayou_utils::assert_yaml_snapshot_with_source;
 
 use super::{ParseError, Parser};
-use crate::ast::Module;
+use crate::ast::{Module, ReplInput};
 use crate::lexer::Lexer;
 
 fn parse(source: &str) -> (Module, Vec<ParseError>) {
@@ -17,6 +17,15 @@ fn parse(source: &str) -> (Module, Vec<ParseError>) {
     parser.parse()
 }
 
+fn parse_repl(source: &str) -> (ReplInput, Vec<ParseError>) {
+    let interner = Interner::new();
+
+    let (tokens, lexer_errors) = Lexer::new(source, &interner).lex();
+    assert!(lexer_errors.is_empty(), "lexer errors in parser tests");
+
+    Parser::new(tokens).parse_repl_input()
+}
+
 macro_rules! assert_parse {
     ($source:expr) => {{
         let source = $source;
@@ -63,3 +72,57 @@ fn no_space() {
 fn wrong_case() {
     assert_parse!("func main() -> i64 { RETURN 0; }");
 }
+
+#[test]
+fn pointers() {
+    assert_parse!("func set(p: *i64, x: i64) { *(p + 1) = *&x as i64; }");
+}
+
+#[test]
+fn extern_func() {
+    assert_parse!(
+        "extern func malloc(size: i64) -> *void; func main() -> i64 { free(malloc(8)); 0 }"
+    );
+}
+
+#[test]
+fn export_func() {
+    assert_parse!("export func add(a: i64, b: i64) -> i64 { a + b } export extern func f();");
+}
+
+#[test]
+fn package_call() {
+    assert_parse!("func main() -> i64 { math::square(2) + square(3) }");
+}
+
+#[test]
+fn package_call_without_args() {
+    assert_parse!("func main() -> i64 { math::PI }");
+}
+
+#[test]
+fn arrays() {
+    assert_parse!("func first(xs: []i64) -> i64 { let a: [i64; 2] = [xs[0], xs.len]; a[0] }");
+}
+
+#[test]
+fn comparison_precedence() {
+    assert_parse!("func f(x: f64) -> bool { x * 2.0 < 1.5 == x > 0.0 }");
+}
+
+#[test]
+fn global_vars() {
+    assert_parse!("const N: i64 = 4 * 2; static COUNT: i64 = N;");
+}
+
+#[test]
+fn repl_items_and_expr() {
+    let source = "func sq(x: i64) -> i64 { x * x } let a: i64 = sq(3); a + 1";
+    assert_yaml_snapshot_with_source!(source => parse_repl(source));
+}
+
+#[test]
+fn repl_missing_semicolon() {
+    let source = "let a: i64 = 1 a";
+    assert_yaml_snapshot_with_source!(source => parse_repl(source));
+}
@@ -1,4 +1,5 @@
 use bayou_interner::Istr;
+use bayou_ir::{BinOp, F64Bits};
 use bayou_session::diagnostics::span::Span;
 
 use crate::NodeCopy;
@@ -14,15 +15,21 @@ pub enum TokenKind {
     Keyword(Keyword),
     Identifier(Istr),
     Integer(i64),
+    Float(F64Bits),
     Bool(bool),
+    Char(char),
+    String(Istr),
 
     LBrace,
     RBrace,
     LParen,
     RParen,
+    LBracket,
+    RBracket,
 
     Dot,
     Colon,
+    DoubleColon,
     Comma,
     Semicolon,
     Bang,
@@ -41,62 +48,132 @@ pub enum TokenKind {
     // MulEq,
     // DivEq,
     // ModEq,
+    EqEq,
+    NotEq,
 
-    // TODO: uncomment when boolean operations are added
-    // EqEq,
-    // NotEq,
+    Gt,
+    Lt,
+    GtEq,
+    LtEq,
 
-    // Gt,
-    // Lt,
-    // GtEq,
-    // LtEq,
     BitwiseAnd,
     BitwiseOr,
     BitwiseXor,
     BitwiseInvert,
 }
 
+/// Text between tokens, which the lexer only keeps when it is asked to,
+/// for building a lossless syntax tree.
+#[derive(NodeCopy!)]
+pub struct Trivia {
+    pub kind: TriviaKind,
+    pub span: Span,
+}
+
+#[derive(NodeCopy!)]
+pub enum TriviaKind {
+    Whitespace,
+    Comment,
+
+    /// Text that isn't a token, which the lexer reported an error for.
+    Invalid,
+
+    /// Text between tokens when the lexer didn't keep trivia.
+    Skipped,
+}
+
 #[derive(NodeCopy!)]
 pub enum Keyword {
     Submodule,
 
     Func,
+    Extern,
+    Export,
+    Const,
+    Static,
     Return,
 
     Let,
     If,
     Then,
     Else,
+    As,
 
     I64,
+    F32,
+    F64,
     Bool,
+    Char,
+    Str,
     Void,
 }
 
 impl TokenKind {
+    /// The binary operator that this token is, if it is one.
+    pub fn bin_op(self) -> Option<BinOp> {
+        let op = match self {
+            TokenKind::Add => BinOp::Add,
+            TokenKind::Sub => BinOp::Sub,
+            TokenKind::Mul => BinOp::Mul,
+            TokenKind::Div => BinOp::Div,
+            TokenKind::Mod => BinOp::Mod,
+
+            TokenKind::BitwiseAnd => BinOp::BitwiseAnd,
+            TokenKind::BitwiseOr => BinOp::BitwiseOr,
+            TokenKind::BitwiseXor => BinOp::BitwiseXor,
+
+            TokenKind::EqEq => BinOp::Eq,
+            TokenKind::NotEq => BinOp::NotEq,
+
+            TokenKind::Gt => BinOp::Gt,
+            TokenKind::Lt => BinOp::Lt,
+            TokenKind::GtEq => BinOp::GtEq,
+            TokenKind::LtEq => BinOp::LtEq,
+
+            _ => return None,
+        };
+
+        Some(op)
+    }
+
     pub fn token_name(&self) -> &'static str {
         match self {
             TokenKind::Keyword(kw) => match kw {
                 Keyword::Submodule => "keyword `submodule`",
                 Keyword::Func => "keyword `func`",
+                Keyword::Extern => "keyword `extern`",
+                Keyword::Export => "keyword `export`",
+                Keyword::Const => "keyword `const`",
+                Keyword::Static => "keyword `static`",
                 Keyword::Return => "keyword `return`",
                 Keyword::Let => "keyword `let`",
                 Keyword::If => "keyword `if`",
                 Keyword::Then => "keyword `then`",
                 Keyword::Else => "keyword `else`",
+                Keyword::As => "keyword `as`",
                 Keyword::I64 => "keyword `i64`",
+                Keyword::F32 => "keyword `f32`",
+                Keyword::F64 => "keyword `f64`",
                 Keyword::Bool => "keyword `bool`",
+                Keyword::Char => "keyword `char`",
+                Keyword::Str => "keyword `str`",
                 Keyword::Void => "keyword `void`",
             },
             TokenKind::Identifier(_) => "identifier",
             TokenKind::Integer(_) => "integer",
+            TokenKind::Float(_) => "float",
             TokenKind::Bool(_) => "boolean",
+            TokenKind::Char(_) => "character",
+            TokenKind::String(_) => "string",
             TokenKind::LBrace => "`{`",
             TokenKind::RBrace => "`}`",
             TokenKind::LParen => "`(`",
             TokenKind::RParen => "`)`",
+            TokenKind::LBracket => "`[`",
+            TokenKind::RBracket => "`]`",
             TokenKind::Dot => "`.`",
             TokenKind::Colon => "`:`",
+            TokenKind::DoubleColon => "`::`",
             TokenKind::Comma => "`,`",
             TokenKind::Semicolon => "`;`",
             TokenKind::Bang => "`!`",
@@ -107,6 +184,12 @@ impl TokenKind {
             TokenKind::Div => "`/`",
             TokenKind::Mod => "`%`",
             TokenKind::Assign => "`=`",
+            TokenKind::EqEq => "`==`",
+            TokenKind::NotEq => "`!=`",
+            TokenKind::Gt => "`>`",
+            TokenKind::Lt => "`<`",
+            TokenKind::GtEq => "`>=`",
+            TokenKind::LtEq => "`<=`",
             TokenKind::BitwiseAnd => "`&`",
             TokenKind::BitwiseOr => "`|`",
             TokenKind::BitwiseXor => "`^`",
@@ -0,0 +1,78 @@
+//! Interning the same strings from several threads at once, with a [`SyncInterner`]
+//! and with an [`Interner`] behind a mutex, and from one thread with an [`Interner`].
+
+use std::sync::Mutex;
+
+use bayou_interner::{Interner, SyncInterner};
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+
+/// Identifiers like a lexer would see them, where most have been seen before.
+fn identifiers() -> Vec<String> {
+    (0..20_000)
+        .map(|n: u64| format!("ident_{}", n.wrapping_mul(2_654_435_761) % 2_000))
+        .collect()
+}
+
+/// Intern every identifier on each thread.
+fn on_threads(threads: usize, identifiers: &[String], intern: impl Fn(&str) + Sync) {
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                for ident in identifiers {
+                    intern(ident);
+                }
+            });
+        }
+    });
+}
+
+fn contention(c: &mut Criterion) {
+    let identifiers = identifiers();
+
+    let mut group = c.benchmark_group("intern");
+
+    group.throughput(Throughput::Elements(identifiers.len() as u64));
+    group.bench_function("Interner", |b| {
+        b.iter(|| {
+            let interner = Interner::new();
+            for ident in &identifiers {
+                interner.intern(ident);
+            }
+        });
+    });
+
+    for threads in [1, 2, 4, 8] {
+        group.throughput(Throughput::Elements((identifiers.len() * threads) as u64));
+
+        group.bench_with_input(
+            BenchmarkId::new("Mutex<Interner>", threads),
+            &threads,
+            |b, &threads| {
+                b.iter(|| {
+                    let interner = Mutex::new(Interner::new());
+                    on_threads(threads, &identifiers, |ident| {
+                        interner.lock().unwrap().intern(ident);
+                    });
+                });
+            },
+        );
+
+        group.bench_with_input(
+            BenchmarkId::new("SyncInterner", threads),
+            &threads,
+            |b, &threads| {
+                b.iter(|| {
+                    let interner = SyncInterner::new();
+                    on_threads(threads, &identifiers, |ident| {
+                        interner.intern(ident);
+                    });
+                });
+            },
+        );
+    }
+
+    group.finish();
+}
+
+criterion_group!(benches, contention);
+criterion_main!(benches);
@@ -28,6 +28,17 @@ impl InternerArena {
         index
     }
 
+    #[inline]
+    pub fn len(&self) -> usize {
+        self.vec.borrow().len()
+    }
+
+    /// The bytes allocated for strings and their pointers, including unused capacity.
+    pub fn allocated_bytes(&self) -> usize {
+        self.alloc.allocated_bytes()
+            + self.vec.borrow().capacity() * std::mem::size_of::<*const str>()
+    }
+
     #[inline]
     pub fn get(&self, index: usize) -> Option<&str> {
         let ptr = *self.vec.borrow().get(index)?;
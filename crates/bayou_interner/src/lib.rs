@@ -12,6 +12,10 @@
 //! This does mean that accessing the underlying string requires calling a method on the interner, but this is a
 //! single array lookup.
 //!
+//! For interning from several threads at once there is also [`SyncInterner`], which splits its hash table
+//! into shards with their own locks and looks up strings without locking. It creates the same IDs as
+//! [`Interner`], and the two can be converted into each other.
+//!
 //! # Example
 //! ```rust
 //! use bayou_interner::Interner;
@@ -33,6 +37,12 @@
 //! ```
 
 mod arena;
+mod remap;
+#[cfg(feature = "serde")]
+mod serialize;
+mod stats;
+mod symbols;
+mod sync;
 
 use std::cell::RefCell;
 use std::fmt;
@@ -43,6 +53,9 @@ use ahash::RandomState;
 use arena::InternerArena;
 use hashbrown::hash_table::Entry;
 use hashbrown::HashTable;
+pub use remap::Remap;
+pub use stats::{InternerStats, LONGEST_STRINGS};
+pub use sync::SyncInterner;
 
 /// An ID for an interned string. Cheap to copy, and to perform string equality checks on, as
 /// internally it is simply a [`NonZeroU32`] ID. It can also be stored inside an [`Option`] for free
@@ -69,6 +82,18 @@ impl Istr {
     fn to_index(self) -> usize {
         self.0.get() as usize - 1
     }
+
+    /// Used by [`symbols!`] to create symbols at compile time.
+    ///
+    /// # Panics
+    /// Panics if the index is too big to be an ID.
+    #[doc(hidden)]
+    pub const fn __from_symbol_index(index: u32) -> Self {
+        match NonZeroU32::new(index.wrapping_add(1)) {
+            Some(id) => Self(id),
+            None => panic!("Too many symbols"),
+        }
+    }
 }
 
 #[derive(Clone, Copy)]
@@ -103,6 +128,22 @@ impl Interner {
         Self::default()
     }
 
+    /// Create an interner with strings interned in order, so that the first string
+    /// is given the first ID and so on. Used by [`symbols!`].
+    ///
+    /// # Panics
+    /// Panics if a string is in the list more than once.
+    pub fn with_symbols(strs: &[&str]) -> Self {
+        let interner = Self::new();
+
+        for (index, s) in strs.iter().enumerate() {
+            let interned = interner.intern(s);
+            assert_eq!(interned.to_index(), index, "Duplicate symbol `{s}`");
+        }
+
+        interner
+    }
+
     /// Intern a string, returning an interned string that it is cheap to copy and
     /// perform equality checks on. Strings are only stored in the interner once, no
     /// matter how many times they are interned.
@@ -209,6 +250,61 @@ impl Interner {
     pub fn get_str(&self, interned: Istr) -> Option<&str> {
         self.arena.get(interned.to_index())
     }
+
+    /// Intern every string from another interner, returning the IDs they were given
+    /// in this interner. Strings that are in both interners keep their IDs in this one.
+    ///
+    /// # Panics
+    /// Panics if there are no more available IDs.
+    pub fn merge(&self, other: &Interner) -> Remap {
+        Remap::new(other.strs().map(|s| self.intern(s)).collect())
+    }
+
+    /// Get statistics about the memory used by this interner and its hash table.
+    ///
+    /// ```rust
+    /// # use bayou_interner::Interner;
+    /// #
+    /// # fn main() {
+    /// let interner = Interner::new();
+    /// let hello = interner.intern("hello");
+    /// interner.intern("hi");
+    ///
+    /// let stats = interner.stats();
+    ///
+    /// assert_eq!(stats.strings, 2);
+    /// assert_eq!(stats.string_bytes, 7);
+    /// assert_eq!(stats.longest[0], hello);
+    /// # }
+    /// ```
+    pub fn stats(&self) -> InternerStats {
+        let lookup = self.lookup.borrow();
+
+        let mut longest: Vec<(Istr, &str)> = self
+            .strs()
+            .enumerate()
+            .filter_map(|(index, s)| Some((Istr::from_index(index)?, s)))
+            .collect();
+        longest.sort_by_key(|&(_, s)| std::cmp::Reverse(s.len()));
+
+        InternerStats {
+            strings: self.arena.len(),
+            string_bytes: self.strs().map(str::len).sum(),
+            arena_bytes: self.arena.allocated_bytes(),
+            table_len: lookup.table.len(),
+            table_capacity: lookup.table.capacity(),
+            longest: longest
+                .into_iter()
+                .take(LONGEST_STRINGS)
+                .map(|(interned, _)| interned)
+                .collect(),
+        }
+    }
+
+    /// Every interned string, ordered by ID.
+    fn strs(&self) -> impl Iterator<Item = &str> {
+        (0..self.arena.len()).map_while(|index| self.arena.get(index))
+    }
 }
 
 impl Index<Istr> for Interner {
@@ -220,6 +316,20 @@ impl Index<Istr> for Interner {
     }
 }
 
+/// Interning strings and looking them up, which every interner can do, so that
+/// code that interns strings can be given an [`Interner`] or a [`SyncInterner`].
+pub trait Intern: Index<Istr, Output = str> {
+    /// Intern a string. See [`Interner::intern`].
+    fn intern(&self, key: &str) -> Istr;
+}
+
+impl Intern for Interner {
+    #[inline]
+    fn intern(&self, key: &str) -> Istr {
+        self.intern(key)
+    }
+}
+
 #[test]
 fn test_interner() {
     let interner = Interner::new();
@@ -234,3 +344,91 @@ fn test_interner() {
         assert_eq!(interner.get_str(a), Some(s.as_str()));
     }
 }
+
+#[test]
+fn test_symbols() {
+    symbols! {
+        mod sym {
+            A = "a",
+            B = "b",
+        }
+    }
+
+    let interner = sym::interner();
+    assert_eq!(interner.get_interned("a"), Some(sym::A));
+    assert_eq!(interner.intern("b"), sym::B);
+    assert_ne!(interner.intern("c"), sym::A);
+
+    // symbols keep their IDs when interners are converted
+    let interner = SyncInterner::from(interner);
+    assert_eq!(interner.get_interned("b"), Some(sym::B));
+}
+
+#[test]
+#[should_panic = "Duplicate symbol `a`"]
+fn test_duplicate_symbols() {
+    Interner::with_symbols(&["a", "b", "a"]);
+}
+
+#[test]
+fn test_stats() {
+    let interner = Interner::new();
+    assert_eq!(interner.stats().table_capacity, 0);
+
+    let strings: Vec<String> = (0..1000).map(|n| "a".repeat(n % 10)).collect();
+    for s in &strings {
+        interner.intern(s);
+    }
+
+    let stats = interner.stats();
+    assert_eq!(stats.strings, 10);
+    assert_eq!(stats.string_bytes, 45);
+    assert!(stats.arena_bytes >= 45);
+    assert_eq!(stats.table_len, 10);
+    assert!(stats.table_capacity >= 10);
+
+    let longest: Vec<&str> = stats.longest.iter().map(|&istr| &interner[istr]).collect();
+    assert_eq!(
+        longest,
+        ["aaaaaaaaa", "aaaaaaaa", "aaaaaaa", "aaaaaa", "aaaaa"]
+    );
+}
+
+#[test]
+fn test_merge() {
+    let interner = Interner::new();
+    let a = interner.intern("a");
+    let b = interner.intern("b");
+
+    let other = Interner::new();
+    let other_b = other.intern("b");
+    let other_c = other.intern("c");
+
+    let remap = interner.merge(&other);
+
+    assert_eq!(remap.get(other_b), Some(b));
+    assert_eq!(interner.get_str(remap[other_c]), Some("c"));
+    assert_eq!(interner.get_interned("a"), Some(a));
+    assert_eq!(remap.get(Istr::from_index(2).unwrap()), None);
+}
+
+#[cfg(feature = "serde")]
+#[test]
+fn test_serde() {
+    let interner = Interner::new();
+    let interned: Vec<Istr> = ["hello", "world", "\"quoted\""]
+        .into_iter()
+        .map(|s| interner.intern(s))
+        .collect();
+
+    let json = serde_json::to_string(&interner).unwrap();
+    assert_eq!(json, r#"["hello","world","\"quoted\""]"#);
+
+    let deserialized: Interner = serde_json::from_str(&json).unwrap();
+    for istr in interned {
+        assert_eq!(deserialized.get_str(istr), interner.get_str(istr));
+    }
+
+    let error = serde_json::from_str::<Interner>(r#"["a","b","a"]"#).unwrap_err();
+    assert!(error.to_string().contains("duplicate string `a`"));
+}
@@ -0,0 +1,53 @@
+use std::ops::Index;
+
+use crate::Istr;
+
+/// The IDs that the strings of one interner were given when they were merged into
+/// another interner, returned by [`Interner::merge`](crate::Interner::merge).
+///
+/// ```rust
+/// # use bayou_interner::Interner;
+/// #
+/// # fn main() {
+/// let interner = Interner::new();
+/// let world = interner.intern("world");
+///
+/// let other = Interner::new();
+/// let hello = other.intern("hello");
+/// let other_world = other.intern("world");
+///
+/// let remap = interner.merge(&other);
+///
+/// assert_eq!(remap[other_world], world);
+/// assert_eq!(interner.get_str(remap[hello]), Some("hello"));
+/// # }
+/// ```
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Remap {
+    table: Vec<Istr>,
+}
+
+impl Remap {
+    #[inline]
+    pub(crate) fn new(table: Vec<Istr>) -> Self {
+        Self { table }
+    }
+
+    /// Get the new ID of an interned string from the merged interner, or `None`
+    /// if it wasn't created by that interner.
+    #[inline]
+    pub fn get(&self, interned: Istr) -> Option<Istr> {
+        self.table.get(interned.to_index()).copied()
+    }
+}
+
+impl Index<Istr> for Remap {
+    type Output = Istr;
+
+    #[inline]
+    fn index(&self, interned: Istr) -> &Self::Output {
+        self.table
+            .get(interned.to_index())
+            .expect("String not in merged interner")
+    }
+}
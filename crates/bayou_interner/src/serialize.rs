@@ -0,0 +1,57 @@
+//! Interners are serialized as a list of their strings, ordered by ID, so
+//! that they are given the same IDs when they are deserialized.
+
+use std::fmt;
+
+use serde::de::{Error as _, SeqAccess, Visitor};
+use serde::ser::SerializeSeq;
+use serde::{Deserialize, Deserializer, Serialize, Serializer};
+
+use crate::Interner;
+
+impl Serialize for Interner {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut seq = serializer.serialize_seq(Some(self.arena.len()))?;
+        for s in self.strs() {
+            seq.serialize_element(s)?;
+        }
+        seq.end()
+    }
+}
+
+impl<'de> Deserialize<'de> for Interner {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        deserializer.deserialize_seq(InternerVisitor)
+    }
+}
+
+struct InternerVisitor;
+
+impl<'de> Visitor<'de> for InternerVisitor {
+    type Value = Interner;
+
+    fn expecting(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "a list of unique strings")
+    }
+
+    fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+        let interner = Interner::new();
+
+        let mut index = 0;
+        while let Some(s) = seq.next_element::<String>()? {
+            let interned = interner
+                .try_intern(&s)
+                .ok_or_else(|| A::Error::custom("too many strings"))?;
+
+            // a duplicate would be given the ID of the first one,
+            // and every string after it would be given the wrong ID
+            if interned.to_index() != index {
+                return Err(A::Error::custom(format_args!("duplicate string `{s}`")));
+            }
+
+            index += 1;
+        }
+
+        Ok(interner)
+    }
+}
@@ -0,0 +1,28 @@
+use crate::Istr;
+
+/// The number of strings in [`InternerStats::longest`].
+pub const LONGEST_STRINGS: usize = 5;
+
+/// Statistics about the memory an [`Interner`](crate::Interner) uses and how
+/// full its hash table is, from [`Interner::stats`](crate::Interner::stats).
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct InternerStats {
+    /// The number of interned strings.
+    pub strings: usize,
+
+    /// The total length of the interned strings.
+    pub string_bytes: usize,
+
+    /// The bytes allocated by the arena for the strings and the table of their IDs,
+    /// including space that hasn't been used yet.
+    pub arena_bytes: usize,
+
+    /// The number of strings in the hash table.
+    pub table_len: usize,
+
+    /// The number of strings the hash table can hold before it has to grow.
+    pub table_capacity: usize,
+
+    /// The longest strings, from longest to shortest.
+    pub longest: Vec<Istr>,
+}
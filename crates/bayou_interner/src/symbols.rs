@@ -0,0 +1,56 @@
+/// Declare a module of symbols, which are strings that are interned at known IDs,
+/// so that they can be compared with interned strings without using an interner.
+///
+/// The module has a constant [`Istr`](crate::Istr) for every symbol, and an `interner`
+/// function that creates an [`Interner`](crate::Interner) with every symbol interned,
+/// which is the only kind of interner the constants can be used with.
+///
+/// ```rust
+/// # use bayou_interner::Istr;
+/// #
+/// bayou_interner::symbols! {
+///     pub mod sym {
+///         MAIN = "main",
+///         SELF = "self",
+///     }
+/// }
+///
+/// # fn main() {
+/// let interner = sym::interner();
+///
+/// assert_eq!(interner.intern("main"), sym::MAIN);
+/// assert_eq!(&interner[sym::SELF], "self");
+/// # }
+/// ```
+#[macro_export]
+macro_rules! symbols {
+    (
+        $(#[$attr:meta])*
+        $vis:vis mod $name:ident {
+            $($symbol:ident = $s:literal),* $(,)?
+        }
+    ) => {
+        $(#[$attr])*
+        $vis mod $name {
+            #[allow(non_camel_case_types, clippy::upper_case_acronyms)]
+            #[repr(u32)]
+            enum __SymbolIndex {
+                $($symbol,)*
+            }
+
+            $(
+                #[doc = concat!("`", $s, "`")]
+                pub const $symbol: $crate::Istr =
+                    $crate::Istr::__from_symbol_index(__SymbolIndex::$symbol as u32);
+            )*
+
+            /// The string of every symbol, ordered by ID.
+            pub const STRS: &[&str] = &[$($s,)*];
+
+            /// Create an interner with every symbol interned.
+            pub fn interner() -> $crate::Interner {
+                $crate::Interner::with_symbols(STRS)
+            }
+        }
+    };
+}
@@ -0,0 +1,410 @@
+use std::fmt;
+use std::ops::Index;
+use std::ptr;
+use std::sync::atomic::{AtomicPtr, AtomicUsize, Ordering};
+use std::sync::{Mutex, PoisonError, RwLock};
+
+use ahash::RandomState;
+use bumpalo::Bump;
+use hashbrown::HashTable;
+
+use crate::{Intern, Interner, Istr, Metadata, Remap};
+
+/// The number of shards, which is a power of two so that a shard can be picked with a mask.
+const SHARDS: usize = 32;
+
+/// Shards are picked with bits in the middle of the hash, because hash tables use
+/// the low bits to pick buckets, and `hashbrown` uses the high bits as tags.
+const SHARD_SHIFT: u32 = 40;
+
+/// Storage for interned strings that can be shared between threads.
+///
+/// The hash table is split into shards with their own locks, so threads interning
+/// different strings rarely wait for each other, and strings are looked up
+/// without locking at all.
+///
+/// It creates the same [`Istr`]s as an [`Interner`], and converting between them
+/// keeps every interned string the same, so strings can be interned from many
+/// threads and then looked up in an [`Interner`] afterwards.
+///
+/// ```rust
+/// # use bayou_interner::{Interner, SyncInterner};
+/// #
+/// # fn main() {
+/// let interner = SyncInterner::new();
+///
+/// let (hello, hello2) = std::thread::scope(|scope| {
+///     let a = scope.spawn(|| interner.intern("hello"));
+///     let b = scope.spawn(|| interner.intern("hello"));
+///     (a.join().unwrap(), b.join().unwrap())
+/// });
+/// assert_eq!(hello, hello2);
+///
+/// let interner: Interner = interner.into_interner();
+/// assert_eq!(interner.get_str(hello), Some("hello"));
+/// # }
+/// ```
+pub struct SyncInterner {
+    random_state: RandomState,
+    shards: Box<[Shard]>,
+    arena: SyncArena,
+}
+
+#[derive(Default)]
+struct Shard {
+    table: RwLock<HashTable<Metadata>>,
+
+    /// The strings interned into this shard, which are only
+    /// allocated while the table is locked for writing.
+    alloc: Mutex<Bump>,
+}
+
+impl Default for SyncInterner {
+    fn default() -> Self {
+        Self {
+            random_state: RandomState::new(),
+            shards: (0..SHARDS).map(|_| Shard::default()).collect(),
+            arena: SyncArena::default(),
+        }
+    }
+}
+
+impl fmt::Debug for SyncInterner {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.debug_struct("SyncInterner").finish_non_exhaustive()
+    }
+}
+
+impl SyncInterner {
+    /// Create a new interner.
+    #[inline]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Convert into an [`Interner`], which has the same interned strings.
+    pub fn into_interner(self) -> Interner {
+        let interner = Interner::new();
+
+        // strings are interned in the order of their IDs, so they keep the same IDs
+        for s in (0..self.arena.len()).map_while(|index| self.arena.get(index)) {
+            interner.intern(s);
+        }
+
+        interner
+    }
+
+    /// Intern a string. See [`Interner::intern`].
+    ///
+    /// # Panics
+    /// Panics if there are no more available IDs.
+    #[inline]
+    pub fn intern(&self, key: &str) -> Istr {
+        self.try_intern(key).expect("Too many interned strings")
+    }
+
+    /// Like [`SyncInterner::intern`], but non-panicking in the case that there are no
+    /// more available IDs.
+    pub fn try_intern(&self, key: &str) -> Option<Istr> {
+        let hash = self.random_state.hash_one(key);
+        let shard = self.shard(hash);
+        let eq = |metadata: &Metadata| self.get_str(metadata.interned) == Some(key);
+
+        // most strings have already been interned, which only needs a read lock
+        let table = shard.table.read().unwrap_or_else(PoisonError::into_inner);
+        if let Some(metadata) = table.find(hash, eq) {
+            return Some(metadata.interned);
+        }
+        drop(table);
+
+        let mut table = shard.table.write().unwrap_or_else(PoisonError::into_inner);
+
+        // another thread could have interned the string without the lock
+        if let Some(metadata) = table.find(hash, eq) {
+            return Some(metadata.interned);
+        }
+
+        let s = {
+            let alloc = shard.alloc.lock().unwrap_or_else(PoisonError::into_inner);
+            let s: *const str = alloc.alloc_str(key);
+            s
+        };
+
+        // Safety: the string is never moved or deallocated while `self` is alive.
+        let interned = self.arena.push(unsafe { &*s })?;
+        table.insert_unique(hash, Metadata { interned, hash }, |metadata| metadata.hash);
+
+        Some(interned)
+    }
+
+    /// Intern every string from an [`Interner`] in the order of their IDs.
+    /// See [`Interner::merge`].
+    ///
+    /// # Panics
+    /// Panics if there are no more available IDs.
+    pub fn merge(&self, other: &Interner) -> Remap {
+        Remap::new(other.strs().map(|s| self.intern(s)).collect())
+    }
+
+    /// Get an interned string if this string is interned, otherwise return `None`.
+    pub fn get_interned(&self, key: &str) -> Option<Istr> {
+        let hash = self.random_state.hash_one(key);
+
+        self.shard(hash)
+            .table
+            .read()
+            .unwrap_or_else(PoisonError::into_inner)
+            .find(hash, |metadata| {
+                self.get_str(metadata.interned) == Some(key)
+            })
+            .map(|metadata| metadata.interned)
+    }
+
+    /// Look up an interned string to get the associated string, without locking.
+    /// See [`Interner::get_str`].
+    #[inline]
+    pub fn get_str(&self, interned: Istr) -> Option<&str> {
+        self.arena.get(interned.to_index())
+    }
+
+    #[inline]
+    fn shard(&self, hash: u64) -> &Shard {
+        &self.shards[(hash >> SHARD_SHIFT) as usize % SHARDS]
+    }
+}
+
+impl From<Interner> for SyncInterner {
+    fn from(interner: Interner) -> Self {
+        let sync_interner = Self::new();
+
+        for s in interner.strs() {
+            sync_interner.intern(s);
+        }
+
+        sync_interner
+    }
+}
+
+impl Index<Istr> for SyncInterner {
+    type Output = str;
+
+    #[inline]
+    fn index(&self, interned: Istr) -> &Self::Output {
+        self.get_str(interned).expect("String not in interner")
+    }
+}
+
+impl Intern for SyncInterner {
+    #[inline]
+    fn intern(&self, key: &str) -> Istr {
+        self.intern(key)
+    }
+}
+
+/// The number of strings in the first chunk of the arena,
+/// after which every chunk is twice as big as the last.
+const FIRST_CHUNK_LEN: usize = 64;
+
+/// Enough chunks for every possible [`Istr`].
+const CHUNKS: usize = 27;
+
+/// The strings of a [`SyncInterner`] by their index, which are appended
+/// and looked up without locking.
+///
+/// Strings are stored in chunks that are never moved once they are allocated,
+/// so that a string can be read while another thread adds more strings.
+struct SyncArena {
+    chunks: [AtomicPtr<Slot>; CHUNKS],
+    len: AtomicUsize,
+}
+
+/// A string in the arena, which is null until the string is written.
+#[derive(Default)]
+struct Slot {
+    ptr: AtomicPtr<u8>,
+    len: AtomicUsize,
+}
+
+impl Default for SyncArena {
+    fn default() -> Self {
+        Self {
+            chunks: std::array::from_fn(|_| AtomicPtr::new(ptr::null_mut())),
+            len: AtomicUsize::new(0),
+        }
+    }
+}
+
+// Safety:
+// - The strings point to allocated strings that are never mutated,
+//   so race conditions are impossible.
+// - Chunks are only written with atomic operations.
+unsafe impl Send for SyncArena {}
+unsafe impl Sync for SyncArena {}
+
+impl SyncArena {
+    /// The number of strings, which are all written unless a thread is appending one.
+    fn len(&self) -> usize {
+        self.len.load(Ordering::Acquire)
+    }
+
+    /// Append a string, which has to stay allocated for as long as the arena,
+    /// returning its ID.
+    fn push(&self, s: &str) -> Option<Istr> {
+        let index = self.len.fetch_add(1, Ordering::AcqRel);
+        let interned = Istr::from_index(index)?;
+
+        let (chunk, offset) = chunk_of(index);
+        let slot = &self.get_or_alloc_chunk(chunk)[offset];
+
+        // the length is written first, so it is there when the pointer is read
+        slot.len.store(s.len(), Ordering::Relaxed);
+        slot.ptr.store(s.as_ptr().cast_mut(), Ordering::Release);
+
+        Some(interned)
+    }
+
+    fn get(&self, index: usize) -> Option<&str> {
+        let (chunk, offset) = chunk_of(index);
+        let slot = &self.chunk(chunk)?[offset];
+
+        let ptr = slot.ptr.load(Ordering::Acquire);
+        if ptr.is_null() {
+            return None;
+        }
+
+        let len = slot.len.load(Ordering::Relaxed);
+
+        // Safety:
+        // - The pointer and length are of a string that isn't moved or
+        //   deallocated while the arena is alive, and is never mutated.
+        // - The length was written before the pointer.
+        Some(unsafe { std::str::from_utf8_unchecked(std::slice::from_raw_parts(ptr, len)) })
+    }
+
+    fn chunk(&self, chunk: usize) -> Option<&[Slot]> {
+        let ptr = self.chunks.get(chunk)?.load(Ordering::Acquire);
+        if ptr.is_null() {
+            return None;
+        }
+
+        // Safety: chunks are allocated with their length and not deallocated until the arena is dropped.
+        Some(unsafe { std::slice::from_raw_parts(ptr, chunk_len(chunk)) })
+    }
+
+    fn get_or_alloc_chunk(&self, chunk: usize) -> &[Slot] {
+        if let Some(slots) = self.chunk(chunk) {
+            return slots;
+        }
+
+        let new: Box<[Slot]> = (0..chunk_len(chunk)).map(|_| Slot::default()).collect();
+        let new = Box::into_raw(new).cast::<Slot>();
+
+        if let Err(_other) = self.chunks[chunk].compare_exchange(
+            ptr::null_mut(),
+            new,
+            Ordering::AcqRel,
+            Ordering::Acquire,
+        ) {
+            // another thread allocated the chunk first
+            // Safety: the chunk was allocated above, and was never shared.
+            drop(unsafe { Box::from_raw(ptr::slice_from_raw_parts_mut(new, chunk_len(chunk))) });
+        }
+
+        self.chunk(chunk).unwrap()
+    }
+}
+
+impl Drop for SyncArena {
+    fn drop(&mut self) {
+        for (chunk, ptr) in self.chunks.iter_mut().enumerate() {
+            let ptr = *ptr.get_mut();
+            if !ptr.is_null() {
+                // Safety: chunks are allocated as boxed slices of their length.
+                drop(unsafe {
+                    Box::from_raw(ptr::slice_from_raw_parts_mut(ptr, chunk_len(chunk)))
+                });
+            }
+        }
+    }
+}
+
+fn chunk_len(chunk: usize) -> usize {
+    FIRST_CHUNK_LEN << chunk
+}
+
+/// The chunk of a string in the arena, and its offset in the chunk.
+fn chunk_of(index: usize) -> (usize, usize) {
+    // chunk `n` starts at `FIRST_CHUNK_LEN * (2^n - 1)`
+    let biased = index / FIRST_CHUNK_LEN + 1;
+    let chunk = biased.ilog2() as usize;
+    let start = FIRST_CHUNK_LEN * ((1 << chunk) - 1);
+
+    (chunk, index - start)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{chunk_len, chunk_of, SyncInterner, FIRST_CHUNK_LEN};
+    use crate::{Interner, Istr};
+
+    #[test]
+    fn chunks() {
+        assert_eq!(chunk_of(0), (0, 0));
+        assert_eq!(chunk_of(FIRST_CHUNK_LEN - 1), (0, FIRST_CHUNK_LEN - 1));
+        assert_eq!(chunk_of(FIRST_CHUNK_LEN), (1, 0));
+        assert_eq!(
+            chunk_of(FIRST_CHUNK_LEN * 3 - 1),
+            (1, FIRST_CHUNK_LEN * 2 - 1)
+        );
+        assert_eq!(chunk_of(FIRST_CHUNK_LEN * 3), (2, 0));
+
+        // the last chunk fits the biggest ID
+        let (chunk, offset) = chunk_of(u32::MAX as usize - 1);
+        assert!(chunk < super::CHUNKS && offset < chunk_len(chunk));
+    }
+
+    #[test]
+    fn intern_from_threads() {
+        let interner = SyncInterner::from(Interner::new());
+        let strings: Vec<String> = (0..1000).map(|n| n.to_string()).collect();
+
+        let interned: Vec<Vec<Istr>> = std::thread::scope(|scope| {
+            let threads: Vec<_> = (0..4)
+                .map(|_| scope.spawn(|| strings.iter().map(|s| interner.intern(s)).collect()))
+                .collect();
+
+            threads.into_iter().map(|t| t.join().unwrap()).collect()
+        });
+
+        for other in &interned[1..] {
+            assert_eq!(*other, interned[0]);
+        }
+
+        for (s, &istr) in strings.iter().zip(&interned[0]) {
+            assert_eq!(interner.get_str(istr), Some(s.as_str()));
+            assert_eq!(interner.get_interned(s), Some(istr));
+        }
+
+        let interner = interner.into_interner();
+        for (s, &istr) in strings.iter().zip(&interned[0]) {
+            assert_eq!(interner.get_str(istr), Some(s.as_str()));
+            assert_eq!(interner.get_interned(s), Some(istr));
+        }
+    }
+
+    #[test]
+    fn conversion_keeps_ids() {
+        let interner = Interner::new();
+        let hello = interner.intern("hello");
+
+        let interner = SyncInterner::from(interner);
+        assert_eq!(interner.get_interned("hello"), Some(hello));
+
+        let world = interner.intern("world");
+        assert_ne!(hello, world);
+
+        let interner = interner.into_interner();
+        assert_eq!(interner.get_str(hello), Some("hello"));
+        assert_eq!(interner.get_str(world), Some("world"));
+    }
+}
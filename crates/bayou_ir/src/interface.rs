@@ -0,0 +1,68 @@
+//! The interface of a package, which is what other packages can use from it.
+//!
+//! Interfaces hold strings instead of [`Istr`](bayou_interner::Istr)s, because every
+//! package is compiled with its own interner. Names are interned into the interner
+//! of the package that uses them.
+
+use crate::ir::Package;
+use crate::symbols::FuncId;
+use crate::Type;
+
+/// The exported items of a package, which packages that depend on it can refer
+/// to as `package::item` without having its IR or symbols.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct PackageInterface {
+    pub name: String,
+    pub funcs: Vec<FuncInterface>,
+}
+
+/// The signature of an exported function.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+pub struct FuncInterface {
+    pub name: String,
+
+    /// The path of the module the function is declared in, within its package.
+    pub module: Vec<String>,
+
+    /// The name of the function when linking, which is [mangled](crate::mangle)
+    /// from its package, module and name.
+    pub symbol: String,
+
+    pub params: Vec<Type>,
+    pub ret_ty: Type,
+}
+
+impl PackageInterface {
+    pub fn new(package: &Package) -> Self {
+        let symbols = &package.symbols;
+        let interner = &package.interner;
+
+        let funcs = symbols
+            .funcs
+            .iter()
+            .enumerate()
+            .filter(|(_, func)| func.is_exported)
+            .map(|(id, func)| FuncInterface {
+                name: interner[func.ident.istr].to_owned(),
+                module: func
+                    .module
+                    .components()
+                    .iter()
+                    .map(|&component| interner[component].to_owned())
+                    .collect(),
+                symbol: package.func_symbol(FuncId(id)),
+                params: func
+                    .params
+                    .iter()
+                    .map(|&param| symbols.locals[param].ty.clone())
+                    .collect(),
+                ret_ty: func.ret_ty.clone(),
+            })
+            .collect();
+
+        Self {
+            name: package.name.clone(),
+            funcs,
+        }
+    }
+}
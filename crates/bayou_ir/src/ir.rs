@@ -1,12 +1,15 @@
-use bayou_interner::Interner;
+use bayou_interner::{Interner, Istr};
 use bayou_session::sourcemap::SourceSpan;
+use bayou_session::CrateType;
 
 use super::{BinOp, NodeCopyTraits, NodeTraits, UnOp};
-use crate::symbols::{FuncId, LocalId, Symbols};
-use crate::Type;
+use crate::symbols::{ConstId, FuncId, LocalId, StaticId, Symbols};
+use crate::visit::{self, Visitor};
+use crate::{F32Bits, F64Bits, NodeCounts, Type};
 
 pub struct Package {
     pub name: String,
+    pub crate_type: CrateType,
 
     pub ir: PackageIr,
     pub symbols: Symbols,
@@ -19,9 +22,40 @@ pub struct PackageIr {
     pub main_func: Option<FuncId>,
 }
 
+impl PackageIr {
+    /// Count the items, statements and expressions in the package.
+    pub fn node_counts(&self) -> NodeCounts {
+        let mut counter = NodeCounter::default();
+        visit::walk_package(&mut counter, self);
+        counter.0
+    }
+}
+
+#[derive(Default)]
+struct NodeCounter(NodeCounts);
+
+impl Visitor<'_> for NodeCounter {
+    fn visit_item(&mut self, item: &Item) {
+        self.0.items += 1;
+        visit::walk_item(self, item);
+    }
+
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        self.0.statements += 1;
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        self.0.exprs += 1;
+        visit::walk_expr(self, expr);
+    }
+}
+
 #[derive(NodeTraits!)]
 pub enum Item {
     FuncDecl(FuncDecl),
+    Const(GlobalVarDecl<ConstId>),
+    Static(GlobalVarDecl<StaticId>),
 }
 
 #[derive(NodeTraits!)]
@@ -30,9 +64,17 @@ pub struct FuncDecl {
     pub block: Block,
 }
 
+/// A `const` or `static` item, with its initializer.
+#[derive(NodeTraits!)]
+pub struct GlobalVarDecl<Id> {
+    pub id: Id,
+    pub expr: Expr,
+}
+
 #[derive(NodeTraits!)]
 pub enum Stmt {
     Assign { local: LocalId, expr: Expr },
+    Store { place: Expr, expr: Expr },
     Drop(Expr),
     Return(Expr),
 }
@@ -56,6 +98,8 @@ pub struct Expr {
 pub enum ExprKind {
     Constant(Constant),
     Var(LocalId),
+    Const(ConstId),
+    Static(StaticId),
     UnOp {
         op: UnOp,
         expr: Box<Expr>,
@@ -65,6 +109,22 @@ pub enum ExprKind {
         lhs: Box<Expr>,
         rhs: Box<Expr>,
     },
+    AddressOf(Box<Expr>),
+    Deref(Box<Expr>),
+    Cast {
+        expr: Box<Expr>,
+        ty: Type,
+    },
+    Call {
+        func: FuncId,
+        args: Vec<Expr>,
+    },
+    Array(Vec<Expr>),
+    Index {
+        base: Box<Expr>,
+        index: Box<Expr>,
+    },
+    Len(Box<Expr>),
     Block(Box<Block>),
     If {
         cond: Box<Expr>,
@@ -73,10 +133,28 @@ pub enum ExprKind {
     },
 }
 
+impl ExprKind {
+    /// Whether this expression refers to a memory location that
+    /// can be assigned to or have its address taken.
+    pub fn is_place(&self) -> bool {
+        matches!(
+            self,
+            Self::Var(_) | Self::Static(_) | Self::Deref(_) | Self::Index { .. }
+        )
+    }
+}
+
 #[derive(NodeCopyTraits!)]
 pub enum Constant {
     I64(i64),
+    F32(F32Bits),
+    F64(F64Bits),
     Bool(bool),
+    Char(char),
+
+    /// A string literal, stored in read-only memory.
+    Str(Istr),
+
     Void,
 }
 
@@ -84,7 +162,11 @@ impl Constant {
     pub fn ty(&self) -> Type {
         match self {
             Self::I64(_) => Type::I64,
+            Self::F32(_) => Type::F32,
+            Self::F64(_) => Type::F64,
             Self::Bool(_) => Type::Bool,
+            Self::Char(_) => Type::Char,
+            Self::Str(_) => Type::Str,
             Self::Void => Type::Void,
         }
     }
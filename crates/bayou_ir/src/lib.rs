@@ -3,8 +3,14 @@
 #[macro_use]
 extern crate macro_rules_attribute;
 
+pub mod interface;
 pub mod ir;
+pub mod mangle;
 pub mod symbols;
+pub mod visit;
+
+use std::fmt;
+use std::ops::AddAssign;
 
 use bayou_interner::Istr;
 use bayou_session::{
@@ -17,7 +23,7 @@ derive_alias! {
     #[derive(NodeCopyTraits!)] = #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize)];
 }
 
-#[derive(NodeCopyTraits!)]
+#[derive(NodeCopyTraits!, serde::Deserialize)]
 pub enum BinOp {
     Add,
     Sub,
@@ -28,30 +34,170 @@ pub enum BinOp {
     BitwiseAnd,
     BitwiseOr,
     BitwiseXor,
-    // Eq,
-    // NotEq,
 
-    // Gt,
-    // Lt,
-    // GtEq,
-    // LtEq,
+    Eq,
+    NotEq,
+
+    Gt,
+    Lt,
+    GtEq,
+    LtEq,
 }
 
-#[derive(NodeCopyTraits!)]
+impl BinOp {
+    /// Whether this operator compares its operands, producing a `bool`.
+    pub fn is_comparison(self) -> bool {
+        matches!(
+            self,
+            Self::Eq | Self::NotEq | Self::Gt | Self::Lt | Self::GtEq | Self::LtEq
+        )
+    }
+}
+
+impl fmt::Display for BinOp {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let s = match self {
+            Self::Add => "+",
+            Self::Sub => "-",
+            Self::Mul => "*",
+            Self::Div => "/",
+            Self::Mod => "%",
+
+            Self::BitwiseAnd => "&",
+            Self::BitwiseOr => "|",
+            Self::BitwiseXor => "^",
+
+            Self::Eq => "==",
+            Self::NotEq => "!=",
+
+            Self::Gt => ">",
+            Self::Lt => "<",
+            Self::GtEq => ">=",
+            Self::LtEq => "<=",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[derive(NodeCopyTraits!, serde::Deserialize)]
 pub enum UnOp {
     Negate,
     BitwiseInvert,
 }
 
-#[derive(NodeCopyTraits!)]
+#[derive(NodeTraits!, Hash, serde::Deserialize)]
 pub enum Type {
     I64,
+    F32,
+    F64,
     Bool,
+    Char,
     Void,
     Never,
+
+    /// A slice of UTF-8 bytes.
+    Str,
+
+    Ptr(Box<Type>),
+    Array(Box<Type>, u64),
+    Slice(Box<Type>),
 }
 
-#[derive(NodeCopyTraits!)]
+impl Type {
+    /// Get the type of a pointer to this type.
+    pub fn ptr(self) -> Self {
+        Self::Ptr(Box::new(self))
+    }
+
+    /// Get the type that this type points to, if it is a pointer.
+    pub fn pointee(&self) -> Option<&Type> {
+        match self {
+            Self::Ptr(pointee) => Some(pointee),
+            _ => None,
+        }
+    }
+
+    /// Whether this is a floating-point type.
+    pub fn is_float(&self) -> bool {
+        matches!(self, Self::F32 | Self::F64)
+    }
+
+    /// Get the element type, if this is an array or a slice.
+    pub fn element(&self) -> Option<&Type> {
+        match self {
+            Self::Array(element, _) | Self::Slice(element) => Some(element),
+            _ => None,
+        }
+    }
+}
+
+impl fmt::Display for Type {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Self::I64 => write!(f, "i64"),
+            Self::F32 => write!(f, "f32"),
+            Self::F64 => write!(f, "f64"),
+            Self::Bool => write!(f, "bool"),
+            Self::Char => write!(f, "char"),
+            Self::Str => write!(f, "str"),
+            Self::Void => write!(f, "void"),
+            Self::Never => write!(f, "!"),
+            Self::Ptr(pointee) => write!(f, "*{pointee}"),
+            Self::Array(element, len) => write!(f, "[{element}; {len}]"),
+            Self::Slice(element) => write!(f, "[]{element}"),
+        }
+    }
+}
+
+macro_rules! float_bits {
+    ($(#[$attr:meta])* $name:ident($float:ty, $bits:ty)) => {
+        $(#[$attr])*
+        #[derive(Clone, Copy, PartialEq, Eq, Hash)]
+        pub struct $name($bits);
+
+        impl $name {
+            pub fn new(value: $float) -> Self {
+                Self(value.to_bits())
+            }
+
+            pub fn get(self) -> $float {
+                <$float>::from_bits(self.0)
+            }
+        }
+
+        impl fmt::Debug for $name {
+            fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+                fmt::Debug::fmt(&self.get(), f)
+            }
+        }
+
+        impl serde::Serialize for $name {
+            fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+                self.get().serialize(serializer)
+            }
+        }
+
+        impl<'de> serde::Deserialize<'de> for $name {
+            fn deserialize<D: serde::Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+                <$float>::deserialize(deserializer).map(Self::new)
+            }
+        }
+    };
+}
+
+float_bits! {
+    /// An `f32` that is compared and hashed by its bits, so
+    /// that nodes containing it can implement `Eq` and `Hash`.
+    F32Bits(f32, u32)
+}
+
+float_bits! {
+    /// An `f64` that is compared and hashed by its bits, so
+    /// that nodes containing it can implement `Eq` and `Hash`.
+    F64Bits(f64, u64)
+}
+
+#[derive(NodeCopyTraits!, serde::Deserialize)]
 pub struct Ident {
     pub istr: Istr,
     pub span: Span,
@@ -71,3 +217,19 @@ pub struct IdentWithSource {
     pub istr: Istr,
     pub span: SourceSpan,
 }
+
+/// The number of nodes in a syntax tree or in the IR.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct NodeCounts {
+    pub items: usize,
+    pub statements: usize,
+    pub exprs: usize,
+}
+
+impl AddAssign for NodeCounts {
+    fn add_assign(&mut self, other: Self) {
+        self.items += other.items;
+        self.statements += other.statements;
+        self.exprs += other.exprs;
+    }
+}
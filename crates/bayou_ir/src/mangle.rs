@@ -0,0 +1,69 @@
+//! Names of functions and statics when linking.
+//!
+//! Functions and statics are linked by a name made from their package, module and
+//! name, so that items with the same name in different modules or packages don't
+//! collide. Only functions that C code links against keep their own names.
+
+use bayou_session::module_loader::ModulePath;
+use bayou_session::CrateType;
+
+use crate::ir::Package;
+use crate::symbols::{FuncId, StaticId};
+
+/// Mangle the path of a function or static, with each component prefixed by its length so
+/// that different paths can't mangle the same. The `_B` prefix is reserved in C,
+/// so mangled names don't collide with C functions either.
+pub fn mangle<'a>(package: &str, module: impl IntoIterator<Item = &'a str>, name: &str) -> String {
+    let mut symbol = String::from("_B");
+    let mut push = |component: &str| {
+        symbol.push_str(&component.len().to_string());
+        symbol.push_str(component);
+    };
+
+    push(package);
+    module.into_iter().for_each(&mut push);
+    push(name);
+
+    symbol
+}
+
+impl Package {
+    /// The name of a function when linking.
+    ///
+    /// Extern functions, including the functions imported from other packages, are
+    /// declared by the name they are linked by. The `main` function of an executable
+    /// and the exported functions of libraries for C keep their names, and the other
+    /// functions are [mangled](mangle).
+    pub fn func_symbol(&self, id: FuncId) -> String {
+        let func = &self.symbols.funcs[id];
+        let name = &self.interner[func.ident.istr];
+
+        let is_c_symbol = match self.crate_type {
+            CrateType::Bin => self.ir.main_func == Some(id),
+            CrateType::Obj | CrateType::StaticLib | CrateType::Cdylib => func.is_exported,
+            CrateType::Lib => false,
+        };
+
+        if func.is_extern || is_c_symbol {
+            return name.to_owned();
+        }
+
+        self.mangle_item(&func.module, name)
+    }
+
+    /// The name of a static when linking, which is always [mangled](mangle),
+    /// since statics can't be exported to C code.
+    pub fn static_symbol(&self, id: StaticId) -> String {
+        let static_ = &self.symbols.statics[id];
+        self.mangle_item(&static_.module, &self.interner[static_.ident.istr])
+    }
+
+    fn mangle_item(&self, module: &ModulePath, name: &str) -> String {
+        let module = module
+            .components()
+            .iter()
+            .map(|&component| &self.interner[component]);
+
+        mangle(&self.name, module, name)
+    }
+}
@@ -1,12 +1,16 @@
+use bayou_session::module_loader::ModulePath;
 use bayou_session::sourcemap::SourceSpan;
 use bayou_utils::keyvec::{declare_key_type, KeyVec};
 
+use crate::ir::Constant;
 use crate::{IdentWithSource, Type};
 
 #[derive(Default, Debug, Clone, serde::Serialize)]
 pub struct Symbols {
     pub locals: KeyVec<LocalId, LocalSymbol>,
     pub funcs: KeyVec<FuncId, FunctionSymbol>,
+    pub consts: KeyVec<ConstId, GlobalVarSymbol>,
+    pub statics: KeyVec<StaticId, GlobalVarSymbol>,
 }
 
 declare_key_type! {
@@ -19,18 +23,60 @@ declare_key_type! {
     pub struct FuncId;
 }
 
+declare_key_type! {
+    #[derive(serde::Serialize)]
+    pub struct ConstId;
+}
+
+declare_key_type! {
+    #[derive(serde::Serialize)]
+    pub struct StaticId;
+}
+
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct LocalSymbol {
     pub ident: IdentWithSource,
 
     pub ty: Type,
     pub ty_span: SourceSpan,
+
+    /// Whether the address of this local is taken anywhere, in which case
+    /// it has to live in memory rather than in a register.
+    pub address_taken: bool,
 }
 
 #[derive(Debug, Clone, serde::Serialize)]
 pub struct FunctionSymbol {
     pub ident: IdentWithSource,
 
+    /// The module the function is declared in, which for
+    /// functions from other packages is in that package.
+    pub module: ModulePath,
+
+    pub params: Vec<LocalId>,
+
     pub ret_ty: Type,
     pub ret_ty_span: SourceSpan,
+
+    /// Whether this function is declared with `extern` and defined elsewhere.
+    pub is_extern: bool,
+
+    /// Whether this function is declared with `export`, and should be visible
+    /// outside of a library.
+    pub is_exported: bool,
+}
+
+/// A `const` or `static` item.
+#[derive(Debug, Clone, serde::Serialize)]
+pub struct GlobalVarSymbol {
+    pub ident: IdentWithSource,
+
+    /// The module the item is declared in.
+    pub module: ModulePath,
+
+    pub ty: Type,
+    pub ty_span: SourceSpan,
+
+    /// The value of the initializer, once it has been evaluated at compile time.
+    pub value: Option<Constant>,
 }
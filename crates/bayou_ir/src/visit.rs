@@ -0,0 +1,99 @@
+//! Walking over the IR, for passes that only care about some of its nodes.
+//!
+//! A [`Visitor`] overrides the methods for the nodes it cares about, and calls the
+//! matching `walk_` function from them to keep walking into the children of a node.
+//! The other methods just walk, so a pass doesn't need to know every kind of node.
+
+use crate::ir::{Block, Expr, ExprKind, Item, PackageIr, Stmt};
+
+pub trait Visitor<'ir> {
+    fn visit_item(&mut self, item: &'ir Item) {
+        walk_item(self, item);
+    }
+
+    fn visit_block(&mut self, block: &'ir Block) {
+        walk_block(self, block);
+    }
+
+    fn visit_stmt(&mut self, stmt: &'ir Stmt) {
+        walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &'ir Expr) {
+        walk_expr(self, expr);
+    }
+}
+
+/// Visit every item of a package.
+pub fn walk_package<'ir, V: Visitor<'ir> + ?Sized>(visitor: &mut V, ir: &'ir PackageIr) {
+    for item in &ir.items {
+        visitor.visit_item(item);
+    }
+}
+
+pub fn walk_item<'ir, V: Visitor<'ir> + ?Sized>(visitor: &mut V, item: &'ir Item) {
+    match item {
+        Item::FuncDecl(func_decl) => visitor.visit_block(&func_decl.block),
+        Item::Const(decl) => visitor.visit_expr(&decl.expr),
+        Item::Static(decl) => visitor.visit_expr(&decl.expr),
+    }
+}
+
+pub fn walk_block<'ir, V: Visitor<'ir> + ?Sized>(visitor: &mut V, block: &'ir Block) {
+    for stmt in &block.statements {
+        visitor.visit_stmt(stmt);
+    }
+
+    visitor.visit_expr(&block.final_expr);
+}
+
+pub fn walk_stmt<'ir, V: Visitor<'ir> + ?Sized>(visitor: &mut V, stmt: &'ir Stmt) {
+    match stmt {
+        Stmt::Assign { expr, .. } | Stmt::Drop(expr) | Stmt::Return(expr) => {
+            visitor.visit_expr(expr);
+        }
+        Stmt::Store { place, expr } => {
+            visitor.visit_expr(place);
+            visitor.visit_expr(expr);
+        }
+    }
+}
+
+/// Visit the expressions in an expression, in the order they are evaluated.
+pub fn walk_expr<'ir, V: Visitor<'ir> + ?Sized>(visitor: &mut V, expr: &'ir Expr) {
+    match &expr.kind {
+        ExprKind::Constant(_) | ExprKind::Var(_) | ExprKind::Const(_) | ExprKind::Static(_) => {}
+
+        ExprKind::UnOp { expr, .. }
+        | ExprKind::AddressOf(expr)
+        | ExprKind::Deref(expr)
+        | ExprKind::Cast { expr, .. }
+        | ExprKind::Len(expr) => visitor.visit_expr(expr),
+
+        ExprKind::BinOp { lhs, rhs, .. } => {
+            visitor.visit_expr(lhs);
+            visitor.visit_expr(rhs);
+        }
+
+        ExprKind::Index { base, index } => {
+            visitor.visit_expr(base);
+            visitor.visit_expr(index);
+        }
+
+        ExprKind::Call { args: exprs, .. } | ExprKind::Array(exprs) => {
+            for expr in exprs {
+                visitor.visit_expr(expr);
+            }
+        }
+
+        ExprKind::Block(block) => visitor.visit_block(block),
+
+        ExprKind::If { cond, then, else_ } => {
+            visitor.visit_expr(cond);
+            visitor.visit_expr(then);
+            if let Some(else_) = else_ {
+                visitor.visit_expr(else_);
+            }
+        }
+    }
+}
@@ -0,0 +1,55 @@
+use bayou_interner::Interner;
+use bayou_ir::ir::*;
+use bayou_ir::symbols::Symbols;
+
+use crate::interp::{InterpError, Interpreter};
+
+/// Evaluates the initializers of `const` and `static` items,
+/// storing the results in their symbols.
+pub struct ConstEvaluator<'a> {
+    symbols: &'a mut Symbols,
+    interner: &'a Interner,
+}
+
+impl<'a> ConstEvaluator<'a> {
+    pub fn new(symbols: &'a mut Symbols, interner: &'a Interner) -> Self {
+        Self { symbols, interner }
+    }
+
+    pub fn run(self, ir: &PackageIr) -> Vec<InterpError> {
+        let mut interp = Interpreter::new(ir, self.symbols, self.interner);
+
+        let mut consts = vec![];
+        let mut statics = vec![];
+
+        for item in &ir.items {
+            match item {
+                Item::Const(decl) => {
+                    if let Some(value) = interp.eval_const(decl.id) {
+                        consts.push((decl.id, value));
+                    }
+                }
+
+                Item::Static(decl) => {
+                    if let Some(value) = interp.eval_static(decl.id) {
+                        statics.push((decl.id, value));
+                    }
+                }
+
+                Item::FuncDecl(_) => {}
+            }
+        }
+
+        let errors = interp.into_errors();
+
+        for (id, value) in consts {
+            self.symbols.consts[id].value = Some(value);
+        }
+
+        for (id, value) in statics {
+            self.symbols.statics[id].value = Some(value);
+        }
+
+        errors
+    }
+}
@@ -6,6 +6,9 @@ use bayou_session::sourcemap::SourceSpan;
 
 pub enum EntrypointError {
     Missing,
+    HasParams {
+        span: SourceSpan,
+    },
     WrongSignature {
         expected: Type,
 
@@ -19,14 +22,22 @@ impl IntoDiagnostic<()> for EntrypointError {
         match self {
             EntrypointError::Missing => Diagnostic::error().with_message("`main` function missing"),
 
+            EntrypointError::HasParams { span } => Diagnostic::error()
+                .with_message("main function can't take parameters")
+                .with_snippet(Snippet::primary(
+                    "main function has wrong signature",
+                    span.source_id,
+                    span.span,
+                )),
+
             EntrypointError::WrongSignature {
                 expected,
                 found,
                 found_span,
             } => Diagnostic::error()
                 .with_message(format!(
-                    "expected main function with return type {expected:?}, \
-                    but it returned type {found:?}"
+                    "expected main function with return type `{expected}`, \
+                    but it returned type `{found}`"
                 ))
                 .with_snippet(Snippet::primary(
                     "main function has wrong signature",
@@ -42,11 +53,17 @@ pub fn check_entrypoint(ir: &PackageIr, symbols: &Symbols) -> Result<(), Entrypo
 
     let func = &symbols.funcs[main_func_id];
 
+    if !func.params.is_empty() {
+        return Err(EntrypointError::HasParams {
+            span: func.ident.span,
+        });
+    }
+
     if func.ret_ty != Type::I64 {
         return Err(EntrypointError::WrongSignature {
             expected: Type::I64,
 
-            found: func.ret_ty,
+            found: func.ret_ty.clone(),
             found_span: func.ret_ty_span,
         });
     }
@@ -0,0 +1,242 @@
+use std::fmt;
+
+use bayou_ir::Type;
+
+use super::Value;
+
+/// An address in the interpreter's memory.
+///
+/// The upper 32 bits select an allocation, and the lower 32 bits are the
+/// offset into it, so addresses can be freely converted to and from integers.
+/// Zero is the null pointer.
+pub type Address = u64;
+
+const OFFSET_BITS: u32 = 32;
+
+/// The size of pointers, and of the length field of slices.
+pub const PTR_SIZE: usize = 8;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AllocKind {
+    /// A local variable or temporary, freed when its function returns.
+    Stack,
+
+    /// Memory from `malloc`, freed by `free`.
+    Heap,
+
+    /// The storage of a `static` item.
+    Static,
+
+    /// The bytes of a string literal.
+    ReadOnly,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MemoryError {
+    Null,
+    Dangling,
+    OutOfBounds,
+    ReadOnly,
+    InvalidFree,
+    InvalidValue,
+}
+
+impl fmt::Display for MemoryError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let message = match self {
+            Self::Null => "null pointer dereference",
+            Self::Dangling => "use of memory that has been freed",
+            Self::OutOfBounds => "access outside the bounds of an allocation",
+            Self::ReadOnly => "write to read-only memory",
+            Self::InvalidFree => "freed a pointer that wasn't returned by `malloc`",
+            Self::InvalidValue => "read an invalid value from memory",
+        };
+
+        f.write_str(message)
+    }
+}
+
+pub type MemoryResult<T> = Result<T, MemoryError>;
+
+struct Allocation {
+    bytes: Vec<u8>,
+    kind: AllocKind,
+}
+
+/// Byte-addressed memory with separately tracked allocations,
+/// so that invalid accesses are caught instead of corrupting other values.
+#[derive(Default)]
+pub struct Memory {
+    /// Freed allocations are kept as `None`, so their addresses are never reused.
+    allocs: Vec<Option<Allocation>>,
+}
+
+impl Memory {
+    pub fn allocate(&mut self, size: usize, kind: AllocKind) -> Address {
+        self.allocs.push(Some(Allocation {
+            bytes: vec![0; size],
+            kind,
+        }));
+
+        (self.allocs.len() as u64) << OFFSET_BITS
+    }
+
+    pub fn allocate_bytes(&mut self, bytes: &[u8], kind: AllocKind) -> Address {
+        let addr = self.allocate(bytes.len(), kind);
+        self.allocs.last_mut().unwrap().as_mut().unwrap().bytes = bytes.to_vec();
+        addr
+    }
+
+    /// Free the allocation that starts at `addr`, which must have the given kind.
+    pub fn free(&mut self, addr: Address, kind: AllocKind) -> MemoryResult<()> {
+        let (index, offset) = split(addr).ok_or(MemoryError::InvalidFree)?;
+
+        let slot = self.allocs.get_mut(index).ok_or(MemoryError::InvalidFree)?;
+
+        match slot {
+            Some(alloc) if offset == 0 && alloc.kind == kind => {
+                *slot = None;
+                Ok(())
+            }
+            Some(_) => Err(MemoryError::InvalidFree),
+            None => Err(MemoryError::Dangling),
+        }
+    }
+
+    pub fn read(&self, addr: Address, len: usize) -> MemoryResult<&[u8]> {
+        let (alloc, offset) = self.resolve(addr, len)?;
+        Ok(&alloc.bytes[offset..offset + len])
+    }
+
+    pub fn write(&mut self, addr: Address, bytes: &[u8]) -> MemoryResult<()> {
+        let (index, offset) = split(addr).ok_or(MemoryError::Null)?;
+        self.resolve(addr, bytes.len())?;
+
+        let alloc = self.allocs[index].as_mut().unwrap();
+        if alloc.kind == AllocKind::ReadOnly {
+            return Err(MemoryError::ReadOnly);
+        }
+
+        alloc.bytes[offset..offset + bytes.len()].copy_from_slice(bytes);
+        Ok(())
+    }
+
+    fn resolve(&self, addr: Address, len: usize) -> MemoryResult<(&Allocation, usize)> {
+        let (index, offset) = split(addr).ok_or(MemoryError::Null)?;
+
+        let alloc = self
+            .allocs
+            .get(index)
+            .ok_or(MemoryError::OutOfBounds)?
+            .as_ref()
+            .ok_or(MemoryError::Dangling)?;
+
+        if offset + len > alloc.bytes.len() {
+            return Err(MemoryError::OutOfBounds);
+        }
+
+        Ok((alloc, offset))
+    }
+
+    pub fn load(&self, addr: Address, ty: &Type) -> MemoryResult<Value> {
+        let value = match ty {
+            Type::I64 => Value::I64(i64::from_le_bytes(self.read_array(addr)?)),
+            Type::F32 => Value::F32(f32::from_le_bytes(self.read_array(addr)?)),
+            Type::F64 => Value::F64(f64::from_le_bytes(self.read_array(addr)?)),
+            Type::Ptr(_) => Value::Ptr(u64::from_le_bytes(self.read_array(addr)?)),
+
+            Type::Bool => match self.read_array(addr)? {
+                [0] => Value::Bool(false),
+                [1] => Value::Bool(true),
+                _ => return Err(MemoryError::InvalidValue),
+            },
+
+            Type::Char => char::from_u32(u32::from_le_bytes(self.read_array(addr)?))
+                .map(Value::Char)
+                .ok_or(MemoryError::InvalidValue)?,
+
+            Type::Void | Type::Never => Value::Void,
+
+            Type::Array(element, len) => {
+                let stride = stride_of(element) as u64;
+                let elements = (0..*len)
+                    .map(|i| self.load(addr.wrapping_add(i * stride), element))
+                    .collect::<MemoryResult<_>>()?;
+
+                Value::Array(elements)
+            }
+
+            Type::Slice(_) | Type::Str => Value::Slice {
+                ptr: u64::from_le_bytes(self.read_array(addr)?),
+                len: i64::from_le_bytes(self.read_array(addr.wrapping_add(PTR_SIZE as u64))?),
+            },
+        };
+
+        Ok(value)
+    }
+
+    pub fn store(&mut self, addr: Address, ty: &Type, value: &Value) -> MemoryResult<()> {
+        match (ty, value) {
+            (_, Value::I64(n)) => self.write(addr, &n.to_le_bytes()),
+            (_, Value::F32(f)) => self.write(addr, &f.to_le_bytes()),
+            (_, Value::F64(f)) => self.write(addr, &f.to_le_bytes()),
+            (_, Value::Ptr(ptr)) => self.write(addr, &ptr.to_le_bytes()),
+            (_, Value::Bool(b)) => self.write(addr, &[*b as u8]),
+            (_, Value::Char(ch)) => self.write(addr, &(*ch as u32).to_le_bytes()),
+            (_, Value::Void) => Ok(()),
+
+            (Type::Array(element, _), Value::Array(elements)) => {
+                let stride = stride_of(element) as u64;
+                for (i, value) in elements.iter().enumerate() {
+                    self.store(addr.wrapping_add(i as u64 * stride), element, value)?;
+                }
+
+                Ok(())
+            }
+
+            (_, Value::Slice { ptr, len }) => {
+                self.write(addr, &ptr.to_le_bytes())?;
+                self.write(addr.wrapping_add(PTR_SIZE as u64), &len.to_le_bytes())
+            }
+
+            (_, Value::Array(_)) => unreachable!("array stored as `{ty}`"),
+        }
+    }
+
+    fn read_array<const N: usize>(&self, addr: Address) -> MemoryResult<[u8; N]> {
+        Ok(self.read(addr, N)?.try_into().unwrap())
+    }
+}
+
+fn split(addr: Address) -> Option<(usize, usize)> {
+    let index = (addr >> OFFSET_BITS) as usize;
+    let offset = (addr & ((1 << OFFSET_BITS) - 1)) as usize;
+
+    // allocation indices start at one so that zero can be null
+    Some((index.checked_sub(1)?, offset))
+}
+
+pub fn size_of(ty: &Type) -> usize {
+    match ty {
+        Type::I64 | Type::F64 | Type::Ptr(_) => 8,
+        Type::F32 | Type::Char => 4,
+        Type::Bool => 1,
+        Type::Void | Type::Never => 0,
+        Type::Array(element, len) => stride_of(element) * *len as usize,
+        Type::Slice(_) | Type::Str => PTR_SIZE + 8,
+    }
+}
+
+pub fn align_of(ty: &Type) -> usize {
+    match ty {
+        Type::Void | Type::Never => 1,
+        Type::Array(element, _) => align_of(element),
+        Type::Slice(_) | Type::Str => 8,
+        _ => size_of(ty),
+    }
+}
+
+/// The distance between consecutive elements of this type in an array.
+pub fn stride_of(ty: &Type) -> usize {
+    size_of(ty).next_multiple_of(align_of(ty))
+}
@@ -0,0 +1,980 @@
+//! A tree-walking interpreter for the typed IR.
+//!
+//! This is used to evaluate `const` and `static` initializers, to run programs
+//! without a backend, and as the reference semantics that the generated code is
+//! tested against. Unlike the generated code, it reports integer overflow and
+//! invalid memory accesses as errors.
+
+mod memory;
+
+use std::collections::HashMap;
+use std::io::Write;
+
+use bayou_interner::{Interner, Istr};
+use bayou_ir::ir::*;
+use bayou_ir::symbols::{ConstId, FuncId, LocalId, StaticId, Symbols};
+use bayou_ir::{BinOp, F32Bits, F64Bits, IdentWithSource, Type, UnOp};
+use bayou_session::diagnostics::prelude::*;
+use bayou_session::sourcemap::SourceSpan;
+
+pub use memory::{size_of, stride_of, Address, MemoryError, PTR_SIZE};
+use memory::{AllocKind, Memory};
+
+/// The default limit on the number of nested function calls.
+pub const DEFAULT_CALL_DEPTH_LIMIT: usize = 256;
+
+/// The extern functions that the interpreter provides itself.
+const SUPPORTED_EXTERNS: &[&str] = &["write", "malloc", "free"];
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Value {
+    I64(i64),
+    F32(f32),
+    F64(f64),
+    Bool(bool),
+    Char(char),
+    Ptr(Address),
+
+    /// A slice or a string, pointing to its first element.
+    Slice {
+        ptr: Address,
+        len: i64,
+    },
+
+    Array(Vec<Value>),
+    Void,
+}
+
+impl Value {
+    fn as_i64(&self) -> i64 {
+        match self {
+            Self::I64(n) => *n,
+            _ => unreachable!("expected `i64`, found {self:?}"),
+        }
+    }
+
+    fn as_bool(&self) -> bool {
+        match self {
+            Self::Bool(b) => *b,
+            _ => unreachable!("expected `bool`, found {self:?}"),
+        }
+    }
+
+    fn as_ptr(&self) -> Address {
+        match self {
+            Self::Ptr(ptr) => *ptr,
+            _ => unreachable!("expected pointer, found {self:?}"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum InterpError {
+    NotConst(SourceSpan),
+
+    /// An initializer evaluating to an array, which can't be stored in a constant yet.
+    ArrayConst(SourceSpan),
+
+    /// Constants that depend on their own values, in the
+    /// order that they depend on each other.
+    Cycle(Vec<IdentWithSource>),
+
+    DivisionByZero(SourceSpan),
+    Overflow(SourceSpan),
+
+    IndexOutOfBounds {
+        index: i64,
+        len: i64,
+        span: SourceSpan,
+    },
+
+    InvalidMemoryAccess {
+        error: MemoryError,
+        span: SourceSpan,
+    },
+
+    UnsupportedExtern {
+        func: IdentWithSource,
+        span: SourceSpan,
+    },
+
+    CallDepthExceeded {
+        limit: usize,
+        span: SourceSpan,
+    },
+}
+
+impl IntoDiagnostic<Interner> for InterpError {
+    fn into_diagnostic(self, interner: &Interner) -> Diagnostic {
+        match self {
+            Self::NotConst(span) => Diagnostic::error()
+                .with_message("initializer can't be evaluated at compile time")
+                .with_snippet(Snippet::primary(
+                    "not a constant expression",
+                    span.source_id,
+                    span.span,
+                )),
+
+            Self::ArrayConst(span) => Diagnostic::error()
+                .with_message("arrays aren't supported in `const` and `static` items yet")
+                .with_snippet(Snippet::primary(
+                    "this initializer is an array",
+                    span.source_id,
+                    span.span,
+                )),
+
+            Self::Cycle(cycle) => {
+                let name = |ident: &IdentWithSource| &interner[ident.istr];
+
+                let mut diagnostic = Diagnostic::error().with_message(format!(
+                    "cycle detected when evaluating constant `{}`",
+                    name(&cycle[0])
+                ));
+
+                for (i, ident) in cycle.iter().enumerate() {
+                    let next = &cycle[(i + 1) % cycle.len()];
+                    let label = format!("`{}` depends on `{}`", name(ident), name(next));
+
+                    diagnostic = diagnostic.with_snippet(if i == 0 {
+                        Snippet::primary(label, ident.span.source_id, ident.span.span)
+                    } else {
+                        Snippet::secondary(label, ident.span.source_id, ident.span.span)
+                    });
+                }
+
+                diagnostic
+            }
+
+            Self::DivisionByZero(span) => Diagnostic::error()
+                .with_message("division by zero")
+                .with_snippet(Snippet::primary(
+                    "attempted to divide by zero",
+                    span.source_id,
+                    span.span,
+                )),
+
+            Self::Overflow(span) => Diagnostic::error()
+                .with_message("arithmetic overflow")
+                .with_snippet(Snippet::primary(
+                    "this operation overflows",
+                    span.source_id,
+                    span.span,
+                )),
+
+            Self::IndexOutOfBounds { index, len, span } => Diagnostic::error()
+                .with_message("index out of bounds")
+                .with_snippet(Snippet::primary(
+                    format!("the index is {index} but the length is {len}"),
+                    span.source_id,
+                    span.span,
+                )),
+
+            Self::InvalidMemoryAccess { error, span } => Diagnostic::error()
+                .with_message("invalid memory access")
+                .with_snippet(Snippet::primary(
+                    error.to_string(),
+                    span.source_id,
+                    span.span,
+                )),
+
+            Self::UnsupportedExtern { func, span } => Diagnostic::error()
+                .with_message(format!(
+                    "extern function `{}` isn't supported by the interpreter",
+                    &interner[func.istr]
+                ))
+                .with_snippet(Snippet::primary("called here", span.source_id, span.span))
+                .with_snippet(Snippet::secondary(
+                    "declared here",
+                    func.span.source_id,
+                    func.span.span,
+                ))
+                .with_note(format!(
+                    "supported extern functions are {}",
+                    SUPPORTED_EXTERNS
+                        .iter()
+                        .map(|name| format!("`{name}`"))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                )),
+
+            Self::CallDepthExceeded { limit, span } => Diagnostic::error()
+                .with_message(format!("more than {limit} nested function calls"))
+                .with_snippet(Snippet::primary(
+                    "call depth limit exceeded here",
+                    span.source_id,
+                    span.span,
+                )),
+        }
+    }
+}
+
+/// Why evaluation stopped before producing a value.
+enum Unwind {
+    /// A `return` statement, which unwinds to the enclosing call.
+    Return(Value),
+
+    Error(InterpError),
+
+    /// An error has already been recorded, such as for a constant that failed.
+    Failed,
+}
+
+impl From<InterpError> for Unwind {
+    fn from(error: InterpError) -> Self {
+        Self::Error(error)
+    }
+}
+
+type InterpResult<T> = Result<T, Unwind>;
+
+#[derive(Default)]
+struct Frame {
+    locals: HashMap<LocalId, Address>,
+
+    /// Everything allocated on the stack by this frame, including locals.
+    allocs: Vec<Address>,
+}
+
+pub struct Interpreter<'a> {
+    ir: &'a PackageIr,
+    symbols: &'a Symbols,
+    interner: &'a Interner,
+
+    funcs: HashMap<FuncId, &'a FuncDecl>,
+    const_initializers: HashMap<ConstId, &'a Expr>,
+    static_initializers: HashMap<StaticId, &'a Expr>,
+
+    memory: Memory,
+    frames: Vec<Frame>,
+    call_depth_limit: usize,
+
+    /// Constants without a value in their symbol, evaluated on demand.
+    /// `None` if evaluation failed.
+    const_values: HashMap<ConstId, Option<Value>>,
+
+    /// Constants that are currently being evaluated, used to find cycles.
+    const_stack: Vec<ConstId>,
+
+    /// How many `const` or `static` initializers are being evaluated,
+    /// which can't touch statics or call extern functions.
+    const_context: usize,
+
+    statics: HashMap<StaticId, Address>,
+    strings: HashMap<Istr, Address>,
+
+    stdout: Box<dyn Write + 'a>,
+    stderr: Box<dyn Write + 'a>,
+
+    errors: Vec<InterpError>,
+}
+
+impl<'a> Interpreter<'a> {
+    pub fn new(ir: &'a PackageIr, symbols: &'a Symbols, interner: &'a Interner) -> Self {
+        let mut funcs = HashMap::new();
+        let mut const_initializers = HashMap::new();
+        let mut static_initializers = HashMap::new();
+
+        for item in &ir.items {
+            match item {
+                Item::FuncDecl(decl) => {
+                    funcs.insert(decl.id, decl);
+                }
+                Item::Const(decl) => {
+                    const_initializers.insert(decl.id, &decl.expr);
+                }
+                Item::Static(decl) => {
+                    static_initializers.insert(decl.id, &decl.expr);
+                }
+            }
+        }
+
+        Self {
+            ir,
+            symbols,
+            interner,
+
+            funcs,
+            const_initializers,
+            static_initializers,
+
+            memory: Memory::default(),
+            frames: vec![],
+            call_depth_limit: DEFAULT_CALL_DEPTH_LIMIT,
+
+            const_values: HashMap::new(),
+            const_stack: vec![],
+            const_context: 0,
+
+            statics: HashMap::new(),
+            strings: HashMap::new(),
+
+            stdout: Box::new(std::io::stdout()),
+            stderr: Box::new(std::io::stderr()),
+
+            errors: vec![],
+        }
+    }
+
+    /// Send output written to file descriptors 1 and 2 to the given writers,
+    /// instead of the process's stdout and stderr.
+    #[must_use]
+    pub fn with_output(mut self, stdout: impl Write + 'a, stderr: impl Write + 'a) -> Self {
+        self.stdout = Box::new(stdout);
+        self.stderr = Box::new(stderr);
+        self
+    }
+
+    #[must_use]
+    pub fn with_call_depth_limit(mut self, limit: usize) -> Self {
+        self.call_depth_limit = limit;
+        self
+    }
+
+    /// Run the `main` function, returning its result.
+    ///
+    /// # Panics
+    ///
+    /// Panics if the package has no `main` function.
+    pub fn run_main(&mut self) -> Result<i64, Vec<InterpError>> {
+        let main = self.ir.main_func.expect("no `main` function");
+        let span = self.symbols.funcs[main].ident.span;
+
+        let result = self.call(main, vec![], span);
+        let _ = self.stdout.flush();
+
+        match result {
+            Ok(value) => Ok(value.as_i64()),
+            Err(unwind) => {
+                self.record(unwind);
+                Err(std::mem::take(&mut self.errors))
+            }
+        }
+    }
+
+    /// Evaluate the initializer of a `const` item. If this fails,
+    /// the errors are available from [`Self::into_errors`].
+    pub fn eval_const(&mut self, id: ConstId) -> Option<Constant> {
+        let span = self.const_initializers[&id].span;
+        let ty = &self.symbols.consts[id].ty;
+
+        let value = self.const_value(id).ok()?;
+        self.value_to_constant(value, ty, span)
+    }
+
+    /// Evaluate the initializer of a `static` item. If this fails,
+    /// the errors are available from [`Self::into_errors`].
+    pub fn eval_static(&mut self, id: StaticId) -> Option<Constant> {
+        let span = self.static_initializers[&id].span;
+        let ty = &self.symbols.statics[id].ty;
+
+        let value = self.static_initializer(id).ok()?;
+        self.value_to_constant(value, ty, span)
+    }
+
+    pub fn into_errors(self) -> Vec<InterpError> {
+        self.errors
+    }
+
+    fn const_value(&mut self, id: ConstId) -> InterpResult<Value> {
+        if let Some(constant) = self.symbols.consts[id].value {
+            return Ok(self.constant_value(constant));
+        }
+
+        match self.const_values.get(&id) {
+            Some(Some(value)) => return Ok(value.clone()),
+            Some(None) => return Err(Unwind::Failed),
+            None => {}
+        }
+
+        if let Some(start) = self.const_stack.iter().position(|&other| other == id) {
+            let cycle = self.const_stack[start..]
+                .iter()
+                .map(|&id| self.symbols.consts[id].ident)
+                .collect();
+
+            // every constant in the cycle fails when this error propagates
+            return Err(InterpError::Cycle(cycle).into());
+        }
+
+        let initializer = self.const_initializers[&id];
+
+        self.const_stack.push(id);
+        let result = self.eval_initializer(initializer);
+        self.const_stack.pop();
+
+        self.const_values.insert(id, result.as_ref().ok().cloned());
+        result
+    }
+
+    fn static_initializer(&mut self, id: StaticId) -> InterpResult<Value> {
+        let initializer = self.static_initializers[&id];
+        self.eval_initializer(initializer)
+    }
+
+    /// Evaluate a `const` or `static` initializer in its own frame,
+    /// recording any error so that it is only reported once.
+    fn eval_initializer(&mut self, expr: &Expr) -> InterpResult<Value> {
+        self.const_context += 1;
+        self.frames.push(Frame::default());
+
+        let result = self.eval_expr(expr);
+
+        self.pop_frame();
+        self.const_context -= 1;
+
+        result.map_err(|unwind| {
+            self.record(unwind);
+            Unwind::Failed
+        })
+    }
+
+    fn record(&mut self, unwind: Unwind) {
+        match unwind {
+            Unwind::Error(error) => self.errors.push(error),
+            Unwind::Failed => {}
+            Unwind::Return(_) => unreachable!("return outside of a function"),
+        }
+    }
+
+    fn static_addr(&mut self, id: StaticId, span: SourceSpan) -> InterpResult<Address> {
+        if self.const_context > 0 {
+            return Err(InterpError::NotConst(span).into());
+        }
+
+        if let Some(&addr) = self.statics.get(&id) {
+            return Ok(addr);
+        }
+
+        let symbol = &self.symbols.statics[id];
+        let value = match symbol.value {
+            Some(constant) => self.constant_value(constant),
+            None => self.static_initializer(id)?,
+        };
+
+        let addr = self
+            .memory
+            .allocate(memory::size_of(&symbol.ty), AllocKind::Static);
+        self.store(addr, &symbol.ty, &value, span)?;
+
+        self.statics.insert(id, addr);
+        Ok(addr)
+    }
+
+    fn local_addr(&mut self, local: LocalId) -> Address {
+        let frame = self.frames.last_mut().expect("no stack frame");
+
+        if let Some(&addr) = frame.locals.get(&local) {
+            return addr;
+        }
+
+        let size = memory::size_of(&self.symbols.locals[local].ty);
+        let addr = self.memory.allocate(size, AllocKind::Stack);
+
+        frame.locals.insert(local, addr);
+        frame.allocs.push(addr);
+        addr
+    }
+
+    fn pop_frame(&mut self) {
+        let frame = self.frames.pop().expect("no stack frame");
+
+        for addr in frame.allocs {
+            self.memory
+                .free(addr, AllocKind::Stack)
+                .expect("stack allocation already freed");
+        }
+    }
+
+    fn constant_value(&mut self, constant: Constant) -> Value {
+        match constant {
+            Constant::I64(n) => Value::I64(n),
+            Constant::F32(f) => Value::F32(f.get()),
+            Constant::F64(f) => Value::F64(f.get()),
+            Constant::Bool(b) => Value::Bool(b),
+            Constant::Char(ch) => Value::Char(ch),
+            Constant::Void => Value::Void,
+
+            Constant::Str(istr) => {
+                let string = &self.interner[istr];
+                let ptr = *self.strings.entry(istr).or_insert_with(|| {
+                    self.memory
+                        .allocate_bytes(string.as_bytes(), AllocKind::ReadOnly)
+                });
+
+                Value::Slice {
+                    ptr,
+                    len: string.len() as i64,
+                }
+            }
+        }
+    }
+
+    fn value_to_constant(&mut self, value: Value, ty: &Type, span: SourceSpan) -> Option<Constant> {
+        let constant = match value {
+            Value::I64(n) => Constant::I64(n),
+            Value::F32(f) => Constant::F32(F32Bits::new(f)),
+            Value::F64(f) => Constant::F64(F64Bits::new(f)),
+            Value::Bool(b) => Constant::Bool(b),
+            Value::Char(ch) => Constant::Char(ch),
+            Value::Void => Constant::Void,
+
+            Value::Slice { ptr, len } if *ty == Type::Str => {
+                let string = self
+                    .memory
+                    .read(ptr, len as usize)
+                    .ok()
+                    .and_then(|bytes| std::str::from_utf8(bytes).ok());
+
+                match string {
+                    Some(string) => Constant::Str(self.interner.intern(string)),
+                    None => {
+                        self.errors.push(InterpError::NotConst(span));
+                        return None;
+                    }
+                }
+            }
+
+            Value::Array(_) => {
+                self.errors.push(InterpError::ArrayConst(span));
+                return None;
+            }
+
+            // there's no way to represent memory in a constant
+            Value::Ptr(_) | Value::Slice { .. } => {
+                self.errors.push(InterpError::NotConst(span));
+                return None;
+            }
+        };
+
+        Some(constant)
+    }
+
+    fn call(&mut self, func: FuncId, args: Vec<Value>, span: SourceSpan) -> InterpResult<Value> {
+        if self.frames.len() >= self.call_depth_limit {
+            return Err(InterpError::CallDepthExceeded {
+                limit: self.call_depth_limit,
+                span,
+            }
+            .into());
+        }
+
+        self.frames.push(Frame::default());
+
+        let symbol = &self.symbols.funcs[func];
+        let mut result = Ok(());
+
+        for (&param, arg) in symbol.params.iter().zip(&args) {
+            let addr = self.local_addr(param);
+            result =
+                result.and_then(|()| self.store(addr, &self.symbols.locals[param].ty, arg, span));
+        }
+
+        let result = result.and_then(|()| match self.eval_block(&self.funcs[&func].block) {
+            Err(Unwind::Return(value)) => Ok(value),
+            result => result,
+        });
+
+        self.pop_frame();
+        result
+    }
+
+    fn call_extern(
+        &mut self,
+        func: FuncId,
+        args: Vec<Value>,
+        span: SourceSpan,
+    ) -> InterpResult<Value> {
+        if self.const_context > 0 {
+            return Err(InterpError::NotConst(span).into());
+        }
+
+        let symbol = &self.symbols.funcs[func];
+
+        let result = match (&self.interner[symbol.ident.istr], args.as_slice()) {
+            ("write", [fd, buf, len]) => {
+                let bytes = self
+                    .memory
+                    .read(buf.as_ptr(), len.as_i64() as usize)
+                    .map_err(|error| InterpError::InvalidMemoryAccess { error, span })?;
+
+                let written = match fd.as_i64() {
+                    1 => self.stdout.write_all(bytes),
+                    2 => self.stderr.write_all(bytes),
+                    _ => return Ok(Value::I64(-1)),
+                };
+
+                Value::I64(if written.is_ok() {
+                    bytes.len() as i64
+                } else {
+                    -1
+                })
+            }
+
+            ("malloc", [size]) => match usize::try_from(size.as_i64()) {
+                Ok(size) => Value::Ptr(self.memory.allocate(size, AllocKind::Heap)),
+                Err(_) => Value::Ptr(0),
+            },
+
+            ("free", [ptr]) => {
+                let ptr = ptr.as_ptr();
+
+                // like C, freeing null does nothing
+                if ptr != 0 {
+                    self.memory
+                        .free(ptr, AllocKind::Heap)
+                        .map_err(|error| InterpError::InvalidMemoryAccess { error, span })?;
+                }
+
+                Value::Void
+            }
+
+            _ => {
+                return Err(InterpError::UnsupportedExtern {
+                    func: symbol.ident,
+                    span,
+                }
+                .into())
+            }
+        };
+
+        // the declaration might ignore the return value
+        if symbol.ret_ty == Type::Void {
+            Ok(Value::Void)
+        } else {
+            Ok(result)
+        }
+    }
+
+    fn eval_block(&mut self, block: &Block) -> InterpResult<Value> {
+        for stmt in &block.statements {
+            self.eval_stmt(stmt)?;
+        }
+
+        self.eval_expr(&block.final_expr)
+    }
+
+    fn eval_stmt(&mut self, stmt: &Stmt) -> InterpResult<()> {
+        match stmt {
+            Stmt::Assign { local, expr } => {
+                let value = self.eval_expr(expr)?;
+                let addr = self.local_addr(*local);
+                self.store(addr, &self.symbols.locals[*local].ty, &value, expr.span)?;
+            }
+
+            Stmt::Store { place, expr } => {
+                // like Rust, evaluate the value before the place
+                let value = self.eval_expr(expr)?;
+                let addr = self.eval_place(place)?;
+                self.store(addr, place.ty.as_ref().unwrap(), &value, place.span)?;
+            }
+
+            Stmt::Drop(expr) => {
+                self.eval_expr(expr)?;
+            }
+
+            Stmt::Return(expr) => {
+                let value = self.eval_expr(expr)?;
+                return Err(Unwind::Return(value));
+            }
+        }
+
+        Ok(())
+    }
+
+    fn eval_expr(&mut self, expr: &Expr) -> InterpResult<Value> {
+        match &expr.kind {
+            ExprKind::Constant(constant) => Ok(self.constant_value(*constant)),
+            ExprKind::Const(id) => self.const_value(*id),
+
+            ExprKind::Var(_)
+            | ExprKind::Static(_)
+            | ExprKind::Deref(_)
+            | ExprKind::Index { .. } => {
+                let addr = self.eval_place(expr)?;
+                self.load(addr, expr.ty.as_ref().unwrap(), expr.span)
+            }
+
+            ExprKind::UnOp { op, expr: operand } => {
+                let value = self.eval_expr(operand)?;
+                self.eval_unop(*op, value, expr.span)
+            }
+
+            ExprKind::BinOp { op, lhs, rhs } => self.eval_binop(*op, lhs, rhs, expr.span),
+
+            ExprKind::AddressOf(place) => Ok(Value::Ptr(self.eval_place(place)?)),
+
+            ExprKind::Cast { expr: inner, ty } => {
+                let value = self.eval_expr(inner)?;
+                Ok(cast(value, inner.ty.as_ref().unwrap(), ty))
+            }
+
+            ExprKind::Call { func, args } => {
+                let args = args
+                    .iter()
+                    .map(|arg| self.eval_expr(arg))
+                    .collect::<InterpResult<Vec<_>>>()?;
+
+                if self.symbols.funcs[*func].is_extern {
+                    self.call_extern(*func, args, expr.span)
+                } else {
+                    self.call(*func, args, expr.span)
+                }
+            }
+
+            ExprKind::Array(elements) => elements
+                .iter()
+                .map(|element| self.eval_expr(element))
+                .collect::<InterpResult<_>>()
+                .map(Value::Array),
+
+            ExprKind::Len(array) => match self.eval_expr(array)? {
+                Value::Array(elements) => Ok(Value::I64(elements.len() as i64)),
+                Value::Slice { len, .. } => Ok(Value::I64(len)),
+                value => unreachable!("length of {value:?}"),
+            },
+
+            ExprKind::Block(block) => self.eval_block(block),
+
+            ExprKind::If { cond, then, else_ } => {
+                if self.eval_expr(cond)?.as_bool() {
+                    self.eval_expr(then)
+                } else if let Some(else_) = else_ {
+                    self.eval_expr(else_)
+                } else {
+                    Ok(Value::Void)
+                }
+            }
+        }
+    }
+
+    /// Get the address of a place expression.
+    fn eval_place(&mut self, expr: &Expr) -> InterpResult<Address> {
+        match &expr.kind {
+            ExprKind::Var(local) => Ok(self.local_addr(*local)),
+            ExprKind::Static(id) => self.static_addr(*id, expr.span),
+            ExprKind::Deref(ptr) => Ok(self.eval_expr(ptr)?.as_ptr()),
+
+            ExprKind::Index { base, index } => {
+                let base_ty = base.ty.as_ref().unwrap();
+
+                let (ptr, len) = match base_ty {
+                    Type::Array(_, len) if base.kind.is_place() => (self.eval_place(base)?, *len),
+
+                    // arrays that aren't in memory yet are spilled to a temporary
+                    Type::Array(_, len) => {
+                        let value = self.eval_expr(base)?;
+                        let addr = self
+                            .memory
+                            .allocate(memory::size_of(base_ty), AllocKind::Stack);
+                        self.frames.last_mut().unwrap().allocs.push(addr);
+                        self.store(addr, base_ty, &value, base.span)?;
+
+                        (addr, *len)
+                    }
+
+                    Type::Slice(_) => match self.eval_expr(base)? {
+                        Value::Slice { ptr, len } => (ptr, len as u64),
+                        value => unreachable!("expected slice, found {value:?}"),
+                    },
+
+                    _ => unreachable!("indexing into `{base_ty}`"),
+                };
+
+                let index = self.eval_expr(index)?.as_i64();
+
+                if !(0..len as i64).contains(&index) {
+                    return Err(InterpError::IndexOutOfBounds {
+                        index,
+                        len: len as i64,
+                        span: expr.span,
+                    }
+                    .into());
+                }
+
+                let stride = stride_of(expr.ty.as_ref().unwrap()) as u64;
+                Ok(ptr.wrapping_add(index as u64 * stride))
+            }
+
+            _ => unreachable!("not a place expression"),
+        }
+    }
+
+    fn eval_unop(&mut self, op: UnOp, value: Value, span: SourceSpan) -> InterpResult<Value> {
+        let value = match (op, value) {
+            (UnOp::Negate, Value::I64(n)) => {
+                Value::I64(n.checked_neg().ok_or(InterpError::Overflow(span))?)
+            }
+            (UnOp::Negate, Value::F32(f)) => Value::F32(-f),
+            (UnOp::Negate, Value::F64(f)) => Value::F64(-f),
+
+            (UnOp::BitwiseInvert, Value::I64(n)) => Value::I64(!n),
+
+            (op, value) => unreachable!("invalid operand for `{op:?}`: {value:?}"),
+        };
+
+        Ok(value)
+    }
+
+    fn eval_binop(
+        &mut self,
+        op: BinOp,
+        lhs: &Expr,
+        rhs: &Expr,
+        span: SourceSpan,
+    ) -> InterpResult<Value> {
+        let pointee = lhs.ty.as_ref().and_then(Type::pointee).cloned();
+
+        let lhs = self.eval_expr(lhs)?;
+        let rhs = self.eval_expr(rhs)?;
+
+        if op.is_comparison() {
+            return Ok(Value::Bool(compare(op, &lhs, &rhs)));
+        }
+
+        let value = match (lhs, rhs) {
+            (Value::I64(a), Value::I64(b)) => {
+                if matches!(op, BinOp::Div | BinOp::Mod) && b == 0 {
+                    return Err(InterpError::DivisionByZero(span).into());
+                }
+
+                let result = match op {
+                    BinOp::Add => a.checked_add(b),
+                    BinOp::Sub => a.checked_sub(b),
+                    BinOp::Mul => a.checked_mul(b),
+                    BinOp::Div => a.checked_div(b),
+                    BinOp::Mod => a.checked_rem(b),
+                    BinOp::BitwiseAnd => Some(a & b),
+                    BinOp::BitwiseOr => Some(a | b),
+                    BinOp::BitwiseXor => Some(a ^ b),
+                    _ => unreachable!(),
+                };
+
+                Value::I64(result.ok_or(InterpError::Overflow(span))?)
+            }
+
+            (Value::F32(a), Value::F32(b)) => Value::F32(float_op(op, a, b)),
+            (Value::F64(a), Value::F64(b)) => Value::F64(float_op(op, a, b)),
+
+            // pointers to zero-sized types are offset in bytes
+            (Value::Ptr(a), Value::Ptr(b)) => {
+                let elem_size = stride_of(&pointee.unwrap()).max(1) as i64;
+                Value::I64((a.wrapping_sub(b) as i64) / elem_size)
+            }
+
+            (Value::Ptr(ptr), Value::I64(n)) => {
+                let elem_size = stride_of(&pointee.unwrap()).max(1) as i64;
+                let offset = n.wrapping_mul(elem_size) as u64;
+
+                Value::Ptr(match op {
+                    BinOp::Add => ptr.wrapping_add(offset),
+                    BinOp::Sub => ptr.wrapping_sub(offset),
+                    _ => unreachable!("invalid pointer arithmetic"),
+                })
+            }
+
+            (lhs, rhs) => unreachable!("invalid operands for `{op}`: {lhs:?} and {rhs:?}"),
+        };
+
+        Ok(value)
+    }
+
+    fn load(&self, addr: Address, ty: &Type, span: SourceSpan) -> InterpResult<Value> {
+        self.memory
+            .load(addr, ty)
+            .map_err(|error| InterpError::InvalidMemoryAccess { error, span }.into())
+    }
+
+    fn store(
+        &mut self,
+        addr: Address,
+        ty: &Type,
+        value: &Value,
+        span: SourceSpan,
+    ) -> InterpResult<()> {
+        self.memory
+            .store(addr, ty, value)
+            .map_err(|error| InterpError::InvalidMemoryAccess { error, span }.into())
+    }
+}
+
+fn float_op<F>(op: BinOp, a: F, b: F) -> F
+where
+    F: std::ops::Add<Output = F>
+        + std::ops::Sub<Output = F>
+        + std::ops::Mul<Output = F>
+        + std::ops::Div<Output = F>,
+{
+    match op {
+        BinOp::Add => a + b,
+        BinOp::Sub => a - b,
+        BinOp::Mul => a * b,
+        BinOp::Div => a / b,
+        _ => unreachable!("invalid float operation"),
+    }
+}
+
+/// Compare two values, with the same semantics as the generated code.
+fn compare(op: BinOp, lhs: &Value, rhs: &Value) -> bool {
+    fn ordered<T: PartialOrd>(op: BinOp, a: T, b: T) -> bool {
+        match op {
+            BinOp::Eq => a == b,
+            BinOp::NotEq => a != b,
+            BinOp::Gt => a > b,
+            BinOp::Lt => a < b,
+            BinOp::GtEq => a >= b,
+            BinOp::LtEq => a <= b,
+            _ => unreachable!("not a comparison"),
+        }
+    }
+
+    match (lhs, rhs) {
+        (Value::I64(a), Value::I64(b)) => ordered(op, a, b),
+        (Value::F32(a), Value::F32(b)) => ordered(op, a, b),
+        (Value::F64(a), Value::F64(b)) => ordered(op, a, b),
+        (Value::Bool(a), Value::Bool(b)) => ordered(op, a, b),
+        (Value::Char(a), Value::Char(b)) => ordered(op, a, b),
+        (Value::Ptr(a), Value::Ptr(b)) => ordered(op, a, b),
+        _ => unreachable!("can't compare {lhs:?} and {rhs:?}"),
+    }
+}
+
+/// Cast a value to the given type, with the same semantics as the generated code.
+fn cast(value: Value, from: &Type, to: &Type) -> Value {
+    match (value, to) {
+        (value, to) if from == to => value,
+
+        // array pointer to slice
+        (Value::Ptr(ptr), Type::Slice(_)) => {
+            let Some(Type::Array(_, len)) = from.pointee() else {
+                unreachable!("cast from `{from}` to slice");
+            };
+
+            Value::Slice {
+                ptr,
+                len: *len as i64,
+            }
+        }
+
+        // slice or string to element pointer
+        (Value::Slice { ptr, .. }, Type::Ptr(_)) => Value::Ptr(ptr),
+
+        (Value::Ptr(ptr), Type::Ptr(_)) => Value::Ptr(ptr),
+        (Value::Ptr(ptr), Type::I64) => Value::I64(ptr as i64),
+        (Value::I64(n), Type::Ptr(_)) => Value::Ptr(n as u64),
+
+        (Value::I64(n), Type::F32) => Value::F32(n as f32),
+        (Value::I64(n), Type::F64) => Value::F64(n as f64),
+
+        // `as` saturates, like the generated code
+        (Value::F32(f), Type::I64) => Value::I64(f as i64),
+        (Value::F64(f), Type::I64) => Value::I64(f as i64),
+
+        (Value::F32(f), Type::F64) => Value::F64(f as f64),
+        (Value::F64(f), Type::F32) => Value::F32(f as f32),
+
+        (Value::Bool(b), Type::I64) => Value::I64(b as i64),
+        (Value::Char(ch), Type::I64) => Value::I64(ch as i64),
+
+        (value, to) => unreachable!("invalid cast of {value:?} from `{from}` to `{to}`"),
+    }
+}
@@ -1,4 +1,7 @@
+pub mod const_eval;
 pub mod entry_point;
+pub mod interp;
+pub mod lints;
 pub mod type_check;
 
 // use std::ops::ControlFlow;
@@ -0,0 +1,108 @@
+//! Lints, which warn about code that is valid but probably a mistake.
+//!
+//! The name of a lint is the id of its diagnostics, so that its level can be set,
+//! from the `[lints]` table of the package manifest.
+
+use std::collections::HashSet;
+
+use bayou_interner::{Interner, Istr};
+use bayou_ir::ir::{Expr, ExprKind, Item, PackageIr, Stmt};
+use bayou_ir::symbols::{LocalId, Symbols};
+use bayou_ir::visit::{self, Visitor};
+use bayou_session::diagnostics::prelude::*;
+use bayou_session::sourcemap::SourceSpan;
+
+pub const UNUSED_VARIABLES: &str = "unused_variables";
+
+/// The names of every lint.
+pub const LINTS: &[&str] = &[UNUSED_VARIABLES];
+
+pub enum Lint {
+    UnusedVariable { ident: Istr, span: SourceSpan },
+}
+
+impl IntoDiagnostic<Interner> for Lint {
+    fn into_diagnostic(self, interner: &Interner) -> Diagnostic {
+        match self {
+            Lint::UnusedVariable { ident, span } => Diagnostic::warning()
+                .with_message(format!("unused variable `{}`", &interner[ident]))
+                .with_id(UNUSED_VARIABLES)
+                .with_snippet(Snippet::primary(
+                    "this variable is never read",
+                    span.source_id,
+                    span.span,
+                ))
+                .with_note("variables starting with an underscore are allowed to be unused"),
+        }
+    }
+}
+
+/// Find parameters and local variables that are never read.
+pub fn unused_variables(ir: &PackageIr, symbols: &Symbols, interner: &Interner) -> Vec<Lint> {
+    let mut finder = UnusedVariables::default();
+
+    for item in &ir.items {
+        if let Item::FuncDecl(decl) = item {
+            for &param in &symbols.funcs[decl.id].params {
+                finder.declare(param);
+            }
+        }
+
+        finder.visit_item(item);
+    }
+
+    finder
+        .declared
+        .into_iter()
+        .filter(|id| !finder.used.contains(id))
+        .map(|id| &symbols.locals[id].ident)
+        .filter(|ident| !interner[ident.istr].starts_with('_'))
+        .map(|ident| Lint::UnusedVariable {
+            ident: ident.istr,
+            span: ident.span,
+        })
+        .collect()
+}
+
+#[derive(Default)]
+struct UnusedVariables {
+    /// Locals in the order they are declared, for a stable order of warnings.
+    declared: Vec<LocalId>,
+    declared_set: HashSet<LocalId>,
+
+    used: HashSet<LocalId>,
+}
+
+impl UnusedVariables {
+    fn declare(&mut self, local: LocalId) {
+        if self.declared_set.insert(local) {
+            self.declared.push(local);
+        }
+    }
+}
+
+impl Visitor<'_> for UnusedVariables {
+    fn visit_stmt(&mut self, stmt: &Stmt) {
+        // assigning to a local doesn't read it
+        match stmt {
+            Stmt::Assign { local, .. } => self.declare(*local),
+
+            Stmt::Store { place, expr } if matches!(place.kind, ExprKind::Var(_)) => {
+                self.visit_expr(expr);
+                return;
+            }
+
+            _ => {}
+        }
+
+        visit::walk_stmt(self, stmt);
+    }
+
+    fn visit_expr(&mut self, expr: &Expr) {
+        if let ExprKind::Var(local) = expr.kind {
+            self.used.insert(local);
+        }
+
+        visit::walk_expr(self, expr);
+    }
+}
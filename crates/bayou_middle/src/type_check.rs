@@ -12,6 +12,45 @@ pub enum TypeError {
         found: Type,
         found_span: SourceSpan,
     },
+
+    BinOpMismatch {
+        op: BinOp,
+
+        lhs: Type,
+        rhs: Type,
+
+        span: SourceSpan,
+    },
+
+    NotAPointer {
+        found: Type,
+        found_span: SourceSpan,
+    },
+
+    NotAPlace(SourceSpan),
+
+    NotAnArray {
+        found: Type,
+        found_span: SourceSpan,
+    },
+
+    EmptyArray(SourceSpan),
+
+    InvalidCast {
+        from: Type,
+        to: Type,
+
+        span: SourceSpan,
+    },
+
+    ArgCountMismatch {
+        expected: usize,
+        found: usize,
+
+        span: SourceSpan,
+    },
+
+    ReturnOutsideFunc(SourceSpan),
 }
 
 impl IntoDiagnostic<()> for TypeError {
@@ -24,7 +63,7 @@ impl IntoDiagnostic<()> for TypeError {
                 found_span,
             } => {
                 let mut diagnostic = Diagnostic::error()
-                    .with_message(format!("expected type {expected:?}, found type {found:?}"))
+                    .with_message(format!("expected type `{expected}`, found type `{found}`"))
                     .with_snippet(Snippet::primary(
                         "unexpected type",
                         found_span.source_id,
@@ -41,6 +80,70 @@ impl IntoDiagnostic<()> for TypeError {
 
                 diagnostic
             }
+
+            TypeError::BinOpMismatch { op, lhs, rhs, span } => Diagnostic::error()
+                .with_message(format!(
+                    "can't apply operator `{op}` to types `{lhs}` and `{rhs}`"
+                ))
+                .with_snippet(Snippet::primary(
+                    "invalid operands",
+                    span.source_id,
+                    span.span,
+                )),
+
+            TypeError::NotAPointer { found, found_span } => Diagnostic::error()
+                .with_message(format!("can't dereference value of type `{found}`"))
+                .with_snippet(Snippet::primary(
+                    "not a pointer",
+                    found_span.source_id,
+                    found_span.span,
+                )),
+
+            TypeError::NotAPlace(span) => Diagnostic::error()
+                .with_message("expression doesn't refer to a memory location")
+                .with_snippet(Snippet::primary(
+                    "expected a variable or a dereference",
+                    span.source_id,
+                    span.span,
+                )),
+
+            TypeError::NotAnArray { found, found_span } => Diagnostic::error()
+                .with_message(format!("expected an array or slice, found type `{found}`"))
+                .with_snippet(Snippet::primary(
+                    "not an array or slice",
+                    found_span.source_id,
+                    found_span.span,
+                )),
+
+            TypeError::EmptyArray(span) => Diagnostic::error()
+                .with_message("can't infer the type of an empty array")
+                .with_snippet(Snippet::primary("empty array", span.source_id, span.span)),
+
+            TypeError::InvalidCast { from, to, span } => Diagnostic::error()
+                .with_message(format!("can't cast type `{from}` to type `{to}`"))
+                .with_snippet(Snippet::primary("invalid cast", span.source_id, span.span)),
+
+            TypeError::ArgCountMismatch {
+                expected,
+                found,
+                span,
+            } => Diagnostic::error()
+                .with_message(format!(
+                    "expected {expected} argument(s), found {found} argument(s)"
+                ))
+                .with_snippet(Snippet::primary(
+                    "wrong number of arguments",
+                    span.source_id,
+                    span.span,
+                )),
+
+            TypeError::ReturnOutsideFunc(span) => Diagnostic::error()
+                .with_message("can't return from outside of a function")
+                .with_snippet(Snippet::primary(
+                    "return outside of a function",
+                    span.source_id,
+                    span.span,
+                )),
         }
     }
 }
@@ -64,6 +167,16 @@ impl<'a> TypeChecker<'a> {
                 Item::FuncDecl(func_decl) => {
                     self.check_func_decl(func_decl);
                 }
+                Item::Const(decl) => {
+                    let symbol = &self.symbols.consts[decl.id];
+                    let (ty, ty_span) = (symbol.ty.clone(), symbol.ty_span);
+                    self.check_global_var_decl(&mut decl.expr, ty, ty_span);
+                }
+                Item::Static(decl) => {
+                    let symbol = &self.symbols.statics[decl.id];
+                    let (ty, ty_span) = (symbol.ty.clone(), symbol.ty_span);
+                    self.check_global_var_decl(&mut decl.expr, ty, ty_span);
+                }
             }
         }
 
@@ -72,12 +185,12 @@ impl<'a> TypeChecker<'a> {
 
     fn check_func_decl(&mut self, func_decl: &mut FuncDecl) {
         let (block_type, block_type_span) =
-            self.check_block_expr(&mut func_decl.block, func_decl.id);
+            self.check_block_expr(&mut func_decl.block, Some(func_decl.id));
 
         let func_symbol = &self.symbols.funcs[func_decl.id];
         if let Some(block_type) = block_type {
             self.check_types_match(
-                func_symbol.ret_ty,
+                func_symbol.ret_ty.clone(),
                 Some(func_symbol.ret_ty_span),
                 block_type,
                 block_type_span,
@@ -85,15 +198,38 @@ impl<'a> TypeChecker<'a> {
         }
     }
 
+    fn check_global_var_decl(&mut self, expr: &mut Expr, ty: Type, ty_span: SourceSpan) {
+        self.check_expr_expecting(expr, &ty, None);
+
+        if let Some(expr_ty) = expr.ty.clone() {
+            self.check_types_match(ty, Some(ty_span), expr_ty, expr.span);
+        }
+    }
+
     // FIXME: create a FuncTypeChecker struct that stores function information so we don't have to pass it around.
-    fn check_stmt(&mut self, stmt: &mut Stmt, func_id: FuncId) {
+    fn check_stmt(&mut self, stmt: &mut Stmt, func_id: Option<FuncId>) {
         match stmt {
             Stmt::Assign { local, expr } => {
-                self.check_expr(expr, func_id);
+                let local_ty = self.symbols.locals[*local].ty.clone();
+                self.check_expr_expecting(expr, &local_ty, func_id);
 
                 let local = &self.symbols.locals[*local];
-                if let Some(ty) = expr.ty {
-                    self.check_types_match(local.ty, Some(local.ty_span), ty, expr.span);
+                if let Some(ty) = expr.ty.clone() {
+                    self.check_types_match(local.ty.clone(), Some(local.ty_span), ty, expr.span);
+                }
+            }
+
+            Stmt::Store { place, expr } => {
+                self.check_expr(place, func_id);
+                self.check_expr(expr, func_id);
+
+                if !place.kind.is_place() {
+                    self.errors.push(TypeError::NotAPlace(place.span));
+                    return;
+                }
+
+                if let (Some(place_ty), Some(ty)) = (place.ty.clone(), expr.ty.clone()) {
+                    self.check_types_match(place_ty, Some(place.span), ty, expr.span);
                 }
             }
 
@@ -103,10 +239,16 @@ impl<'a> TypeChecker<'a> {
 
             Stmt::Return(expr) => {
                 self.check_expr(expr, func_id);
-                if let Some(ty) = expr.ty {
+
+                let Some(func_id) = func_id else {
+                    self.errors.push(TypeError::ReturnOutsideFunc(expr.span));
+                    return;
+                };
+
+                if let Some(ty) = expr.ty.clone() {
                     let func_symbol = &self.symbols.funcs[func_id];
                     self.check_types_match(
-                        func_symbol.ret_ty,
+                        func_symbol.ret_ty.clone(),
                         Some(func_symbol.ret_ty_span),
                         ty,
                         expr.span,
@@ -116,14 +258,42 @@ impl<'a> TypeChecker<'a> {
         }
     }
 
-    fn check_expr(&mut self, expr: &mut Expr, func_id: FuncId) {
+    /// Check an expression where a value of the `expected` type is needed,
+    /// which gives empty arrays in it their type.
+    fn check_expr_expecting(&mut self, expr: &mut Expr, expected: &Type, func_id: Option<FuncId>) {
+        match (&mut expr.kind, expected) {
+            (ExprKind::Array(elements), Type::Array(element_ty, _)) => {
+                expr.ty = self.check_array_expr(elements, Some(element_ty), expr.span, func_id);
+            }
+            _ => self.check_expr(expr, func_id),
+        }
+    }
+
+    fn check_expr(&mut self, expr: &mut Expr, func_id: Option<FuncId>) {
         expr.ty = match &mut expr.kind {
             ExprKind::Constant(constant) => Some(constant.ty()),
 
-            ExprKind::Var(local) => Some(self.symbols.locals[*local].ty),
+            ExprKind::Var(local) => Some(self.symbols.locals[*local].ty.clone()),
+            ExprKind::Const(id) => Some(self.symbols.consts[*id].ty.clone()),
+            ExprKind::Static(id) => Some(self.symbols.statics[*id].ty.clone()),
 
             ExprKind::UnOp { op, expr } => self.check_unop_expr(*op, expr, func_id),
-            ExprKind::BinOp { op, lhs, rhs } => self.check_binop_expr(*op, lhs, rhs, func_id),
+            ExprKind::BinOp { op, lhs, rhs } => {
+                self.check_binop_expr(*op, lhs, rhs, expr.span, func_id)
+            }
+
+            ExprKind::AddressOf(place) => self.check_address_of_expr(place, func_id),
+            ExprKind::Deref(ptr) => self.check_deref_expr(ptr, func_id),
+
+            ExprKind::Cast { expr: inner, ty } => {
+                self.check_cast_expr(inner, ty, expr.span, func_id)
+            }
+
+            ExprKind::Call { func, args } => self.check_call_expr(*func, args, expr.span, func_id),
+
+            ExprKind::Array(elements) => self.check_array_expr(elements, None, expr.span, func_id),
+            ExprKind::Index { base, index } => self.check_index_expr(base, index, func_id),
+            ExprKind::Len(array) => self.check_len_expr(array, func_id),
 
             ExprKind::Block(block) => self.check_block_expr(block, func_id).0,
 
@@ -133,16 +303,25 @@ impl<'a> TypeChecker<'a> {
         };
     }
 
-    fn check_unop_expr(&mut self, op: UnOp, expr: &mut Expr, func_id: FuncId) -> Option<Type> {
+    fn check_unop_expr(
+        &mut self,
+        op: UnOp,
+        expr: &mut Expr,
+        func_id: Option<FuncId>,
+    ) -> Option<Type> {
         self.check_expr(expr, func_id);
 
         match op {
-            UnOp::Negate => expr.ty.map(|ty| {
+            UnOp::Negate => expr.ty.clone().map(|ty| {
+                if ty.is_float() {
+                    return ty;
+                }
+
                 self.check_types_match(Type::I64, None, ty, expr.span);
                 Type::I64
             }),
 
-            UnOp::BitwiseInvert => expr.ty.map(|ty| {
+            UnOp::BitwiseInvert => expr.ty.clone().map(|ty| {
                 self.check_types_match(Type::I64, None, ty, expr.span);
                 Type::I64
             }),
@@ -154,37 +333,291 @@ impl<'a> TypeChecker<'a> {
         op: BinOp,
         lhs: &mut Expr,
         rhs: &mut Expr,
-        func_id: FuncId,
+        span: SourceSpan,
+        func_id: Option<FuncId>,
     ) -> Option<Type> {
         self.check_expr(lhs, func_id);
         self.check_expr(rhs, func_id);
 
-        let (exp_lhs, exp_rhs, out) = match op {
-            BinOp::Add => (Type::I64, Type::I64, Type::I64),
-            BinOp::Sub => (Type::I64, Type::I64, Type::I64),
-            BinOp::Mul => (Type::I64, Type::I64, Type::I64),
-            BinOp::Div => (Type::I64, Type::I64, Type::I64),
-            BinOp::Mod => (Type::I64, Type::I64, Type::I64),
-            BinOp::BitwiseAnd => (Type::I64, Type::I64, Type::I64),
-            BinOp::BitwiseOr => (Type::I64, Type::I64, Type::I64),
-            BinOp::BitwiseXor => (Type::I64, Type::I64, Type::I64),
+        let (lhs_ty, rhs_ty) = (lhs.ty.clone()?, rhs.ty.clone()?);
+
+        if op.is_comparison() {
+            return self.check_comparison(op, lhs_ty, rhs_ty, span);
+        }
+
+        match (op, lhs_ty, rhs_ty) {
+            // pointer arithmetic
+            (BinOp::Add | BinOp::Sub, ptr @ Type::Ptr(_), offset) => {
+                // pointer difference
+                if op == BinOp::Sub && offset == ptr {
+                    return Some(Type::I64);
+                }
+
+                self.check_types_match(Type::I64, None, offset, rhs.span);
+                Some(ptr)
+            }
+
+            (op, lhs_ty @ Type::Ptr(_), rhs_ty) | (op, lhs_ty, rhs_ty @ Type::Ptr(_)) => {
+                self.errors.push(TypeError::BinOpMismatch {
+                    op,
+                    lhs: lhs_ty,
+                    rhs: rhs_ty,
+                    span,
+                });
+                None
+            }
+
+            // float arithmetic
+            (
+                BinOp::Add | BinOp::Sub | BinOp::Mul | BinOp::Div,
+                lhs_ty @ (Type::F32 | Type::F64),
+                rhs_ty,
+            ) => {
+                self.check_types_match(lhs_ty.clone(), Some(lhs.span), rhs_ty, rhs.span);
+                Some(lhs_ty)
+            }
+
+            (op, lhs_ty, rhs_ty) if lhs_ty.is_float() || rhs_ty.is_float() => {
+                self.errors.push(TypeError::BinOpMismatch {
+                    op,
+                    lhs: lhs_ty,
+                    rhs: rhs_ty,
+                    span,
+                });
+                None
+            }
+
+            (_, lhs_ty, rhs_ty) => {
+                self.check_types_match(Type::I64, None, lhs_ty, lhs.span);
+                self.check_types_match(Type::I64, None, rhs_ty, rhs.span);
+                Some(Type::I64)
+            }
+        }
+    }
+
+    fn check_comparison(
+        &mut self,
+        op: BinOp,
+        lhs_ty: Type,
+        rhs_ty: Type,
+        span: SourceSpan,
+    ) -> Option<Type> {
+        let comparable = match &lhs_ty {
+            // `!` can be compared to anything, since the comparison never happens
+            _ if lhs_ty == Type::Never || rhs_ty == Type::Never => true,
+            _ if lhs_ty != rhs_ty => false,
+
+            Type::I64 | Type::F32 | Type::F64 | Type::Char | Type::Ptr(_) => true,
+            Type::Bool => matches!(op, BinOp::Eq | BinOp::NotEq),
+
+            _ => false,
+        };
+
+        if !comparable {
+            self.errors.push(TypeError::BinOpMismatch {
+                op,
+                lhs: lhs_ty,
+                rhs: rhs_ty,
+                span,
+            });
+        }
+
+        Some(Type::Bool)
+    }
+
+    fn check_address_of_expr(&mut self, place: &mut Expr, func_id: Option<FuncId>) -> Option<Type> {
+        self.check_expr(place, func_id);
+
+        if !place.kind.is_place() {
+            self.errors.push(TypeError::NotAPlace(place.span));
+            return None;
+        }
+
+        place.ty.clone().map(Type::ptr)
+    }
+
+    fn check_deref_expr(&mut self, ptr: &mut Expr, func_id: Option<FuncId>) -> Option<Type> {
+        self.check_expr(ptr, func_id);
+
+        match ptr.ty.clone()? {
+            Type::Ptr(pointee) => Some(*pointee),
+            Type::Never => Some(Type::Never),
+
+            found => {
+                self.errors.push(TypeError::NotAPointer {
+                    found,
+                    found_span: ptr.span,
+                });
+                None
+            }
+        }
+    }
+
+    fn check_cast_expr(
+        &mut self,
+        expr: &mut Expr,
+        ty: &Type,
+        span: SourceSpan,
+        func_id: Option<FuncId>,
+    ) -> Option<Type> {
+        self.check_expr(expr, func_id);
+
+        let from = expr.ty.clone()?;
+
+        let valid = match (&from, ty) {
+            (a, b) if a == b => true,
+            (Type::Never, _) => true,
+
+            (Type::Ptr(_), Type::Ptr(_)) => true,
+            (Type::Ptr(_), Type::I64) | (Type::I64, Type::Ptr(_)) => true,
+            (Type::Bool | Type::Char, Type::I64) => true,
+            (Type::I64 | Type::F32 | Type::F64, Type::I64 | Type::F32 | Type::F64) => true,
+
+            // array pointer to slice, and slice to element pointer
+            (Type::Ptr(array), Type::Slice(b)) => array.element() == Some(b),
+            (Type::Slice(a), Type::Ptr(b)) => a == b,
+
+            // strings can be passed to C as a pointer to their bytes
+            (Type::Str, Type::Ptr(_)) => true,
+
+            _ => false,
         };
 
-        if let Some(ty) = lhs.ty {
-            self.check_types_match(exp_lhs, None, ty, lhs.span);
+        if !valid {
+            self.errors.push(TypeError::InvalidCast {
+                from,
+                to: ty.clone(),
+                span,
+            });
+        }
+
+        Some(ty.clone())
+    }
+
+    fn check_call_expr(
+        &mut self,
+        func: FuncId,
+        args: &mut [Expr],
+        span: SourceSpan,
+        func_id: Option<FuncId>,
+    ) -> Option<Type> {
+        for arg in args.iter_mut() {
+            self.check_expr(arg, func_id);
+        }
+
+        let params = self.symbols.funcs[func].params.clone();
+
+        if params.len() != args.len() {
+            self.errors.push(TypeError::ArgCountMismatch {
+                expected: params.len(),
+                found: args.len(),
+                span,
+            });
+        }
+
+        for (&param, arg) in params.iter().zip(args.iter()) {
+            let param = &self.symbols.locals[param];
+            if let Some(ty) = arg.ty.clone() {
+                self.check_types_match(param.ty.clone(), Some(param.ty_span), ty, arg.span);
+            }
+        }
+
+        Some(self.symbols.funcs[func].ret_ty.clone())
+    }
+
+    fn check_array_expr(
+        &mut self,
+        elements: &mut [Expr],
+        expected_element_ty: Option<&Type>,
+        span: SourceSpan,
+        func_id: Option<FuncId>,
+    ) -> Option<Type> {
+        let mut element_ty: Option<(Type, SourceSpan)> = None;
+
+        for element in elements.iter_mut() {
+            match expected_element_ty {
+                Some(expected) => self.check_expr_expecting(element, expected, func_id),
+                None => self.check_expr(element, func_id),
+            }
+
+            let Some(ty) = element.ty.clone() else {
+                continue;
+            };
+
+            match &element_ty {
+                // the first element with a known type decides the element type
+                None | Some((Type::Never, _)) => element_ty = Some((ty, element.span)),
+
+                Some((expected, expected_span)) => {
+                    self.check_types_match(
+                        expected.clone(),
+                        Some(*expected_span),
+                        ty,
+                        element.span,
+                    );
+                }
+            }
         }
 
-        if let Some(ty) = rhs.ty {
-            self.check_types_match(exp_rhs, None, ty, rhs.span);
+        if elements.is_empty() {
+            let Some(expected) = expected_element_ty else {
+                self.errors.push(TypeError::EmptyArray(span));
+                return None;
+            };
+
+            return Some(Type::Array(Box::new(expected.clone()), 0));
         }
 
-        Some(out)
+        let (element_ty, _) = element_ty?;
+        Some(Type::Array(Box::new(element_ty), elements.len() as u64))
+    }
+
+    fn check_index_expr(
+        &mut self,
+        base: &mut Expr,
+        index: &mut Expr,
+        func_id: Option<FuncId>,
+    ) -> Option<Type> {
+        self.check_expr(base, func_id);
+        self.check_expr(index, func_id);
+
+        if let Some(ty) = index.ty.clone() {
+            self.check_types_match(Type::I64, None, ty, index.span);
+        }
+
+        self.check_array_like(base)
+    }
+
+    fn check_len_expr(&mut self, array: &mut Expr, func_id: Option<FuncId>) -> Option<Type> {
+        self.check_expr(array, func_id);
+
+        if array.ty == Some(Type::Str) {
+            return Some(Type::I64);
+        }
+
+        self.check_array_like(array).map(|_| Type::I64)
+    }
+
+    /// Check that an expression is an array or a slice, returning its element type.
+    fn check_array_like(&mut self, expr: &Expr) -> Option<Type> {
+        match expr.ty.clone()? {
+            Type::Array(element, _) | Type::Slice(element) => Some(*element),
+            Type::Never => Some(Type::Never),
+
+            found => {
+                self.errors.push(TypeError::NotAnArray {
+                    found,
+                    found_span: expr.span,
+                });
+                None
+            }
+        }
     }
 
     fn check_block_expr(
         &mut self,
         block: &mut Block,
-        func_id: FuncId,
+        func_id: Option<FuncId>,
     ) -> (Option<Type>, SourceSpan) {
         let mut diverging = false;
 
@@ -198,7 +631,7 @@ impl<'a> TypeChecker<'a> {
         if diverging {
             (Some(Type::Never), block.span)
         } else {
-            (block.final_expr.ty, block.final_expr.span)
+            (block.final_expr.ty.clone(), block.final_expr.span)
         }
     }
 
@@ -207,11 +640,11 @@ impl<'a> TypeChecker<'a> {
         cond: &mut Expr,
         then: &mut Expr,
         else_: Option<&mut Expr>,
-        func_id: FuncId,
+        func_id: Option<FuncId>,
     ) -> Option<Type> {
         self.check_expr(cond, func_id);
 
-        if let Some(ty) = cond.ty {
+        if let Some(ty) = cond.ty.clone() {
             self.check_types_match(Type::Bool, None, ty, cond.span);
         }
 
@@ -221,7 +654,7 @@ impl<'a> TypeChecker<'a> {
             if let Some(else_) = else_ {
                 self.check_expr(else_, func_id);
 
-                if let (Some(then_ty), Some(else_ty)) = (then.ty, else_.ty) {
+                if let (Some(then_ty), Some(else_ty)) = (then.ty.clone(), else_.ty.clone()) {
                     match (then_ty, else_ty) {
                         // If one side is never, assume the other side is the expected type
                         (Type::Never, ty) | (ty, Type::Never) => {
@@ -232,7 +665,7 @@ impl<'a> TypeChecker<'a> {
 
                         (a, b) => {
                             self.errors.push(TypeError::TypeMismatch {
-                                expected: a,
+                                expected: a.clone(),
                                 expected_span: Some(then.span),
                                 found: b,
                                 found_span: else_.span,
@@ -243,9 +676,16 @@ impl<'a> TypeChecker<'a> {
                         }
                     }
                 }
-            }
 
-            None
+                None
+            } else {
+                // without an `else`, the `if` has no value
+                if let Some(ty) = then.ty.clone() {
+                    self.check_types_match(Type::Void, None, ty, then.span);
+                }
+
+                Some(Type::Void)
+            }
         }
     }
 
@@ -256,7 +696,7 @@ impl<'a> TypeChecker<'a> {
         found: Type,
         found_span: SourceSpan,
     ) {
-        let types_match = match (expected, found) {
+        let types_match = match (&expected, &found) {
             (_, Type::Never) => true,
             (Type::Never, _) => false,
             (a, b) => a == b,
@@ -277,6 +717,8 @@ impl<'a> TypeChecker<'a> {
 fn stmt_is_diverging(stmt: &Stmt) -> bool {
     match stmt {
         Stmt::Return(_) => true,
-        Stmt::Drop(expr) | Stmt::Assign { expr, .. } => expr.ty == Some(Type::Never),
+        Stmt::Drop(expr) | Stmt::Assign { expr, .. } | Stmt::Store { expr, .. } => {
+            expr.ty == Some(Type::Never)
+        }
     }
 }
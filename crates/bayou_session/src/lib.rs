@@ -1,4 +1,5 @@
 pub mod diagnostics;
+pub mod lints;
 pub mod module_loader;
 pub mod sourcemap;
 
@@ -7,10 +8,30 @@ use std::{collections::HashMap, path::PathBuf};
 use bayou_interner::Interner;
 use diagnostics::DiagnosticEmitter;
 use diagnostics::*;
+use lints::LintLevels;
 use module_loader::{FsLoader, HashMapLoader, ModuleLoader};
 use sourcemap::SourceMap;
 use target_lexicon::Triple;
 
+bayou_interner::symbols! {
+    /// Strings the compiler compares identifiers with, which are interned by every
+    /// package interner, so they can be compared without looking up the identifier.
+    pub mod sym {
+        MAIN = "main",
+        PACKAGE = "package",
+        SELF = "self",
+        SUPER = "super",
+
+        I64 = "i64",
+        F32 = "f32",
+        F64 = "f64",
+        BOOL = "bool",
+        CHAR = "char",
+        STR = "str",
+        VOID = "void",
+    }
+}
+
 #[derive(thiserror::Error, Debug, Clone, Copy)]
 #[error("errors emitted")]
 pub struct ErrorsEmitted;
@@ -27,12 +48,19 @@ pub trait Session {
 
     fn emit_diagnostic(&mut self, diagnostic: Diagnostic);
 
+    fn lint_levels(&self) -> &LintLevels;
+
     fn report<Context>(
         &mut self,
         diagnostic: impl IntoDiagnostic<Context>,
         context: &Context,
     ) -> Result<(), ErrorsEmitted> {
-        let diagnostic = diagnostic.into_diagnostic(context);
+        let Some(diagnostic) = self
+            .lint_levels()
+            .apply(diagnostic.into_diagnostic(context))
+        else {
+            return Ok(());
+        };
         let kind = diagnostic.severity;
 
         self.emit_diagnostic(diagnostic);
@@ -56,7 +84,12 @@ pub trait Session {
         let mut had_error = false;
 
         for diagnostic in diagnostics {
-            let diagnostic = diagnostic.into_diagnostic(context);
+            let Some(diagnostic) = self
+                .lint_levels()
+                .apply(diagnostic.into_diagnostic(context))
+            else {
+                continue;
+            };
             had_error |= diagnostic.severity >= Severity::Error;
             self.emit_diagnostic(diagnostic);
         }
@@ -72,12 +105,66 @@ pub trait Session {
 /// Like [`Session`] but supporting code generation.
 pub trait CodegenSession: Session {
     fn target_triple(&self) -> &Triple;
+    fn codegen_options(&self) -> &CodegenOptions;
+}
+
+/// Options that affect code generation.
+#[derive(Default, Debug, Clone, Hash)]
+pub struct CodegenOptions {
+    /// Don't emit bounds checks when indexing into arrays and slices.
+    pub unchecked_indexing: bool,
+
+    pub opt_level: OptLevel,
+
+    /// Whether to generate position independent code. If not set, code is position
+    /// independent when compiling to an object file, but not when compiling with the JIT.
+    pub pic: Option<bool>,
+
+    /// Generate DWARF debug information. Only used when compiling to an object file.
+    pub debug_info: bool,
+
+    /// Extra Cranelift settings as name and value pairs,
+    /// which take precedence over the settings from the other options.
+    pub cranelift_settings: Vec<(String, String)>,
+}
+
+#[derive(Default, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum OptLevel {
+    /// No optimizations.
+    O0,
+    O1,
+    #[default]
+    O2,
+    /// Optimize for size as well as speed.
+    Os,
+}
+
+/// The kind of output a package is compiled into.
+#[derive(
+    Default, Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize,
+)]
+#[serde(rename_all = "lowercase")]
+pub enum CrateType {
+    /// An executable, which needs a `main` function.
+    #[default]
+    Bin,
+    /// A single object file.
+    Obj,
+    /// A static library, which is an archive of object files.
+    StaticLib,
+    /// A shared library that can be loaded by C programs.
+    Cdylib,
+    /// An object file of a dependency, which is linked into the packages that
+    /// depend on it. Packages are only compiled as this to be dependencies.
+    #[serde(skip)]
+    Lib,
 }
 
 /// Session for a single package compilation.
 #[derive(Debug)]
 pub struct PackageSession<S: Session + ?Sized> {
     pub name: String,
+    pub crate_type: CrateType,
     pub interner: Interner,
     pub module_loader: S::ModuleLoader,
 }
@@ -86,6 +173,7 @@ pub struct PackageSession<S: Session + ?Sized> {
 pub struct TestSession {
     pub diagnostics: Vec<Diagnostic>,
     pub source_map: SourceMap,
+    pub lint_levels: LintLevels,
 }
 
 impl TestSession {
@@ -93,6 +181,7 @@ impl TestSession {
         Self {
             diagnostics: vec![],
             source_map: SourceMap::default(),
+            lint_levels: LintLevels::default(),
         }
     }
 }
@@ -103,11 +192,16 @@ impl Session for TestSession {
 
     fn build_package_session(
         &self,
-        TestSessionConfig { name, modules }: Self::PackageConfig,
+        TestSessionConfig {
+            name,
+            crate_type,
+            modules,
+        }: Self::PackageConfig,
     ) -> PackageSession<Self> {
         PackageSession {
             name,
-            interner: Interner::new(),
+            crate_type,
+            interner: sym::interner(),
             module_loader: HashMapLoader { modules },
         }
     }
@@ -123,11 +217,16 @@ impl Session for TestSession {
     fn emit_diagnostic(&mut self, diagnostic: Diagnostic) {
         self.diagnostics.push(diagnostic);
     }
+
+    fn lint_levels(&self) -> &LintLevels {
+        &self.lint_levels
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct TestSessionConfig {
     pub name: String,
+    pub crate_type: CrateType,
     pub modules: HashMap<String, String>,
 }
 
@@ -135,6 +234,7 @@ impl TestSessionConfig {
     pub fn new(name: impl Into<String>, modules: impl Into<HashMap<String, String>>) -> Self {
         Self {
             name: name.into(),
+            crate_type: CrateType::Bin,
             modules: modules.into(),
         }
     }
@@ -143,17 +243,25 @@ impl TestSessionConfig {
 #[derive(Debug)]
 pub struct FullSession {
     pub target_triple: Triple,
+    pub codegen_options: CodegenOptions,
+    pub lint_levels: LintLevels,
 
     pub diagnostics: PrettyDiagnosticEmitter,
     pub source_map: SourceMap,
+
+    /// The number of diagnostics emitted so far, including warnings.
+    pub diagnostic_count: usize,
 }
 
 impl FullSession {
-    pub fn new(target_triple: Triple) -> Self {
+    pub fn new(target_triple: Triple, codegen_options: CodegenOptions) -> Self {
         Self {
             target_triple,
+            codegen_options,
+            lint_levels: LintLevels::default(),
             diagnostics: PrettyDiagnosticEmitter::default(),
             source_map: SourceMap::default(),
+            diagnostic_count: 0,
         }
     }
 }
@@ -164,12 +272,22 @@ impl Session for FullSession {
 
     fn build_package_session(
         &self,
-        FullSessionConfig { name, root_dir }: Self::PackageConfig,
+        FullSessionConfig {
+            name,
+            crate_type,
+            root_dir,
+            root_file,
+        }: Self::PackageConfig,
     ) -> PackageSession<Self> {
         PackageSession {
             name,
-            interner: Interner::new(),
-            module_loader: FsLoader { root_dir },
+            crate_type,
+            interner: sym::interner(),
+            module_loader: FsLoader {
+                root_dir,
+                root_file,
+                overlays: self.source_map.overlays().clone(),
+            },
         }
     }
 
@@ -184,6 +302,11 @@ impl Session for FullSession {
     fn emit_diagnostic(&mut self, diagnostic: Diagnostic) {
         self.diagnostics
             .emit_diagnostic(diagnostic, &self.source_map);
+        self.diagnostic_count += 1;
+    }
+
+    fn lint_levels(&self) -> &LintLevels {
+        &self.lint_levels
     }
 }
 
@@ -191,10 +314,17 @@ impl CodegenSession for FullSession {
     fn target_triple(&self) -> &Triple {
         &self.target_triple
     }
+
+    fn codegen_options(&self) -> &CodegenOptions {
+        &self.codegen_options
+    }
 }
 
 #[derive(Debug, Clone)]
 pub struct FullSessionConfig {
     pub name: String,
+    pub crate_type: CrateType,
     pub root_dir: PathBuf,
+    /// The file name of the root module, in the root directory.
+    pub root_file: PathBuf,
 }
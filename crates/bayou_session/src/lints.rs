@@ -0,0 +1,93 @@
+//! Lint levels, which decide how warnings are reported.
+//!
+//! A lint is a warning with a diagnostic id, like `unused_variables`.
+
+use std::collections::BTreeMap;
+
+use crate::diagnostics::{Diagnostic, Severity};
+
+/// The lint group containing every lint.
+pub const WARNINGS: &str = "warnings";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, serde::Serialize, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LintLevel {
+    /// Don't report the lint.
+    Allow,
+    /// Report the lint as a warning, which is the default.
+    Warn,
+    /// Report the lint as an error.
+    Deny,
+}
+
+#[derive(Default, Debug, Clone, Hash)]
+pub struct LintLevels {
+    levels: BTreeMap<String, LintLevel>,
+}
+
+impl LintLevels {
+    /// Set the level of a lint, or of every lint with [`WARNINGS`].
+    pub fn set(&mut self, lint: impl Into<String>, level: LintLevel) {
+        self.levels.insert(lint.into(), level);
+    }
+
+    /// Apply the level of a warning's lint, returning `None` if it's allowed.
+    /// The level of a lint takes precedence over the level of [`WARNINGS`].
+    pub fn apply(&self, mut diagnostic: Diagnostic) -> Option<Diagnostic> {
+        if diagnostic.severity != Severity::Warning {
+            return Some(diagnostic);
+        }
+
+        let level = diagnostic
+            .id
+            .as_deref()
+            .and_then(|id| self.levels.get(id))
+            .or_else(|| self.levels.get(WARNINGS));
+
+        match level {
+            Some(LintLevel::Allow) => None,
+            Some(LintLevel::Warn) | None => Some(diagnostic),
+            Some(LintLevel::Deny) => {
+                diagnostic.severity = Severity::Error;
+                Some(diagnostic)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::{LintLevel, LintLevels, WARNINGS};
+    use crate::diagnostics::{Diagnostic, Severity};
+
+    fn lint(id: &str) -> Diagnostic {
+        Diagnostic::warning().with_id(id)
+    }
+
+    #[test]
+    fn lint_levels() {
+        let mut levels = LintLevels::default();
+
+        let severity = |levels: &LintLevels, diagnostic| {
+            levels
+                .apply(diagnostic)
+                .map(|diagnostic| diagnostic.severity)
+        };
+
+        assert_eq!(severity(&levels, lint("a")), Some(Severity::Warning));
+
+        levels.set(WARNINGS, LintLevel::Deny);
+        levels.set("a", LintLevel::Allow);
+
+        assert_eq!(severity(&levels, lint("a")), None);
+        assert_eq!(severity(&levels, lint("b")), Some(Severity::Error));
+        assert_eq!(
+            severity(&levels, Diagnostic::warning()),
+            Some(Severity::Error)
+        );
+        assert_eq!(
+            severity(&levels, Diagnostic::error()),
+            Some(Severity::Error)
+        );
+    }
+}
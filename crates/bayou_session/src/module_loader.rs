@@ -6,7 +6,7 @@ use std::{
 };
 
 use bayou_diagnostic::Snippet;
-use bayou_interner::{Interner, Istr};
+use bayou_interner::{Intern, Interner, Istr};
 use serde::ser::SerializeStruct;
 
 use crate::{sourcemap::SourceSpan, Diagnostic, IntoDiagnostic};
@@ -50,7 +50,7 @@ impl ModulePath {
     /// # Panics
     /// Calling [`DisplayModulePath::fmt`] panics or produces an invalid result if any of
     /// the path components are not from this interner.
-    pub fn display<'a>(&'a self, interner: &'a Interner) -> DisplayModulePath<'a> {
+    pub fn display<'a>(&'a self, interner: &'a dyn Intern) -> DisplayModulePath<'a> {
         DisplayModulePath {
             path: self,
             interner,
@@ -58,10 +58,10 @@ impl ModulePath {
     }
 }
 
-#[derive(Debug, Clone)]
+#[derive(Clone, Copy)]
 pub struct DisplayModulePath<'a> {
     path: &'a ModulePath,
-    interner: &'a Interner,
+    interner: &'a dyn Intern,
 }
 
 impl Display for DisplayModulePath<'_> {
@@ -76,18 +76,24 @@ impl Display for DisplayModulePath<'_> {
     }
 }
 
-pub trait ModuleLoader {
+/// Loads the sources of modules, which can happen on multiple threads at once.
+pub trait ModuleLoader: Sync {
     fn load_module(
         &self,
         path: &ModulePath,
-        interner: &Interner,
+        interner: &dyn Intern,
     ) -> Result<String, ModuleLoaderError>;
+
+    /// The file that a module is loaded from, if modules are loaded from files.
+    fn module_file(&self, _path: &ModulePath, _interner: &dyn Intern) -> Option<PathBuf> {
+        None
+    }
 }
 
 #[derive(Debug)]
 pub struct ModuleLoaderError {
     pub path: ModulePath,
-    pub cause: Option<Box<dyn std::error::Error>>,
+    pub cause: Option<Box<dyn std::error::Error + Send + Sync>>,
 }
 
 impl IntoDiagnostic<(Option<SourceSpan>, &Interner)> for ModuleLoaderError {
@@ -130,23 +136,52 @@ impl serde::Serialize for ModuleLoaderError {
     }
 }
 
+/// The file of the root module, unless a package says otherwise.
+pub const DEFAULT_ROOT_FILE: &str = "main.by";
+
 #[derive(Debug, Clone)]
 pub struct FsLoader {
+    /// The directory of the root module, which submodules are loaded relative to.
     pub root_dir: PathBuf,
+    /// The file name of the root module.
+    pub root_file: PathBuf,
+
+    /// Sources to use instead of the files at these paths, from [`SourceMap::overlays`].
+    ///
+    /// [`SourceMap::overlays`]: crate::sourcemap::SourceMap::overlays
+    pub overlays: HashMap<PathBuf, String>,
+}
+
+impl FsLoader {
+    fn module_file_path(&self, path: &ModulePath, interner: &dyn Intern) -> PathBuf {
+        if path.components().is_empty() {
+            self.root_dir.join(&self.root_file)
+        } else {
+            module_path_to_pathbuf(path, &self.root_dir, interner)
+        }
+    }
 }
 
 impl ModuleLoader for FsLoader {
     fn load_module(
         &self,
         path: &ModulePath,
-        interner: &Interner,
+        interner: &dyn Intern,
     ) -> Result<String, ModuleLoaderError> {
-        let pathbuf = module_path_to_pathbuf(path, &self.root_dir, interner);
+        let pathbuf = self.module_file_path(path, interner);
+        if let Some(source) = self.overlays.get(&pathbuf) {
+            return Ok(source.clone());
+        }
+
         fs::read_to_string(&pathbuf).map_err(|io_error| ModuleLoaderError {
             path: path.clone(),
             cause: Some(Box::new(FsLoaderError { pathbuf, io_error })),
         })
     }
+
+    fn module_file(&self, path: &ModulePath, interner: &dyn Intern) -> Option<PathBuf> {
+        Some(self.module_file_path(path, interner))
+    }
 }
 
 #[derive(thiserror::Error, Debug)]
@@ -165,7 +200,7 @@ impl ModuleLoader for HashMapLoader {
     fn load_module(
         &self,
         path: &ModulePath,
-        interner: &Interner,
+        interner: &dyn Intern,
     ) -> Result<String, ModuleLoaderError> {
         let path_str = path.display(interner).to_string();
         self.modules
@@ -181,12 +216,12 @@ impl ModuleLoader for HashMapLoader {
 fn module_path_to_pathbuf(
     module_path: &ModulePath,
     root_dir: impl Into<PathBuf>,
-    interner: &Interner,
+    interner: &dyn Intern,
 ) -> PathBuf {
     let mut path: PathBuf = root_dir.into();
 
     match module_path.components() {
-        [] => path.push("main.by"),
+        [] => path.push(DEFAULT_ROOT_FILE),
 
         [parents @ .., name] => {
             for &parent in parents {
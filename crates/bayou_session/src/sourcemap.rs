@@ -1,8 +1,11 @@
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
+
 use bayou_diagnostic::{
     sources::{Cached, SourceMap as _},
     span::Span,
 };
-use bayou_utils::keyvec::{declare_key_type, KeyVec};
+use bayou_utils::keyvec::{declare_key_type, Key, KeyVec};
 
 declare_key_type! {
     #[derive(serde::Serialize)]
@@ -12,12 +15,19 @@ declare_key_type! {
 #[derive(Default, Debug, Clone)]
 pub struct SourceMap {
     inner: KeyVec<SourceId, Cached<Source>>,
+
+    /// The contents of files that are used instead of what is on disk,
+    /// like the unsaved buffers of an editor.
+    overlays: HashMap<PathBuf, String>,
 }
 
 #[derive(Debug, Clone)]
 pub struct Source {
     pub name: String,
     pub source: String,
+
+    /// The file the source was read from, if any.
+    pub path: Option<PathBuf>,
 }
 
 impl Source {
@@ -25,8 +35,15 @@ impl Source {
         Self {
             name: name.into(),
             source: source.into(),
+            path: None,
         }
     }
+
+    #[must_use]
+    pub fn with_path(mut self, path: impl Into<PathBuf>) -> Self {
+        self.path = Some(path.into());
+        self
+    }
 }
 
 impl SourceMap {
@@ -34,6 +51,33 @@ impl SourceMap {
         self.inner.insert(Cached::new(source))
     }
 
+    /// Every source, in the order they were inserted.
+    pub fn sources(&self) -> impl Iterator<Item = &Source> {
+        self.inner.iter().map(Cached::as_source)
+    }
+
+    /// The source read from a file, if there is one.
+    pub fn find_path(&self, path: &Path) -> Option<SourceId> {
+        self.inner
+            .iter()
+            .position(|source| source.as_source().path.as_deref() == Some(path))
+            .map(SourceId::from_usize)
+    }
+
+    /// Read the file at `path` as `source` instead of from disk, when modules are loaded
+    /// for a session with this source map. Replaces any overlay the file already has.
+    pub fn set_overlay(&mut self, path: impl Into<PathBuf>, source: impl Into<String>) {
+        self.overlays.insert(path.into(), source.into());
+    }
+
+    pub fn remove_overlay(&mut self, path: &Path) {
+        self.overlays.remove(path);
+    }
+
+    pub fn overlays(&self) -> &HashMap<PathBuf, String> {
+        &self.overlays
+    }
+
     #[allow(clippy::missing_panics_doc)]
     pub fn insert_and_get(&mut self, source: Source) -> (SourceId, &Cached<Source>) {
         let id = self.insert(source);
@@ -55,8 +99,8 @@ impl bayou_diagnostic::sources::Source for Source {
         &self.name
     }
 
-    fn path(&self) -> Option<&std::path::Path> {
-        None
+    fn path(&self) -> Option<&Path> {
+        self.path.as_deref()
     }
 
     fn source_str(&self) -> &str {
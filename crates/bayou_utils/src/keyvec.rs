@@ -4,7 +4,8 @@ use std::ops::{Deref, Index, IndexMut};
 
 pub struct KeyVec<K, V> {
     inner: Vec<V>,
-    _phantom: PhantomData<*const K>,
+    // `fn() -> K` so that the key type doesn't affect `Send` and `Sync`
+    _phantom: PhantomData<fn() -> K>,
 }
 
 impl<K: Key, V> KeyVec<K, V> {
@@ -27,7 +28,7 @@ impl<K: Key, V> KeyVec<K, V> {
         self.inner.get_mut(key.as_usize())
     }
 
-    pub fn iter_mut(&mut self) -> std::slice::IterMut<V> {
+    pub fn iter_mut(&mut self) -> std::slice::IterMut<'_, V> {
         self.inner.iter_mut()
     }
 }
@@ -1,4 +1,5 @@
 pub mod keyvec;
+pub mod parallel;
 pub mod peek;
 
 #[macro_export]
@@ -0,0 +1,115 @@
+//! Running work on multiple threads.
+
+use std::num::NonZeroUsize;
+use std::sync::atomic::{AtomicUsize, Ordering};
+use std::sync::Mutex;
+
+/// The number of threads to use for parallel work, which is the
+/// number of CPUs, or `BAYOU_THREADS` if it is set.
+pub fn thread_count() -> usize {
+    std::env::var("BAYOU_THREADS")
+        .ok()
+        .and_then(|threads| threads.parse::<NonZeroUsize>().ok())
+        .or_else(|| std::thread::available_parallelism().ok())
+        .map_or(1, NonZeroUsize::get)
+}
+
+/// Map every item on a pool of [`thread_count`] threads, returning the results in
+/// the order of the items, like [`map_with_threads`].
+///
+/// # Panics
+/// Panics if `init` or `f` panics on any thread.
+pub fn map_with<T, S, R>(
+    items: &[T],
+    init: impl Fn() -> S + Sync,
+    f: impl Fn(&mut S, &T) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    map_with_threads(thread_count(), items, init, f)
+}
+
+/// Map every item on a pool of `threads` threads, returning the results in the order
+/// of the items.
+///
+/// Each thread creates its own state with `init`, which is passed to `f` for
+/// every item the thread maps, so that it can be reused between items. Items
+/// are handed out one at a time, so slow items don't hold up the others.
+///
+/// Everything runs on the current thread if there is only one item or thread.
+///
+/// # Panics
+/// Panics if `init` or `f` panics on any thread.
+pub fn map_with_threads<T, S, R>(
+    threads: usize,
+    items: &[T],
+    init: impl Fn() -> S + Sync,
+    f: impl Fn(&mut S, &T) -> R + Sync,
+) -> Vec<R>
+where
+    T: Sync,
+    R: Send,
+{
+    let threads = threads.min(items.len());
+    if threads <= 1 {
+        let mut state = init();
+        return items.iter().map(|item| f(&mut state, item)).collect();
+    }
+
+    let next = AtomicUsize::new(0);
+    let results = Mutex::new(Vec::with_capacity(items.len()));
+
+    std::thread::scope(|scope| {
+        for _ in 0..threads {
+            scope.spawn(|| {
+                let mut state = init();
+
+                loop {
+                    let index = next.fetch_add(1, Ordering::Relaxed);
+                    let Some(item) = items.get(index) else {
+                        break;
+                    };
+
+                    let result = f(&mut state, item);
+                    results.lock().unwrap().push((index, result));
+                }
+            });
+        }
+    });
+
+    let mut results = results.into_inner().unwrap();
+    results.sort_unstable_by_key(|&(index, _)| index);
+    results.into_iter().map(|(_, result)| result).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::atomic::{AtomicUsize, Ordering};
+
+    use super::map_with;
+
+    #[test]
+    fn results_in_order() {
+        let items: Vec<usize> = (0..1000).collect();
+        let inits = AtomicUsize::new(0);
+
+        let results = map_with(
+            &items,
+            || inits.fetch_add(1, Ordering::Relaxed),
+            |_, &item| item * 2,
+        );
+
+        assert_eq!(results, (0..1000).map(|item| item * 2).collect::<Vec<_>>());
+
+        // state is created once per thread, not once per item
+        assert!(inits.load(Ordering::Relaxed) <= super::thread_count());
+    }
+
+    #[test]
+    fn no_items() {
+        let results: Vec<()> = map_with(&[] as &[()], || (), |(), ()| ());
+        assert!(results.is_empty());
+    }
+}